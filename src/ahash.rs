@@ -0,0 +1,59 @@
+use crate::hash::ahash;
+use crate::{Blockhash, Blockhash256, Blockhash64, Image};
+
+/// Generates a 64-bit average hash (aHash) of an image.
+///
+/// The image is divided into the same grid of 8 by 8 blocks as for
+/// [`blockhash64`](crate::blockhash64), and each bit is set if the block is
+/// brighter than the mean of all the blocks, rather than the median of its
+/// band.
+///
+/// Average hashes are slightly cheaper to compute, and less robust to changes
+/// such as gamma correction that affect some parts of an image more than
+/// others. This makes them useful as a fast first pass for finding
+/// near-duplicates in very large collections, with blockhash used to confirm
+/// the matches. The two kinds of hash use the same digest types, but aren't
+/// comparable with each other.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::ahash64;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = ahash64(&img);
+///
+/// assert_eq!(hash.to_string(), "efcf8f8180426c00");
+/// # }
+/// ```
+#[inline]
+#[must_use]
+pub fn ahash64<I: Image>(img: &I) -> Blockhash64 {
+    Blockhash(ahash::<I, 8, 64, 8>(img))
+}
+
+/// Generates a 256-bit average hash (aHash) of an image.
+///
+/// This is the same as [`ahash64`], but uses a grid of 16 by 16 blocks.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::ahash256;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = ahash256(&img);
+///
+/// assert_eq!(
+///     hash.to_string(),
+///     "fc7ff87ff0ffe0ffe0ffc0ffc067c043c00000000009301e3c747fd00eb00000",
+/// );
+/// # }
+/// ```
+#[inline]
+#[must_use]
+pub fn ahash256<I: Image>(img: &I) -> Blockhash256 {
+    Blockhash(ahash::<I, 16, 256, 32>(img))
+}
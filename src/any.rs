@@ -0,0 +1,260 @@
+use crate::compare::{bit_at, majority, span};
+use crate::{Blockhash1024, Blockhash144, Blockhash16, Blockhash256, Blockhash64, Digest};
+use core::convert::TryInto;
+use core::fmt::{self, Display, Formatter};
+
+/// A hash digest of any size.
+///
+/// This allows hashes of different sizes to be stored together, such as in a
+/// bundle created by [`concat_digests`](crate::concat_digests).
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{AnyBlockhash, Blockhash16, Blockhash64};
+///
+/// let a = AnyBlockhash::from(Blockhash16::from(0x00ff));
+/// let b = AnyBlockhash::from(Blockhash16::from(0x00fe));
+/// let c = AnyBlockhash::from(Blockhash64::from(0));
+///
+/// assert_eq!(a.bits(), 16);
+/// assert_eq!(a.distance(&b), Some(1));
+/// assert_eq!(a.distance(&c), None);
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnyBlockhash {
+    /// A 16-bit hash.
+    Blockhash16(Blockhash16),
+    /// A 64-bit hash.
+    Blockhash64(Blockhash64),
+    /// A 144-bit hash.
+    Blockhash144(Blockhash144),
+    /// A 256-bit hash.
+    Blockhash256(Blockhash256),
+    /// A 1024-bit hash.
+    Blockhash1024(Blockhash1024),
+}
+
+impl AnyBlockhash {
+    /// Returns the number of bits in the hash.
+    #[inline]
+    #[must_use]
+    pub fn bits(&self) -> u32 {
+        match self {
+            AnyBlockhash::Blockhash16(_) => Blockhash16::BITS,
+            AnyBlockhash::Blockhash64(_) => Blockhash64::BITS,
+            AnyBlockhash::Blockhash144(_) => Blockhash144::BITS,
+            AnyBlockhash::Blockhash256(_) => Blockhash256::BITS,
+            AnyBlockhash::Blockhash1024(_) => Blockhash1024::BITS,
+        }
+    }
+
+    /// Returns the bytes of the hash.
+    #[inline]
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            AnyBlockhash::Blockhash16(hash) => hash.as_bytes(),
+            AnyBlockhash::Blockhash64(hash) => hash.as_bytes(),
+            AnyBlockhash::Blockhash144(hash) => hash.as_bytes(),
+            AnyBlockhash::Blockhash256(hash) => hash.as_bytes(),
+            AnyBlockhash::Blockhash1024(hash) => hash.as_bytes(),
+        }
+    }
+
+    /// Returns the Hamming distance between two hashes, or `None` if the
+    /// hashes have different sizes.
+    #[inline]
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> Option<u32> {
+        match (self, other) {
+            (AnyBlockhash::Blockhash16(a), AnyBlockhash::Blockhash16(b)) => Some(a.distance(b)),
+            (AnyBlockhash::Blockhash64(a), AnyBlockhash::Blockhash64(b)) => Some(a.distance(b)),
+            (AnyBlockhash::Blockhash144(a), AnyBlockhash::Blockhash144(b)) => Some(a.distance(b)),
+            (AnyBlockhash::Blockhash256(a), AnyBlockhash::Blockhash256(b)) => Some(a.distance(b)),
+            (AnyBlockhash::Blockhash1024(a), AnyBlockhash::Blockhash1024(b)) => Some(a.distance(b)),
+            _ => None,
+        }
+    }
+
+    /// Returns the distance between two hashes, comparing hashes of different
+    /// sizes according to the given policy.
+    ///
+    /// Hashes of the same size are always compared directly, in the same way
+    /// as [`distance`](Self::distance).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{AnyBlockhash, Blockhash16, Blockhash64, SizePolicy};
+    ///
+    /// let a = AnyBlockhash::from(Blockhash16::from(0xc800));
+    /// let b = AnyBlockhash::from(Blockhash64::from(0xf0f0_f0f0_0000_0000));
+    ///
+    /// assert_eq!(a.distance_with(&b, SizePolicy::Reject), None);
+    /// assert_eq!(a.distance_with(&b, SizePolicy::Coarsen), Some(1));
+    /// assert_eq!(a.distance_with(&b, SizePolicy::Scale), Some(4));
+    /// ```
+    #[must_use]
+    pub fn distance_with(&self, other: &Self, policy: SizePolicy) -> Option<u32> {
+        if let Some(dist) = self.distance(other) {
+            return Some(dist);
+        }
+
+        let (small, large) = if self.bits() < other.bits() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let (s, l) = (small.columns(), large.columns());
+        let (small, large) = (small.as_bytes(), large.as_bytes());
+
+        match policy {
+            SizePolicy::Reject => None,
+            SizePolicy::Coarsen => {
+                let mut dist = 0;
+                for cy in 0..s {
+                    for cx in 0..s {
+                        let set = majority(large, l, span(cx, s, l), span(cy, s, l));
+                        dist += u32::from(set != bit_at(small, s, cx, cy));
+                    }
+                }
+                Some(dist)
+            }
+            SizePolicy::Scale => {
+                let mut dist = 0;
+                for y in 0..l {
+                    // The block of the smaller grid containing the centre of
+                    // this block
+                    let sy = (2 * y + 1) * s / (2 * l);
+                    for x in 0..l {
+                        let sx = (2 * x + 1) * s / (2 * l);
+                        dist += u32::from(bit_at(large, l, x, y) != bit_at(small, s, sx, sy));
+                    }
+                }
+                Some(dist)
+            }
+        }
+    }
+
+    /// Returns the number of columns in the grid of the hash.
+    fn columns(&self) -> u32 {
+        match self {
+            AnyBlockhash::Blockhash16(_) => Blockhash16::COLUMNS,
+            AnyBlockhash::Blockhash64(_) => Blockhash64::COLUMNS,
+            AnyBlockhash::Blockhash144(_) => Blockhash144::COLUMNS,
+            AnyBlockhash::Blockhash256(_) => Blockhash256::COLUMNS,
+            AnyBlockhash::Blockhash1024(_) => Blockhash1024::COLUMNS,
+        }
+    }
+
+    /// Creates a hash from its bytes, choosing the size from the number of
+    /// bytes.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(match bytes.len() {
+            2 => Blockhash16::from(to_array::<2>(bytes)).into(),
+            8 => Blockhash64::from(to_array::<8>(bytes)).into(),
+            18 => Blockhash144::from(to_array::<18>(bytes)).into(),
+            32 => Blockhash256::from(to_array::<32>(bytes)).into(),
+            128 => Blockhash1024::from(to_array::<128>(bytes)).into(),
+            _ => return None,
+        })
+    }
+}
+
+/// How to compare hashes of different sizes.
+///
+/// See [`AnyBlockhash::distance_with`].
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum SizePolicy {
+    /// Hashes of different sizes can't be compared, and have no distance.
+    #[default]
+    Reject,
+    /// The larger hash is reduced to the size of the smaller one, by taking
+    /// the majority value of the blocks that overlap each block of the smaller
+    /// grid, and the distance is measured in bits of the smaller hash.
+    ///
+    /// If the blocks are split evenly, the value of the top-left block is
+    /// used.
+    Coarsen,
+    /// The smaller hash is enlarged to the size of the larger one, by
+    /// repeating each of its blocks, and the distance is measured in bits of
+    /// the larger hash.
+    ///
+    /// This keeps the detail of the larger hash, so it is more precise than
+    /// coarsening, and the distances can be compared against the same
+    /// thresholds as for the larger size.
+    Scale,
+}
+
+impl Display for AnyBlockhash {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AnyBlockhash::Blockhash16(hash) => Display::fmt(hash, f),
+            AnyBlockhash::Blockhash64(hash) => Display::fmt(hash, f),
+            AnyBlockhash::Blockhash144(hash) => Display::fmt(hash, f),
+            AnyBlockhash::Blockhash256(hash) => Display::fmt(hash, f),
+            AnyBlockhash::Blockhash1024(hash) => Display::fmt(hash, f),
+        }
+    }
+}
+
+impl From<Blockhash16> for AnyBlockhash {
+    #[inline]
+    fn from(hash: Blockhash16) -> Self {
+        AnyBlockhash::Blockhash16(hash)
+    }
+}
+
+impl From<Blockhash64> for AnyBlockhash {
+    #[inline]
+    fn from(hash: Blockhash64) -> Self {
+        AnyBlockhash::Blockhash64(hash)
+    }
+}
+
+impl From<Blockhash144> for AnyBlockhash {
+    #[inline]
+    fn from(hash: Blockhash144) -> Self {
+        AnyBlockhash::Blockhash144(hash)
+    }
+}
+
+impl From<Blockhash256> for AnyBlockhash {
+    #[inline]
+    fn from(hash: Blockhash256) -> Self {
+        AnyBlockhash::Blockhash256(hash)
+    }
+}
+
+impl From<Blockhash1024> for AnyBlockhash {
+    #[inline]
+    fn from(hash: Blockhash1024) -> Self {
+        AnyBlockhash::Blockhash1024(hash)
+    }
+}
+
+/// Reduces a square grid of `blocks` by `blocks` bits to a grid of `cells` by
+/// `cells` bits, by taking the majority value of the blocks that overlap each
+/// cell.
+pub(crate) fn downsample<const SIZE: usize>(bytes: &[u8], blocks: u32, cells: u32) -> [u8; SIZE] {
+    let mut res = [0_u8; SIZE];
+
+    for cy in 0..cells {
+        let rows = span(cy, cells, blocks);
+
+        for cx in 0..cells {
+            if majority(bytes, blocks, span(cx, cells, blocks), rows) {
+                let idx = (cy * cells + cx) as usize;
+                res[idx / 8] |= 0x80 >> (idx % 8);
+            }
+        }
+    }
+
+    res
+}
+
+fn to_array<const SIZE: usize>(bytes: &[u8]) -> [u8; SIZE] {
+    bytes.try_into().unwrap()
+}
@@ -0,0 +1,123 @@
+use crate::Digest;
+
+/// A fixed-capacity list of hashes that can be searched for near matches.
+///
+/// The hashes are stored inline, so this doesn't require heap allocation and
+/// can be used on `no_std` targets, for example to check images against a
+/// small, known set of hashes on-device. Searches compare the query against
+/// every hash in the list.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, Blocklist};
+///
+/// let mut list = Blocklist::<Blockhash64, 4>::new();
+/// list.insert(Blockhash64::from(0xffff_0000_ffff_0000)).unwrap();
+/// list.insert(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f)).unwrap();
+///
+/// let query = Blockhash64::from(0x0f0f_0f0f_0f0f_0f00);
+/// assert_eq!(list.nearest(&query, 10), Some((1, 4)));
+/// assert_eq!(list.nearest(&query, 3), None);
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Blocklist<D, const N: usize> {
+    entries: [Option<D>; N],
+    len: usize,
+}
+
+impl<D: Digest, const N: usize> Blocklist<D, N> {
+    /// Creates an empty list.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Blocklist {
+            entries: [None; N],
+            len: 0,
+        }
+    }
+
+    /// Adds a hash to the list.
+    ///
+    /// # Errors
+    ///
+    /// Returns the hash back if the list is full.
+    #[inline]
+    pub fn insert(&mut self, hash: D) -> Result<(), D> {
+        if self.len == N {
+            return Err(hash);
+        }
+
+        self.entries[self.len] = Some(hash);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the number of hashes in the list.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the list is empty.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the maximum number of hashes the list can hold.
+    #[inline]
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the hash at the given position in the list.
+    #[inline]
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&D> {
+        self.entries.get(index)?.as_ref()
+    }
+
+    /// Returns an iterator over the hashes in the list, in the order they were
+    /// inserted.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &D> + '_ {
+        self.entries[..self.len].iter().flatten()
+    }
+
+    /// Finds the hash closest to the query, if it is within the given distance.
+    ///
+    /// Returns the position of the hash in the list and its distance from the
+    /// query. If several hashes are equally close, the first one is returned.
+    #[must_use]
+    pub fn nearest(&self, query: &D, max_distance: u32) -> Option<(usize, u32)> {
+        let mut best: Option<(usize, u32)> = None;
+
+        for (i, hash) in self.iter().enumerate() {
+            let dist = hash.distance(query);
+            if dist <= max_distance && best.map_or(true, |(_, best)| dist < best) {
+                best = Some((i, dist));
+            }
+        }
+
+        best
+    }
+
+    /// Returns whether any hash in the list is within the given distance of the
+    /// query.
+    #[inline]
+    #[must_use]
+    pub fn contains_near(&self, query: &D, max_distance: u32) -> bool {
+        self.iter().any(|hash| hash.distance(query) <= max_distance)
+    }
+}
+
+impl<D: Digest, const N: usize> Default for Blocklist<D, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::compare::{majority, span};
+use crate::Digest;
+
+/// The number of columns of cells in a bucket code.
+const COLUMNS: u32 = 8;
+
+/// The number of rows of cells in a bucket code.
+const ROWS: u32 = 4;
+
+/// Reduces a hash to a 32-bit code that is likely to be the same for similar
+/// images.
+///
+/// The block grid of the hash is divided into 8 columns and 4 rows of cells,
+/// and each bit of the code is the majority value of the blocks in a cell. If a
+/// cell is split evenly, the value of its top-left block is used. The bits are
+/// packed in row-major order, with the first cell in the most significant bit.
+///
+/// This allows similar images to be grouped by an exact key, for systems that
+/// can only look up keys of a limited size, such as a sharded cache. Unlike the
+/// hashes themselves, codes should only be compared for equality: similar
+/// images usually have the same code, but not always, so a lookup by code
+/// should be treated as a fast first pass.
+///
+/// # Collisions
+///
+/// Measured with 256-bit hashes of 2,000 synthetic 64x64 images (each a sum of
+/// a few random soft spots), with 100,000 trials of flipping random bits. The
+/// images and trials are generated from a fixed seed by the ignored
+/// `bucket_code_collisions` test.
+///
+/// | Bits flipped | Code unchanged |
+/// |--------------|----------------|
+/// | 1            | 92.9%          |
+/// | 4            | 75.2%          |
+/// | 8            | 57.7%          |
+/// | 16           | 34.9%          |
+/// | 32           | 12.7%          |
+///
+/// The 2,000 unrelated images produced 1,926 distinct codes, so each bucket
+/// should be expected to contain some unrelated images as well. These rates
+/// depend on the images, and haven't been measured on real photographs.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{bucket_code, Blockhash64};
+///
+/// let a = Blockhash64::from(0xf0f0_f0f0_0f0f_0f0f);
+/// let b = Blockhash64::from(0xf0f0_f0f1_0f0f_0f0f);
+///
+/// assert_eq!(bucket_code(&a), 0xf0f0_0f0f);
+/// assert_eq!(bucket_code(&a), bucket_code(&b));
+/// ```
+#[must_use]
+pub fn bucket_code<D: Digest>(hash: &D) -> u32 {
+    let mut code = 0;
+
+    for cy in 0..ROWS {
+        let rows = span(cy, ROWS, D::ROWS);
+
+        for cx in 0..COLUMNS {
+            let columns = span(cx, COLUMNS, D::COLUMNS);
+            let set = majority(hash.as_bytes(), D::COLUMNS, columns, rows);
+            code = (code << 1) | u32::from(set);
+        }
+    }
+
+    code
+}
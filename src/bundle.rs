@@ -0,0 +1,150 @@
+use crate::AnyBlockhash;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt::{self, Display, Formatter};
+
+/// The bytes at the start of every bundle.
+const MAGIC: [u8; 2] = *b"BH";
+
+/// The version of the bundle format.
+const VERSION: u8 = 1;
+
+/// The length of the header, including the magic bytes, version, and count.
+const HEADER_LEN: usize = 5;
+
+/// An error returned by [`split_digests`] when a bundle is invalid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BundleError {
+    /// The bundle doesn't start with the expected header.
+    InvalidHeader,
+    /// The bundle was created by an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// A hash in the bundle has an unknown size.
+    UnknownSize(u8),
+    /// The bundle ended before all of its hashes were read.
+    Truncated,
+    /// The bundle contains bytes after its last hash.
+    TrailingBytes,
+}
+
+impl Display for BundleError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            BundleError::InvalidHeader => f.write_str("invalid bundle header"),
+            BundleError::UnsupportedVersion(version) => {
+                write!(f, "unsupported bundle version {}", version)
+            }
+            BundleError::UnknownSize(size) => write!(f, "unknown hash size {}", size),
+            BundleError::Truncated => f.write_str("bundle is truncated"),
+            BundleError::TrailingBytes => f.write_str("unexpected bytes at end of bundle"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BundleError {}
+
+/// Concatenates several hashes into a single bundle of bytes.
+///
+/// This is useful for storing related hashes of an image together, such as
+/// hashes of several regions or at several sizes, in a single database column.
+/// The bundle can be split back into the original hashes with
+/// [`split_digests`].
+///
+/// The bundle starts with a header of the bytes `BH`, a format version of 1,
+/// and the number of hashes as a big-endian `u16`. Each hash is then stored as
+/// the number of columns in its grid (4, 8, 12, 16, or 32), followed by its bytes.
+///
+/// # Panics
+///
+/// Panics if there are more than 65535 hashes.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{concat_digests, split_digests, AnyBlockhash, Blockhash16, Blockhash64};
+///
+/// let hashes = [
+///     AnyBlockhash::from(Blockhash16::from(0xa396)),
+///     AnyBlockhash::from(Blockhash64::from(0xc7c4_8f89_89c7_7e0c)),
+/// ];
+///
+/// let bundle = concat_digests(&hashes);
+/// assert_eq!(bundle.len(), 5 + 3 + 9);
+/// assert_eq!(split_digests(&bundle), Ok(hashes.to_vec()));
+/// ```
+#[must_use]
+pub fn concat_digests(hashes: &[AnyBlockhash]) -> Vec<u8> {
+    let count: u16 = hashes.len().try_into().expect("too many hashes");
+
+    let len = HEADER_LEN + hashes.iter().map(|h| 1 + h.as_bytes().len()).sum::<usize>();
+    let mut bundle = Vec::with_capacity(len);
+
+    bundle.extend_from_slice(&MAGIC);
+    bundle.push(VERSION);
+    bundle.extend_from_slice(&count.to_be_bytes());
+
+    for hash in hashes {
+        bundle.push(size_code(hash));
+        bundle.extend_from_slice(hash.as_bytes());
+    }
+
+    bundle
+}
+
+/// Splits a bundle created by [`concat_digests`] back into its hashes.
+///
+/// # Errors
+///
+/// Returns an error if the bundle doesn't have a valid header, contains a hash
+/// of an unknown size, or doesn't contain exactly the number of hashes given
+/// in its header.
+pub fn split_digests(bundle: &[u8]) -> Result<Vec<AnyBlockhash>, BundleError> {
+    if bundle.len() < HEADER_LEN || bundle[..2] != MAGIC {
+        return Err(BundleError::InvalidHeader);
+    }
+    if bundle[2] != VERSION {
+        return Err(BundleError::UnsupportedVersion(bundle[2]));
+    }
+
+    let count = u16::from_be_bytes([bundle[3], bundle[4]]);
+    let mut rest = &bundle[HEADER_LEN..];
+    let mut hashes = Vec::with_capacity(count.into());
+
+    for _ in 0..count {
+        let (&code, tail) = rest.split_first().ok_or(BundleError::Truncated)?;
+        let len = match code {
+            4 => 2,
+            8 => 8,
+            12 => 18,
+            16 => 32,
+            32 => 128,
+            _ => return Err(BundleError::UnknownSize(code)),
+        };
+        if tail.len() < len {
+            return Err(BundleError::Truncated);
+        }
+
+        let (bytes, tail) = tail.split_at(len);
+        hashes.push(AnyBlockhash::from_bytes(bytes).unwrap());
+        rest = tail;
+    }
+
+    if rest.is_empty() {
+        Ok(hashes)
+    } else {
+        Err(BundleError::TrailingBytes)
+    }
+}
+
+/// Returns the code used to identify the size of a hash in a bundle.
+fn size_code(hash: &AnyBlockhash) -> u8 {
+    match hash {
+        AnyBlockhash::Blockhash16(_) => 4,
+        AnyBlockhash::Blockhash64(_) => 8,
+        AnyBlockhash::Blockhash144(_) => 12,
+        AnyBlockhash::Blockhash256(_) => 16,
+        AnyBlockhash::Blockhash1024(_) => 32,
+    }
+}
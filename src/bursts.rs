@@ -0,0 +1,139 @@
+use crate::Digest;
+use alloc::vec::Vec;
+
+/// A run of consecutive near-duplicate frames found by [`summarize_bursts`].
+///
+/// The run covers the frames from `start` (inclusive) to `end` (exclusive).
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Burst {
+    /// The position of the first frame in the run.
+    pub start: usize,
+    /// The position after the last frame in the run.
+    pub end: usize,
+    /// The position of the frame chosen to represent the run.
+    pub representative: usize,
+}
+
+impl Burst {
+    /// Returns the number of frames in the run.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns whether the run is empty.
+    ///
+    /// Runs returned by [`summarize_bursts`] are never empty.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Groups an ordered sequence of frames into runs of near-duplicates, such as
+/// photos taken in a burst or a series of similar screenshots.
+///
+/// Each frame is a timestamp and a hash, and the frames must be sorted by
+/// timestamp. A frame joins the run of the previous frame if its hash is
+/// within `max_distance` of the previous hash, and its timestamp is no more
+/// than `max_gap` after the previous timestamp. Timestamps can be in any unit,
+/// as long as `max_gap` uses the same unit.
+///
+/// Every frame belongs to exactly one run, so frames that aren't similar to
+/// their neighbors form runs of their own. The representative of each run is
+/// the frame with the smallest total distance to the other frames in the run,
+/// with ties going to the earliest frame. It is found by counting the set bits
+/// of each position in the run, so the time taken grows with the number of
+/// frames times the number of bits in a hash, rather than with the square of
+/// the length of the run.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{summarize_bursts, Blockhash16, Burst};
+///
+/// let frames = [
+///     (1000, Blockhash16::from(0x0f0f)),
+///     (1200, Blockhash16::from(0x0f0e)),
+///     (1400, Blockhash16::from(0x0f0f)),
+///     (1500, Blockhash16::from(0xf0f0)),
+///     (9000, Blockhash16::from(0xf0f0)),
+/// ];
+///
+/// let bursts = summarize_bursts(&frames, 2, 1000);
+/// assert_eq!(
+///     bursts,
+///     [
+///         Burst { start: 0, end: 3, representative: 0 },
+///         Burst { start: 3, end: 4, representative: 3 },
+///         Burst { start: 4, end: 5, representative: 4 },
+///     ],
+/// );
+/// ```
+#[must_use]
+pub fn summarize_bursts<D: Digest>(
+    frames: &[(u64, D)],
+    max_distance: u32,
+    max_gap: u64,
+) -> Vec<Burst> {
+    let mut bursts = Vec::new();
+    let mut start = 0;
+
+    for end in 1..=frames.len() {
+        let continues = end < frames.len() && {
+            let (prev_time, prev_hash) = &frames[end - 1];
+            let (time, hash) = &frames[end];
+            time.saturating_sub(*prev_time) <= max_gap && hash.distance(prev_hash) <= max_distance
+        };
+
+        if !continues {
+            bursts.push(Burst {
+                start,
+                end,
+                representative: medoid(&frames[start..end]) + start,
+            });
+            start = end;
+        }
+    }
+
+    bursts
+}
+
+/// Returns the position of the frame with the smallest total distance to the
+/// other frames.
+///
+/// The total distance of a frame is the sum, over each bit, of the number of
+/// frames that differ from it in that bit, so only the number of frames with
+/// each bit set is needed.
+fn medoid<D: Digest>(frames: &[(u64, D)]) -> usize {
+    // Both frames of a pair are equally distant from each other
+    if frames.len() <= 2 {
+        return 0;
+    }
+
+    let bit = |hash: &D, idx: usize| hash.as_bytes()[idx / 8] & (0x80 >> (idx % 8)) != 0;
+
+    let mut ones = alloc::vec![0_u64; D::BITS as usize];
+    for (_, hash) in frames {
+        for (idx, count) in ones.iter_mut().enumerate() {
+            *count += u64::from(bit(hash, idx));
+        }
+    }
+
+    let len = frames.len() as u64;
+    let mut best = (0, u64::MAX);
+    for (i, (_, hash)) in frames.iter().enumerate() {
+        let dist: u64 = ones
+            .iter()
+            .enumerate()
+            .map(|(idx, &count)| if bit(hash, idx) { len - count } else { count })
+            .sum();
+        if dist < best.1 {
+            best = (i, dist);
+        }
+    }
+
+    best.0
+}
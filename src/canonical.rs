@@ -0,0 +1,53 @@
+use crate::Digest;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageReader};
+use std::path::Path;
+
+/// Opens and hashes an image file with a fixed pipeline, so that the same file
+/// always produces the same hash regardless of how the caller would otherwise
+/// load it.
+///
+/// Hashes of the same image can differ if it is loaded in different ways, such
+/// as with or without applying the orientation recorded by a camera, or at
+/// different bit depths. This function defines one way of loading and hashing
+/// a file, so independent systems that use it produce matching hashes:
+///
+/// 1. The format is detected from the contents of the file, not its
+///    extension.
+/// 2. The image is decoded with the default limits of the [`image`] crate.
+/// 3. The orientation in the Exif metadata, if any, is applied, so a photo
+///    that is stored sideways with an orientation tag hashes the same as the
+///    same photo stored upright. All other metadata, such as GPS coordinates,
+///    is ignored, so stripping it doesn't change the hash.
+/// 4. The image is converted to 8-bit RGBA, so 8- and 16-bit exports of the
+///    same image, and grayscale and color copies of a grayscale image,
+///    produce the same hash.
+/// 5. The image is hashed with [`Digest::from_image`], with the default
+///    options.
+///
+/// This pipeline won't change between versions of this crate with the same
+/// major version, although the decoders of the [`image`] crate might.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read, or if its format isn't
+/// recognized or it can't be decoded.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{canonical_hash, Blockhash64};
+///
+/// let hash: Blockhash64 = canonical_hash("images/example.png").unwrap();
+/// assert_eq!(hash.to_string(), "c7c48f8989c77e0c");
+/// ```
+pub fn canonical_hash<D: Digest, P: AsRef<Path>>(path: P) -> Result<D, ImageError> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+
+    Ok(D::from_image(&img.into_rgba8()))
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "image")]
+use image::ImageFormat;
+
+/// The optional functionality available in this build of the crate.
+///
+/// This reflects the [feature flags](crate#feature-flags) the crate was built
+/// with, so applications can report what they support, or route work that
+/// needs a missing feature elsewhere, without having to mirror the feature
+/// flags themselves.
+///
+/// # Examples
+///
+/// ```
+/// let capabilities = blockhash::capabilities();
+///
+/// if !capabilities.image() {
+///     println!("decoding images is unavailable");
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities;
+
+/// Returns the optional functionality available in this build of the crate.
+#[inline]
+#[must_use]
+pub const fn capabilities() -> Capabilities {
+    Capabilities
+}
+
+impl Capabilities {
+    /// Returns whether functionality that requires the standard library is
+    /// available, such as hashing frames on multiple threads and reading and
+    /// writing index files.
+    #[inline]
+    #[must_use]
+    pub const fn std(&self) -> bool {
+        cfg!(feature = "std")
+    }
+
+    /// Returns whether functionality that requires allocation is available,
+    /// such as searching indexes of hashes.
+    #[inline]
+    #[must_use]
+    pub const fn alloc(&self) -> bool {
+        cfg!(feature = "alloc")
+    }
+
+    /// Returns whether images from the [`image`](https://docs.rs/image) crate
+    /// can be hashed, and files can be decoded.
+    #[inline]
+    #[must_use]
+    pub const fn image(&self) -> bool {
+        cfg!(feature = "image")
+    }
+
+    /// Returns whether hashes and other types can be serialized with
+    /// [`serde`](https://docs.rs/serde).
+    #[inline]
+    #[must_use]
+    pub const fn serde(&self) -> bool {
+        cfg!(feature = "serde")
+    }
+
+    /// Returns whether files in the given format can be decoded.
+    ///
+    /// This depends on the features of the [`image`] crate, which might be
+    /// enabled by other crates in the same build.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use image::ImageFormat;
+    ///
+    /// let capabilities = blockhash::capabilities();
+    /// assert!(capabilities.can_decode(ImageFormat::Png));
+    /// ```
+    #[cfg(feature = "image")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+    #[inline]
+    #[must_use]
+    pub fn can_decode(&self, format: ImageFormat) -> bool {
+        format.reading_enabled()
+    }
+
+    /// Returns every format of file that can be decoded.
+    ///
+    /// See [`can_decode`](Self::can_decode).
+    #[cfg(feature = "image")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+    pub fn decodable_formats(&self) -> impl Iterator<Item = ImageFormat> {
+        ImageFormat::all().filter(|format| format.reading_enabled())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{Image, Region};
+
+/// Caption bars at the top and bottom of an image.
+///
+/// Memes and social media reposts often add a bar of text above or below an
+/// image, which changes the hash of the whole image. These bars can be
+/// detected and excluded before hashing, so that variants of the same image
+/// with different captions produce similar hashes.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash64, CaptionStrips, RawImage};
+///
+/// // A 64x48 image with a white caption bar of black text in the top 8 rows
+/// let mut data = vec![0_u8; 64 * 48];
+/// for y in 0..48 {
+///     for x in 0..64 {
+///         data[y * 64 + x] = if y < 8 {
+///             if y % 4 == 2 && x % 3 == 0 { 0 } else { 255 }
+///         } else {
+///             (x * 2 + y) as u8
+///         };
+///     }
+/// }
+///
+/// let img = RawImage::<_, 1>::new(&data, 64, 48);
+/// let strips = CaptionStrips::detect(&img);
+/// assert_eq!(strips, CaptionStrips { top: 8, bottom: 0 });
+///
+/// let body = RawImage::<_, 1>::new(&data[(64 * 8)..], 64, 40);
+/// assert_eq!(blockhash64(&strips.exclude(&img)), blockhash64(&body));
+/// ```
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct CaptionStrips {
+    /// The number of rows in the caption bar at the top of the image.
+    pub top: u32,
+    /// The number of rows in the caption bar at the bottom of the image.
+    pub bottom: u32,
+}
+
+impl CaptionStrips {
+    /// Detects caption bars at the top and bottom of an image.
+    ///
+    /// A caption bar is a run of rows at the edge of the image in which nearly
+    /// every pixel is close to either black or white, with the same one of the
+    /// two as the background of every row, and which contains some text of the
+    /// other. Bars that don't contain any text aren't detected, since they
+    /// can't be distinguished from uniform image content. Each bar can cover
+    /// at most a third of the height of the image.
+    #[must_use]
+    pub fn detect<I: Image>(img: &I) -> Self {
+        let (_, height) = img.dimensions();
+        let limit = height / 3;
+
+        CaptionStrips {
+            top: strip_height(img, 0..limit),
+            bottom: strip_height(img, (0..limit).map(|y| height - 1 - y)),
+        }
+    }
+
+    /// Returns the part of an image between the caption bars.
+    ///
+    /// If the bars cover the whole image, the whole image is returned.
+    #[must_use]
+    pub fn exclude<'a, I: Image>(&self, img: &'a I) -> Region<'a, I> {
+        let (width, height) = img.dimensions();
+
+        match height.checked_sub(self.top.saturating_add(self.bottom)) {
+            Some(remaining) if remaining > 0 => Region::new(img, 0, self.top, width, remaining),
+            _ => Region::new(img, 0, 0, width, height),
+        }
+    }
+}
+
+/// Statistics about the pixels in a row.
+struct Row {
+    dark: u64,
+    light: u64,
+    total: u64,
+}
+
+impl Row {
+    fn read<I: Image>(img: &I, y: u32) -> Self {
+        let (width, _) = img.dimensions();
+        let max = img.max_brightness();
+        let (low, high) = (max / 8, max - max / 8);
+
+        let mut row = Row {
+            dark: 0,
+            light: 0,
+            total: u64::from(width),
+        };
+
+        for x in 0..width {
+            let val = img.brightness(x, y);
+            if val <= low {
+                row.dark += 1;
+            } else if val >= high {
+                row.light += 1;
+            }
+        }
+
+        row
+    }
+
+    /// Returns whether nearly every pixel in the row is dark or light.
+    fn is_extreme(&self) -> bool {
+        (self.dark + self.light) * 10 >= self.total * 9
+    }
+
+    /// Returns the number of background and foreground pixels in the row, if
+    /// the background is light or dark as given.
+    fn split(&self, light_background: bool) -> (u64, u64) {
+        if light_background {
+            (self.light, self.dark)
+        } else {
+            (self.dark, self.light)
+        }
+    }
+}
+
+/// Returns the height of the caption bar formed by the given rows, starting at
+/// the edge of the image.
+fn strip_height<I: Image, R: Iterator<Item = u32>>(img: &I, rows: R) -> u32 {
+    let mut background = None;
+    let mut height = 0;
+    let mut has_text = false;
+
+    for y in rows {
+        let row = Row::read(img, y);
+        if !row.is_extreme() {
+            break;
+        }
+
+        let light = *background.get_or_insert(row.light >= row.dark);
+        let (bg, fg) = row.split(light);
+        if bg < fg {
+            break;
+        }
+
+        // Text makes up at least 2% of the row
+        has_text |= fg * 50 >= row.total;
+        height += 1;
+    }
+
+    if has_text {
+        height
+    } else {
+        0
+    }
+}
@@ -0,0 +1,182 @@
+use crate::srgb::{linear_to_srgb, srgb_to_linear, to_linear};
+use core::convert::TryInto;
+
+/// The linear intensity of each 8-bit Adobe RGB value, scaled to
+/// `0..=u16::MAX`.
+#[rustfmt::skip]
+static ADOBE_RGB_TO_LINEAR: [u16; 256] = [
+    0, 0, 2, 4, 7, 12, 17, 24, 32, 42, 53, 65,
+    79, 94, 111, 129, 149, 170, 193, 217, 243, 270, 299, 330,
+    363, 397, 432, 470, 509, 550, 592, 636, 683, 730, 780, 831,
+    884, 939, 996, 1055, 1115, 1177, 1241, 1307, 1375, 1445, 1516, 1590,
+    1665, 1742, 1821, 1902, 1985, 2070, 2157, 2246, 2337, 2430, 2524, 2621,
+    2720, 2820, 2923, 3028, 3134, 3243, 3354, 3467, 3581, 3698, 3817, 3938,
+    4061, 4186, 4313, 4443, 4574, 4707, 4843, 4980, 5120, 5262, 5406, 5552,
+    5700, 5850, 6003, 6157, 6314, 6473, 6634, 6797, 6962, 7130, 7300, 7472,
+    7646, 7822, 8000, 8181, 8364, 8549, 8736, 8926, 9117, 9311, 9507, 9706,
+    9906, 10109, 10314, 10522, 10731, 10943, 11157, 11373, 11592, 11813, 12036, 12262,
+    12489, 12719, 12952, 13186, 13423, 13662, 13904, 14148, 14394, 14642, 14893, 15146,
+    15402, 15660, 15920, 16182, 16447, 16714, 16984, 17255, 17530, 17806, 18085, 18366,
+    18650, 18936, 19224, 19515, 19808, 20104, 20402, 20702, 21005, 21310, 21617, 21927,
+    22240, 22554, 22871, 23191, 23513, 23837, 24164, 24493, 24825, 25159, 25496, 25835,
+    26176, 26520, 26866, 27215, 27566, 27920, 28276, 28635, 28996, 29360, 29726, 30094,
+    30465, 30839, 31215, 31593, 31974, 32357, 32743, 33132, 33522, 33916, 34312, 34710,
+    35111, 35514, 35920, 36329, 36740, 37153, 37569, 37988, 38409, 38833, 39259, 39687,
+    40119, 40552, 40989, 41428, 41869, 42313, 42760, 43209, 43660, 44114, 44571, 45031,
+    45492, 45957, 46424, 46894, 47366, 47841, 48318, 48798, 49280, 49766, 50253, 50744,
+    51237, 51732, 52230, 52731, 53234, 53740, 54249, 54760, 55274, 55790, 56309, 56831,
+    57355, 57882, 58411, 58943, 59478, 60016, 60556, 61098, 61644, 62192, 62742, 63295,
+    63851, 64410, 64971, 65535,
+];
+
+/// The red and green colorants of each color space, as they appear in the
+/// `rXYZ` and `gXYZ` tags of an ICC profile, in `s15Fixed16` format.
+const COLORANTS: [(ColorSpace, [[i32; 3]; 2]); 3] = [
+    (
+        ColorSpace::Srgb,
+        [[28580, 14582, 911], [25238, 46983, 6364]],
+    ),
+    (
+        ColorSpace::DisplayP3,
+        [[33758, 15807, -72], [19137, 45371, 2746]],
+    ),
+    (
+        ColorSpace::AdobeRgb,
+        [[39957, 20388, 1278], [13455, 41006, 3991]],
+    ),
+];
+
+/// The largest difference from the expected colorants that is accepted, in
+/// `s15Fixed16` format.
+const TOLERANCE: i32 = 328;
+
+/// The RGB color space that the channels of an image are encoded in.
+///
+/// See [`BlockhashOptions::color_space`](crate::BlockhashOptions::color_space).
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColorSpace {
+    /// [sRGB], the color space of almost all images without a color profile.
+    ///
+    /// [sRGB]: https://en.wikipedia.org/wiki/SRGB
+    #[default]
+    Srgb,
+    /// [Display P3], the wide-gamut color space of many phone cameras and
+    /// displays, which uses the same transfer function as sRGB.
+    ///
+    /// [Display P3]: https://en.wikipedia.org/wiki/DCI-P3#Display_P3
+    DisplayP3,
+    /// [Adobe RGB (1998)], a wide-gamut color space common in photography and
+    /// print workflows.
+    ///
+    /// [Adobe RGB (1998)]: https://en.wikipedia.org/wiki/Adobe_RGB_color_space
+    AdobeRgb,
+}
+
+impl ColorSpace {
+    /// Identifies the color space described by an ICC profile.
+    ///
+    /// The color space is identified from the red and green colorants of the
+    /// profile, so any profile for one of the supported color spaces is
+    /// recognized, whatever it is called. Returns `None` if the profile isn't
+    /// a valid RGB profile, or doesn't match any of the supported color
+    /// spaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{BlockhashOptions, ColorSpace};
+    /// use image::{ImageDecoder, ImageReader};
+    ///
+    /// let mut decoder = ImageReader::open("images/example.png")
+    ///     .unwrap()
+    ///     .into_decoder()
+    ///     .unwrap();
+    /// let color_space = decoder
+    ///     .icc_profile()
+    ///     .unwrap()
+    ///     .and_then(|profile| ColorSpace::from_icc_profile(&profile))
+    ///     .unwrap_or_default();
+    /// let img = image::DynamicImage::from_decoder(decoder).unwrap();
+    ///
+    /// let options = BlockhashOptions::new().color_space(color_space);
+    /// assert_eq!(options.blockhash64(&img).to_string(), "c7c48f8989c77e0c");
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn from_icc_profile(profile: &[u8]) -> Option<Self> {
+        if profile.get(36..40)? != b"acsp" || profile.get(16..20)? != b"RGB " {
+            return None;
+        }
+
+        let red = read_xyz_tag(profile, b"rXYZ")?;
+        let green = read_xyz_tag(profile, b"gXYZ")?;
+
+        COLORANTS
+            .iter()
+            .find(|(_, colorants)| {
+                colorants
+                    .iter()
+                    .zip(&[red, green])
+                    .flat_map(|(expected, actual)| expected.iter().zip(actual))
+                    .all(|(expected, actual)| (expected - actual).abs() <= TOLERANCE)
+            })
+            .map(|&(color_space, _)| color_space)
+    }
+
+    /// Converts red, green, and blue channels in the range `0..=max` from this
+    /// color space to sRGB.
+    ///
+    /// Colors outside of the sRGB gamut are clipped.
+    pub(crate) fn to_srgb(self, rgb: [u32; 3], max: u32) -> [u32; 3] {
+        // Matrices from linear RGB to linear sRGB, scaled by 65536
+        let (matrix, linear) = match self {
+            ColorSpace::Srgb => return rgb,
+            ColorSpace::DisplayP3 => (
+                [[80278, -14742, 0], [-2756, 68292, 0], [-1287, -5153, 71976]],
+                rgb.map(|val| srgb_to_linear(val, max)),
+            ),
+            ColorSpace::AdobeRgb => (
+                [[91643, -26107, 0], [0, 65536, 0], [0, -2813, 68349]],
+                rgb.map(|val| to_linear(&ADOBE_RGB_TO_LINEAR, val, max)),
+            ),
+        };
+
+        matrix.map(|row| {
+            let sum: i64 = row
+                .iter()
+                .zip(&linear)
+                .map(|(&weight, &val)| weight * i64::from(val))
+                .sum();
+            let val = ((sum + 32768) >> 16).clamp(0, u16::MAX.into());
+            linear_to_srgb(val as u32, max)
+        })
+    }
+}
+
+/// Reads the values of an `XYZ` tag with the given signature from an ICC
+/// profile.
+fn read_xyz_tag(profile: &[u8], signature: &[u8; 4]) -> Option<[i32; 3]> {
+    let read_u32 = |pos: usize| -> Option<u32> {
+        let bytes = profile.get(pos..pos.checked_add(4)?)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    };
+
+    let count = read_u32(128)? as usize;
+    let offset = (0..count)
+        .map(|i| 132 + i * 12)
+        .take_while(|&pos| pos + 12 <= profile.len())
+        .find(|&pos| &profile[pos..pos + 4] == signature)
+        .and_then(|pos| read_u32(pos + 4))? as usize;
+
+    if profile.get(offset..offset.checked_add(4)?)? != b"XYZ " {
+        return None;
+    }
+
+    let mut xyz = [0; 3];
+    for (i, val) in xyz.iter_mut().enumerate() {
+        *val = read_u32(offset + 8 + i * 4)? as i32;
+    }
+    Some(xyz)
+}
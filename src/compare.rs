@@ -0,0 +1,498 @@
+use crate::Digest;
+use core::cmp::Ordering;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Returns the bit for the block at the given position in the grid.
+#[inline]
+pub(crate) fn bit<D: Digest>(hash: &D, x: u32, y: u32) -> bool {
+    bit_at(hash.as_bytes(), D::COLUMNS, x, y)
+}
+
+/// Returns the bit for the block at the given position in a grid with the
+/// given number of columns.
+#[inline]
+pub(crate) fn bit_at(bytes: &[u8], columns: u32, x: u32, y: u32) -> bool {
+    let idx = (y * columns + x) as usize;
+    bytes[idx / 8] & (0x80 >> (idx % 8)) != 0
+}
+
+/// Returns the range of blocks covered by a cell, when a grid of blocks is
+/// divided into a coarser grid of cells, rounded outwards.
+#[inline]
+pub(crate) fn span(cell: u32, cells: u32, blocks: u32) -> (u32, u32) {
+    let start = cell * blocks / cells;
+    let end = ((cell + 1) * blocks + cells - 1) / cells;
+    (start, end)
+}
+
+/// Returns the majority value of the blocks in the given ranges of columns
+/// and rows of a grid with the given number of columns.
+///
+/// If the blocks are split evenly, the value of the top-left block is used.
+pub(crate) fn majority(
+    bytes: &[u8],
+    columns: u32,
+    (left, right): (u32, u32),
+    (top, bottom): (u32, u32),
+) -> bool {
+    let mut ones = 0;
+    for y in top..bottom {
+        for x in left..right {
+            ones += u32::from(bit_at(bytes, columns, x, y));
+        }
+    }
+
+    let total = (right - left) * (bottom - top);
+    match (ones * 2).cmp(&total) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => bit_at(bytes, columns, left, top),
+    }
+}
+
+/// The part of the block grid of a hash that isn't covered by letterbox or
+/// pillarbox bars.
+///
+/// The region is measured in blocks, from `left` and `top` (inclusive) to
+/// `right` and `bottom` (exclusive).
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct ActiveRegion {
+    /// The first column of the region.
+    pub left: u32,
+    /// The first row of the region.
+    pub top: u32,
+    /// The column after the last column of the region.
+    pub right: u32,
+    /// The row after the last row of the region.
+    pub bottom: u32,
+}
+
+impl ActiveRegion {
+    /// Detects letterbox and pillarbox bars in a hash.
+    ///
+    /// Bars are detected as rows at the top and bottom of the grid, and then
+    /// columns at the left and right, in which every block has the same value as
+    /// the block in the corner. If every block in the hash has the same value,
+    /// the whole grid is returned.
+    ///
+    /// Note that this can't distinguish bars from content that is uniform
+    /// along the edges of the image, such as a clear sky.
+    #[must_use]
+    pub fn detect<D: Digest>(hash: &D) -> Self {
+        let row_is_bar = |y: u32, val: bool| (0..D::COLUMNS).all(|x| bit(hash, x, y) == val);
+
+        let mut top = 0;
+        let mut bottom = D::ROWS;
+        let (top_val, bottom_val) = (bit(hash, 0, 0), bit(hash, 0, D::ROWS - 1));
+        while top < bottom && row_is_bar(top, top_val) {
+            top += 1;
+        }
+        while bottom > top && row_is_bar(bottom - 1, bottom_val) {
+            bottom -= 1;
+        }
+
+        if top == bottom {
+            return Self::full::<D>();
+        }
+
+        let column_is_bar = |x: u32, val: bool| (top..bottom).all(|y| bit(hash, x, y) == val);
+
+        let mut left = 0;
+        let mut right = D::COLUMNS;
+        let (left_val, right_val) = (bit(hash, 0, top), bit(hash, D::COLUMNS - 1, top));
+        while left < right && column_is_bar(left, left_val) {
+            left += 1;
+        }
+        while right > left && column_is_bar(right - 1, right_val) {
+            right -= 1;
+        }
+
+        if left == right {
+            left = 0;
+            right = D::COLUMNS;
+        }
+
+        ActiveRegion {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Returns the region covering the whole grid of a hash.
+    #[inline]
+    #[must_use]
+    pub fn full<D: Digest>() -> Self {
+        ActiveRegion {
+            left: 0,
+            top: 0,
+            right: D::COLUMNS,
+            bottom: D::ROWS,
+        }
+    }
+
+    /// Returns the region covered by both regions.
+    #[inline]
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        ActiveRegion {
+            left,
+            top,
+            right: self.right.min(other.right).max(left),
+            bottom: self.bottom.min(other.bottom).max(top),
+        }
+    }
+
+    /// Returns the number of blocks in the region.
+    ///
+    /// A region whose `right` or `bottom` is before its `left` or `top` is
+    /// empty.
+    #[inline]
+    #[must_use]
+    pub fn area(&self) -> u32 {
+        self.right.saturating_sub(self.left) * self.bottom.saturating_sub(self.top)
+    }
+
+    /// Returns whether the region contains the block at the given position.
+    #[inline]
+    #[must_use]
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+}
+
+/// A distance computed over only some of the bits of two hashes.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct PartialDistance {
+    distance: u32,
+    compared: u32,
+    total: u32,
+}
+
+impl PartialDistance {
+    #[inline]
+    pub(crate) fn new(distance: u32, compared: u32, total: u32) -> Self {
+        PartialDistance {
+            distance,
+            compared,
+            total,
+        }
+    }
+
+    /// Returns the number of compared bits that differ.
+    #[inline]
+    #[must_use]
+    pub fn distance(&self) -> u32 {
+        self.distance
+    }
+
+    /// Returns the number of bits that were compared.
+    #[inline]
+    #[must_use]
+    pub fn compared(&self) -> u32 {
+        self.compared
+    }
+
+    /// Returns the distance scaled up to the full size of the hash, rounded to
+    /// the nearest integer.
+    ///
+    /// This allows using the same thresholds as for the full Hamming distance.
+    /// If no bits were compared, this is zero.
+    #[inline]
+    #[must_use]
+    pub fn scaled(&self) -> u32 {
+        match self.compared {
+            0 => 0,
+            n => {
+                ((u64::from(self.distance) * u64::from(self.total) + u64::from(n) / 2)
+                    / u64::from(n)) as u32
+            }
+        }
+    }
+}
+
+/// Returns the distance between two hashes, ignoring any letterbox or
+/// pillarbox bars.
+///
+/// Bars are detected in both hashes with [`ActiveRegion::detect`]. The two
+/// active regions are then resampled to a common grid, as many blocks wide
+/// and high as the smaller of the two, with each cell taking the majority
+/// value of the blocks it covers, and only the cells of that grid are
+/// compared. This allows matching an image with a copy of it that has been
+/// padded with bars, or with bars of a different size or color.
+///
+/// When the bars differ in size, the distance is only approximate: the bars
+/// also change the median brightness that the bits of the content are
+/// compared against, and bars that don't line up with the blocks blur the
+/// edges of the active region. To match such copies exactly, remove the bars
+/// from the image with [`Letterboxed`](crate::Letterboxed) before hashing it.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{letterbox_distance, Blockhash16};
+///
+/// // The same content, with black and white bars at the top and bottom
+/// let a = Blockhash16::from(0b0000_0110_1001_0000);
+/// let b = Blockhash16::from(0b1111_0110_1001_1111);
+///
+/// let dist = letterbox_distance(&a, &b);
+/// assert_eq!(dist.distance(), 0);
+/// assert_eq!(dist.compared(), 8);
+/// assert_eq!(a.distance(&b), 8);
+///
+/// // The same content, squeezed between white bars at the left and right
+/// let c = Blockhash16::from(0b0011_0011_1100_1100);
+/// let d = Blockhash16::from(0b1011_1011_1101_1101);
+///
+/// let dist = letterbox_distance(&c, &d);
+/// assert_eq!(dist.distance(), 0);
+/// assert_eq!(dist.compared(), 8);
+/// assert_eq!(c.distance(&d), 4);
+/// ```
+#[must_use]
+pub fn letterbox_distance<D: Digest>(a: &D, b: &D) -> PartialDistance {
+    let (a_region, b_region) = (ActiveRegion::detect(a), ActiveRegion::detect(b));
+    let a_size = (
+        a_region.right - a_region.left,
+        a_region.bottom - a_region.top,
+    );
+    let b_size = (
+        b_region.right - b_region.left,
+        b_region.bottom - b_region.top,
+    );
+    let (columns, rows) = (a_size.0.min(b_size.0), a_size.1.min(b_size.1));
+
+    let cell = |hash: &D, region: &ActiveRegion, (width, height): (u32, u32), x, y| {
+        let (left, right) = span(x, columns, width);
+        let (top, bottom) = span(y, rows, height);
+        majority(
+            hash.as_bytes(),
+            D::COLUMNS,
+            (region.left + left, region.left + right),
+            (region.top + top, region.top + bottom),
+        )
+    };
+
+    let mut distance = 0;
+    for y in 0..rows {
+        for x in 0..columns {
+            if cell(a, &a_region, a_size, x, y) != cell(b, &b_region, b_size, x, y) {
+                distance += 1;
+            }
+        }
+    }
+
+    PartialDistance::new(distance, columns * rows, D::BITS)
+}
+
+/// A rectangle in image coordinates.
+///
+/// See [`localize_changes`], [`hash_sprites`](crate::hash_sprites), and
+/// [`Excluded`](crate::Excluded).
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct BlockRect {
+    /// The left edge of the rectangle.
+    pub x: u32,
+    /// The top edge of the rectangle.
+    pub y: u32,
+    /// The width of the rectangle.
+    pub width: u32,
+    /// The height of the rectangle.
+    pub height: u32,
+}
+
+/// Finds the regions of an image that differ between two hashes.
+///
+/// Blocks whose bits differ are grouped into connected regions (where blocks
+/// are connected if they share an edge), and the bounding rectangle of each
+/// region is returned in the coordinates of an image with the given
+/// dimensions. Rectangles are rounded outwards to whole pixels, and are
+/// returned in the order of their top-left block.
+///
+/// Note that a change to one part of an image can also flip bits elsewhere in
+/// the same band, since each bit depends on the median brightness of its band.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{localize_changes, BlockRect, Blockhash16};
+///
+/// let a = Blockhash16::from(0b0000_0110_0110_0000);
+/// let b = Blockhash16::from(0b0000_0110_0000_1000);
+///
+/// assert_eq!(
+///     localize_changes(&a, &b, 400, 300),
+///     [
+///         BlockRect { x: 100, y: 150, width: 200, height: 75 },
+///         BlockRect { x: 0, y: 225, width: 100, height: 75 },
+///     ],
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[must_use]
+pub fn localize_changes<D: Digest>(a: &D, b: &D, width: u32, height: u32) -> Vec<BlockRect> {
+    group_blocks(D::COLUMNS, D::ROWS, width, height, |x, y| {
+        bit(a, x, y) != bit(b, x, y)
+    })
+}
+
+/// Groups the blocks of a grid for which `selected` returns true into
+/// connected regions, and returns the bounding rectangle of each region in the
+/// coordinates of an image with the given dimensions.
+#[cfg(feature = "alloc")]
+pub(crate) fn group_blocks<F: Fn(u32, u32) -> bool>(
+    columns: u32,
+    rows: u32,
+    width: u32,
+    height: u32,
+    selected: F,
+) -> Vec<BlockRect> {
+    let mut visited = alloc::vec![false; (columns * rows) as usize];
+    let mut stack = Vec::new();
+    let mut rects = Vec::new();
+
+    for start_y in 0..rows {
+        for start_x in 0..columns {
+            let idx = (start_y * columns + start_x) as usize;
+            if visited[idx] || !selected(start_x, start_y) {
+                continue;
+            }
+
+            visited[idx] = true;
+            stack.push((start_x, start_y));
+
+            let (mut left, mut top, mut right, mut bottom) = (start_x, start_y, start_x, start_y);
+
+            while let Some((x, y)) = stack.pop() {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x);
+                bottom = bottom.max(y);
+
+                let neighbors = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+
+                for &(nx, ny) in &neighbors {
+                    if nx < columns && ny < rows {
+                        let idx = (ny * columns + nx) as usize;
+                        if !visited[idx] && selected(nx, ny) {
+                            visited[idx] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+
+            let x0 = scale_down(left, width, columns);
+            let y0 = scale_down(top, height, rows);
+            let x1 = scale_up(right + 1, width, columns);
+            let y1 = scale_up(bottom + 1, height, rows);
+
+            rects.push(BlockRect {
+                x: x0,
+                y: y0,
+                width: x1 - x0,
+                height: y1 - y0,
+            });
+        }
+    }
+
+    rects
+}
+
+/// Converts a block boundary to a pixel position, rounding down.
+#[cfg(feature = "alloc")]
+pub(crate) fn scale_down(block: u32, size: u32, blocks: u32) -> u32 {
+    (u64::from(block) * u64::from(size) / u64::from(blocks)) as u32
+}
+
+/// Converts a block boundary to a pixel position, rounding up.
+#[cfg(feature = "alloc")]
+pub(crate) fn scale_up(block: u32, size: u32, blocks: u32) -> u32 {
+    ((u64::from(block) * u64::from(size) + u64::from(blocks) - 1) / u64::from(blocks)) as u32
+}
+
+/// A corner of an image.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Corner {
+    /// The top left corner.
+    TopLeft,
+    /// The top right corner.
+    TopRight,
+    /// The bottom left corner.
+    BottomLeft,
+    /// The bottom right corner.
+    BottomRight,
+}
+
+/// Returns the distance between two hashes, ignoring the blocks in one corner.
+///
+/// Watermarks and channel logos are usually placed in a corner of an image,
+/// and often flip the bits for the blocks they cover. This ignores a rectangle
+/// of `columns` by `rows` blocks in the given corner, which is clamped to the
+/// size of the grid. Use [`PartialDistance::scaled`] to compare the result
+/// against thresholds for the full distance.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{watermark_distance, Blockhash64, Corner};
+///
+/// // The same image, with a logo in the bottom right corner
+/// let a = Blockhash64::from(0xf0f0_f0f0_0f0f_0f0f);
+/// let b = Blockhash64::from(0xf0f0_f0f0_0f0f_0808);
+///
+/// let dist = watermark_distance(&a, &b, Corner::BottomRight, 3, 2);
+/// assert_eq!(dist.distance(), 0);
+/// assert_eq!(dist.compared(), 58);
+/// assert_eq!(a.distance(&b), 6);
+/// ```
+#[must_use]
+pub fn watermark_distance<D: Digest>(
+    a: &D,
+    b: &D,
+    corner: Corner,
+    columns: u32,
+    rows: u32,
+) -> PartialDistance {
+    let (columns, rows) = (columns.min(D::COLUMNS), rows.min(D::ROWS));
+
+    let (left, right) = match corner {
+        Corner::TopLeft | Corner::BottomLeft => (0, columns),
+        Corner::TopRight | Corner::BottomRight => (D::COLUMNS - columns, D::COLUMNS),
+    };
+    let (top, bottom) = match corner {
+        Corner::TopLeft | Corner::TopRight => (0, rows),
+        Corner::BottomLeft | Corner::BottomRight => (D::ROWS - rows, D::ROWS),
+    };
+    let ignored = ActiveRegion {
+        left,
+        top,
+        right,
+        bottom,
+    };
+
+    let mut distance = 0;
+    for y in 0..D::ROWS {
+        for x in 0..D::COLUMNS {
+            if !ignored.contains(x, y) && bit(a, x, y) != bit(b, x, y) {
+                distance += 1;
+            }
+        }
+    }
+
+    PartialDistance::new(distance, D::BITS - ignored.area(), D::BITS)
+}
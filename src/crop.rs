@@ -0,0 +1,186 @@
+use crate::{blockhash64, match_hashes, Assignment, Blockhash64, Image, Region};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// The number of cells along the shorter side of the image in the grid used
+/// for segmentation.
+const GRID: u32 = 64;
+
+/// Generates a set of hashes that can be used to match cropped copies of an
+/// image.
+///
+/// Cropping an image moves every block boundary, so a cropped copy usually
+/// has a completely different hash to the original. Instead, this divides the
+/// image into segments of bright and dark areas, and hashes the bounding box of
+/// each segment separately. A segment that is entirely inside the cropped
+/// area produces a similar hash in both images, so the copies can be matched
+/// with [`count_matching_segments`].
+///
+/// The image is segmented on a grid of square cells, with 64 cells along its
+/// shorter side. Each cell is either bright or dark, depending on whether its
+/// average brightness is above half of the maximum, and segments are the
+/// connected groups of bright or dark cells. Segments covering less than 1/64
+/// of the grid are ignored. The hashes are returned in order of the size of
+/// their segments, largest first.
+///
+/// Segment boundaries can only fall on cell boundaries, so matches are closest
+/// when the crop is aligned to the cells of the original image, and a maximum
+/// distance of about 10 bits works well for most crops.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{count_matching_segments, crop_resistant_hash};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let cropped = img.crop_imm(42, 0, 200, 192);
+///
+/// let a = crop_resistant_hash(&img);
+/// let b = crop_resistant_hash(&cropped);
+/// assert!(count_matching_segments(&a, &b, 10) >= 1);
+/// # }
+/// ```
+#[must_use]
+pub fn crop_resistant_hash<I: Image>(img: &I) -> Vec<Blockhash64> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let grid = Grid::new(img);
+    let mut segments = grid.segments();
+    segments.sort_by_key(|&(size, _)| Reverse(size));
+
+    segments
+        .into_iter()
+        .map(|(_, [left, top, right, bottom])| {
+            let x0 = left * grid.cell_size;
+            let y0 = top * grid.cell_size;
+            let x1 = (right * grid.cell_size).min(width);
+            let y1 = (bottom * grid.cell_size).min(height);
+            blockhash64(&Region::new(img, x0, y0, x1 - x0, y1 - y0))
+        })
+        .collect()
+}
+
+/// Returns the number of segments that match between two sets of hashes
+/// generated by [`crop_resistant_hash`].
+///
+/// Each segment can only be matched once, and segments are paired closest
+/// first. Requiring a single matching segment is usually enough to find
+/// cropped copies, but requiring more reduces the number of false matches.
+#[must_use]
+pub fn count_matching_segments(a: &[Blockhash64], b: &[Blockhash64], max_distance: u32) -> usize {
+    match_hashes(a, b, max_distance, Assignment::OneToOne).len()
+}
+
+/// A grid of square cells covering an image, each of which is either bright
+/// or dark.
+struct Grid {
+    bright: Vec<bool>,
+    columns: u32,
+    rows: u32,
+    cell_size: u32,
+}
+
+impl Grid {
+    fn new<I: Image>(img: &I) -> Self {
+        let (width, height) = img.dimensions();
+
+        // Cells have a fixed size in pixels, rather than dividing the image
+        // into a fixed number of cells, so that the cells cover the same
+        // content after the image is cropped.
+        let cell_size = (width.min(height) / GRID).max(1);
+        let columns = (width + cell_size - 1) / cell_size;
+        let rows = (height + cell_size - 1) / cell_size;
+
+        let mut sums = alloc::vec![0_u64; (columns * rows) as usize];
+        let mut counts = alloc::vec![0_u64; (columns * rows) as usize];
+
+        for y in 0..height {
+            let row = (y / cell_size) * columns;
+            for x in 0..width {
+                let idx = (row + x / cell_size) as usize;
+                sums[idx] += u64::from(img.brightness(x, y));
+                counts[idx] += 1;
+            }
+        }
+
+        let max = u64::from(img.max_brightness());
+        let bright = sums
+            .iter()
+            .zip(&counts)
+            .map(|(&sum, &count)| sum * 2 > max * count)
+            .collect();
+
+        Grid {
+            bright,
+            columns,
+            rows,
+            cell_size,
+        }
+    }
+
+    /// Finds the connected groups of cells with the same value, and returns
+    /// the size of each group and its bounding box in cells.
+    ///
+    /// Groups covering less than 1/64 of the grid are ignored.
+    fn segments(&self) -> Vec<(usize, [u32; 4])> {
+        let (columns, rows) = (self.columns, self.rows);
+        let cells = self.bright.len();
+        let min_size = (cells / 64).max(1);
+
+        let mut visited = alloc::vec![false; cells];
+        let mut stack = Vec::new();
+        let mut segments = Vec::new();
+
+        for start in 0..cells {
+            if visited[start] {
+                continue;
+            }
+
+            let value = self.bright[start];
+            visited[start] = true;
+            stack.push(start);
+
+            let (x, y) = ((start as u32) % columns, (start as u32) / columns);
+            let mut bounds = [x, y, x + 1, y + 1];
+            let mut size = 0;
+
+            while let Some(idx) = stack.pop() {
+                let (x, y) = ((idx as u32) % columns, (idx as u32) / columns);
+                bounds = [
+                    bounds[0].min(x),
+                    bounds[1].min(y),
+                    bounds[2].max(x + 1),
+                    bounds[3].max(y + 1),
+                ];
+                size += 1;
+
+                let neighbors = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+
+                for &(nx, ny) in &neighbors {
+                    if nx < columns && ny < rows {
+                        let idx = (ny * columns + nx) as usize;
+                        if !visited[idx] && self.bright[idx] == value {
+                            visited[idx] = true;
+                            stack.push(idx);
+                        }
+                    }
+                }
+            }
+
+            if size >= min_size {
+                segments.push((size, bounds));
+            }
+        }
+
+        segments
+    }
+}
@@ -0,0 +1,158 @@
+use crate::hash::{blockhash, convert_to_bits, get_grid_values, Accumulation, Threshold};
+use crate::Image;
+
+/// Checks the relationships between the parameters of a custom grid.
+struct Grid<const COLUMNS: u32, const ROWS: u32, const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>;
+
+impl<const COLUMNS: u32, const ROWS: u32, const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>
+    Grid<COLUMNS, ROWS, NUM_BLOCKS, DIGEST_SIZE>
+{
+    const VALID: () = {
+        assert!(
+            COLUMNS > 0 && ROWS > 0,
+            "the grid must have at least one block in each direction",
+        );
+        assert!(
+            NUM_BLOCKS == (COLUMNS * ROWS) as usize,
+            "the number of blocks must be the number of columns multiplied by the number of rows",
+        );
+        assert!(
+            NUM_BLOCKS % 4 == 0,
+            "the number of blocks must be a multiple of 4",
+        );
+        assert!(
+            DIGEST_SIZE == (NUM_BLOCKS + 7) / 8,
+            "the size of the digest must be the number of blocks divided by 8, rounded up",
+        );
+    };
+}
+
+/// Generates a perceptual hash of an image with a custom grid size.
+///
+/// The image is divided into a grid of `BITS` by `BITS` blocks, exactly as for
+/// [`blockhash16`], [`blockhash64`], [`blockhash144`], and [`blockhash256`],
+/// which use grids of 4, 8, 12, and 16 blocks. `NUM_BLOCKS` must be the total
+/// number of blocks, and `DIGEST_SIZE` the number of bytes needed to store one
+/// bit per block, since these can't yet be computed from `BITS` by the
+/// compiler. `BITS` must be even, so that the blocks can be divided evenly
+/// into the 4 bands used for thresholding. Parameters that don't meet these
+/// requirements are rejected at compile time.
+///
+/// The bytes are in the same order as [`Digest::as_bytes`], so the hashes of
+/// the standard sizes are identical to those of the named functions. If the
+/// number of blocks isn't a multiple of 8, as for a grid of 6 by 6 blocks, the
+/// unused bits at the end of the last byte are always zero. The
+/// values of the blocks are kept on the stack, which requires about
+/// `NUM_BLOCKS * 17` bytes.
+///
+/// [`blockhash16`]: crate::blockhash16
+/// [`blockhash64`]: crate::blockhash64
+/// [`blockhash144`]: crate::blockhash144
+/// [`blockhash256`]: crate::blockhash256
+/// [`Digest::as_bytes`]: crate::Digest::as_bytes
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, blockhash_custom};
+///
+/// let img = image::open("images/example.png").unwrap();
+///
+/// // A 400-bit hash with a grid of 20 by 20 blocks
+/// let hash = blockhash_custom::<_, 20, 400, 50>(&img);
+/// assert_eq!(hash.len(), 50);
+///
+/// // A 36-bit hash with a grid of 6 by 6 blocks, padded to 5 bytes
+/// let hash = blockhash_custom::<_, 6, 36, 5>(&img);
+/// assert_eq!(hash[4] & 0x0f, 0);
+///
+/// let hash = blockhash_custom::<_, 8, 64, 8>(&img);
+/// assert_eq!(hash, <[u8; 8]>::from(blockhash64(&img)));
+/// # }
+/// ```
+///
+/// Inconsistent parameters fail to compile:
+///
+/// ```compile_fail
+/// # #[cfg(feature = "image")] {
+/// use blockhash::blockhash_custom;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = blockhash_custom::<_, 20, 400, 40>(&img);
+/// # }
+/// # #[cfg(not(feature = "image"))]
+/// # compile_error!("requires the image feature");
+/// ```
+#[inline]
+#[must_use]
+pub fn blockhash_custom<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+) -> [u8; DIGEST_SIZE] {
+    #[allow(clippy::let_unit_value)]
+    let () = Grid::<BITS, BITS, NUM_BLOCKS, DIGEST_SIZE>::VALID;
+    blockhash::<I, BITS, NUM_BLOCKS, DIGEST_SIZE>(img)
+}
+
+/// Generates a perceptual hash of an image with a grid of `COLUMNS` by `ROWS`
+/// blocks.
+///
+/// This is the same as [`blockhash_custom`], except that the grid doesn't
+/// need to be square. A grid with more columns than rows, such as 16 by 9,
+/// suits panoramas and banners, where a square grid would have blocks that
+/// are much wider than they are tall. `NUM_BLOCKS` must be `COLUMNS * ROWS`,
+/// which must be a multiple of 4, and `DIGEST_SIZE` the number of bytes
+/// needed to store one bit per block. Parameters that don't meet these
+/// requirements are rejected at compile time.
+///
+/// The bits are in row-major order, like those of the square hashes. A digest
+/// type that records the shape of the grid can be defined with
+/// [`define_blockhash!`](crate::define_blockhash).
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash256, blockhash_grid};
+///
+/// let img = image::open("images/example.png").unwrap();
+///
+/// // A 144-bit hash with a grid of 16 by 9 blocks
+/// let hash = blockhash_grid::<_, 16, 9, 144, 18>(&img);
+/// assert_eq!(hash.len(), 18);
+///
+/// let hash = blockhash_grid::<_, 16, 16, 256, 32>(&img);
+/// assert_eq!(hash, <[u8; 32]>::from(blockhash256(&img)));
+/// # }
+/// ```
+#[must_use]
+pub fn blockhash_grid<
+    I: Image,
+    const COLUMNS: u32,
+    const ROWS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+) -> [u8; DIGEST_SIZE] {
+    #[allow(clippy::let_unit_value)]
+    let () = Grid::<COLUMNS, ROWS, NUM_BLOCKS, DIGEST_SIZE>::VALID;
+
+    let (width, height) = img.dimensions();
+    let accumulation = Accumulation::select_grid(width, height, COLUMNS, ROWS);
+    let values = get_grid_values::<I, COLUMNS, ROWS, NUM_BLOCKS>(img, accumulation);
+
+    convert_to_bits(
+        width,
+        height,
+        &values,
+        img.max_brightness(),
+        4,
+        Threshold::Median,
+    )
+}
@@ -0,0 +1,282 @@
+use crate::compare::group_blocks;
+use crate::hash::{convert_to_bits, Threshold};
+use crate::{BlockRect, Blockhash, Blockhash144, Blockhash16, Blockhash256, Blockhash64, Image};
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::ops::Range;
+
+/// Hashes the frames of a video, reusing the work done for the previous frame
+/// in rows that haven't changed.
+///
+/// Each frame is hashed along with the ranges of rows that changed since the
+/// previous frame, such as the dirty rectangles reported by a screen capture
+/// API or a video encoder. Only those rows are read, so hashing frames of a
+/// mostly static stream, such as a screen recording or a security camera, is
+/// much cheaper than hashing each frame from scratch. The hashes are exactly
+/// the same as those of [`blockhash16`], [`blockhash64`], [`blockhash144`],
+/// and [`blockhash256`].
+///
+/// Rows that aren't reported as changed are assumed to be the same as in the
+/// previous frame, so the hash is wrong if any changes are missed. The whole
+/// frame is read if it is the first frame, if its dimensions or maximum
+/// brightness differ from the previous frame, or if a different size of hash
+/// is requested, and after calling [`reset`](Self::reset).
+///
+/// The sum of each row is kept for every block column, which uses 8 bytes per
+/// row per column of the grid.
+///
+/// The brightness of each block is also kept from one frame to the next, so
+/// [`changed_regions`](Self::changed_regions) can return the regions of the
+/// frame that changed as a cheap motion mask.
+///
+/// [`blockhash16`]: crate::blockhash16
+/// [`blockhash64`]: crate::blockhash64
+/// [`blockhash144`]: crate::blockhash144
+/// [`blockhash256`]: crate::blockhash256
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, DeltaHasher};
+/// use image::Rgb;
+///
+/// let mut frame = image::open("images/example.png").unwrap().to_rgb8();
+/// let mut hasher = DeltaHasher::new();
+/// assert_eq!(hasher.blockhash64(&frame, None), blockhash64(&frame));
+///
+/// // Draw a cursor that covers rows 40 to 49
+/// for y in 40..50 {
+///     for x in 100..110 {
+///         frame.put_pixel(x, y, Rgb([255, 255, 255]));
+///     }
+/// }
+/// assert_eq!(hasher.blockhash64(&frame, Some(40..50)), blockhash64(&frame));
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeltaHasher {
+    width: u32,
+    height: u32,
+    bits: u32,
+    max_brightness: u32,
+    /// The brightness of each row summed into each block column, weighted by
+    /// how much of each pixel falls in the column.
+    row_sums: Vec<u64>,
+    /// The value of each block, as computed by `get_values`.
+    values: Vec<u64>,
+    /// The value of each block in the previous frame, or empty if the
+    /// previous frame was read in full.
+    previous: Vec<u64>,
+}
+
+impl DeltaHasher {
+    /// Creates a hasher with no previous frame.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the previous frame, so the next frame is read in full.
+    pub fn reset(&mut self) {
+        self.row_sums.clear();
+        self.values.clear();
+        self.previous.clear();
+    }
+
+    /// Returns the regions of the last frame whose brightness changed since
+    /// the frame before it.
+    ///
+    /// A block has changed if its average brightness differs by more than the
+    /// given tolerance, as a fraction of the maximum brightness. Changed
+    /// blocks are grouped into connected regions in the same way as
+    /// [`localize_changes`](crate::localize_changes), but unlike comparing the
+    /// hashes, blocks in the same band that didn't change aren't affected by
+    /// the change to the median.
+    ///
+    /// No regions are returned if the last frame was read in full, since
+    /// there is no previous frame to compare it with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{BlockRect, DeltaHasher, RawImage};
+    ///
+    /// let mut pixels = vec![0_u8; 64 * 64];
+    /// let mut hasher = DeltaHasher::new();
+    /// hasher.blockhash16(&RawImage::<u8, 1>::new(&pixels, 64, 64), None);
+    ///
+    /// // Brighten a square in the second column of blocks
+    /// for y in 20..28 {
+    ///     pixels[y * 64 + 20..][..8].fill(200);
+    /// }
+    /// hasher.blockhash16(&RawImage::<u8, 1>::new(&pixels, 64, 64), Some(20..28));
+    ///
+    /// assert_eq!(
+    ///     hasher.changed_regions(0.05),
+    ///     [BlockRect { x: 16, y: 16, width: 16, height: 16 }],
+    /// );
+    /// ```
+    #[must_use]
+    pub fn changed_regions(&self, tolerance: f32) -> Vec<BlockRect> {
+        if self.previous.is_empty() {
+            return Vec::new();
+        }
+
+        // The values are scaled so that a white block has this value
+        let full = u64::from(self.max_brightness) * u64::from(self.width) * u64::from(self.height);
+        let limit = (f64::from(tolerance.clamp(0.0, 1.0)) * full as f64) as u64;
+
+        group_blocks(self.bits, self.bits, self.width, self.height, |x, y| {
+            let idx = (y * self.bits + x) as usize;
+            self.values[idx].abs_diff(self.previous[idx]) > limit
+        })
+    }
+
+    /// Generates a 16-bit perceptual hash of a frame, given the ranges of
+    /// rows that changed since the previous frame.
+    ///
+    /// See [`blockhash16`](crate::blockhash16).
+    pub fn blockhash16<I: Image, R: IntoIterator<Item = Range<u32>>>(
+        &mut self,
+        img: &I,
+        dirty_rows: R,
+    ) -> Blockhash16 {
+        Blockhash(self.hash::<I, R, 4, 16, 2>(img, dirty_rows))
+    }
+
+    /// Generates a 64-bit perceptual hash of a frame, given the ranges of
+    /// rows that changed since the previous frame.
+    ///
+    /// See [`blockhash64`](crate::blockhash64).
+    pub fn blockhash64<I: Image, R: IntoIterator<Item = Range<u32>>>(
+        &mut self,
+        img: &I,
+        dirty_rows: R,
+    ) -> Blockhash64 {
+        Blockhash(self.hash::<I, R, 8, 64, 8>(img, dirty_rows))
+    }
+
+    /// Generates a 144-bit perceptual hash of a frame, given the ranges of
+    /// rows that changed since the previous frame.
+    ///
+    /// See [`blockhash144`](crate::blockhash144).
+    pub fn blockhash144<I: Image, R: IntoIterator<Item = Range<u32>>>(
+        &mut self,
+        img: &I,
+        dirty_rows: R,
+    ) -> Blockhash144 {
+        Blockhash(self.hash::<I, R, 12, 144, 18>(img, dirty_rows))
+    }
+
+    /// Generates a 256-bit perceptual hash of a frame, given the ranges of
+    /// rows that changed since the previous frame.
+    ///
+    /// See [`blockhash256`](crate::blockhash256).
+    pub fn blockhash256<I: Image, R: IntoIterator<Item = Range<u32>>>(
+        &mut self,
+        img: &I,
+        dirty_rows: R,
+    ) -> Blockhash256 {
+        Blockhash(self.hash::<I, R, 16, 256, 32>(img, dirty_rows))
+    }
+
+    fn hash<
+        I: Image,
+        R: IntoIterator<Item = Range<u32>>,
+        const BITS: u32,
+        const NUM_BLOCKS: usize,
+        const DIGEST_SIZE: usize,
+    >(
+        &mut self,
+        img: &I,
+        dirty_rows: R,
+    ) -> [u8; DIGEST_SIZE] {
+        let (width, height) = img.dimensions();
+        let max_brightness = img.max_brightness();
+
+        let warm = !self.values.is_empty()
+            && (self.width, self.height, self.bits, self.max_brightness)
+                == (width, height, BITS, max_brightness);
+
+        let mut rows = Vec::new();
+        if warm {
+            self.previous.clone_from(&self.values);
+            for range in dirty_rows {
+                rows.push(range.start.min(height)..range.end.min(height));
+            }
+            merge_ranges(&mut rows);
+        } else {
+            self.width = width;
+            self.height = height;
+            self.bits = BITS;
+            self.max_brightness = max_brightness;
+            self.row_sums.clear();
+            self.row_sums.resize(height as usize * BITS as usize, 0);
+            self.values.clear();
+            self.values.resize(NUM_BLOCKS, 0);
+            self.previous.clear();
+            rows.push(0..height);
+        }
+
+        let columns = BITS as usize;
+        let mut row_sum = alloc::vec![0; columns];
+
+        for y in rows.into_iter().flatten() {
+            row_sum.iter_mut().for_each(|sum| *sum = 0);
+            for x in 0..width {
+                let brightness = u64::from(img.brightness(x, y));
+                for (bx, weight) in overlaps(x, width, BITS) {
+                    row_sum[bx as usize] += brightness * weight;
+                }
+            }
+
+            let old = &mut self.row_sums[y as usize * columns..][..columns];
+            for (by, weight) in overlaps(y, height, BITS) {
+                let values = &mut self.values[by as usize * columns..][..columns];
+                for ((value, &old), &new) in values.iter_mut().zip(&*old).zip(&row_sum) {
+                    *value = *value - old * weight + new * weight;
+                }
+            }
+            old.copy_from_slice(&row_sum);
+        }
+
+        let values: &[u64; NUM_BLOCKS] = self.values[..].try_into().unwrap();
+        convert_to_bits(width, height, values, max_brightness, 4, Threshold::Median)
+    }
+}
+
+/// Returns each block that a pixel overlaps along an axis of the given length,
+/// and the length of the overlap.
+///
+/// The axis is scaled so that each pixel has a length of `bits` and each block
+/// has a length of `len`, which gives the same weights as `get_values`.
+fn overlaps(pos: u32, len: u32, bits: u32) -> impl Iterator<Item = (u32, u64)> {
+    let (len, bits) = (u64::from(len), u64::from(bits));
+    let start = u64::from(pos) * bits;
+    let end = start + bits;
+
+    (start / len..bits)
+        .take_while(move |&block| block * len < end)
+        .map(move |block| {
+            let overlap = end.min((block + 1) * len) - start.max(block * len);
+            (block as u32, overlap)
+        })
+}
+
+/// Sorts a list of ranges and merges any that overlap, so that each row is
+/// only updated once.
+fn merge_ranges(ranges: &mut Vec<Range<u32>>) {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_unstable_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<u32>> = Vec::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    *ranges = merged;
+}
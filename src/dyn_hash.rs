@@ -0,0 +1,192 @@
+use crate::{blockhash_custom, popcount, AnyBlockhash, BlockhashParseError, Digest, Image};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+
+/// The largest number of blocks in each direction supported by
+/// [`BlockhashDyn::from_image`].
+const MAX_SIZE: u32 = 32;
+
+/// A hash digest whose size is chosen at runtime.
+///
+/// The digest types of this crate have sizes that are fixed at compile time,
+/// which doesn't suit applications that let the size be configured, such as a
+/// service where each collection of images has its own resolution. This type
+/// stores the shape of its grid alongside the bytes, and hashes can only be
+/// compared if their grids have the same shape.
+///
+/// Images can be hashed with a square grid with an even number of blocks in
+/// each direction, from 2 to 32. Hashes of the other digest types can also be
+/// converted into this type.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, BlockhashDyn};
+///
+/// let img = image::open("images/example.png").unwrap();
+///
+/// // The size would usually come from a configuration file
+/// let size = 8;
+/// let hash = BlockhashDyn::from_image(&img, size).unwrap();
+///
+/// assert_eq!(hash.bits(), 64);
+/// assert_eq!(hash, BlockhashDyn::from(blockhash64(&img)));
+/// assert_eq!(hash.to_string().parse(), Ok(hash.clone()));
+///
+/// let other = BlockhashDyn::from_image(&img, 10).unwrap();
+/// assert_eq!(hash.distance(&hash), Some(0));
+/// assert_eq!(hash.distance(&other), None);
+/// # }
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BlockhashDyn {
+    columns: u32,
+    rows: u32,
+    bytes: Vec<u8>,
+}
+
+impl BlockhashDyn {
+    /// Generates a perceptual hash of an image with a grid of `size` by
+    /// `size` blocks.
+    ///
+    /// Returns `None` if the size is odd, zero, or greater than 32.
+    #[must_use]
+    pub fn from_image<I: Image>(img: &I, size: u32) -> Option<Self> {
+        macro_rules! hash {
+            ($($size:literal)*) => {
+                match size {
+                    $($size => blockhash_custom::<I, $size, { $size * $size }, {
+                        ($size * $size + 7) / 8
+                    }>(img).to_vec(),)*
+                    _ => return None,
+                }
+            };
+        }
+
+        let bytes = hash!(2 4 6 8 10 12 14 16 18 20 22 24 26 28 30 32);
+
+        Some(BlockhashDyn {
+            columns: size,
+            rows: size,
+            bytes,
+        })
+    }
+
+    /// Returns the number of bits in the hash.
+    #[inline]
+    #[must_use]
+    pub fn bits(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Returns the number of columns of blocks in the grid.
+    #[inline]
+    #[must_use]
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Returns the number of rows of blocks in the grid.
+    #[inline]
+    #[must_use]
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the bytes of the hash.
+    ///
+    /// The bits are in the same order as [`Digest::as_bytes`]. If the number
+    /// of bits isn't a multiple of 8, the unused bits at the end of the last
+    /// byte are zero.
+    #[inline]
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the Hamming distance between two hashes, or `None` if their
+    /// grids have different shapes.
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> Option<u32> {
+        if (self.columns, self.rows) != (other.columns, other.rows) {
+            return None;
+        }
+
+        Some(
+            self.bytes
+                .iter()
+                .zip(&other.bytes)
+                .map(|(a, b)| popcount::count_ones(a ^ b))
+                .sum(),
+        )
+    }
+}
+
+impl FromStr for BlockhashDyn {
+    type Err = BlockhashParseError;
+
+    /// Parses a hash from a hexadecimal string.
+    ///
+    /// The grid is assumed to be square, and its size is chosen from the
+    /// length of the string, which must match one of the sizes supported by
+    /// [`from_image`](Self::from_image). The unused bits at the end of the
+    /// last byte must be zero.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.as_bytes();
+        let size = (1..=MAX_SIZE / 2)
+            .map(|half| half * 2)
+            .find(|&size| ((size * size + 7) / 8 * 2) as usize == s.len())
+            .ok_or(BlockhashParseError)?;
+
+        let bytes = s
+            .chunks_exact(2)
+            .map(|pair| Ok((crate::parse_char(pair[0])? << 4) | crate::parse_char(pair[1])?))
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        let unused = (bytes.len() * 8) as u32 - size * size;
+        if bytes.last().map_or(0, |&last| last & ((1 << unused) - 1)) != 0 {
+            return Err(BlockhashParseError);
+        }
+
+        Ok(BlockhashDyn {
+            columns: size,
+            rows: size,
+            bytes,
+        })
+    }
+}
+
+impl Display for BlockhashDyn {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for byte in &self.bytes {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Digest> From<D> for BlockhashDyn {
+    #[inline]
+    fn from(hash: D) -> Self {
+        BlockhashDyn {
+            columns: D::COLUMNS,
+            rows: D::ROWS,
+            bytes: hash.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<AnyBlockhash> for BlockhashDyn {
+    #[inline]
+    fn from(hash: AnyBlockhash) -> Self {
+        match hash {
+            AnyBlockhash::Blockhash16(hash) => hash.into(),
+            AnyBlockhash::Blockhash64(hash) => hash.into(),
+            AnyBlockhash::Blockhash144(hash) => hash.into(),
+            AnyBlockhash::Blockhash256(hash) => hash.into(),
+            AnyBlockhash::Blockhash1024(hash) => hash.into(),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{BlockRect, Image};
+use alloc::vec::Vec;
+
+/// An image with some rectangles painted over with the color around them,
+/// such as the text regions found by an OCR tool.
+///
+/// Documents generated from the same template, such as invoices or scanned
+/// forms, often differ only in a few fields like a date or a page number.
+/// Those fields can change the hash, but they are usually found by OCR or
+/// layout analysis anyway. Passing their bounding boxes to this adapter
+/// removes them from the hash, so documents that differ only in those fields
+/// hash identically.
+///
+/// Each rectangle is filled with the average of the pixels bordering it that
+/// aren't inside any rectangle, which is usually the color of the paper, so
+/// the result doesn't depend on the exact size of the box the OCR tool
+/// returned. Rectangles are clipped to the image, and can overlap.
+///
+/// Each pixel is checked against every rectangle, so this is intended for a
+/// modest number of rectangles, such as the fields of a form, rather than
+/// every word of a page.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash64, BlockRect, Excluded, RawImage};
+///
+/// // A page with lines of text, and a date field in the top-right corner
+/// let page = |date: usize| {
+///     let mut data = vec![255_u8; 64 * 48];
+///     for y in 0..48 {
+///         for x in 0..64 {
+///             if y % 6 == 3 && y > 12 && x % 8 < 6 {
+///                 data[y * 64 + x] = 40;
+///             } else if (3..9).contains(&y) && (40..60).contains(&x) && x % date == 0 {
+///                 data[y * 64 + x] = 0;
+///             }
+///         }
+///     }
+///     data
+/// };
+/// let (a, b) = (page(2), page(5));
+/// let (a, b) = (RawImage::<_, 1>::new(&a, 64, 48), RawImage::<_, 1>::new(&b, 64, 48));
+/// assert_ne!(blockhash64(&a), blockhash64(&b));
+///
+/// // The boxes found by OCR differ slightly
+/// let box_a = BlockRect { x: 39, y: 2, width: 22, height: 8 };
+/// let box_b = BlockRect { x: 40, y: 3, width: 20, height: 7 };
+/// assert_eq!(
+///     blockhash64(&Excluded::new(&a, &[box_a])),
+///     blockhash64(&Excluded::new(&b, &[box_b])),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Excluded<'a, I> {
+    img: &'a I,
+    rects: Vec<Fill>,
+}
+
+/// A rectangle of an [`Excluded`] image, and the color it is filled with.
+#[derive(Debug, Copy, Clone)]
+struct Fill {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    brightness: u32,
+    channels: [u32; 4],
+}
+
+impl Fill {
+    #[inline]
+    fn contains(&self, x: u32, y: u32) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+}
+
+impl<'a, I: Image> Excluded<'a, I> {
+    /// Creates a view of an image with the given rectangles excluded.
+    #[must_use]
+    pub fn new(img: &'a I, rects: &[BlockRect]) -> Self {
+        let (width, height) = img.dimensions();
+        let mut fills: Vec<Fill> = rects
+            .iter()
+            .map(|rect| Fill {
+                left: rect.x.min(width),
+                top: rect.y.min(height),
+                right: rect.x.saturating_add(rect.width).min(width),
+                bottom: rect.y.saturating_add(rect.height).min(height),
+                brightness: 0,
+                channels: [0, 0, 0, img.max_channel()],
+            })
+            .filter(|fill| fill.left < fill.right && fill.top < fill.bottom)
+            .collect();
+
+        for i in 0..fills.len() {
+            let fill = fills[i];
+            let (mut brightness, mut channels, mut count) = (0_u64, [0_u64; 4], 0_u64);
+
+            // The pixels just outside each edge of the rectangle
+            let left = fill.left.saturating_sub(1);
+            let right = (fill.right + 1).min(width);
+            let top = fill.top.saturating_sub(1);
+            let bottom = (fill.bottom + 1).min(height);
+            for y in top..bottom {
+                for x in left..right {
+                    if fills.iter().any(|other| other.contains(x, y)) {
+                        continue;
+                    }
+
+                    brightness += u64::from(img.brightness(x, y));
+                    for (sum, val) in channels.iter_mut().zip(img.channels(x, y)) {
+                        *sum += u64::from(val);
+                    }
+                    count += 1;
+                }
+            }
+
+            if count > 0 {
+                fills[i].brightness = rounded_mean(brightness, count);
+                fills[i].channels = channels.map(|sum| rounded_mean(sum, count));
+            }
+        }
+
+        Excluded { img, rects: fills }
+    }
+}
+
+impl<I: Image> Image for Excluded<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        match self.rects.iter().find(|fill| fill.contains(x, y)) {
+            Some(fill) => fill.brightness,
+            None => self.img.brightness(x, y),
+        }
+    }
+
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        match self.rects.iter().find(|fill| fill.contains(x, y)) {
+            Some(fill) => fill.channels,
+            None => self.img.channels(x, y),
+        }
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.img.max_channel()
+    }
+}
+
+/// Returns the mean of `count` values that add up to `sum`, rounded to the
+/// nearest integer.
+#[inline]
+fn rounded_mean(sum: u64, count: u64) -> u32 {
+    ((sum + count / 2) / count) as u32
+}
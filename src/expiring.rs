@@ -0,0 +1,191 @@
+use crate::{Digest, HashIndex};
+use alloc::vec::Vec;
+
+/// An index of hashes that are forgotten after a time to live.
+///
+/// This is useful for deduplicating content seen within a rolling window,
+/// such as the pages fetched by a crawler over the past day, without the index
+/// growing forever.
+///
+/// Times are given by the caller as plain numbers in any unit, such as seconds
+/// since the Unix epoch, so the index can be used without the standard
+/// library. An entry inserted at time `now` with a time to live of `ttl` is
+/// returned by searches at any time before `now + ttl`.
+///
+/// Expired entries are skipped by searches straight away, but the memory they
+/// use is only reclaimed by eviction. Eviction happens automatically while
+/// inserting, once enough entries have been inserted since the last eviction
+/// to pay for it, and can also be triggered with [`evict`](Self::evict), such
+/// as from a timer. No background threads are used.
+///
+/// Each entry is identified by a number that is assigned when it is inserted
+/// and doesn't change when other entries are evicted.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, ExpiringIndex};
+///
+/// let mut index = ExpiringIndex::new();
+/// let a = index.insert(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f), 100, 60);
+/// let b = index.insert(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0e), 130, 60);
+///
+/// let query = Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f);
+/// assert_eq!(index.find(&query, 4, 150), [(a, 0), (b, 1)]);
+/// assert_eq!(index.find(&query, 4, 160), [(b, 1)]);
+///
+/// assert_eq!(index.evict(160), 1);
+/// assert_eq!(index.len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExpiringIndex<D> {
+    index: HashIndex<D>,
+    /// The ID and expiry time of each entry, in the order of the index.
+    entries: Vec<(u64, u64)>,
+    next_id: u64,
+    /// The earliest expiry time of any entry, or `u64::MAX` if there are none.
+    next_expiry: u64,
+    /// The number of entries inserted since the last eviction.
+    inserted: usize,
+}
+
+impl<D: Digest> ExpiringIndex<D> {
+    /// Creates an empty index.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        ExpiringIndex {
+            index: HashIndex::new(),
+            entries: Vec::new(),
+            next_id: 0,
+            next_expiry: u64::MAX,
+            inserted: 0,
+        }
+    }
+
+    /// Adds a hash to the index at the given time, to be forgotten after the
+    /// given time to live, and returns its ID.
+    ///
+    /// This may evict expired entries first.
+    pub fn insert(&mut self, hash: D, now: u64, ttl: u64) -> u64 {
+        // Evicting rebuilds the index, so only do it once the number of
+        // inserts since the last eviction is comparable to the size of the
+        // index, which keeps the cost of inserting constant on average
+        if now >= self.next_expiry && self.inserted >= self.entries.len() / 2 {
+            self.evict(now);
+        }
+
+        let id = self.next_id;
+        let expiry = now.saturating_add(ttl);
+        self.next_id += 1;
+        self.index.insert(hash);
+        self.entries.push((id, expiry));
+        self.next_expiry = self.next_expiry.min(expiry);
+        self.inserted += 1;
+        id
+    }
+
+    /// Removes every entry that has expired at the given time, and returns
+    /// the number of entries removed.
+    pub fn evict(&mut self, now: u64) -> usize {
+        self.inserted = 0;
+        if now < self.next_expiry {
+            return 0;
+        }
+
+        let before = self.entries.len();
+        let mut index = HashIndex::new();
+        let mut entries = Vec::new();
+
+        for (pos, &(id, expiry)) in self.entries.iter().enumerate() {
+            if expiry > now {
+                // Positions in the index are always valid here
+                index.insert(*self.index.get(pos).unwrap());
+                entries.push((id, expiry));
+            }
+        }
+
+        self.next_expiry = entries
+            .iter()
+            .map(|&(_, expiry)| expiry)
+            .min()
+            .unwrap_or(u64::MAX);
+        self.index = index;
+        self.entries = entries;
+
+        before - self.entries.len()
+    }
+
+    /// Returns the number of entries in the index, including any that have
+    /// expired but haven't been evicted yet.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the index is empty, including of expired entries.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the hash with the given ID, if it hasn't expired at the given
+    /// time.
+    #[must_use]
+    pub fn get(&self, id: u64, now: u64) -> Option<&D> {
+        // IDs are assigned in increasing order, and eviction preserves it
+        let pos = self.entries.binary_search_by_key(&id, |&(id, _)| id).ok()?;
+        if self.entries[pos].1 > now {
+            self.index.get(pos)
+        } else {
+            None
+        }
+    }
+
+    /// Finds every unexpired hash within the given distance of the query.
+    ///
+    /// Returns the ID of each hash and its distance from the query, sorted by
+    /// distance and then by ID.
+    #[must_use]
+    pub fn find(&self, query: &D, max_distance: u32, now: u64) -> Vec<(u64, u32)> {
+        // Positions are in the same order as IDs, so the order is unchanged
+        self.index
+            .find(query, max_distance)
+            .into_iter()
+            .filter(|&(pos, _)| self.entries[pos].1 > now)
+            .map(|(pos, dist)| (self.entries[pos].0, dist))
+            .collect()
+    }
+
+    /// Finds the unexpired hash closest to the query, if it is within the
+    /// given distance.
+    ///
+    /// If several hashes are equally close, the one inserted first is
+    /// returned.
+    #[must_use]
+    pub fn nearest(&self, query: &D, max_distance: u32, now: u64) -> Option<(u64, u32)> {
+        self.find(query, max_distance, now).into_iter().next()
+    }
+
+    /// Returns an iterator over the ID and hash of every unexpired entry, in
+    /// the order they were inserted.
+    ///
+    /// This can be used to save a snapshot of the index, for example with
+    /// [`IndexFile`](crate::IndexFile).
+    pub fn live(&self, now: u64) -> impl Iterator<Item = (u64, &D)> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(move |&(_, &(_, expiry))| expiry > now)
+            .map(move |(pos, &(id, _))| (id, self.index.get(pos).unwrap()))
+    }
+}
+
+impl<D: Digest> Default for ExpiringIndex<D> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
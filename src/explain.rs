@@ -0,0 +1,143 @@
+use crate::compare::bit;
+use crate::{Digest, Image};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A record of how each block contributed to the distance between the hashes
+/// of two images.
+///
+/// This is intended to be stored alongside decisions based on hash matches,
+/// so they can be audited later. With the `serde` feature, it can be
+/// serialized in any format supported by [Serde](https://serde.rs/).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Explanation {
+    /// The hash of the first image.
+    pub hash_a: String,
+    /// The hash of the second image.
+    pub hash_b: String,
+    /// The number of columns of blocks in the grid.
+    pub columns: u32,
+    /// The number of rows of blocks in the grid.
+    pub rows: u32,
+    /// The distance between the hashes.
+    pub distance: u32,
+    /// The blocks of the grid, in row-major order.
+    pub blocks: Vec<BlockContribution>,
+}
+
+/// A block of an [`Explanation`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlockContribution {
+    /// The column of the block.
+    pub x: u32,
+    /// The row of the block.
+    pub y: u32,
+    /// Whether the bits of the block are the same in both hashes.
+    pub agrees: bool,
+    /// The average brightness of the block in the first image, in the range
+    /// `0..=255`.
+    pub brightness_a: u8,
+    /// The average brightness of the block in the second image, in the range
+    /// `0..=255`.
+    pub brightness_b: u8,
+    /// The difference in brightness from the first image to the second.
+    pub delta: i16,
+}
+
+impl Explanation {
+    /// Returns the blocks whose bits differ between the hashes.
+    pub fn disagreements(&self) -> impl Iterator<Item = &BlockContribution> {
+        self.blocks.iter().filter(|block| !block.agrees)
+    }
+}
+
+/// Hashes two images, and records how each block contributed to the distance
+/// between them.
+///
+/// The brightness of each block is averaged over the pixels whose top-left
+/// corner lies within it, which differs slightly from the weighting used when
+/// hashing, but is close enough to show why a bit was or wasn't set. Note
+/// that a bit can differ even if the brightness of its block hasn't changed,
+/// since each bit depends on the median brightness of its band.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{explain_match, Blockhash64};
+///
+/// let a = image::open("images/example.png").unwrap();
+/// let b = a.brighten(20);
+///
+/// let explanation = explain_match::<Blockhash64, _>(&a, &b);
+/// assert_eq!(explanation.hash_a, "c7c48f8989c77e0c");
+/// assert_eq!(explanation.blocks.len(), 64);
+/// assert_eq!(
+///     explanation.disagreements().count() as u32,
+///     explanation.distance,
+/// );
+/// # }
+/// ```
+#[must_use]
+pub fn explain_match<D: Digest, I: Image>(a: &I, b: &I) -> Explanation {
+    let (hash_a, hash_b) = (D::from_image(a), D::from_image(b));
+    let (brightness_a, brightness_b) = (block_brightness::<D, I>(a), block_brightness::<D, I>(b));
+
+    let mut blocks = Vec::with_capacity(D::BITS as usize);
+    for y in 0..D::ROWS {
+        for x in 0..D::COLUMNS {
+            let idx = (y * D::COLUMNS + x) as usize;
+            let (brightness_a, brightness_b) = (brightness_a[idx], brightness_b[idx]);
+            blocks.push(BlockContribution {
+                x,
+                y,
+                agrees: bit(&hash_a, x, y) == bit(&hash_b, x, y),
+                brightness_a,
+                brightness_b,
+                delta: i16::from(brightness_b) - i16::from(brightness_a),
+            });
+        }
+    }
+
+    Explanation {
+        hash_a: hash_a.to_string(),
+        hash_b: hash_b.to_string(),
+        columns: D::COLUMNS,
+        rows: D::ROWS,
+        distance: hash_a.distance(&hash_b),
+        blocks,
+    }
+}
+
+/// Returns the average brightness of each block of an image, scaled to the
+/// range `0..=255`.
+fn block_brightness<D: Digest, I: Image>(img: &I) -> Vec<u8> {
+    let (width, height) = img.dimensions();
+    let mut sums = vec![0_u64; D::BITS as usize];
+    let mut counts = vec![0_u64; D::BITS as usize];
+
+    for y in 0..height {
+        let row = (u64::from(y) * u64::from(D::ROWS) / u64::from(height)) as u32;
+        for x in 0..width {
+            let col = (u64::from(x) * u64::from(D::COLUMNS) / u64::from(width)) as u32;
+            let idx = (row * D::COLUMNS + col) as usize;
+            sums[idx] += u64::from(img.brightness(x, y));
+            counts[idx] += 1;
+        }
+    }
+
+    let max = u64::from(img.max_brightness().max(1));
+    sums.iter()
+        .zip(&counts)
+        .map(|(&sum, &count)| match count {
+            0 => 0,
+            _ => (sum * 255 / (count * max)).min(255) as u8,
+        })
+        .collect()
+}
@@ -0,0 +1,75 @@
+use crate::{Channel, Digest, RawImage};
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// Hashes a sequence of frames stored contiguously in a single buffer.
+///
+/// Each frame is a [`RawImage`] with the given dimensions and number of
+/// channels, and the buffer must contain a whole number of frames. The frames
+/// are divided between as many threads as are available, and the hashes are
+/// returned in the same order as the frames.
+///
+/// # Panics
+///
+/// Panics if the length of the buffer isn't a multiple of the size of a frame,
+/// or if the frames are empty.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{hash_frames, Blockhash16};
+///
+/// let frames = [
+///     0_u8, 0, 255, 255, 0, 0, 255, 255, // frame 1
+///     255, 255, 0, 0, 255, 255, 0, 0, // frame 2
+/// ];
+/// let hashes: Vec<Blockhash16> = hash_frames::<_, _, 1>(&frames, 4, 2);
+///
+/// assert_eq!(hashes[0].to_string(), "3333");
+/// assert_eq!(hashes[1].to_string(), "cccc");
+/// ```
+#[must_use]
+pub fn hash_frames<D, T, const CHANNELS: usize>(data: &[T], width: u32, height: u32) -> Vec<D>
+where
+    D: Digest + Send,
+    T: Channel + Sync,
+{
+    let frame_len = width as usize * height as usize * CHANNELS;
+    assert_ne!(frame_len, 0);
+    assert_eq!(data.len() % frame_len, 0);
+
+    let num_frames = data.len() / frame_len;
+    let num_threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(num_frames);
+
+    if num_threads <= 1 {
+        return data
+            .chunks_exact(frame_len)
+            .map(|frame| D::from_image(&RawImage::<T, CHANNELS>::new(frame, width, height)))
+            .collect();
+    }
+
+    let frames_per_thread = (num_frames + num_threads - 1) / num_threads;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = data
+            .chunks(frames_per_thread * frame_len)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .chunks_exact(frame_len)
+                        .map(|frame| {
+                            D::from_image(&RawImage::<T, CHANNELS>::new(frame, width, height))
+                        })
+                        .collect::<Vec<D>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
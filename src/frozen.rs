@@ -0,0 +1,252 @@
+use crate::{Digest, HashIndex};
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// A builder for a [`FrozenIndex`] that can be filled from multiple threads.
+///
+/// Hashes are distributed between a number of shards, each of which is
+/// protected by its own lock, so threads inserting at the same time rarely
+/// wait for each other. When all the hashes have been inserted, the builder is
+/// frozen into an immutable index, with each shard built on its own thread.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, IndexBuilder};
+/// use std::thread;
+///
+/// let builder = IndexBuilder::new(4);
+///
+/// thread::scope(|scope| {
+///     for t in 0..4_u64 {
+///         let builder = &builder;
+///         scope.spawn(move || {
+///             for i in 0..100 {
+///                 let id = (t * 100 + i) as usize;
+///                 builder.insert(id, Blockhash64::from(t << 32 | i));
+///             }
+///         });
+///     }
+/// });
+///
+/// let index = builder.freeze();
+/// assert_eq!(index.len(), 400);
+///
+/// let query = Blockhash64::from(2 << 32 | 3);
+/// assert_eq!(index.nearest(&query, 0), Some((203, 0)));
+/// ```
+#[derive(Debug)]
+pub struct IndexBuilder<D> {
+    shards: Vec<Mutex<Vec<(usize, D)>>>,
+    next: AtomicUsize,
+}
+
+impl<D: Digest + Send> IndexBuilder<D> {
+    /// Creates an empty builder with the given number of shards.
+    ///
+    /// Using about as many shards as there are threads inserting hashes is
+    /// usually a good choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of shards is zero.
+    #[must_use]
+    pub fn new(shards: usize) -> Self {
+        assert_ne!(shards, 0);
+
+        IndexBuilder {
+            shards: (0..shards).map(|_| Mutex::new(Vec::new())).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds a hash to the index, identified by the given ID.
+    ///
+    /// IDs are chosen by the caller, and don't need to be unique or
+    /// contiguous.
+    pub fn insert(&self, id: usize, hash: D) {
+        let shard = self.next.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        self.shards[shard]
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push((id, hash));
+    }
+
+    /// Builds the immutable index.
+    #[must_use]
+    pub fn freeze(self) -> FrozenIndex<D> {
+        let shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.into_inner().unwrap_or_else(|err| err.into_inner()));
+
+        let trees = thread::scope(|scope| {
+            let handles: Vec<_> = shards
+                .map(|entries| scope.spawn(move || FlatTree::build(entries)))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        FrozenIndex { trees }
+    }
+}
+
+/// An immutable index of hashes that can be searched for near matches.
+///
+/// This is created by [`IndexBuilder::freeze`]. Like [`HashIndex`], it is made
+/// up of BK-trees, but each tree is stored in a few contiguous arrays rather
+/// than as separate nodes, which uses less memory and makes searches more
+/// cache-friendly.
+#[derive(Debug, Clone)]
+pub struct FrozenIndex<D> {
+    trees: Vec<FlatTree<D>>,
+}
+
+impl<D: Digest> FrozenIndex<D> {
+    /// Returns the number of hashes in the index.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trees.iter().map(|tree| tree.hashes.len()).sum()
+    }
+
+    /// Returns whether the index is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the approximate number of bytes of memory used by the index.
+    ///
+    /// See [`HashIndex::memory_usage`].
+    #[must_use]
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.trees.capacity() * size_of::<FlatTree<D>>()
+            + self.trees.iter().map(FlatTree::heap_size).sum::<usize>()
+    }
+
+    /// Finds every hash within the given distance of the query.
+    ///
+    /// Returns the ID of each hash and its distance from the query, sorted by
+    /// distance and then by ID.
+    #[must_use]
+    pub fn find(&self, query: &D, max_distance: u32) -> Vec<(usize, u32)> {
+        let mut results = Vec::new();
+        for tree in &self.trees {
+            tree.search(query, max_distance, |id, dist| results.push((id, dist)));
+        }
+        results.sort_unstable_by_key(|&(id, dist)| (dist, id));
+        results
+    }
+
+    /// Finds the hash closest to the query, if it is within the given distance.
+    ///
+    /// If several hashes are equally close, the one with the lowest ID is
+    /// returned.
+    #[must_use]
+    pub fn nearest(&self, query: &D, max_distance: u32) -> Option<(usize, u32)> {
+        let mut best: Option<(usize, u32)> = None;
+        for tree in &self.trees {
+            tree.search(query, max_distance, |id, dist| {
+                if best.map_or(true, |best| (dist, id) < (best.1, best.0)) {
+                    best = Some((id, dist));
+                }
+            });
+        }
+        best
+    }
+}
+
+/// A BK-tree stored in breadth-first order.
+#[derive(Debug, Clone)]
+struct FlatTree<D> {
+    hashes: Vec<D>,
+    ids: Vec<usize>,
+    /// The range of `edges` for the children of each node.
+    ranges: Vec<(u32, u32)>,
+    /// The distance to each child, and the position of the child.
+    edges: Vec<(u32, u32)>,
+}
+
+impl<D: Digest> FlatTree<D> {
+    fn build(entries: Vec<(usize, D)>) -> Self {
+        assert!(
+            entries.len() <= u32::MAX as usize,
+            "too many hashes in shard"
+        );
+
+        let index: HashIndex<D> = entries.iter().map(|&(_, hash)| hash).collect();
+
+        let mut tree = FlatTree {
+            hashes: Vec::with_capacity(entries.len()),
+            ids: Vec::with_capacity(entries.len()),
+            ranges: Vec::with_capacity(entries.len()),
+            edges: Vec::with_capacity(entries.len().saturating_sub(1)),
+        };
+
+        if entries.is_empty() {
+            return tree;
+        }
+
+        // Nodes are numbered in the order they are added to the queue, so the
+        // children of each node are numbered consecutively.
+        let mut queue = VecDeque::from([0]);
+        let mut queued = 1;
+
+        while let Some(old) = queue.pop_front() {
+            let (hash, children) = index.node(old);
+            tree.hashes.push(*hash);
+            tree.ids.push(entries[old].0);
+
+            let start = tree.edges.len() as u32;
+            for &(dist, child) in children {
+                tree.edges.push((dist, queued));
+                queue.push_back(child);
+                queued += 1;
+            }
+            tree.ranges.push((start, tree.edges.len() as u32));
+        }
+
+        tree
+    }
+
+    /// Returns the number of bytes allocated for the arrays of the tree.
+    fn heap_size(&self) -> usize {
+        self.hashes.capacity() * size_of::<D>()
+            + self.ids.capacity() * size_of::<usize>()
+            + self.ranges.capacity() * size_of::<(u32, u32)>()
+            + self.edges.capacity() * size_of::<(u32, u32)>()
+    }
+
+    fn search<F: FnMut(usize, u32)>(&self, query: &D, max_distance: u32, mut f: F) {
+        if self.hashes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![0_u32];
+
+        while let Some(node) = stack.pop() {
+            let node = node as usize;
+            let dist = self.hashes[node].distance(query);
+            if dist <= max_distance {
+                f(self.ids[node], dist);
+            }
+
+            let range = dist.saturating_sub(max_distance)..=dist.saturating_add(max_distance);
+            let (start, end) = self.ranges[node];
+            stack.extend(
+                self.edges[(start as usize)..(end as usize)]
+                    .iter()
+                    .filter(|(d, _)| range.contains(d))
+                    .map(|&(_, child)| child),
+            );
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Digest, Image};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The result of checking an output against a baseline with a [`DiffGate`].
+///
+/// With the `serde` feature, it can be serialized in any format supported by
+/// [Serde](https://serde.rs/), to be stored in build reports.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Verdict {
+    /// The output is within the identical distance of the baseline.
+    Identical {
+        /// The distance between the hashes.
+        distance: u32,
+    },
+    /// The output differs from the baseline, but is within the acceptable
+    /// distance.
+    Drift {
+        /// The distance between the hashes.
+        distance: u32,
+    },
+    /// The output differs from the baseline by more than the acceptable
+    /// distance.
+    Fail {
+        /// The distance between the hashes.
+        distance: u32,
+    },
+}
+
+impl Verdict {
+    /// Returns the distance between the output and the baseline.
+    #[inline]
+    #[must_use]
+    pub fn distance(&self) -> u32 {
+        match *self {
+            Verdict::Identical { distance }
+            | Verdict::Drift { distance }
+            | Verdict::Fail { distance } => distance,
+        }
+    }
+
+    /// Returns whether the output passed the gate, either because it is
+    /// identical or because its drift is acceptable.
+    #[inline]
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        !matches!(self, Verdict::Fail { .. })
+    }
+}
+
+/// Compares rendered outputs against baseline hashes, and decides whether
+/// each output is identical, has drifted acceptably, or has failed.
+///
+/// Only the hash of each baseline needs to be stored, rather than the image
+/// itself, so this suits pipelines that produce many large outputs, such as
+/// render farms checking frames after an update to a renderer or its
+/// drivers. Outputs within the identical distance are identical, and outputs
+/// within the acceptable distance have drifted, which is usually worth
+/// reporting but not blocking on. Anything further fails.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, DiffGate, Verdict};
+///
+/// let gate = DiffGate::new(6).identical_distance(1);
+/// let baseline = Blockhash64::from(0xc7c4_8f89_89c7_7e0c);
+///
+/// assert_eq!(
+///     gate.check(&baseline, &Blockhash64::from(0xc7c4_8f89_89c7_7e0d)),
+///     Verdict::Identical { distance: 1 },
+/// );
+/// assert_eq!(
+///     gate.check(&baseline, &Blockhash64::from(0xc7c4_8f89_89c7_7e3f)),
+///     Verdict::Drift { distance: 4 },
+/// );
+///
+/// let verdict = gate.check(&baseline, &Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f));
+/// assert!(!verdict.passed());
+/// assert_eq!(verdict.distance(), 24);
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct DiffGate {
+    identical_distance: u32,
+    acceptable_distance: u32,
+}
+
+impl DiffGate {
+    /// Creates a gate with the given acceptable distance.
+    ///
+    /// Only outputs with the same hash as the baseline are identical, unless
+    /// the identical distance is changed with
+    /// [`identical_distance`](Self::identical_distance).
+    #[inline]
+    #[must_use]
+    pub fn new(acceptable_distance: u32) -> Self {
+        DiffGate {
+            identical_distance: 0,
+            acceptable_distance,
+        }
+    }
+
+    /// Sets the maximum distance for an output to be considered identical to
+    /// the baseline.
+    ///
+    /// This allows for noise that is expected between runs, such as from
+    /// dithering or nondeterministic sampling. It should be no greater than
+    /// the acceptable distance. If it is greater, outputs within the
+    /// identical distance are still identical.
+    #[inline]
+    #[must_use]
+    pub fn identical_distance(mut self, distance: u32) -> Self {
+        self.identical_distance = distance;
+        self
+    }
+
+    /// Compares the hash of an output against the hash of its baseline.
+    #[must_use]
+    pub fn check<D: Digest>(&self, baseline: &D, output: &D) -> Verdict {
+        let distance = baseline.distance(output);
+        if distance <= self.identical_distance {
+            Verdict::Identical { distance }
+        } else if distance <= self.acceptable_distance {
+            Verdict::Drift { distance }
+        } else {
+            Verdict::Fail { distance }
+        }
+    }
+
+    /// Hashes an output image, and compares it against the hash of its
+    /// baseline.
+    #[inline]
+    #[must_use]
+    pub fn check_image<D: Digest, I: Image>(&self, baseline: &D, img: &I) -> Verdict {
+        self.check(baseline, &D::from_image(img))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::Image;
+
+/// An image of the local contrast of another image, which makes hashes
+/// tolerant of uneven changes in illumination.
+///
+/// A hash compares the average brightness of each block against the median,
+/// so it is unaffected by changes in brightness that are uniform across the
+/// image, but not by changes that vary across it, such as a moving shadow,
+/// the falloff of a flash, or the sun coming out over part of a scene. This
+/// is common in the frames of fixed cameras, such as wildlife camera traps,
+/// where the same scene can otherwise hash differently as the light changes.
+///
+/// The brightness of each pixel of this adapter is the largest difference
+/// between the neighbors of the pixel in either direction, relative to their
+/// sum. Flat areas are dark, and edges and textures are bright, regardless of
+/// how brightly they are lit. Hashes of this adapter can only be compared
+/// with other hashes of this adapter.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, Gradient};
+///
+/// let img = image::open("images/example.png").unwrap();
+///
+/// // A shadow falls over the left half of the image
+/// let mut shaded = img.to_rgb8();
+/// for (x, _, pixel) in shaded.enumerate_pixels_mut() {
+///     if x < 160 {
+///         pixel.0 = pixel.0.map(|val| val / 2);
+///     }
+/// }
+///
+/// assert!(blockhash64(&img).distance(&blockhash64(&shaded)) > 16);
+/// assert_eq!(
+///     blockhash64(&Gradient::new(&img)),
+///     blockhash64(&Gradient::new(&shaded)),
+/// );
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Gradient<'a, I> {
+    img: &'a I,
+}
+
+impl<'a, I: Image> Gradient<'a, I> {
+    /// Creates a gradient view of an image.
+    #[inline]
+    #[must_use]
+    pub fn new(img: &'a I) -> Self {
+        Gradient { img }
+    }
+}
+
+impl<I: Image> Image for Gradient<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let (width, height) = self.img.dimensions();
+        let max = u64::from(self.img.max_brightness());
+        // Keeps noise in the darkest areas from being amplified
+        let floor = max / 32 + 1;
+
+        // Neighbors outside the image are replaced by the pixel itself
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+        let (top, bottom) = (y.saturating_sub(1), (y + 1).min(height - 1));
+
+        let contrast = |a: u32, b: u32| {
+            let (a, b) = (u64::from(a), u64::from(b));
+            (a.abs_diff(b) * max / (a + b + floor)) as u32
+        };
+        let dx = contrast(self.img.brightness(left, y), self.img.brightness(right, y));
+        let dy = contrast(self.img.brightness(x, top), self.img.brightness(x, bottom));
+        dx.max(dy)
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+}
@@ -1,5 +1,88 @@
+use crate::order::flip;
 use crate::Image;
 
+/// The strategy used to sum the brightness of the pixels in each block.
+///
+/// Each strategy produces the same block values, but the more specialized
+/// strategies are faster. The fastest strategy that supports the dimensions of
+/// the image is chosen automatically.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Accumulation {
+    /// Each pixel belongs to exactly one block.
+    ///
+    /// This requires the width and height of the image to be multiples of the
+    /// number of blocks in each direction.
+    Aligned,
+    /// Each pixel is split between at most two blocks in each direction.
+    ///
+    /// This requires the image to be at least as large as the number of blocks
+    /// in each direction.
+    Larger,
+    /// Each pixel can be split between any number of blocks.
+    ///
+    /// This supports images of any size.
+    Generic,
+}
+
+impl Accumulation {
+    /// Returns the fastest strategy that supports the given dimensions.
+    pub(crate) fn select(width: u32, height: u32, bits: u32) -> Self {
+        Self::select_grid(width, height, bits, bits)
+    }
+
+    /// Returns the fastest strategy that supports the given dimensions, for a
+    /// grid of `columns` by `rows` blocks.
+    pub(crate) fn select_grid(width: u32, height: u32, columns: u32, rows: u32) -> Self {
+        if width % columns == 0 && height % rows == 0 {
+            Accumulation::Aligned
+        } else if width >= columns && height >= rows {
+            Accumulation::Larger
+        } else {
+            Accumulation::Generic
+        }
+    }
+
+    /// Returns whether this strategy supports the given dimensions.
+    pub(crate) fn supports(self, width: u32, height: u32, bits: u32) -> bool {
+        self.supports_grid(width, height, bits, bits)
+    }
+
+    /// Returns whether this strategy supports the given dimensions, for a
+    /// grid of `columns` by `rows` blocks.
+    pub(crate) fn supports_grid(self, width: u32, height: u32, columns: u32, rows: u32) -> bool {
+        match self {
+            Accumulation::Aligned => width % columns == 0 && height % rows == 0,
+            Accumulation::Larger => width >= columns && height >= rows,
+            Accumulation::Generic => true,
+        }
+    }
+}
+
+/// The method used to choose the threshold for the bits of each band.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Threshold {
+    /// Each bit is set if its block is brighter than the median of the band,
+    /// so about half of the bits of each band are set.
+    ///
+    /// This is the method used by the reference implementation.
+    #[default]
+    Median,
+    /// The threshold is chosen with [Otsu's method], which splits the blocks
+    /// of each band into the two groups with the least variance within each
+    /// group, so the bits separate light and dark areas rather than being
+    /// split evenly.
+    ///
+    /// This suits images with two distinct levels of brightness, such as
+    /// scanned documents. If every block in a band is equally bright, the
+    /// bits are set if the blocks are brighter than half of the maximum
+    /// brightness. The threshold is chosen using floating-point arithmetic,
+    /// so in rare cases the hash of an image can differ between platforms.
+    ///
+    /// [Otsu's method]: https://en.wikipedia.org/wiki/Otsu%27s_method
+    Otsu,
+}
+
 pub(crate) fn blockhash<
     I: Image,
     const BITS: u32,
@@ -8,38 +91,238 @@ pub(crate) fn blockhash<
 >(
     img: &I,
 ) -> [u8; DIGEST_SIZE] {
-    debug_assert_eq!(BITS % 4, 0);
+    let (width, height) = img.dimensions();
+    let accumulation = Accumulation::select(width, height, BITS);
+    blockhash_with::<I, BITS, NUM_BLOCKS, DIGEST_SIZE>(img, accumulation, 4, Threshold::Median)
+}
+
+pub(crate) fn blockhash_with<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+    accumulation: Accumulation,
+    bands: u32,
+    threshold: Threshold,
+) -> [u8; DIGEST_SIZE] {
+    debug_assert_eq!(BITS % 2, 0);
     debug_assert_ne!(BITS, 0);
 
     let (width, height) = img.dimensions();
+    let values = get_values::<I, BITS, NUM_BLOCKS>(img, accumulation);
+
+    convert_to_bits(
+        width,
+        height,
+        &values,
+        img.max_brightness(),
+        bands,
+        threshold,
+    )
+}
 
-    let values = if width % BITS == 0 && height % BITS == 0 {
-        get_values_aligned::<I, BITS, NUM_BLOCKS>(img)
-    } else if width >= BITS && height >= BITS {
-        get_values_larger::<I, BITS, NUM_BLOCKS>(img)
-    } else {
-        get_values_generic::<I, BITS, NUM_BLOCKS>(img)
+/// Computes a hash where each block overlaps its neighbors by half of its
+/// width and height.
+///
+/// The image is divided into a grid with one more cell in each direction than
+/// there are blocks, and each block is the sum of 2 by 2 cells, so the values
+/// are exact.
+pub(crate) fn blockhash_overlapping<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+    accumulation: Accumulation,
+    bands: u32,
+    threshold: Threshold,
+) -> [u8; DIGEST_SIZE] {
+    let values = match BITS {
+        4 => get_overlapping_values::<I, BITS, NUM_BLOCKS, 5, 25>(img, accumulation),
+        8 => get_overlapping_values::<I, BITS, NUM_BLOCKS, 9, 81>(img, accumulation),
+        12 => get_overlapping_values::<I, BITS, NUM_BLOCKS, 13, 169>(img, accumulation),
+        16 => get_overlapping_values::<I, BITS, NUM_BLOCKS, 17, 289>(img, accumulation),
+        32 => get_overlapping_values::<I, BITS, NUM_BLOCKS, 33, 1089>(img, accumulation),
+        _ => unreachable!("unsupported grid size"),
     };
 
-    convert_to_bits(width, height, &values, I::MAX_BRIGHTNESS)
+    // Each block covers 4 cells rather than 1, so the values of a white block
+    // are 4 times those of a white cell
+    let (width, height) = img.dimensions();
+    convert_to_bits(
+        width * 2,
+        height * 2,
+        &values,
+        img.max_brightness(),
+        bands,
+        threshold,
+    )
 }
 
-fn get_values_aligned<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
+/// Returns the total brightness of each block in a grid of `BITS` by `BITS`
+/// overlapping blocks, from a grid of `CELLS` by `CELLS` cells.
+fn get_overlapping_values<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const CELLS: u32,
+    const NUM_CELLS: usize,
+>(
     img: &I,
+    accumulation: Accumulation,
 ) -> [u64; NUM_BLOCKS] {
-    // These values are related, but need to be passed in separately due to
-    // limitations with const generics.
-    debug_assert_eq!(NUM_BLOCKS, (BITS * BITS) as usize);
+    debug_assert_eq!(CELLS, BITS + 1);
+
+    let cells = get_grid_values::<I, CELLS, CELLS, NUM_CELLS>(img, accumulation);
+    let mut values = [0_u64; NUM_BLOCKS];
+
+    for y in 0..BITS as usize {
+        for x in 0..BITS as usize {
+            let top = y * CELLS as usize + x;
+            let bottom = top + CELLS as usize;
+            values[y * BITS as usize + x] =
+                cells[top] + cells[top + 1] + cells[bottom] + cells[bottom + 1];
+        }
+    }
 
+    values
+}
+
+/// Computes a hash in the same way as the quick mode of the reference
+/// implementation.
+///
+/// Each block is a whole number of pixels, and any pixels to the right of or
+/// below the last block are ignored. This requires the image to be at least as
+/// large as the number of blocks in each direction.
+pub(crate) fn blockhash_quick<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+    bands: u32,
+    threshold: Threshold,
+) -> [u8; DIGEST_SIZE] {
     let (width, height) = img.dimensions();
     let block_width = width / BITS;
     let block_height = height / BITS;
+    debug_assert!(block_width > 0 && block_height > 0);
+
+    let mut values = [0_u64; NUM_BLOCKS];
+
+    for y in 0..(block_height * BITS) {
+        let idx_row = (y / block_height * BITS) as usize;
+        for x in 0..(block_width * BITS) {
+            let idx_x = (x / block_width) as usize;
+            values[idx_row + idx_x] += u64::from(img.brightness(x, y));
+        }
+    }
+
+    convert_to_bits(
+        block_width,
+        block_height,
+        &values,
+        img.max_brightness(),
+        bands,
+        threshold,
+    )
+}
+
+/// Returns the smallest of a hash and its horizontal, vertical, and combined
+/// mirror images, so that an image and its mirror images hash to the same
+/// value.
+pub(crate) fn canonicalize_mirroring<const SIZE: usize>(
+    bytes: [u8; SIZE],
+    bits: u32,
+) -> [u8; SIZE] {
+    let h = flip(&bytes, bits, bits, true, false);
+    let v = flip(&bytes, bits, bits, false, true);
+    let hv = flip(&bytes, bits, bits, true, true);
+    bytes.min(h).min(v).min(hv)
+}
+
+/// Computes an average hash, where each bit is set if the block is brighter
+/// than the mean of all blocks.
+pub(crate) fn ahash<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+) -> [u8; DIGEST_SIZE] {
+    // These values are related, but need to be passed in separately due to
+    // limitations with const generics.
+    debug_assert_eq!(NUM_BLOCKS, DIGEST_SIZE * 8);
+
+    let (width, height) = img.dimensions();
+    let accumulation = Accumulation::select(width, height, BITS);
+    let values = get_values::<I, BITS, NUM_BLOCKS>(img, accumulation);
+
+    // Compare each value against the mean without dividing, to avoid rounding
+    let sum: u128 = values.iter().map(|&val| u128::from(val)).sum();
+
+    let mut res = [0_u8; DIGEST_SIZE];
+
+    for (i, &val) in values.iter().enumerate() {
+        if u128::from(val) * NUM_BLOCKS as u128 > sum {
+            res[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+
+    res
+}
+
+/// Returns the total brightness of each block in a grid of `BITS` by `BITS`
+/// blocks, scaled by the number of blocks.
+pub(crate) fn get_values<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
+    img: &I,
+    accumulation: Accumulation,
+) -> [u64; NUM_BLOCKS] {
+    get_grid_values::<I, BITS, BITS, NUM_BLOCKS>(img, accumulation)
+}
+
+/// Returns the total brightness of each block in a grid of `COLUMNS` by
+/// `ROWS` blocks, scaled by the number of blocks.
+pub(crate) fn get_grid_values<
+    I: Image,
+    const COLUMNS: u32,
+    const ROWS: u32,
+    const NUM_BLOCKS: usize,
+>(
+    img: &I,
+    accumulation: Accumulation,
+) -> [u64; NUM_BLOCKS] {
+    let (width, height) = img.dimensions();
+    debug_assert!(accumulation.supports_grid(width, height, COLUMNS, ROWS));
+
+    match accumulation {
+        Accumulation::Aligned => get_values_aligned::<I, COLUMNS, ROWS, NUM_BLOCKS>(img),
+        Accumulation::Larger => get_values_larger::<I, COLUMNS, ROWS, NUM_BLOCKS>(img),
+        Accumulation::Generic => get_values_generic::<I, COLUMNS, ROWS, NUM_BLOCKS>(img),
+    }
+}
+
+fn get_values_aligned<I: Image, const COLUMNS: u32, const ROWS: u32, const NUM_BLOCKS: usize>(
+    img: &I,
+) -> [u64; NUM_BLOCKS] {
+    // These values are related, but need to be passed in separately due to
+    // limitations with const generics.
+    debug_assert_eq!(NUM_BLOCKS, (COLUMNS * ROWS) as usize);
+
+    let (width, height) = img.dimensions();
+    let block_width = width / COLUMNS;
+    let block_height = height / ROWS;
 
     let mut values = [0_u64; NUM_BLOCKS];
 
     for y in 0..height {
         let block_y = y / block_height;
-        let idx_row = (block_y * BITS) as usize;
+        let idx_row = (block_y * COLUMNS) as usize;
 
         for x in 0..width {
             let block_x = x / block_width;
@@ -54,12 +337,12 @@ fn get_values_aligned<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
     values
 }
 
-fn get_values_larger<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
+fn get_values_larger<I: Image, const COLUMNS: u32, const ROWS: u32, const NUM_BLOCKS: usize>(
     img: &I,
 ) -> [u64; NUM_BLOCKS] {
     // These values are related, but need to be passed in separately due to
     // limitations with const generics.
-    debug_assert_eq!(NUM_BLOCKS, (BITS * BITS) as usize);
+    debug_assert_eq!(NUM_BLOCKS, (COLUMNS * ROWS) as usize);
 
     let (width, height) = img.dimensions();
     let (width, height) = (u64::from(width), u64::from(height));
@@ -69,22 +352,22 @@ fn get_values_larger<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
     let mut block_top;
     let mut block_bottom = 0;
 
-    let mut weight_top = u64::from(BITS);
+    let mut weight_top = u64::from(ROWS);
     let mut weight_bottom = 0;
 
     for y in 0..height {
         block_top = block_bottom;
 
-        let end_y = (y + 1) * u64::from(BITS) % height;
-        if end_y < u64::from(BITS) {
+        let end_y = (y + 1) * u64::from(ROWS) % height;
+        if end_y < u64::from(ROWS) {
             block_bottom += 1;
-            weight_top = u64::from(BITS) - end_y;
+            weight_top = u64::from(ROWS) - end_y;
             weight_bottom = end_y;
         }
 
-        let idx_top = (block_top * BITS) as usize;
-        let idx_bottom = if block_bottom < BITS {
-            (block_bottom * BITS) as usize
+        let idx_top = (block_top * COLUMNS) as usize;
+        let idx_bottom = if block_bottom < ROWS {
+            (block_bottom * COLUMNS) as usize
         } else {
             0 // to avoid out-of-bounds access (the weight will be zero)
         };
@@ -92,21 +375,21 @@ fn get_values_larger<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
         let mut block_left;
         let mut block_right = 0;
 
-        let mut weight_left = u64::from(BITS);
+        let mut weight_left = u64::from(COLUMNS);
         let mut weight_right = 0;
 
         for x in 0..width {
             block_left = block_right;
 
-            let end_x = (x + 1) * u64::from(BITS) % width;
-            if end_x < u64::from(BITS) {
+            let end_x = (x + 1) * u64::from(COLUMNS) % width;
+            if end_x < u64::from(COLUMNS) {
                 block_right += 1;
-                weight_left = u64::from(BITS) - end_x;
+                weight_left = u64::from(COLUMNS) - end_x;
                 weight_right = end_x;
             }
 
             let idx_left = block_left as usize;
-            let idx_right = if block_right < BITS {
+            let idx_right = if block_right < COLUMNS {
                 block_right as usize
             } else {
                 0 // to avoid out-of-bounds access (the weight will be zero)
@@ -124,12 +407,12 @@ fn get_values_larger<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
     values
 }
 
-fn get_values_generic<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
+fn get_values_generic<I: Image, const COLUMNS: u32, const ROWS: u32, const NUM_BLOCKS: usize>(
     img: &I,
 ) -> [u64; NUM_BLOCKS] {
     // These values are related, but need to be passed in separately due to
     // limitations with const generics.
-    debug_assert_eq!(NUM_BLOCKS, (BITS * BITS) as usize);
+    debug_assert_eq!(NUM_BLOCKS, (COLUMNS * ROWS) as usize);
 
     let (width, height) = img.dimensions();
     let (width, height) = (u64::from(width), u64::from(height));
@@ -139,22 +422,22 @@ fn get_values_generic<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
     let mut block_top;
     let mut block_bottom = 0;
 
-    let mut weight_top = u64::from(BITS);
+    let mut weight_top = u64::from(ROWS);
     let mut weight_bottom = 0;
 
     for y in 0..height {
         block_top = block_bottom;
 
-        let end_y = (y + 1) * u64::from(BITS) % height;
-        if end_y < u64::from(BITS) {
-            block_bottom = (y + 1) * u64::from(BITS) / height;
-            weight_top = (u64::from(BITS) - 1 - end_y) % height + 1;
+        let end_y = (y + 1) * u64::from(ROWS) % height;
+        if end_y < u64::from(ROWS) {
+            block_bottom = (y + 1) * u64::from(ROWS) / height;
+            weight_top = (u64::from(ROWS) - 1 - end_y) % height + 1;
             weight_bottom = end_y;
         }
 
-        let idx_top = (block_top * u64::from(BITS)) as usize;
-        let idx_bottom = if block_bottom < u64::from(BITS) {
-            (block_bottom * u64::from(BITS)) as usize
+        let idx_top = (block_top * u64::from(COLUMNS)) as usize;
+        let idx_bottom = if block_bottom < u64::from(ROWS) {
+            (block_bottom * u64::from(COLUMNS)) as usize
         } else {
             0 // to avoid out-of-bounds access (the weight will be zero)
         };
@@ -162,21 +445,21 @@ fn get_values_generic<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
         let mut block_left;
         let mut block_right = 0;
 
-        let mut weight_left = u64::from(BITS);
+        let mut weight_left = u64::from(COLUMNS);
         let mut weight_right = 0;
 
         for x in 0..width {
             block_left = block_right;
 
-            let end_x = (x + 1) * u64::from(BITS) % width;
-            if end_x < u64::from(BITS) {
-                block_right = (x + 1) * u64::from(BITS) / width;
-                weight_left = (u64::from(BITS) - 1 - end_x) % width + 1;
+            let end_x = (x + 1) * u64::from(COLUMNS) % width;
+            if end_x < u64::from(COLUMNS) {
+                block_right = (x + 1) * u64::from(COLUMNS) / width;
+                weight_left = (u64::from(COLUMNS) - 1 - end_x) % width + 1;
                 weight_right = end_x;
             }
 
             let idx_left = block_left as usize;
-            let idx_right = if block_right < u64::from(BITS) {
+            let idx_right = if block_right < u64::from(COLUMNS) {
                 block_right as usize
             } else {
                 0 // to avoid out-of-bounds access (the weight will be zero)
@@ -196,14 +479,14 @@ fn get_values_generic<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
             }
 
             for by in (block_top + 1)..block_bottom {
-                let idx_y = (by * u64::from(BITS)) as usize;
+                let idx_y = (by * u64::from(COLUMNS)) as usize;
                 values[idx_y + idx_left] += brightness * height * weight_left;
                 values[idx_y + idx_right] += brightness * height * weight_right;
             }
 
             let full_value = brightness * width * height;
             for by in (block_top + 1)..block_bottom {
-                let idx_y = (by * u64::from(BITS)) as usize;
+                let idx_y = (by * u64::from(COLUMNS)) as usize;
                 for bx in (block_left + 1)..block_right {
                     let idx_x = bx as usize;
                     values[idx_y + idx_x] += full_value;
@@ -215,32 +498,45 @@ fn get_values_generic<I: Image, const BITS: u32, const NUM_BLOCKS: usize>(
     values
 }
 
-fn convert_to_bits<const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>(
+pub(crate) fn convert_to_bits<const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>(
     width: u32,
     height: u32,
     values: &[u64; NUM_BLOCKS],
     max_value: u32,
+    bands: u32,
+    threshold: Threshold,
 ) -> [u8; DIGEST_SIZE] {
     // These values are related, but need to be passed in separately due to
-    // limitations with const generics.
-    debug_assert_eq!(NUM_BLOCKS, DIGEST_SIZE * 8);
+    // limitations with const generics. The last byte is padded with zeros if
+    // the number of blocks isn't a multiple of 8.
+    debug_assert_eq!((NUM_BLOCKS + 7) / 8, DIGEST_SIZE);
+    debug_assert_eq!(NUM_BLOCKS % bands as usize, 0);
 
-    let band_size: usize = NUM_BLOCKS / 4;
+    let band_size: usize = NUM_BLOCKS / bands as usize;
     let half_value = u64::from(max_value) * u64::from(width) * u64::from(height) / 2;
 
-    let mut bands = *values;
+    let mut sorted = *values;
     let mut bits = [0_u8; NUM_BLOCKS];
 
-    for i in 0..4 {
-        let offset = i * band_size;
-
-        let band = &mut bands[offset..(offset + band_size)];
+    for offset in (0..NUM_BLOCKS).step_by(band_size) {
+        let band = &mut sorted[offset..(offset + band_size)];
         band.sort_unstable();
 
-        let median = (band[band_size / 2 - 1] + band[band_size / 2]) / 2;
+        // Blocks equal to the threshold are set if they are brighter than
+        // half of the maximum brightness
+        let (threshold, compare_ties) = match threshold {
+            Threshold::Median if band_size % 2 == 0 => {
+                ((band[band_size / 2 - 1] + band[band_size / 2]) / 2, true)
+            }
+            Threshold::Median => (band[band_size / 2], true),
+            Threshold::Otsu => match otsu(band) {
+                Some(threshold) => (threshold, false),
+                None => (band[0], true),
+            },
+        };
 
         for (n, &val) in values.iter().enumerate().skip(offset).take(band_size) {
-            if val > median || (val == median && val > half_value) {
+            if val > threshold || (compare_ties && val == threshold && val > half_value) {
                 bits[n] = 1;
             } else {
                 bits[n] = 0;
@@ -255,7 +551,41 @@ fn convert_to_bits<const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>(
             res[i] <<= 1;
             res[i] |= bit;
         }
+        res[i] <<= 8 - octet.len();
     }
 
     res
 }
+
+/// Chooses a threshold for a sorted band of values with Otsu's method.
+///
+/// Returns the largest value in the darker group, or `None` if every value is
+/// the same.
+fn otsu(sorted: &[u64]) -> Option<u64> {
+    let n = sorted.len() as f64;
+    let total: f64 = sorted.iter().map(|&v| v as f64).sum();
+
+    let mut best = None;
+    let mut best_variance = 0.0;
+    let mut sum = 0.0;
+
+    for k in 1..sorted.len() {
+        sum += sorted[k - 1] as f64;
+        if sorted[k] == sorted[k - 1] {
+            continue;
+        }
+
+        // The between-class variance, up to a constant factor
+        let n0 = k as f64;
+        let n1 = n - n0;
+        let diff = sum / n0 - (total - sum) / n1;
+        let variance = n0 * n1 * diff * diff;
+
+        if variance > best_variance {
+            best_variance = variance;
+            best = Some(sorted[k - 1]);
+        }
+    }
+
+    best
+}
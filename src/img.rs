@@ -1,12 +1,93 @@
-use crate::Image;
-use image::{GenericImageView, Luma, LumaA, Rgb, Rgba};
+use crate::{blockhash1024, blockhash144, blockhash16, blockhash256, blockhash64};
+use crate::{Blockhash1024, Blockhash144, Blockhash16, Blockhash256, Blockhash64};
+use crate::{Channel, Image, Region};
+use image::{DynamicImage, GenericImageView, LumaA, Pixel, Rgba, SubImage};
+
+/// Calls a hash function with the concrete image buffer inside a
+/// [`DynamicImage`], if it has 8-bit channels.
+///
+/// Reading a pixel of any other buffer through the [`DynamicImage`] rounds it
+/// to 8 bits, so hashing the buffer itself could give a different hash.
+macro_rules! dispatch {
+    ($func:ident, $img:expr) => {
+        match $img {
+            DynamicImage::ImageLuma8(img) => $func(img),
+            DynamicImage::ImageLumaA8(img) => $func(img),
+            DynamicImage::ImageRgb8(img) => $func(img),
+            DynamicImage::ImageRgba8(img) => $func(img),
+            img => $func(img),
+        }
+    };
+}
+
+/// Generates a 16-bit perceptual hash of a [`DynamicImage`].
+///
+/// A [`DynamicImage`] can be hashed directly with [`blockhash16`], but then
+/// every pixel is converted to 8-bit RGBA as it is read. This function instead
+/// checks the type of the image once and, if it has 8-bit channels, hashes the
+/// underlying image buffer, which is faster. The hash is always the same as
+/// the one from [`blockhash16`].
+///
+/// Other images are still converted to 8 bits. To use the full precision of a
+/// 16-bit or floating-point image, hash its buffer instead, for example the
+/// one returned by [`DynamicImage::as_rgb16`].
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::blockhash16_dynamic;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = blockhash16_dynamic(&img);
+///
+/// assert_eq!(hash.to_string(), "a396");
+/// ```
+#[must_use]
+pub fn blockhash16_dynamic(img: &DynamicImage) -> Blockhash16 {
+    dispatch!(blockhash16, img)
+}
+
+/// Generates a 64-bit perceptual hash of a [`DynamicImage`].
+///
+/// See [`blockhash16_dynamic`] for how this differs from [`blockhash64`].
+#[must_use]
+pub fn blockhash64_dynamic(img: &DynamicImage) -> Blockhash64 {
+    dispatch!(blockhash64, img)
+}
+
+/// Generates a 144-bit perceptual hash of a [`DynamicImage`].
+///
+/// See [`blockhash16_dynamic`] for how this differs from [`blockhash144`].
+#[must_use]
+pub fn blockhash144_dynamic(img: &DynamicImage) -> Blockhash144 {
+    dispatch!(blockhash144, img)
+}
+
+/// Generates a 256-bit perceptual hash of a [`DynamicImage`].
+///
+/// See [`blockhash16_dynamic`] for how this differs from [`blockhash256`].
+#[must_use]
+pub fn blockhash256_dynamic(img: &DynamicImage) -> Blockhash256 {
+    dispatch!(blockhash256, img)
+}
+
+/// Generates a 1024-bit perceptual hash of a [`DynamicImage`].
+///
+/// See [`blockhash16_dynamic`] for how this differs from [`blockhash1024`].
+#[must_use]
+pub fn blockhash1024_dynamic(img: &DynamicImage) -> Blockhash1024 {
+    dispatch!(blockhash1024, img)
+}
 
 impl<T, P> Image for T
 where
     T: GenericImageView<Pixel = P>,
     P: PixelExt,
 {
-    const MAX_BRIGHTNESS: u32 = P::MAX_BRIGHTNESS;
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        P::MAX_BRIGHTNESS
+    }
 
     #[inline]
     fn dimensions(&self) -> (u32, u32) {
@@ -17,105 +98,95 @@ where
     fn brightness(&self, x: u32, y: u32) -> u32 {
         PixelExt::brightness(self.get_pixel(x, y))
     }
-}
-
-/// Extension trait for [`image`] pixel types.
-trait PixelExt: Copy {
-    /// The maximum possible brightness for a pixel.
-    const MAX_BRIGHTNESS: u32;
-
-    /// Returns the brightness of the pixel, in the range `0..=MAX_BRIGHTNESS`.
-    fn brightness(self) -> u32;
-}
-
-impl PixelExt for Luma<u8> {
-    const MAX_BRIGHTNESS: u32 = u8::MAX as u32;
-
-    #[inline]
-    fn brightness(self) -> u32 {
-        let Self([y]) = self;
-        u32::from(y)
-    }
-}
-
-impl PixelExt for Luma<u16> {
-    const MAX_BRIGHTNESS: u32 = u16::MAX as u32;
 
     #[inline]
-    fn brightness(self) -> u32 {
-        let Self([y]) = self;
-        u32::from(y)
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        PixelExt::channels(self.get_pixel(x, y))
     }
-}
-
-impl PixelExt for LumaA<u8> {
-    const MAX_BRIGHTNESS: u32 = u8::MAX as u32;
 
     #[inline]
-    fn brightness(self) -> u32 {
-        let Self([y, a]) = self;
-        match a {
-            0 => Self::MAX_BRIGHTNESS,
-            _ => u32::from(y),
-        }
+    fn max_channel(&self) -> u32 {
+        P::MAX_CHANNEL
     }
 }
 
-impl PixelExt for LumaA<u16> {
-    const MAX_BRIGHTNESS: u32 = u16::MAX as u32;
-
+impl<'a, T> From<&'a SubImage<&'a T>> for Region<'a, T>
+where
+    T: GenericImageView,
+    T: Image,
+{
     #[inline]
-    fn brightness(self) -> u32 {
-        let Self([y, a]) = self;
-        match a {
-            0 => Self::MAX_BRIGHTNESS,
-            _ => u32::from(y),
-        }
+    fn from(view: &'a SubImage<&'a T>) -> Self {
+        let (x, y) = view.offsets();
+        let (width, height) = GenericImageView::dimensions(&**view);
+        Region::new(view.inner(), x, y, width, height)
     }
 }
 
-impl PixelExt for Rgb<u8> {
-    const MAX_BRIGHTNESS: u32 = u8::MAX as u32 * 3;
+/// Extension trait for [`image`] pixel types.
+trait PixelExt: Copy {
+    /// The maximum possible brightness for a pixel.
+    const MAX_BRIGHTNESS: u32;
 
-    #[inline]
-    fn brightness(self) -> u32 {
-        let Self([r, g, b]) = self;
-        u32::from(r) + u32::from(g) + u32::from(b)
-    }
-}
+    /// Returns the brightness of the pixel, in the range `0..=MAX_BRIGHTNESS`.
+    fn brightness(self) -> u32;
 
-impl PixelExt for Rgb<u16> {
-    const MAX_BRIGHTNESS: u32 = u16::MAX as u32 * 3;
+    /// The maximum possible value of a channel.
+    const MAX_CHANNEL: u32;
 
-    #[inline]
-    fn brightness(self) -> u32 {
-        let Self([r, g, b]) = self;
-        u32::from(r) + u32::from(g) + u32::from(b)
-    }
+    /// Returns the red, green, blue, and alpha channels of the pixel, in the
+    /// range `0..=MAX_CHANNEL`.
+    fn channels(self) -> [u32; 4];
 }
 
-impl PixelExt for Rgba<u8> {
-    const MAX_BRIGHTNESS: u32 = u8::MAX as u32 * 3;
+/// Any pixel type with 8- or 16-bit channels is supported.
+///
+/// The brightness of a color pixel is the sum of its red, green, and blue
+/// channels, as in the reference implementation, while the brightness of a
+/// grayscale pixel is its luma. Both are obtained through the pixel's own color
+/// conversions, so pixel types other than those provided by the [`image`] crate
+/// are supported as well.
+///
+/// Fully transparent pixels are treated as having the maximum brightness.
+impl<P> PixelExt for P
+where
+    P: Pixel,
+    P::Subpixel: Channel,
+{
+    const MAX_BRIGHTNESS: u32 = if P::CHANNEL_COUNT > 2 {
+        P::Subpixel::MAX * 3
+    } else {
+        P::Subpixel::MAX
+    };
 
     #[inline]
     fn brightness(self) -> u32 {
-        let Self([r, g, b, a]) = self;
-        match a {
-            0 => Self::MAX_BRIGHTNESS,
-            _ => u32::from(r) + u32::from(g) + u32::from(b),
+        if P::CHANNEL_COUNT > 2 {
+            let Rgba([r, g, b, a]) = self.to_rgba();
+            match a.into() {
+                0 => Self::MAX_BRIGHTNESS,
+                _ => r.into() + g.into() + b.into(),
+            }
+        } else {
+            let LumaA([y, a]) = self.to_luma_alpha();
+            match a.into() {
+                0 => Self::MAX_BRIGHTNESS,
+                _ => y.into(),
+            }
         }
     }
-}
 
-impl PixelExt for Rgba<u16> {
-    const MAX_BRIGHTNESS: u32 = u16::MAX as u32 * 3;
+    const MAX_CHANNEL: u32 = P::Subpixel::MAX;
 
     #[inline]
-    fn brightness(self) -> u32 {
-        let Self([r, g, b, a]) = self;
-        match a {
-            0 => Self::MAX_BRIGHTNESS,
-            _ => u32::from(r) + u32::from(g) + u32::from(b),
+    fn channels(self) -> [u32; 4] {
+        if P::CHANNEL_COUNT > 2 {
+            let Rgba([r, g, b, a]) = self.to_rgba();
+            [r.into(), g.into(), b.into(), a.into()]
+        } else {
+            let LumaA([y, a]) = self.to_luma_alpha();
+            let y = y.into();
+            [y, y, y, a.into()]
         }
     }
 }
@@ -0,0 +1,432 @@
+use crate::Digest;
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+use core::mem::size_of;
+
+/// An index of hashes that can be searched for near matches.
+///
+/// This is a [BK-tree], which uses the triangle inequality to skip most of the
+/// hashes that are too far from the query, so searches with small distances
+/// are much faster than comparing the query against every hash.
+///
+/// Each hash is identified by its position in the order of insertion.
+///
+/// Hashes can also be tagged with a namespace, such as a tenant of a shared
+/// service, with [`insert_in`](Self::insert_in). Searches with
+/// [`find_in`](Self::find_in), [`find_page_in`](Self::find_page_in), and
+/// [`nearest_in`](Self::nearest_in) only return hashes in the given
+/// namespaces, so a single index can serve many tenants
+/// without building a separate index for each. Hashes inserted with
+/// [`insert`](Self::insert) are in namespace 0.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, HashIndex};
+///
+/// let mut index = HashIndex::new();
+/// index.insert(Blockhash64::from(0xffff_0000_ffff_0000));
+/// index.insert(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f));
+/// index.insert(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0e));
+///
+/// let query = Blockhash64::from(0x0f0f_0f0f_0f0f_0f00);
+/// assert_eq!(index.find(&query, 4), [(2, 3), (1, 4)]);
+/// assert_eq!(index.nearest(&query, 10), Some((2, 3)));
+/// ```
+///
+/// [BK-tree]: https://en.wikipedia.org/wiki/BK-tree
+#[derive(Debug, Clone)]
+pub struct HashIndex<D> {
+    nodes: Vec<Node<D>>,
+}
+
+#[derive(Debug, Clone)]
+struct Node<D> {
+    hash: D,
+    namespace: u32,
+    /// The distance to each child, and the position of the child.
+    children: Vec<(u32, usize)>,
+}
+
+impl<D: Digest> HashIndex<D> {
+    /// Creates an empty index.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        HashIndex { nodes: Vec::new() }
+    }
+
+    /// Adds a hash to the index, and returns its position.
+    #[inline]
+    pub fn insert(&mut self, hash: D) -> usize {
+        self.insert_in(hash, 0)
+    }
+
+    /// Adds a hash to the index in the given namespace, and returns its
+    /// position.
+    pub fn insert_in(&mut self, hash: D, namespace: u32) -> usize {
+        let id = self.nodes.len();
+
+        if id > 0 {
+            let mut parent = 0;
+            loop {
+                let dist = self.nodes[parent].hash.distance(&hash);
+                let node = &mut self.nodes[parent];
+                match node.children.iter().find(|&&(d, _)| d == dist) {
+                    Some(&(_, child)) => parent = child,
+                    None => {
+                        node.children.push((dist, id));
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.nodes.push(Node {
+            hash,
+            namespace,
+            children: Vec::new(),
+        });
+
+        id
+    }
+
+    /// Returns the number of hashes in the index.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether the index is empty.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the approximate number of bytes of memory used by the index.
+    ///
+    /// This includes memory that has been allocated but not used yet, but not
+    /// any overhead of the allocator itself.
+    ///
+    /// To search a large set of hashes within a fixed memory budget, compile
+    /// it into a [`StaticIndex`](crate::StaticIndex) instead, which can be
+    /// searched directly from a memory-mapped file, leaving the operating
+    /// system to keep the parts that are used often in memory.
+    #[must_use]
+    pub fn memory_usage(&self) -> usize {
+        let children: usize = self
+            .nodes
+            .iter()
+            .map(|node| node.children.capacity() * size_of::<(u32, usize)>())
+            .sum();
+
+        size_of::<Self>() + self.nodes.capacity() * size_of::<Node<D>>() + children
+    }
+
+    /// Returns the hash at the given position.
+    #[inline]
+    #[must_use]
+    pub fn get(&self, id: usize) -> Option<&D> {
+        self.nodes.get(id).map(|node| &node.hash)
+    }
+
+    /// Returns the namespace of the hash at the given position.
+    #[inline]
+    #[must_use]
+    pub fn namespace(&self, id: usize) -> Option<u32> {
+        self.nodes.get(id).map(|node| node.namespace)
+    }
+
+    /// Finds every hash within the given distance of the query.
+    ///
+    /// Returns the position of each hash and its distance from the query,
+    /// sorted by distance and then by position.
+    #[must_use]
+    pub fn find(&self, query: &D, max_distance: u32) -> Vec<(usize, u32)> {
+        self.find_filtered(query, max_distance, |_| true)
+    }
+
+    /// Finds every hash in the given namespaces within the given distance of
+    /// the query.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{Blockhash64, HashIndex};
+    ///
+    /// let mut index = HashIndex::new();
+    /// index.insert_in(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f), 1);
+    /// index.insert_in(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0e), 2);
+    /// index.insert_in(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0c), 3);
+    ///
+    /// let query = Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f);
+    /// assert_eq!(index.find_in(&query, 4, &[2, 3]), [(1, 1), (2, 2)]);
+    /// assert_eq!(index.nearest_in(&query, 4, &[3]), Some((2, 2)));
+    /// ```
+    #[must_use]
+    pub fn find_in(&self, query: &D, max_distance: u32, namespaces: &[u32]) -> Vec<(usize, u32)> {
+        self.find_filtered(query, max_distance, |ns| namespaces.contains(&ns))
+    }
+
+    /// Finds one page of the hashes within the given distance of the query.
+    ///
+    /// The results are in the same order as [`find`](Self::find), starting
+    /// after the given cursor, or from the beginning if there is no cursor.
+    /// At most `limit` results are returned, along with a cursor to fetch the
+    /// next page if there are more. Only the results in the page are kept in
+    /// memory, so large result sets can be streamed a page at a time, although
+    /// each page still searches the whole radius. A limit of 0 returns an
+    /// empty page with no cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{Blockhash16, HashIndex};
+    ///
+    /// let index: HashIndex<_> = (0..10).map(Blockhash16::from).collect();
+    /// let query = Blockhash16::from(0);
+    ///
+    /// let mut results = Vec::new();
+    /// let mut cursor = None;
+    /// loop {
+    ///     let page = index.find_page(&query, 2, cursor, 3);
+    ///     results.extend(page.results);
+    ///     match page.next {
+    ///         Some(next) => cursor = Some(next),
+    ///         None => break,
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(results, index.find(&query, 2));
+    /// ```
+    #[must_use]
+    pub fn find_page(
+        &self,
+        query: &D,
+        max_distance: u32,
+        after: Option<Cursor>,
+        limit: usize,
+    ) -> Page {
+        self.find_page_filtered(query, max_distance, after, limit, |_| true)
+    }
+
+    /// Finds one page of the hashes in the given namespaces within the given
+    /// distance of the query.
+    ///
+    /// The results are in the same order as [`find_in`](Self::find_in), and
+    /// are paged as in [`find_page`](Self::find_page). A cursor is only
+    /// meaningful for the same namespaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{Blockhash16, HashIndex};
+    ///
+    /// let mut index = HashIndex::new();
+    /// for i in 0..10 {
+    ///     index.insert_in(Blockhash16::from(i), u32::from(i % 2));
+    /// }
+    /// let query = Blockhash16::from(0);
+    ///
+    /// let page = index.find_page_in(&query, 2, &[1], None, 2);
+    /// assert_eq!(page.results, [(1, 1), (3, 2)]);
+    ///
+    /// let page = index.find_page_in(&query, 2, &[1], page.next, 2);
+    /// assert_eq!(page.results, [(5, 2), (9, 2)]);
+    /// assert_eq!(page.next, None);
+    /// ```
+    #[must_use]
+    pub fn find_page_in(
+        &self,
+        query: &D,
+        max_distance: u32,
+        namespaces: &[u32],
+        after: Option<Cursor>,
+        limit: usize,
+    ) -> Page {
+        self.find_page_filtered(query, max_distance, after, limit, |ns| {
+            namespaces.contains(&ns)
+        })
+    }
+
+    /// Finds the hash closest to the query, if it is within the given distance.
+    ///
+    /// If several hashes are equally close, the first one is returned.
+    #[must_use]
+    pub fn nearest(&self, query: &D, max_distance: u32) -> Option<(usize, u32)> {
+        self.nearest_filtered(query, max_distance, |_| true)
+    }
+
+    /// Finds the hash in the given namespaces closest to the query, if it is
+    /// within the given distance.
+    ///
+    /// If several hashes are equally close, the first one is returned.
+    #[must_use]
+    pub fn nearest_in(
+        &self,
+        query: &D,
+        max_distance: u32,
+        namespaces: &[u32],
+    ) -> Option<(usize, u32)> {
+        self.nearest_filtered(query, max_distance, |ns| namespaces.contains(&ns))
+    }
+
+    fn find_filtered<P: Fn(u32) -> bool>(
+        &self,
+        query: &D,
+        max_distance: u32,
+        namespace: P,
+    ) -> Vec<(usize, u32)> {
+        let mut results = Vec::new();
+        self.search(query, max_distance, |id, dist| {
+            if namespace(self.nodes[id].namespace) {
+                results.push((id, dist));
+            }
+        });
+        results.sort_unstable_by_key(|&(id, dist)| (dist, id));
+        results
+    }
+
+    fn find_page_filtered<P: Fn(u32) -> bool>(
+        &self,
+        query: &D,
+        max_distance: u32,
+        after: Option<Cursor>,
+        limit: usize,
+        namespace: P,
+    ) -> Page {
+        let after = after.map(|cursor| (cursor.distance, cursor.id));
+
+        // The `limit` smallest results after the cursor, with the largest on
+        // top so it can be replaced by anything smaller
+        let mut heap = BinaryHeap::with_capacity(limit.saturating_add(1).min(self.nodes.len()));
+        let mut more = false;
+
+        self.search(query, max_distance, |id, dist| {
+            if after.is_some_and(|after| (dist, id) <= after)
+                || !namespace(self.nodes[id].namespace)
+            {
+                return;
+            }
+            heap.push((dist, id));
+            if heap.len() > limit {
+                heap.pop();
+                more = true;
+            }
+        });
+
+        let results: Vec<_> = heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(dist, id)| (id, dist))
+            .collect();
+        let next = match results.last() {
+            Some(&(id, distance)) if more => Some(Cursor { distance, id }),
+            _ => None,
+        };
+
+        Page { results, next }
+    }
+
+    fn nearest_filtered<P: Fn(u32) -> bool>(
+        &self,
+        query: &D,
+        max_distance: u32,
+        namespace: P,
+    ) -> Option<(usize, u32)> {
+        let mut best: Option<(usize, u32)> = None;
+        self.search(query, max_distance, |id, dist| {
+            if namespace(self.nodes[id].namespace)
+                && best.map_or(true, |best| (dist, id) < (best.1, best.0))
+            {
+                best = Some((id, dist));
+            }
+        });
+        best
+    }
+
+    /// Returns the hash at the given position, and the distance to and
+    /// position of each of its children in the tree.
+    pub(crate) fn node(&self, id: usize) -> (&D, &[(u32, usize)]) {
+        let node = &self.nodes[id];
+        (&node.hash, &node.children)
+    }
+
+    /// Calls `f` with every hash within the given distance of the query, in no
+    /// particular order.
+    fn search<F: FnMut(usize, u32)>(&self, query: &D, max_distance: u32, mut f: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = alloc::vec![0];
+
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id];
+            let dist = node.hash.distance(query);
+            if dist <= max_distance {
+                f(id, dist);
+            }
+
+            let range = dist.saturating_sub(max_distance)..=dist.saturating_add(max_distance);
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| range.contains(d))
+                    .map(|&(_, child)| child),
+            );
+        }
+    }
+}
+
+/// A position in the results of a query, used to fetch the next page of
+/// results with [`HashIndex::find_page`] or [`HashIndex::find_page_in`].
+///
+/// A cursor is only meaningful for the same query and index, and remains
+/// valid after more hashes are inserted, although hashes inserted before the
+/// cursor's position won't be returned.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Cursor {
+    /// The distance of the last result.
+    pub distance: u32,
+    /// The position of the last result.
+    pub id: usize,
+}
+
+/// A page of results returned by [`HashIndex::find_page`] or
+/// [`HashIndex::find_page_in`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// The position of each hash and its distance from the query.
+    pub results: Vec<(usize, u32)>,
+    /// The cursor for the next page, or `None` if this is the last page.
+    pub next: Option<Cursor>,
+}
+
+impl<D: Digest> Default for HashIndex<D> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest> FromIterator<D> for HashIndex<D> {
+    fn from_iter<T: IntoIterator<Item = D>>(iter: T) -> Self {
+        let mut index = Self::new();
+        for hash in iter {
+            index.insert(hash);
+        }
+        index
+    }
+}
+
+impl<D: Digest> Extend<D> for HashIndex<D> {
+    fn extend<T: IntoIterator<Item = D>>(&mut self, iter: T) {
+        for hash in iter {
+            self.insert(hash);
+        }
+    }
+}
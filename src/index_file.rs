@@ -0,0 +1,719 @@
+use crate::{AnyBlockhash, Digest};
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read, Write};
+
+/// The bytes at the start of every index file.
+const MAGIC: [u8; 4] = *b"BHIF";
+
+/// The version of the index file format.
+const VERSION: u8 = 1;
+
+/// The length of the header and bucket table.
+const TABLE_END: u64 = 12 + BUCKETS as u64 * 4;
+
+/// The number of buckets in the bucket table, one for each possible value of
+/// the first byte of a hash.
+const BUCKETS: usize = 256;
+
+/// The algorithm used to generate the hashes in an [`IndexFile`].
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HashAlgorithm {
+    /// Blockhash, as generated by [`blockhash64`](crate::blockhash64) and the
+    /// other functions of the same name.
+    Blockhash,
+    /// Average hash, as generated by [`ahash64`](crate::ahash64).
+    AverageHash,
+    /// DCT-based perceptual hash, as generated by
+    /// [`phash64`](crate::phash64).
+    PerceptualHash,
+}
+
+impl HashAlgorithm {
+    /// Returns the identifier of the algorithm in the file format.
+    fn id(self) -> u8 {
+        match self {
+            HashAlgorithm::Blockhash => 1,
+            HashAlgorithm::AverageHash => 2,
+            HashAlgorithm::PerceptualHash => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(HashAlgorithm::Blockhash),
+            2 => Some(HashAlgorithm::AverageHash),
+            3 => Some(HashAlgorithm::PerceptualHash),
+            _ => None,
+        }
+    }
+}
+
+/// An error returned by [`IndexFile::read_from`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum IndexFileError {
+    /// The file couldn't be read, or ended unexpectedly.
+    Io(io::Error),
+    /// The file doesn't start with the expected header.
+    InvalidHeader,
+    /// The file was written by an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The hashes in the file were generated by an unknown algorithm.
+    UnknownAlgorithm(u8),
+    /// The hashes in the file have an unsupported grid size. Contains the
+    /// number of columns and rows.
+    UnsupportedSize(u8, u8),
+    /// The entries aren't sorted, don't match the bucket table, or are
+    /// followed by unexpected bytes.
+    Corrupt,
+}
+
+impl Display for IndexFileError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            IndexFileError::Io(err) => write!(f, "failed to read index file: {}", err),
+            IndexFileError::InvalidHeader => f.write_str("invalid index file header"),
+            IndexFileError::UnsupportedVersion(version) => {
+                write!(f, "unsupported index file version {}", version)
+            }
+            IndexFileError::UnknownAlgorithm(id) => write!(f, "unknown hash algorithm {}", id),
+            IndexFileError::UnsupportedSize(columns, rows) => {
+                write!(f, "unsupported hash size {}x{}", columns, rows)
+            }
+            IndexFileError::Corrupt => f.write_str("index file is corrupt"),
+        }
+    }
+}
+
+impl Error for IndexFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IndexFileError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for IndexFileError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        IndexFileError::Io(err)
+    }
+}
+
+/// A collection of hashes in a documented format that can be exchanged with
+/// implementations in other languages.
+///
+/// Each hash is stored with a 64-bit ID chosen by the caller, such as a row
+/// ID in a database. All of the hashes in a file must have the same size, and
+/// be generated by the same algorithm, which is recorded in the file so that
+/// readers can refuse to compare incompatible hashes.
+///
+/// # Format
+///
+/// All integers are big-endian, and the file is laid out as follows:
+///
+/// | Offset | Length   | Contents                                        |
+/// |--------|----------|-------------------------------------------------|
+/// | 0      | 4        | The bytes `BHIF`                                |
+/// | 4      | 1        | The format version, currently 1                 |
+/// | 5      | 1        | The algorithm: 1 for blockhash, 2 for average hash, 3 for pHash |
+/// | 6      | 1        | The number of columns in the grid of each hash  |
+/// | 7      | 1        | The number of rows in the grid of each hash     |
+/// | 8      | 4        | The number of entries, *n*, as a `u32`          |
+/// | 12     | 1024     | The bucket table, as 256 `u32`s                 |
+/// | 1036   | *n* × (*b* + 8) | The entries                              |
+/// | 1036 + *n* × (*b* + 8) | 4 | The checksum                            |
+///
+/// Each entry is the *b* bytes of a hash, packed in the same way as
+/// [`Digest::as_bytes`], followed by its ID as a `u64`. Entries are sorted by
+/// their hash bytes, and then by ID.
+///
+/// The bucket table contains the position of the first entry whose hash
+/// starts with each possible byte, or of the first entry after them if there
+/// are none. Readers can use it to find the entries with a particular prefix
+/// without searching the whole file.
+///
+/// The checksum is the CRC-32 (as used by zlib and PNG) of every preceding
+/// byte of the file, as a `u32`.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{AnyBlockhash, Blockhash64, HashAlgorithm, IndexFile};
+///
+/// let file = IndexFile::new(
+///     HashAlgorithm::Blockhash,
+///     [
+///         (10, Blockhash64::from(0xc7c4_8f89_89c7_7e0c)),
+///         (20, Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f)),
+///     ],
+/// );
+///
+/// let mut bytes = Vec::new();
+/// file.write_to(&mut bytes).unwrap();
+/// assert_eq!(bytes.len(), 1036 + 2 * 16 + 4);
+///
+/// let file = IndexFile::read_from(&bytes[..]).unwrap();
+/// let hash = AnyBlockhash::from(Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f));
+/// assert_eq!(file.algorithm(), HashAlgorithm::Blockhash);
+/// assert_eq!(file.lookup(&hash), [(20, hash)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexFile {
+    algorithm: HashAlgorithm,
+    columns: u8,
+    entries: Vec<(u64, AnyBlockhash)>,
+    /// The bucket table, with the number of entries appended.
+    buckets: Vec<u32>,
+}
+
+impl IndexFile {
+    /// Creates an index file from a set of hashes and their IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` entries.
+    #[must_use]
+    pub fn new<D, T>(algorithm: HashAlgorithm, entries: T) -> Self
+    where
+        D: Digest + Into<AnyBlockhash>,
+        T: IntoIterator<Item = (u64, D)>,
+    {
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .map(|(id, hash)| (id, hash.into()))
+            .collect();
+        assert!(entries.len() <= u32::MAX as usize, "too many entries");
+        entries.sort_unstable_by_key(|&(id, hash)| (hash, id));
+
+        let buckets = bucket_table(&entries);
+        IndexFile {
+            algorithm,
+            columns: D::COLUMNS as u8,
+            entries,
+            buckets,
+        }
+    }
+
+    /// Returns the algorithm used to generate the hashes.
+    #[inline]
+    #[must_use]
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the number of entries.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether there are no entries.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the ID and hash of every entry, sorted by hash and then by ID.
+    #[inline]
+    #[must_use]
+    pub fn entries(&self) -> &[(u64, AnyBlockhash)] {
+        &self.entries
+    }
+
+    /// Returns the entries with exactly the given hash, sorted by ID.
+    #[must_use]
+    pub fn lookup(&self, hash: &AnyBlockhash) -> &[(u64, AnyBlockhash)] {
+        let first = usize::from(hash.as_bytes()[0]);
+        let start = self.buckets[first] as usize;
+        let end = self.buckets[first + 1] as usize;
+        let bucket = &self.entries[start..end];
+
+        let start = bucket.partition_point(|(_, h)| h < hash);
+        let end = bucket.partition_point(|(_, h)| h <= hash);
+        &bucket[start..end]
+    }
+
+    /// Writes the index file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = Checksummed::new(writer);
+        let mut header = [0; 8];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4] = VERSION;
+        header[5] = self.algorithm.id();
+        header[6] = self.columns;
+        header[7] = self.columns;
+        writer.write_all(&header)?;
+        writer.write_all(&(self.entries.len() as u32).to_be_bytes())?;
+
+        for start in &self.buckets[..BUCKETS] {
+            writer.write_all(&start.to_be_bytes())?;
+        }
+
+        for (id, hash) in &self.entries {
+            writer.write_all(hash.as_bytes())?;
+            writer.write_all(&id.to_be_bytes())?;
+        }
+
+        let checksum = writer.checksum();
+        writer.write_all(&checksum.to_be_bytes())?;
+        writer.flush()
+    }
+
+    /// Reads an index file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails, or if the file isn't valid.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, IndexFileError> {
+        let mut reader = Checksummed::new(reader);
+        let header = Header::read(&mut reader)?;
+        let (algorithm, columns, len, count) = (
+            header.algorithm,
+            header.columns,
+            header.hash_len,
+            header.count,
+        );
+
+        let mut table = [0; BUCKETS * 4];
+        reader.read_exact(&mut table)?;
+
+        // Entries are read one at a time, so that a bad count in the header
+        // can't cause a huge allocation up front.
+        let mut entries = Vec::new();
+        let mut entry = [0; 136];
+        let entry = &mut entry[..(len + 8)];
+        for _ in 0..count {
+            reader.read_exact(entry)?;
+            let hash = AnyBlockhash::from_bytes(&entry[..len]).unwrap();
+            let id = u64::from_be_bytes(entry[len..].try_into().unwrap());
+            entries.push((id, hash));
+        }
+
+        let checksum = reader.checksum();
+        let mut stored = [0; 4];
+        reader.read_exact(&mut stored)?;
+        if u32::from_be_bytes(stored) != checksum {
+            return Err(IndexFileError::Corrupt);
+        }
+
+        if reader.read(&mut [0])? != 0 {
+            return Err(IndexFileError::Corrupt);
+        }
+
+        let sorted = entries
+            .windows(2)
+            .all(|pair| (pair[0].1, pair[0].0) <= (pair[1].1, pair[1].0));
+        let buckets = bucket_table(&entries);
+        let matches = table
+            .chunks_exact(4)
+            .zip(&buckets)
+            .all(|(bytes, &start)| read_u32(bytes) == start);
+        if !sorted || !matches {
+            return Err(IndexFileError::Corrupt);
+        }
+
+        Ok(IndexFile {
+            algorithm,
+            columns,
+            entries,
+            buckets,
+        })
+    }
+
+    /// Checks an index file for corruption, such as before relying on a
+    /// backup.
+    ///
+    /// Unlike [`read_from`](Self::read_from), this doesn't stop at the first
+    /// problem, and reports the position in the file of every problem it
+    /// finds. The entries are checked as they are read, without keeping them
+    /// in memory, so large files can be verified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails for any reason other than the file
+    /// ending early, or if the header isn't valid, since nothing after it can
+    /// be checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{Blockhash16, CorruptionKind, HashAlgorithm, IndexFile};
+    ///
+    /// let file = IndexFile::new(HashAlgorithm::Blockhash, [(1, Blockhash16::from(0xabcd))]);
+    /// let mut bytes = Vec::new();
+    /// file.write_to(&mut bytes).unwrap();
+    /// assert!(IndexFile::verify(&bytes[..]).unwrap().is_valid());
+    ///
+    /// // Flip a bit of the ID
+    /// bytes[1045] ^= 1;
+    /// let verification = IndexFile::verify(&bytes[..]).unwrap();
+    /// let problem = verification.problems()[0];
+    /// assert!(matches!(problem.kind, CorruptionKind::ChecksumMismatch { .. }));
+    /// assert_eq!(problem.offset, 1046);
+    /// ```
+    pub fn verify<R: Read>(reader: R) -> Result<Verification, IndexFileError> {
+        let mut reader = Checksummed::new(reader);
+        let header = Header::read(&mut reader)?;
+        let mut problems = Vec::new();
+
+        let mut table = [0; BUCKETS * 4];
+        if fill(&mut reader, &mut table)? < table.len() {
+            problems.push(Corruption {
+                offset: 12,
+                kind: CorruptionKind::Truncated,
+            });
+            return Ok(Verification {
+                version: header.version,
+                entries: 0,
+                problems,
+            });
+        }
+
+        let entry_len = header.hash_len + 8;
+        let mut counts = [0_u32; BUCKETS];
+        let mut entry = [0; 136];
+        let mut prev = [0; 136];
+        let mut offset = TABLE_END;
+        let mut entries = 0;
+
+        while entries < header.count {
+            let entry = &mut entry[..entry_len];
+            if fill(&mut reader, entry)? < entry_len {
+                break;
+            }
+
+            // Entries are sorted by hash and then by ID, which is the same as
+            // sorting by their bytes
+            if entries > 0 && entry[..] < prev[..entry_len] {
+                problems.push(Corruption {
+                    offset,
+                    kind: CorruptionKind::Unsorted,
+                });
+            }
+
+            counts[usize::from(entry[0])] += 1;
+            prev[..entry_len].copy_from_slice(entry);
+            offset += entry_len as u64;
+            entries += 1;
+        }
+
+        let truncated = entries < header.count;
+        if !truncated {
+            let mut start = 0;
+            for (bucket, (bytes, &count)) in table.chunks_exact(4).zip(&counts).enumerate() {
+                let stored = read_u32(bytes);
+                if stored != start {
+                    problems.push(Corruption {
+                        offset: 12 + bucket as u64 * 4,
+                        kind: CorruptionKind::BucketMismatch {
+                            bucket: bucket as u8,
+                            stored,
+                            expected: start,
+                        },
+                    });
+                }
+                start += count;
+            }
+        }
+
+        if truncated {
+            problems.push(Corruption {
+                offset,
+                kind: CorruptionKind::Truncated,
+            });
+        } else {
+            let computed = reader.checksum();
+            let mut stored = [0; 4];
+            if fill(&mut reader, &mut stored)? < stored.len() {
+                problems.push(Corruption {
+                    offset,
+                    kind: CorruptionKind::Truncated,
+                });
+            } else {
+                let stored = u32::from_be_bytes(stored);
+                if stored != computed {
+                    problems.push(Corruption {
+                        offset,
+                        kind: CorruptionKind::ChecksumMismatch { stored, computed },
+                    });
+                }
+                offset += 4;
+            }
+        }
+
+        if !truncated && fill(&mut reader, &mut [0])? != 0 {
+            problems.push(Corruption {
+                offset,
+                kind: CorruptionKind::TrailingBytes,
+            });
+        }
+
+        problems.sort_by_key(|problem| problem.offset);
+        Ok(Verification {
+            version: header.version,
+            entries,
+            problems,
+        })
+    }
+}
+
+/// The result of [`IndexFile::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    version: u8,
+    entries: usize,
+    problems: Vec<Corruption>,
+}
+
+impl Verification {
+    /// Returns whether no problems were found.
+    #[inline]
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Returns the problems that were found, in the order of their position
+    /// in the file.
+    #[inline]
+    #[must_use]
+    pub fn problems(&self) -> &[Corruption] {
+        &self.problems
+    }
+
+    /// Returns the version of the format of the file.
+    #[inline]
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the number of entries that were read.
+    #[inline]
+    #[must_use]
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+}
+
+/// A problem found in an index file by [`IndexFile::verify`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Corruption {
+    /// The position in the file of the first byte of the data with the
+    /// problem.
+    pub offset: u64,
+    /// The kind of problem.
+    pub kind: CorruptionKind,
+}
+
+impl Display for Corruption {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "at byte {}: {}", self.offset, self.kind)
+    }
+}
+
+/// The kind of a [`Corruption`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CorruptionKind {
+    /// The file ends before the number of entries in the header, or before
+    /// the checksum.
+    Truncated,
+    /// The file continues after the end of the data.
+    TrailingBytes,
+    /// The entry is out of order with the entry before it.
+    Unsorted,
+    /// An entry of the bucket table doesn't match the entries.
+    BucketMismatch {
+        /// The first byte of the hashes in the bucket.
+        bucket: u8,
+        /// The position of the first entry in the bucket, according to the
+        /// table.
+        stored: u32,
+        /// The position of the first entry in the bucket, according to the
+        /// entries.
+        expected: u32,
+    },
+    /// The checksum doesn't match the rest of the file.
+    ChecksumMismatch {
+        /// The checksum stored in the file.
+        stored: u32,
+        /// The checksum of the rest of the file.
+        computed: u32,
+    },
+}
+
+impl Display for CorruptionKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CorruptionKind::Truncated => f.write_str("file is truncated"),
+            CorruptionKind::TrailingBytes => f.write_str("unexpected bytes after the end"),
+            CorruptionKind::Unsorted => f.write_str("entry is out of order"),
+            CorruptionKind::BucketMismatch {
+                bucket,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "bucket {:02x} starts at entry {}, but the table says {}",
+                bucket, expected, stored
+            ),
+            CorruptionKind::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "checksum is {:08x}, but the data has checksum {:08x}",
+                stored, computed
+            ),
+        }
+    }
+}
+
+/// The fields of the header of an index file.
+struct Header {
+    version: u8,
+    algorithm: HashAlgorithm,
+    columns: u8,
+    /// The number of bytes in each hash.
+    hash_len: usize,
+    count: usize,
+}
+
+impl Header {
+    fn read<R: Read>(reader: &mut R) -> Result<Self, IndexFileError> {
+        let mut header = [0; 12];
+        reader.read_exact(&mut header)?;
+
+        if header[..4] != MAGIC {
+            return Err(IndexFileError::InvalidHeader);
+        }
+        let version = header[4];
+        if version != VERSION {
+            return Err(IndexFileError::UnsupportedVersion(version));
+        }
+        let algorithm =
+            HashAlgorithm::from_id(header[5]).ok_or(IndexFileError::UnknownAlgorithm(header[5]))?;
+
+        let (columns, rows) = (header[6], header[7]);
+        let hash_len = match (columns, rows) {
+            (4, 4) | (8, 8) | (12, 12) | (16, 16) | (32, 32) => {
+                usize::from(columns) * usize::from(rows) / 8
+            }
+            _ => return Err(IndexFileError::UnsupportedSize(columns, rows)),
+        };
+
+        Ok(Header {
+            version,
+            algorithm,
+            columns,
+            hash_len,
+            count: read_u32(&header[8..]) as usize,
+        })
+    }
+}
+
+/// Returns the position of the first entry in each bucket, followed by the
+/// number of entries.
+fn bucket_table(entries: &[(u64, AnyBlockhash)]) -> Vec<u32> {
+    (0..=BUCKETS)
+        .map(|first| {
+            entries.partition_point(|(_, hash)| usize::from(hash.as_bytes()[0]) < first) as u32
+        })
+        .collect()
+}
+
+/// Reads until the buffer is full or the reader ends, and returns the number
+/// of bytes read.
+fn fill<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+/// A reader or writer that computes the CRC-32 of the bytes passing through it.
+struct Checksummed<T> {
+    inner: T,
+    crc: u32,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Checksummed {
+            inner,
+            crc: u32::MAX,
+        }
+    }
+
+    /// Returns the checksum of the bytes so far.
+    fn checksum(&self) -> u32 {
+        !self.crc
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let idx = (self.crc ^ u32::from(byte)) & 0xff;
+            self.crc = (self.crc >> 8) ^ CRC_TABLE[idx as usize];
+        }
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.update(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The CRC-32 of each byte, using the reversed polynomial `0xedb88320`.
+static CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
@@ -0,0 +1,159 @@
+use crate::random::SplitMix64;
+use crate::Image;
+
+/// The number of segments each axis is divided into.
+const SEGMENTS: usize = 16;
+
+/// An image whose block boundaries are shifted by secret, keyed amounts.
+///
+/// Perturbations designed to evade matching typically target the pixels
+/// around the boundaries between blocks, where a small change can move a
+/// block across the median of its band. This adapter warps the image slightly
+/// so that the boundaries fall at positions derived from a secret key, which
+/// makes it harder to craft such perturbations without knowing the key.
+///
+/// Each axis is divided into 16 segments, and the boundary between each pair
+/// of segments is moved by a pseudorandom amount of up to a fraction of the
+/// segment size, set by [`strength`](Self::strength). The edges of the image
+/// don't move, and the warp is proportional to the size of the image, so a
+/// resized copy of an image is warped in the same way.
+///
+/// There are some trade-offs:
+///
+/// * Hashes generated with different keys, or without a key, can't be
+///   compared, so every hash in a collection must be generated with the same
+///   key and strength.
+/// * The key must be kept secret, since anyone who knows it can target the
+///   warped boundaries instead.
+/// * This raises the cost of an attack, but doesn't prevent one. An attacker
+///   who can query whether images match can still search for an evasive
+///   perturbation.
+/// * Hashes are slightly less stable for images that are cropped or padded,
+///   since that changes where the warp falls relative to the content.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, Jittered};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let key = 0x5eed_cafe_f00d_d00d;
+///
+/// let hash = blockhash64(&Jittered::new(&img, key));
+/// assert_eq!(hash, blockhash64(&Jittered::new(&img, key)));
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Jittered<'a, I> {
+    img: &'a I,
+    key: u64,
+    columns: [u32; SEGMENTS + 1],
+    rows: [u32; SEGMENTS + 1],
+}
+
+impl<'a, I: Image> Jittered<'a, I> {
+    /// Creates a jittered view of an image using the given key.
+    ///
+    /// Boundaries are moved by up to a quarter of the segment size.
+    #[must_use]
+    pub fn new(img: &'a I, key: u64) -> Self {
+        Self::with_strength(img, key, 64)
+    }
+
+    /// Sets the maximum distance each boundary can move, as a fraction of the
+    /// segment size in 256ths.
+    ///
+    /// The strength is limited to 127, so that boundaries can't cross each
+    /// other. A strength of 0 disables the warp.
+    #[must_use]
+    pub fn strength(self, strength: u8) -> Self {
+        Self::with_strength(self.img, self.key, strength)
+    }
+
+    fn with_strength(img: &'a I, key: u64, strength: u8) -> Self {
+        let strength = strength.min(127);
+        let (width, height) = img.dimensions();
+
+        Jittered {
+            img,
+            key,
+            columns: boundaries(width, key, 0, strength),
+            rows: boundaries(height, key, 1, strength),
+        }
+    }
+}
+
+impl<I: Image> Image for Jittered<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let (width, height) = self.img.dimensions();
+        let x = warp(x, width, &self.columns);
+        let y = warp(y, height, &self.rows);
+        self.img.brightness(x, y)
+    }
+
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        let (width, height) = self.img.dimensions();
+        let x = warp(x, width, &self.columns);
+        let y = warp(y, height, &self.rows);
+        self.img.channels(x, y)
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.img.max_channel()
+    }
+}
+
+/// Returns the warped position of each boundary along an axis of the given
+/// length.
+fn boundaries(len: u32, key: u64, axis: u64, strength: u8) -> [u32; SEGMENTS + 1] {
+    let mut bounds = [0; SEGMENTS + 1];
+
+    for (i, bound) in bounds.iter_mut().enumerate() {
+        let nominal = i64::from(len) * i as i64 / SEGMENTS as i64;
+        let shift = if i == 0 || i == SEGMENTS {
+            0
+        } else {
+            // A fraction in the range -1..1, scaled by the maximum shift so
+            // that the same boundary moves proportionally at any size
+            let fraction = (SplitMix64(key ^ (axis << 32 | i as u64)).next() >> 48) as i64 - 0x8000;
+            fraction * i64::from(len) * i64::from(strength) / (SEGMENTS as i64 * 256 * 0x8000)
+        };
+        *bound = (nominal + shift) as u32;
+    }
+
+    bounds
+}
+
+/// Maps a position in the warped image to the corresponding position in the
+/// original image.
+#[inline]
+fn warp(pos: u32, len: u32, bounds: &[u32; SEGMENTS + 1]) -> u32 {
+    // Images this small aren't warped, and would have empty segments
+    if len < SEGMENTS as u32 {
+        return pos;
+    }
+
+    let (pos, len) = (u64::from(pos), u64::from(len));
+    let segment = (pos * SEGMENTS as u64 / len) as usize;
+
+    let start = len * segment as u64 / SEGMENTS as u64;
+    let end = len * (segment as u64 + 1) / SEGMENTS as u64;
+    let (from, to) = (u64::from(bounds[segment]), u64::from(bounds[segment + 1]));
+
+    let mapped = from + (pos - start) * (to - from) / (end - start);
+    mapped.min(len - 1) as u32
+}
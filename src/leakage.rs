@@ -0,0 +1,143 @@
+use crate::{match_hashes, Assignment, Digest, Match};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+#[cfg(all(feature = "std", feature = "image"))]
+use std::path::Path;
+
+/// Near-duplicates found between a training set and a test set.
+///
+/// See [`find_leakage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeakageReport {
+    matches: Vec<Match>,
+    train_len: usize,
+    test_len: usize,
+}
+
+impl LeakageReport {
+    /// Returns every pair of near-duplicates, sorted by distance.
+    ///
+    /// [`Match::a`] is the position in the training set, and [`Match::b`] is
+    /// the position in the test set.
+    #[inline]
+    #[must_use]
+    pub fn matches(&self) -> &[Match] {
+        &self.matches
+    }
+
+    /// Returns the positions of the test images that have a near-duplicate in
+    /// the training set, in ascending order.
+    #[must_use]
+    pub fn leaked(&self) -> Vec<usize> {
+        let mut leaked: Vec<usize> = self.matches.iter().map(|m| m.b).collect();
+        leaked.sort_unstable();
+        leaked.dedup();
+        leaked
+    }
+
+    /// Returns whether any test image has a near-duplicate in the training
+    /// set.
+    #[inline]
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Returns the fraction of the test set that has a near-duplicate in the
+    /// training set.
+    ///
+    /// If the test set is empty, this is zero.
+    #[must_use]
+    pub fn leaked_fraction(&self) -> f64 {
+        match self.test_len {
+            0 => 0.0,
+            n => self.leaked().len() as f64 / n as f64,
+        }
+    }
+}
+
+impl Display for LeakageReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} test images have near-duplicates among {} training images",
+            self.leaked().len(),
+            self.test_len,
+            self.train_len,
+        )?;
+
+        for m in &self.matches {
+            writeln!(
+                f,
+                "train[{}] ~ test[{}] (distance {})",
+                m.a, m.b, m.distance
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds near-duplicates between a training set and a test set.
+///
+/// Images that appear in both sets of a machine learning dataset inflate the
+/// measured accuracy of a model. This finds every pair of hashes from the two
+/// sets that are within the given distance of each other, using
+/// [`match_hashes`].
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{find_leakage, Blockhash16};
+///
+/// let train = [0x0000, 0x00ff, 0xf0f0].map(Blockhash16::from);
+/// let test = [0x0f0f, 0x00fe, 0x0001].map(Blockhash16::from);
+///
+/// let report = find_leakage(&train, &test, 2);
+/// assert_eq!(report.leaked(), [1, 2]);
+/// print!("{}", report);
+/// ```
+#[must_use]
+pub fn find_leakage<D: Digest>(train: &[D], test: &[D], max_distance: u32) -> LeakageReport {
+    LeakageReport {
+        matches: match_hashes(train, test, max_distance, Assignment::All),
+        train_len: train.len(),
+        test_len: test.len(),
+    }
+}
+
+/// Finds near-duplicates between a training set and a test set of image
+/// files.
+///
+/// Each image is opened and hashed with [`Digest::from_image`], and the hashes
+/// are compared with [`find_leakage`]. The positions in the report refer to
+/// the positions of the paths in `train` and `test`.
+///
+/// # Errors
+///
+/// Returns an error if any of the images can't be opened or decoded.
+#[cfg(all(feature = "std", feature = "image"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "image"))))]
+pub fn find_leakage_in_files<D, P>(
+    train: &[P],
+    test: &[P],
+    max_distance: u32,
+) -> Result<LeakageReport, image::ImageError>
+where
+    D: Digest,
+    P: AsRef<Path>,
+{
+    let hash_all = |paths: &[P]| -> Result<Vec<D>, image::ImageError> {
+        paths
+            .iter()
+            .map(|path| Ok(D::from_image(&image::open(path)?)))
+            .collect()
+    };
+
+    Ok(find_leakage(
+        &hash_all(train)?,
+        &hash_all(test)?,
+        max_distance,
+    ))
+}
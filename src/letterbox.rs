@@ -0,0 +1,177 @@
+use crate::Image;
+
+/// An image with letterbox or pillarbox bars removed.
+///
+/// Video is often stored with bars above and below the picture (letterbox) or
+/// to its left and right (pillarbox), to fit it into a frame with a different
+/// aspect ratio. The bars shift the content of the picture relative to the
+/// grid of blocks, so a frame with bars doesn't match the same frame without
+/// them. Removing the bars before hashing allows them to match.
+///
+/// Unlike [`Trimmed`](crate::Trimmed), which removes a solid border on any
+/// side, this only removes pairs of bars on opposite sides of the image that
+/// have the same color and roughly the same thickness, as bars added to fit a
+/// frame do. This avoids cropping content that is uniform along one edge of
+/// the image, such as a clear sky. A small fraction of each row or column of
+/// a bar can differ from the color of the bar, to allow for logos and
+/// subtitles placed on the bars.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, Letterboxed};
+/// use image::{imageops, Rgb, RgbImage};
+///
+/// let img = image::open("images/example.png").unwrap().to_rgb8();
+///
+/// // Letterboxed into a frame with a 4:3 aspect ratio
+/// let mut frame = RgbImage::from_pixel(320, 240, Rgb([0, 0, 0]));
+/// imageops::replace(&mut frame, &img, 0, 24);
+///
+/// let letterboxed = Letterboxed::new(&frame);
+/// assert_eq!(letterboxed.offsets(), (0, 24));
+/// assert_eq!(blockhash64(&letterboxed), blockhash64(&img));
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Letterboxed<'a, I> {
+    img: &'a I,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl<'a, I: Image> Letterboxed<'a, I> {
+    /// Detects and removes letterbox and pillarbox bars from an image.
+    ///
+    /// Pixels whose brightness differs from the color of the bar by no more
+    /// than 1/16 of the maximum brightness are treated as part of the bar,
+    /// which allows for noise from lossy compression.
+    #[must_use]
+    pub fn new(img: &'a I) -> Self {
+        Self::with_tolerance(img, img.max_brightness() / 16)
+    }
+
+    /// Detects and removes letterbox and pillarbox bars from an image,
+    /// treating pixels within the given difference in brightness of the color
+    /// of the bar as part of the bar.
+    #[must_use]
+    pub fn with_tolerance(img: &'a I, tolerance: u32) -> Self {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return Letterboxed {
+                img,
+                x: 0,
+                y: 0,
+                width,
+                height,
+            };
+        }
+
+        // A row is part of a bar if almost all of its pixels are close to the
+        // color of the bar
+        let bar_val = img.brightness(0, 0);
+        let row_is_bar = |y: u32| {
+            let outliers = (0..width)
+                .filter(|&x| img.brightness(x, y).abs_diff(bar_val) > tolerance)
+                .count();
+            outliers as u32 <= width / 16
+        };
+        let (top, bottom) = find_bars(height, row_is_bar);
+
+        let bar_val = img.brightness(0, top);
+        let col_is_bar = |x: u32| {
+            let outliers = (top..bottom)
+                .filter(|&y| img.brightness(x, y).abs_diff(bar_val) > tolerance)
+                .count();
+            outliers as u32 <= (bottom - top) / 16
+        };
+        let (left, right) = find_bars(width, col_is_bar);
+
+        Letterboxed {
+            img,
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    /// Returns the position of the top-left corner of the picture within the
+    /// underlying image.
+    #[inline]
+    #[must_use]
+    pub fn offsets(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// Returns whether any bars were removed.
+    #[inline]
+    #[must_use]
+    pub fn has_bars(&self) -> bool {
+        (self.width, self.height) != self.img.dimensions()
+    }
+
+    /// Returns the underlying image.
+    #[inline]
+    #[must_use]
+    pub fn inner(&self) -> &'a I {
+        self.img
+    }
+}
+
+/// Finds a pair of bars at the start and end of an axis of the given length,
+/// returning the range between them.
+///
+/// If the bars are missing from either end, or their thicknesses differ by
+/// more than a quarter, the whole axis is returned.
+fn find_bars<F: Fn(u32) -> bool>(len: u32, is_bar: F) -> (u32, u32) {
+    let mut start = 0;
+    while start < len && is_bar(start) {
+        start += 1;
+    }
+    if start == 0 || start == len {
+        return (0, len);
+    }
+
+    let mut end = len;
+    while end > start && is_bar(end - 1) {
+        end -= 1;
+    }
+
+    let (first, second) = (start, len - end);
+    if second == 0 || first.abs_diff(second) > first.max(second) / 4 + 1 {
+        return (0, len);
+    }
+
+    (start, end)
+}
+
+impl<I: Image> Image for Letterboxed<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        self.img.brightness(self.x + x, self.y + y)
+    }
+
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        self.img.channels(self.x + x, self.y + y)
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.img.max_channel()
+    }
+}
@@ -1,7 +1,7 @@
 //! A perceptual hashing algorithm for detecting similar images.
 //!
 //! This is an implementation of the [Blockhash] algorithm, and can produce 16-,
-//! 64-, 144-, and 256-bit perceptual hashes.
+//! 64-, 144-, 256-, and 1024-bit perceptual hashes.
 //!
 //! # Examples
 //!
@@ -18,11 +18,61 @@
 //! # }
 //! ```
 //!
+//! # Exactness
+//!
+//! Blockhashes are computed using only integer arithmetic, so the same image
+//! always produces exactly the same hash, regardless of the architecture or
+//! endianness of the platform. Hash digests are stored as bytes, with the
+//! first block in the most significant bit of the first byte. [`self_test`]
+//! can be used to verify this at runtime.
+//!
+//! This doesn't apply to [`phash64`] and [`phash256`], which compute a
+//! discrete cosine transform with floating-point arithmetic, so in rare cases
+//! their hashes can differ between platforms. The same is true of blockhashes
+//! computed with a custom [gamma](BlockhashOptions::gamma) or with the
+//! [Otsu](Threshold::Otsu) threshold, which [`self_test`] doesn't cover.
+//!
+//! # Aspect ratio
+//!
+//! The image is always divided into the same number of blocks horizontally and
+//! vertically, with each block covering a fixed fraction of the width and
+//! height. Stretching or squashing an image therefore doesn't move the block
+//! boundaries relative to its content, so frames with non-square pixels (such
+//! as anamorphic video) produce the same hashes as the same frames resampled to
+//! square pixels, apart from any differences introduced by the resampling
+//! itself.
+//!
+//! # Documents
+//!
+//! This crate doesn't read PDF files, since rendering them requires a separate
+//! rendering engine such as PDFium or MuPDF. Instead, each page can be
+//! rendered with a crate for one of those engines, and the rendered page
+//! hashed like any other image. Because of the way blocks are laid out, the
+//! resolution of the render barely affects the hash, but using the same
+//! resolution for every document makes the hashes most comparable. Scanned
+//! and reprinted pages often have margins of different sizes, which can be
+//! removed with [`Trimmed`] before hashing.
+//!
+//! # Memory usage
+//!
+//! Computing a blockhash never allocates memory on the heap. The values for
+//! each block are kept on the stack, which requires a little over 4 KiB for a
+//! 256-bit hash, or 17 KiB for a 1024-bit hash. Other functionality that
+//! requires allocation is only available with the `alloc` feature, except for
+//! [`phash64`] and [`phash256`], which allocate their transform buffers and
+//! require the `std` feature.
+//!
 //! # Feature flags
 //!
 //! * `std`: Enables features that require the Rust Standard Library (enabled by
 //!   default).
+//! * `alloc`: Enables features that require heap allocation (enabled by `std`).
 //! * `image`: Enables integration with the [`image`] crate (enabled by default).
+//! * `icc`: Enables conversion of images in wide-gamut color spaces to sRGB
+//!   before hashing, with `BlockhashOptions::color_space`.
+//! * `serde`: Enables serialization of the hash types and [`Explanation`] with
+//!   [Serde](https://serde.rs/). Hashes are serialized as hexadecimal strings
+//!   in human-readable formats, and as bytes in binary formats.
 //!
 //! [Blockhash]: https://web.archive.org/web/20210827144701/http://blockhash.io/
 
@@ -34,21 +84,299 @@
 #![warn(unreachable_pub)]
 #![warn(unused_qualifications)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod ahash;
+mod any;
+mod blocklist;
+mod bucket;
+mod capabilities;
+mod captions;
+mod compare;
+mod custom;
+mod gate;
+mod gradient;
 mod hash;
+mod jitter;
+mod letterbox;
+mod lut;
+mod macros;
+mod multiscale;
+mod options;
+mod order;
+mod popcount;
+mod pyramid;
+mod quality;
+mod random;
+mod raw;
+mod redact;
+mod region;
+mod sampled;
+mod saturation;
+mod selftest;
+mod similar;
+mod soft;
+mod srgb;
+mod ternary;
 mod tests;
+mod thermal;
+mod trim;
+mod values;
+mod windowed;
+
+#[cfg(feature = "icc")]
+mod color;
 
 #[cfg(feature = "image")]
 mod img;
 
-use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "image")]
+mod report;
+
+#[cfg(feature = "serde")]
+mod serialize;
+
+#[cfg(all(feature = "std", feature = "image"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "image"))))]
+pub mod perturb;
+
+#[cfg(all(feature = "std", feature = "image"))]
+mod canonical;
+
+#[cfg(feature = "std")]
+mod frames;
+
+#[cfg(feature = "std")]
+mod frozen;
+
+#[cfg(feature = "std")]
+mod index_file;
+
+#[cfg(feature = "std")]
+mod phash;
+
+#[cfg(feature = "std")]
+mod stretch;
+
+#[cfg(feature = "alloc")]
+mod bundle;
+
+#[cfg(feature = "alloc")]
+mod bursts;
+
+#[cfg(feature = "alloc")]
+mod crop;
+
+#[cfg(feature = "alloc")]
+mod delta;
+
+#[cfg(feature = "alloc")]
+mod dyn_hash;
+
+#[cfg(feature = "alloc")]
+mod exclude;
+
+#[cfg(feature = "alloc")]
+mod expiring;
+
+#[cfg(feature = "alloc")]
+mod explain;
+
+#[cfg(feature = "alloc")]
+mod index;
+
+#[cfg(feature = "alloc")]
+mod leakage;
+
+#[cfg(feature = "alloc")]
+mod matcher;
+
+#[cfg(feature = "alloc")]
+mod matching;
+
+#[cfg(feature = "alloc")]
+mod slides;
+
+#[cfg(feature = "alloc")]
+mod sprites;
+
+#[cfg(feature = "alloc")]
+mod static_index;
+
+#[cfg(feature = "alloc")]
+mod stats;
+
+#[cfg(feature = "alloc")]
+mod tiled;
+
+use any::downsample;
+use core::fmt::{self, Debug, Display, Formatter};
+use core::hash::Hash;
 use core::str::FromStr;
 use hash::blockhash;
+use order::{flip, repack};
+
+pub use ahash::{ahash256, ahash64};
+pub use any::{AnyBlockhash, SizePolicy};
+pub use blocklist::Blocklist;
+pub use bucket::bucket_code;
+pub use capabilities::{capabilities, Capabilities};
+pub use captions::CaptionStrips;
+pub use compare::{letterbox_distance, watermark_distance, ActiveRegion, Corner, PartialDistance};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use compare::{localize_changes, BlockRect};
+pub use custom::{blockhash_custom, blockhash_grid};
+pub use gate::{DiffGate, Verdict};
+pub use gradient::Gradient;
+pub use hash::{Accumulation, Threshold};
+pub use jitter::Jittered;
+pub use letterbox::Letterboxed;
+pub use lut::Lut;
+#[doc(hidden)]
+pub use macros::__private;
+pub use multiscale::{blockhash_multiscale, ProgressiveHash};
+pub use options::{Background, BlockhashOptions, HashStats, LumaWeights, OptionsError};
+pub use order::BitOrder;
+pub use pyramid::BlockhashPyramid;
+pub use quality::{blockhash_with_quality, ImageQuality};
+pub use raw::{Channel, RawImage};
+pub use redact::RedactedHash;
+pub use region::Region;
+pub use sampled::{Sampled, Sampler};
+pub use saturation::{blockhash256_saturation, blockhash64_saturation};
+pub use selftest::{self_test, SelfTestError};
+pub use similar::{check_similar, NotSimilar};
+pub use soft::{soft_blockhash256, SoftBlockhash256};
+pub use ternary::TernaryBlockhash;
+pub use thermal::{Normalization, ThermalFrame};
+pub use trim::Trimmed;
+pub use values::{
+    blockhash144_with_values, blockhash16_with_values, blockhash256_with_values,
+    blockhash64_with_values,
+};
+pub use windowed::Windowed;
+
+#[cfg(feature = "icc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "icc")))]
+pub use color::ColorSpace;
+
+#[cfg(feature = "image")]
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+pub use img::{
+    blockhash1024_dynamic, blockhash144_dynamic, blockhash16_dynamic, blockhash256_dynamic,
+    blockhash64_dynamic,
+};
+
+#[cfg(feature = "image")]
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+pub use report::comparison_image;
+
+#[cfg(all(feature = "std", feature = "image"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "image"))))]
+pub use canonical::canonical_hash;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use frames::hash_frames;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use frozen::{FrozenIndex, IndexBuilder};
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use index_file::{
+    Corruption, CorruptionKind, HashAlgorithm, IndexFile, IndexFileError, Verification,
+};
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use phash::{phash256, phash64};
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use stretch::Stretched;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use bundle::{concat_digests, split_digests, BundleError};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use bursts::{summarize_bursts, Burst};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use crop::{count_matching_segments, crop_resistant_hash};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use delta::DeltaHasher;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use exclude::Excluded;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use expiring::ExpiringIndex;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use explain::{explain_match, BlockContribution, Explanation};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use dyn_hash::BlockhashDyn;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use index::{Cursor, HashIndex, Page};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use leakage::{find_leakage, LeakageReport};
+
+#[cfg(all(feature = "std", feature = "image"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "image"))))]
+pub use leakage::find_leakage_in_files;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use matcher::{MatchOutcome, Matcher};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use matching::{match_hashes, Assignment, Match};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use slides::{detect_slides, Slide};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use sprites::hash_sprites;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use static_index::{StaticIndex, StaticIndexError};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use stats::{distance_histogram, DistanceHistogram};
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use tiled::{best_tile, best_tile_distance, blockhash256_tiles, TileMatch};
 
 fn distance<const SIZE: usize>(left: &[u8; SIZE], right: &[u8; SIZE]) -> u32 {
     let mut dist = 0;
 
     for i in 0..SIZE {
-        dist += (left[i] ^ right[i]).count_ones();
+        dist += popcount::count_ones(left[i] ^ right[i]);
     }
 
     dist
@@ -64,9 +392,7 @@ fn parse_char(c: u8) -> Result<u8, BlockhashParseError> {
     Ok(val)
 }
 
-fn parse_hash<const SIZE: usize>(s: &str) -> Result<[u8; SIZE], BlockhashParseError> {
-    let s = s.as_bytes();
-
+fn parse_hash<const SIZE: usize>(s: &[u8]) -> Result<[u8; SIZE], BlockhashParseError> {
     if s.len() != SIZE * 2 {
         return Err(BlockhashParseError);
     }
@@ -80,6 +406,14 @@ fn parse_hash<const SIZE: usize>(s: &str) -> Result<[u8; SIZE], BlockhashParseEr
     Ok(bytes)
 }
 
+fn parse_ascii<const SIZE: usize>(s: &[u8]) -> Result<[u8; SIZE], BlockhashParseError> {
+    let len = s
+        .iter()
+        .rposition(|&c| c != 0 && c != b' ')
+        .map_or(0, |i| i + 1);
+    parse_hash(&s[..len])
+}
+
 fn fmt_hash<const SIZE: usize>(f: &mut Formatter, hash: [u8; SIZE]) -> fmt::Result {
     for byte in hash {
         write!(f, "{:02x}", byte)?;
@@ -102,33 +436,80 @@ impl Display for BlockhashParseError {
 #[cfg(feature = "std")]
 impl std::error::Error for BlockhashParseError {}
 
-/// Generates a 16-bit perceptual hash of an image.
+/// A hash digest of `BYTES` bytes.
+///
+/// The digest types of this crate, such as [`Blockhash64`], are aliases of
+/// this type, and implement [`Digest`]. Code that works with hashes of any
+/// size can either be generic over [`Digest`], or over the number of bytes,
+/// with a bound on [`Digest`] for the methods that depend on the grid.
+/// Methods that only apply to some sizes, such as
+/// [`downsample16`](Blockhash64::downsample16), are documented on each alias.
 ///
 /// # Examples
 ///
 /// ```
-/// # #[cfg(feature = "image")] {
-/// use blockhash::{blockhash16, Blockhash16};
+/// use blockhash::{Blockhash, Blockhash16, Blockhash64, Digest};
 ///
-/// let img = image::open("images/example.png").unwrap();
-/// let hash = blockhash16(&img);
+/// fn closest<const BYTES: usize>(
+///     hash: &Blockhash<BYTES>,
+///     candidates: &[Blockhash<BYTES>],
+/// ) -> Option<u32>
+/// where
+///     Blockhash<BYTES>: Digest,
+/// {
+///     candidates.iter().map(|other| hash.distance(other)).min()
+/// }
 ///
-/// assert_eq!(hash.to_string(), "a396");
-/// # }
+/// let hash = Blockhash16::from(0xa396);
+/// assert_eq!(closest(&hash, &[Blockhash16::from(0xa397)]), Some(1));
+///
+/// let hash = Blockhash64::from(0xc7c4_8f89_89c7_7e0c);
+/// assert_eq!(closest(&hash, &[]), None);
 /// ```
-#[inline]
-#[must_use]
-pub fn blockhash16<I: Image>(img: &I) -> Blockhash16 {
-    Blockhash16(blockhash::<I, 4, 16, 2>(img))
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Blockhash<const BYTES: usize>([u8; BYTES]);
+
+// Named after the alias of each size, such as `Blockhash64([..])`, rather
+// than the generic struct
+impl<const BYTES: usize> Debug for Blockhash<BYTES> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Blockhash{}(", BYTES * 8)?;
+        Debug::fmt(&self.0, f)?;
+        f.write_str(")")
+    }
 }
 
-/// A 16-bit hash digest.
-///
-/// See [`blockhash16`].
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Blockhash16([u8; 2]);
+impl<const BYTES: usize> FromStr for Blockhash<BYTES> {
+    type Err = BlockhashParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hash(s.as_bytes()).map(Self)
+    }
+}
+
+impl<const BYTES: usize> Display for Blockhash<BYTES> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_hash(f, self.0)
+    }
+}
+
+impl<const BYTES: usize> From<[u8; BYTES]> for Blockhash<BYTES> {
+    #[inline]
+    fn from(bytes: [u8; BYTES]) -> Self {
+        Blockhash(bytes)
+    }
+}
+
+impl<const BYTES: usize> From<Blockhash<BYTES>> for [u8; BYTES] {
+    #[inline]
+    fn from(hash: Blockhash<BYTES>) -> Self {
+        hash.0
+    }
+}
 
-impl Blockhash16 {
+impl<const BYTES: usize> Blockhash<BYTES> {
     /// Returns the Hamming distance between two hashes.
     ///
     /// # Examples
@@ -143,46 +524,172 @@ impl Blockhash16 {
     /// ```
     #[inline]
     #[must_use]
-    #[allow(clippy::trivially_copy_pass_by_ref)]
     pub fn distance(&self, other: &Self) -> u32 {
         distance(&self.0, &other.0)
     }
-}
 
-impl FromStr for Blockhash16 {
-    type Err = BlockhashParseError;
+    /// Parses a hash from hexadecimal ASCII bytes.
+    ///
+    /// This is the same as parsing a string with [`str::parse`], but doesn't
+    /// require the bytes to be validated as UTF-8 first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes aren't exactly the right number of
+    /// hexadecimal digits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{Blockhash16, Blockhash64};
+    ///
+    /// let hash = Blockhash16::from_hex_bytes(b"a396").unwrap();
+    ///
+    /// assert_eq!(hash.to_string(), "a396");
+    /// assert!(Blockhash16::from_hex_bytes(b"a396 ").is_err());
+    /// assert!(Blockhash64::from_hex_bytes(b"a396").is_err());
+    /// ```
+    #[inline]
+    pub fn from_hex_bytes(hex: &[u8]) -> Result<Self, BlockhashParseError> {
+        parse_hash(hex).map(Self)
+    }
 
+    /// Parses a hash from hexadecimal ASCII bytes that may be padded at the
+    /// end with spaces or NUL bytes, such as a fixed-width database column.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes, without padding, aren't exactly the
+    /// right number of hexadecimal digits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::Blockhash64;
+    ///
+    /// let column = *b"c7c48f8989c77e0c\0\0\0\0";
+    /// let hash = Blockhash64::from_ascii(&column).unwrap();
+    ///
+    /// assert_eq!(hash.to_string(), "c7c48f8989c77e0c");
+    /// ```
     #[inline]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_hash(s).map(Self)
+    pub fn from_ascii(ascii: &[u8]) -> Result<Self, BlockhashParseError> {
+        parse_ascii(ascii).map(Self)
     }
 }
 
-impl Display for Blockhash16 {
+impl<const BYTES: usize> Blockhash<BYTES>
+where
+    Self: Digest,
+{
+    /// Returns the bytes of the hash, with the bits packed in the given order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{BitOrder, Blockhash16, Blockhash64};
+    ///
+    /// let hash = Blockhash16::from(0x4000);
+    ///
+    /// let bytes = hash.to_bytes_ordered(BitOrder::ColumnMajorMsbFirst);
+    /// assert_eq!(bytes, [0x08, 0x00]);
+    /// assert_eq!(Blockhash16::from_bytes_ordered(bytes, BitOrder::ColumnMajorMsbFirst), hash);
+    ///
+    /// let hash = Blockhash64::from(0x4000_0000_0000_0000);
+    ///
+    /// let bytes = hash.to_bytes_ordered(BitOrder::RowMajorLsbFirst);
+    /// assert_eq!(bytes, [0x02, 0, 0, 0, 0, 0, 0, 0]);
+    /// ```
     #[inline]
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        fmt_hash(f, self.0)
+    #[must_use]
+    pub fn to_bytes_ordered(&self, order: BitOrder) -> [u8; BYTES] {
+        repack(
+            &self.0,
+            Self::COLUMNS,
+            Self::ROWS,
+            BitOrder::RowMajorMsbFirst,
+            order,
+        )
     }
-}
 
-impl From<[u8; 2]> for Blockhash16 {
+    /// Creates a hash from bytes with the bits packed in the given order.
+    ///
+    /// See [`to_bytes_ordered`](Self::to_bytes_ordered).
     #[inline]
-    fn from(bytes: [u8; 2]) -> Self {
-        Blockhash16(bytes)
+    #[must_use]
+    pub fn from_bytes_ordered(bytes: [u8; BYTES], order: BitOrder) -> Self {
+        Blockhash(repack(
+            &bytes,
+            Self::COLUMNS,
+            Self::ROWS,
+            order,
+            BitOrder::RowMajorMsbFirst,
+        ))
     }
-}
 
-impl From<Blockhash16> for [u8; 2] {
+    /// Returns the hash of the horizontal mirror image of the image.
+    ///
+    /// Mirroring an image swaps the blocks in each row of the grid, so the
+    /// hash of a mirrored image can be computed from the original hash. This
+    /// is exact when the block edges fall on pixel boundaries, and very close
+    /// otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::Blockhash16;
+    ///
+    /// let hash = Blockhash16::from(0x8000);
+    ///
+    /// assert_eq!(hash.flip_horizontal(), Blockhash16::from(0x1000));
+    /// assert_eq!(hash.flip_vertical(), Blockhash16::from(0x0008));
+    /// ```
     #[inline]
-    fn from(hash: Blockhash16) -> Self {
-        hash.0
+    #[must_use]
+    pub fn flip_horizontal(&self) -> Self {
+        Blockhash(flip(&self.0, Self::COLUMNS, Self::ROWS, true, false))
     }
+
+    /// Returns the hash of the vertical mirror image of the image.
+    ///
+    /// Mirroring an image vertically reverses the order of the rows of the
+    /// grid. See [`flip_horizontal`](Self::flip_horizontal).
+    #[inline]
+    #[must_use]
+    pub fn flip_vertical(&self) -> Self {
+        Blockhash(flip(&self.0, Self::COLUMNS, Self::ROWS, false, true))
+    }
+}
+
+/// Generates a 16-bit perceptual hash of an image.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash16, Blockhash16};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = blockhash16(&img);
+///
+/// assert_eq!(hash.to_string(), "a396");
+/// # }
+/// ```
+#[inline]
+#[must_use]
+pub fn blockhash16<I: Image>(img: &I) -> Blockhash16 {
+    Blockhash(blockhash::<I, 4, 16, 2>(img))
 }
 
+/// A 16-bit hash digest.
+///
+/// See [`blockhash16`].
+pub type Blockhash16 = Blockhash<2>;
+
 impl From<u16> for Blockhash16 {
     #[inline]
     fn from(int: u16) -> Self {
-        Blockhash16(int.to_be_bytes())
+        Blockhash(int.to_be_bytes())
     }
 }
 
@@ -193,6 +700,27 @@ impl From<Blockhash16> for u16 {
     }
 }
 
+impl Digest for Blockhash16 {
+    const BITS: u32 = 16;
+    const COLUMNS: u32 = 4;
+    const ROWS: u32 = 4;
+
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[inline]
+    fn distance(&self, other: &Self) -> u32 {
+        distance(&self.0, &other.0)
+    }
+
+    #[inline]
+    fn from_image<I: Image>(img: &I) -> Self {
+        blockhash16(img)
+    }
+}
+
 /// Generates a 64-bit perceptual hash of an image.
 ///
 /// # Examples
@@ -210,81 +738,71 @@ impl From<Blockhash16> for u16 {
 #[inline]
 #[must_use]
 pub fn blockhash64<I: Image>(img: &I) -> Blockhash64 {
-    Blockhash64(blockhash::<I, 8, 64, 8>(img))
+    Blockhash(blockhash::<I, 8, 64, 8>(img))
 }
 
 /// A 64-bit hash digest.
 ///
 /// See [`blockhash64`].
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Blockhash64([u8; 8]);
+pub type Blockhash64 = Blockhash<8>;
 
 impl Blockhash64 {
-    /// Returns the Hamming distance between two hashes.
+    /// Reduces the hash to a 16-bit hash, by taking the majority value of the
+    /// blocks that overlap each block of the coarser grid.
+    ///
+    /// This approximates the hash of the image at the smaller size, without
+    /// having to hash the image again, such as for a coarse key in the first
+    /// stage of a search. If the blocks are split evenly, the value of the
+    /// top-left block is used.
     ///
     /// # Examples
     ///
     /// ```
-    /// use blockhash::Blockhash64;
+    /// use blockhash::{Blockhash16, Blockhash64};
     ///
-    /// let a = Blockhash64::from([
-    ///     0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef,
-    /// ]);
-    /// let b = Blockhash64::from([
-    ///     0xd0, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xff,
-    /// ]);
+    /// let hash = Blockhash64::from(0xf0f0_f0f0_0c0c_0c0c);
     ///
-    /// assert_eq!(a.distance(&b), 4);
+    /// assert_eq!(hash.downsample16(), Blockhash16::from(0xcc22));
     /// ```
     #[inline]
     #[must_use]
-    #[allow(clippy::trivially_copy_pass_by_ref)]
-    pub fn distance(&self, other: &Self) -> u32 {
-        distance(&self.0, &other.0)
+    pub fn downsample16(&self) -> Blockhash16 {
+        Blockhash(downsample(&self.0, 8, 4))
     }
 }
 
-impl FromStr for Blockhash64 {
-    type Err = BlockhashParseError;
-
+impl From<u64> for Blockhash64 {
     #[inline]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_hash(s).map(Self)
+    fn from(int: u64) -> Self {
+        Blockhash(int.to_be_bytes())
     }
 }
 
-impl Display for Blockhash64 {
+impl From<Blockhash64> for u64 {
     #[inline]
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        fmt_hash(f, self.0)
+    fn from(hash: Blockhash64) -> Self {
+        u64::from_be_bytes(hash.0)
     }
 }
 
-impl From<[u8; 8]> for Blockhash64 {
-    #[inline]
-    fn from(bytes: [u8; 8]) -> Self {
-        Blockhash64(bytes)
-    }
-}
+impl Digest for Blockhash64 {
+    const BITS: u32 = 64;
+    const COLUMNS: u32 = 8;
+    const ROWS: u32 = 8;
 
-impl From<Blockhash64> for [u8; 8] {
     #[inline]
-    fn from(hash: Blockhash64) -> Self {
-        hash.0
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
     }
-}
 
-impl From<u64> for Blockhash64 {
     #[inline]
-    fn from(int: u64) -> Self {
-        Blockhash64(int.to_be_bytes())
+    fn distance(&self, other: &Self) -> u32 {
+        distance(&self.0, &other.0)
     }
-}
 
-impl From<Blockhash64> for u64 {
     #[inline]
-    fn from(hash: Blockhash64) -> Self {
-        u64::from_be_bytes(hash.0)
+    fn from_image<I: Image>(img: &I) -> Self {
+        blockhash64(img)
     }
 }
 
@@ -305,68 +823,52 @@ impl From<Blockhash64> for u64 {
 #[inline]
 #[must_use]
 pub fn blockhash144<I: Image>(img: &I) -> Blockhash144 {
-    Blockhash144(blockhash::<I, 12, 144, 18>(img))
+    Blockhash(blockhash::<I, 12, 144, 18>(img))
 }
 
 /// A 144-bit hash digest.
 ///
 /// See [`blockhash144`].
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Blockhash144([u8; 18]);
+pub type Blockhash144 = Blockhash<18>;
 
 impl Blockhash144 {
-    /// Returns the Hamming distance between two hashes.
-    ///
-    /// # Examples
+    /// Reduces the hash to a 16-bit hash.
     ///
-    /// ```
-    /// use blockhash::Blockhash144;
-    ///
-    /// let a = Blockhash144::from([
-    ///     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88,
-    ///     0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x12, 0x34,
-    /// ]);
-    /// let b = Blockhash144::from([
-    ///     0x00, 0x11, 0x22, 0x33, 0x22, 0x55, 0x66, 0x77, 0x88,
-    ///     0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xe7, 0xff, 0x12, 0x34,
-    /// ]);
-    ///
-    /// assert_eq!(a.distance(&b), 6);
-    /// ```
+    /// See [`Blockhash64::downsample16`].
     #[inline]
     #[must_use]
-    pub fn distance(&self, other: &Self) -> u32 {
-        distance(&self.0, &other.0)
+    pub fn downsample16(&self) -> Blockhash16 {
+        Blockhash(downsample(&self.0, 12, 4))
     }
-}
-
-impl FromStr for Blockhash144 {
-    type Err = BlockhashParseError;
 
+    /// Reduces the hash to a 64-bit hash.
+    ///
+    /// See [`Blockhash64::downsample16`].
     #[inline]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_hash(s).map(Self)
+    #[must_use]
+    pub fn downsample64(&self) -> Blockhash64 {
+        Blockhash(downsample(&self.0, 12, 8))
     }
 }
 
-impl Display for Blockhash144 {
+impl Digest for Blockhash144 {
+    const BITS: u32 = 144;
+    const COLUMNS: u32 = 12;
+    const ROWS: u32 = 12;
+
     #[inline]
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        fmt_hash(f, self.0)
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
     }
-}
 
-impl From<[u8; 18]> for Blockhash144 {
     #[inline]
-    fn from(bytes: [u8; 18]) -> Self {
-        Blockhash144(bytes)
+    fn distance(&self, other: &Self) -> u32 {
+        distance(&self.0, &other.0)
     }
-}
 
-impl From<Blockhash144> for [u8; 18] {
     #[inline]
-    fn from(hash: Blockhash144) -> Self {
-        hash.0
+    fn from_image<I: Image>(img: &I) -> Self {
+        blockhash144(img)
     }
 }
 
@@ -390,75 +892,181 @@ impl From<Blockhash144> for [u8; 18] {
 #[inline]
 #[must_use]
 pub fn blockhash256<I: Image>(img: &I) -> Blockhash256 {
-    Blockhash256(blockhash::<I, 16, 256, 32>(img))
+    Blockhash(blockhash::<I, 16, 256, 32>(img))
 }
 
 /// A 256-bit hash digest.
 ///
 /// See [`blockhash256`].
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Blockhash256([u8; 32]);
+pub type Blockhash256 = Blockhash<32>;
 
 impl Blockhash256 {
-    /// Returns the Hamming distance between two hashes.
+    /// Reduces the hash to a 16-bit hash.
     ///
-    /// # Examples
+    /// See [`Blockhash64::downsample16`].
+    #[inline]
+    #[must_use]
+    pub fn downsample16(&self) -> Blockhash16 {
+        Blockhash(downsample(&self.0, 16, 4))
+    }
+
+    /// Reduces the hash to a 64-bit hash.
     ///
-    /// ```
-    /// use blockhash::Blockhash256;
-    ///
-    /// let a = Blockhash256::from([
-    ///     0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7,
-    ///     0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe, 0xff,
-    ///     0xff, 0xef, 0xdf, 0xcf, 0xbf, 0xaf, 0x9f, 0x8f,
-    ///     0x7f, 0x6f, 0x5f, 0x4f, 0x3f, 0x2f, 0x1f, 0x0f,
-    /// ]);
-    /// let b = Blockhash256::from([
-    ///     0x00, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7,
-    ///     0xf8, 0xf9, 0x3a, 0xfb, 0xfc, 0xfd, 0x0e, 0xff,
-    ///     0xff, 0xff, 0xdf, 0xcf, 0xbf, 0xaf, 0x9f, 0x8f,
-    ///     0x7f, 0x6f, 0x5f, 0x4f, 0x3f, 0x2f, 0x1f, 0x0f,
-    /// ]);
-    ///
-    /// assert_eq!(a.distance(&b), 11);
-    /// ```
+    /// See [`Blockhash64::downsample16`].
     #[inline]
     #[must_use]
-    pub fn distance(&self, other: &Self) -> u32 {
-        distance(&self.0, &other.0)
+    pub fn downsample64(&self) -> Blockhash64 {
+        Blockhash(downsample(&self.0, 16, 8))
+    }
+
+    /// Reduces the hash to a 144-bit hash.
+    ///
+    /// See [`Blockhash64::downsample16`].
+    #[inline]
+    #[must_use]
+    pub fn downsample144(&self) -> Blockhash144 {
+        Blockhash(downsample(&self.0, 16, 12))
     }
 }
 
-impl FromStr for Blockhash256 {
-    type Err = BlockhashParseError;
+impl Digest for Blockhash256 {
+    const BITS: u32 = 256;
+    const COLUMNS: u32 = 16;
+    const ROWS: u32 = 16;
 
     #[inline]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_hash(s).map(Self)
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
     }
-}
 
-impl Display for Blockhash256 {
     #[inline]
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        fmt_hash(f, self.0)
+    fn distance(&self, other: &Self) -> u32 {
+        distance(&self.0, &other.0)
+    }
+
+    #[inline]
+    fn from_image<I: Image>(img: &I) -> Self {
+        blockhash256(img)
     }
 }
 
-impl From<[u8; 32]> for Blockhash256 {
+/// Generates a 1024-bit perceptual hash of an image.
+///
+/// This uses a grid of 32 by 32 blocks, which distinguishes between images
+/// with finer differences than [`blockhash256`], such as in large collections
+/// of high-resolution scans, at the cost of being more sensitive to small
+/// changes.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash1024, Blockhash1024};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = blockhash1024(&img);
+///
+/// assert_eq!(
+///     hash.to_string(),
+///     concat!(
+///         "fc003ffdfc403ffcffc03ffcff007ff8ff007f80fe007f00fc005700f8000300",
+///         "f801fffff800fffff000fffff000fffff0007dfff000381de000300de000200f",
+///         "f000380ff0003007f8003816f0003cf7fd0016e7ff120fe7ff901bfffff407ff",
+///         "3f723fe53ff87fb43ffff9201efcfbbc0ffeff880864cd00000049000041d162",
+///     ),
+/// );
+/// # }
+/// ```
+#[inline]
+#[must_use]
+pub fn blockhash1024<I: Image>(img: &I) -> Blockhash1024 {
+    Blockhash(blockhash::<I, 32, 1024, 128>(img))
+}
+
+/// A 1024-bit hash digest.
+///
+/// See [`blockhash1024`].
+pub type Blockhash1024 = Blockhash<128>;
+
+impl Blockhash1024 {
+    /// Reduces the hash to a 16-bit hash.
+    ///
+    /// See [`Blockhash64::downsample16`].
+    #[inline]
+    #[must_use]
+    pub fn downsample16(&self) -> Blockhash16 {
+        Blockhash(downsample(&self.0, 32, 4))
+    }
+
+    /// Reduces the hash to a 64-bit hash.
+    ///
+    /// See [`Blockhash64::downsample16`].
+    #[inline]
+    #[must_use]
+    pub fn downsample64(&self) -> Blockhash64 {
+        Blockhash(downsample(&self.0, 32, 8))
+    }
+
+    /// Reduces the hash to a 256-bit hash.
+    ///
+    /// See [`Blockhash64::downsample16`].
     #[inline]
-    fn from(bytes: [u8; 32]) -> Self {
-        Blockhash256(bytes)
+    #[must_use]
+    pub fn downsample256(&self) -> Blockhash256 {
+        Blockhash(downsample(&self.0, 32, 16))
     }
 }
 
-impl From<Blockhash256> for [u8; 32] {
+impl Digest for Blockhash1024 {
+    const BITS: u32 = 1024;
+    const COLUMNS: u32 = 32;
+    const ROWS: u32 = 32;
+
     #[inline]
-    fn from(hash: Blockhash256) -> Self {
-        hash.0
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[inline]
+    fn distance(&self, other: &Self) -> u32 {
+        distance(&self.0, &other.0)
+    }
+
+    #[inline]
+    fn from_image<I: Image>(img: &I) -> Self {
+        blockhash1024(img)
     }
 }
 
+/// A hash digest.
+///
+/// This trait is implemented by all of the digest types in this crate, and can
+/// be used to write code that is generic over the size of the hash.
+pub trait Digest:
+    Copy + Eq + Ord + Hash + Debug + Display + FromStr<Err = BlockhashParseError>
+{
+    /// The number of bits in the digest.
+    const BITS: u32;
+
+    /// The number of columns of blocks in the grid.
+    const COLUMNS: u32;
+
+    /// The number of rows of blocks in the grid.
+    const ROWS: u32;
+
+    /// Returns the bytes of the digest.
+    ///
+    /// The bits for each block are stored in row-major order, with the first
+    /// block in the most significant bit of the first byte.
+    fn as_bytes(&self) -> &[u8];
+
+    /// Returns the Hamming distance between two hashes.
+    fn distance(&self, other: &Self) -> u32;
+
+    /// Generates a perceptual hash of an image.
+    fn from_image<I: Image>(img: &I) -> Self;
+}
+
 /// Image data.
 ///
 /// This trait can be implemented on image types in order to add support for
@@ -466,14 +1074,46 @@ impl From<Blockhash256> for [u8; 32] {
 ///
 /// If the `image` feature is enabled (the default), this trait is automatically
 /// implemented for images from the [`image`] crate.
+///
+/// Note that this includes views created with
+/// [`GenericImageView::view`](image::GenericImageView::view) only once they
+/// have been dereferenced (`&*view`). Alternatively, a view can be converted
+/// into a [`Region`], which works the same way for any type implementing this
+/// trait.
 pub trait Image {
-    /// The maximum possible brightness for a pixel.
-    const MAX_BRIGHTNESS: u32;
-
     /// Returns the dimensions of the image.
     fn dimensions(&self) -> (u32, u32);
 
     /// Returns the brightness of the pixel at the given position in the image, in
-    /// the range `0..=MAX_BRIGHTNESS`.
+    /// the range `0..=max_brightness()`.
     fn brightness(&self, x: u32, y: u32) -> u32;
+
+    /// Returns the maximum possible brightness for a pixel in this image.
+    ///
+    /// This is the only source of the range of brightness, so images whose
+    /// range is only known at runtime, such as adapters, can return it here.
+    fn max_brightness(&self) -> u32;
+
+    /// Returns the red, green, blue, and alpha channels of the pixel at the given
+    /// position in the image, each in the range `0..=max_channel()`.
+    ///
+    /// This is only used by adapters that need to know about individual
+    /// channels, such as [`Lut`]. The default implementation returns an opaque
+    /// gray pixel, with each color channel set to the brightness of the pixel.
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        let val = self.brightness(x, y);
+        [val, val, val, self.max_channel()]
+    }
+
+    /// Returns the maximum possible value of a color channel in this image.
+    ///
+    /// Defaults to [`max_brightness`](Self::max_brightness), which matches
+    /// the default implementation of [`channels`](Self::channels). Images
+    /// that override `channels` with a different range must override this as
+    /// well.
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.max_brightness()
+    }
 }
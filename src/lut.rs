@@ -0,0 +1,103 @@
+use crate::Image;
+
+/// An image whose channels are mapped through a lookup table.
+///
+/// The brightness of each pixel is the sum of the table entries for its red,
+/// green, and blue channels, which allows applying custom gamma curves,
+/// thresholds, or sensor linearization before hashing. Fully transparent pixels
+/// are treated as having the maximum brightness.
+///
+/// The table must have one entry for every possible channel value: 256 entries
+/// for images with 8-bit channels, or 65536 entries for images with 16-bit
+/// channels.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, Lut};
+///
+/// let img = image::open("images/example.png").unwrap();
+///
+/// // Invert the image
+/// let table: Vec<u16> = (0..=255).rev().collect();
+/// let hash = blockhash64(&Lut::new(&img, &table));
+///
+/// assert_eq!(hash.to_string(), "383b7076763881f3");
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Lut<'a, I> {
+    img: &'a I,
+    table: &'a [u16],
+    max_entry: u32,
+}
+
+impl<'a, I: Image> Lut<'a, I> {
+    /// Creates an image whose channels are mapped through the given table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the table isn't one more than the
+    /// [`max_channel`](Image::max_channel) of the image.
+    #[must_use]
+    pub fn new(img: &'a I, table: &'a [u16]) -> Self {
+        assert_eq!(table.len() as u64, u64::from(img.max_channel()) + 1);
+
+        let max_entry = table.iter().copied().max().unwrap_or(0);
+
+        Lut {
+            img,
+            table,
+            max_entry: max_entry.into(),
+        }
+    }
+}
+
+impl<I: Image> Image for Lut<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let [r, g, b, a] = self.img.channels(x, y);
+        match a {
+            0 => self.max_brightness(),
+            _ => {
+                u32::from(self.table[r as usize])
+                    + u32::from(self.table[g as usize])
+                    + u32::from(self.table[b as usize])
+            }
+        }
+    }
+
+    /// Returns the table entries for the color channels, and the alpha
+    /// channel scaled to the range of the table.
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        let [r, g, b, a] = self.img.channels(x, y);
+        let max_alpha = u64::from(self.img.max_channel().max(1));
+        let scaled = u64::from(a) * u64::from(self.max_entry);
+        // Only fully transparent pixels stay fully transparent
+        let a = (((scaled + max_alpha / 2) / max_alpha) as u32)
+            .clamp(u32::from(a > 0).min(self.max_entry), self.max_entry);
+        [
+            self.table[r as usize].into(),
+            self.table[g as usize].into(),
+            self.table[b as usize].into(),
+            a,
+        ]
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.max_entry * 3
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.max_entry
+    }
+}
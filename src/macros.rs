@@ -0,0 +1,210 @@
+/// Defines a digest type for a custom grid size.
+///
+/// `define_blockhash!(Blockhash400, 20)` defines a type `Blockhash400` for the
+/// hashes of a grid of 20 by 20 blocks, which works like the digest types of
+/// this crate: it implements [`Digest`], [`Display`], and [`FromStr`], and can
+/// be converted to and from an array of bytes. Images are hashed with
+/// `from_image`, in the same way as [`blockhash_custom`]. The type can be
+/// given a visibility and attributes, such as doc comments.
+///
+/// A rectangular grid is defined by giving the number of columns and rows
+/// instead, as in `define_blockhash!(Blockhash16x9, 16, 9)`, and is hashed in
+/// the same way as [`blockhash_grid`]. The shape of the grid is recorded in
+/// [`Digest::COLUMNS`] and [`Digest::ROWS`].
+///
+/// The sizes must be integer literals, and the number of blocks must be a
+/// multiple of 4. Anything else fails to compile.
+///
+/// [`Digest`]: crate::Digest
+/// [`Display`]: core::fmt::Display
+/// [`FromStr`]: core::str::FromStr
+/// [`blockhash_custom`]: crate::blockhash_custom
+/// [`blockhash_grid`]: crate::blockhash_grid
+/// [`Digest::COLUMNS`]: crate::Digest::COLUMNS
+/// [`Digest::ROWS`]: crate::Digest::ROWS
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash_custom, define_blockhash, Digest};
+///
+/// define_blockhash!(
+///     /// A 400-bit hash digest.
+///     pub Blockhash400, 20
+/// );
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = Blockhash400::from_image(&img);
+///
+/// assert_eq!(Blockhash400::BITS, 400);
+/// assert_eq!(hash.as_bytes(), blockhash_custom::<_, 20, 400, 50>(&img));
+/// assert_eq!(hash.to_string().parse(), Ok(hash));
+///
+/// define_blockhash!(Blockhash16x9, 16, 9);
+///
+/// assert_eq!((Blockhash16x9::COLUMNS, Blockhash16x9::ROWS), (16, 9));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! define_blockhash {
+    ($(#[$attr:meta])* $vis:vis $name:ident, $size:literal) => {
+        $crate::define_blockhash!($(#[$attr])* $vis $name, $size, $size);
+    };
+    ($(#[$attr:meta])* $vis:vis $name:ident, $columns:literal, $rows:literal) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+        $vis struct $name([u8; ($columns * $rows + 7) / 8]);
+
+        const _: () = assert!(
+            $columns > 0 && $rows > 0 && $columns * $rows % 4 == 0,
+            "the number of blocks must be a non-zero multiple of 4",
+        );
+
+        impl $name {
+            /// Generates a perceptual hash of an image.
+            #[inline]
+            #[must_use]
+            $vis fn from_image<I: $crate::Image>(img: &I) -> Self {
+                $name($crate::blockhash_grid::<
+                    I,
+                    $columns,
+                    $rows,
+                    { $columns * $rows },
+                    { ($columns * $rows + 7) / 8 },
+                >(img))
+            }
+
+            /// Returns the Hamming distance between two hashes.
+            #[inline]
+            #[must_use]
+            $vis fn distance(&self, other: &Self) -> u32 {
+                $crate::__private::distance(&self.0, &other.0)
+            }
+        }
+
+        impl ::core::str::FromStr for $name {
+            type Err = $crate::BlockhashParseError;
+
+            #[inline]
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                $crate::__private::parse_hash(s.as_bytes()).map($name)
+            }
+        }
+
+        impl ::core::fmt::Display for $name {
+            #[inline]
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                $crate::__private::fmt_hash(f, self.0)
+            }
+        }
+
+        impl ::core::convert::From<[u8; ($columns * $rows + 7) / 8]> for $name {
+            #[inline]
+            fn from(bytes: [u8; ($columns * $rows + 7) / 8]) -> Self {
+                $name(bytes)
+            }
+        }
+
+        impl ::core::convert::From<$name> for [u8; ($columns * $rows + 7) / 8] {
+            #[inline]
+            fn from(hash: $name) -> Self {
+                hash.0
+            }
+        }
+
+        impl $crate::Digest for $name {
+            const BITS: u32 = $columns * $rows;
+            const COLUMNS: u32 = $columns;
+            const ROWS: u32 = $rows;
+
+            #[inline]
+            fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            #[inline]
+            fn distance(&self, other: &Self) -> u32 {
+                $crate::__private::distance(&self.0, &other.0)
+            }
+
+            #[inline]
+            fn from_image<I: $crate::Image>(img: &I) -> Self {
+                $name::from_image(img)
+            }
+        }
+    };
+}
+
+/// Asserts that an image is within a distance of an expected hash.
+///
+/// `assert_similar!(expected, image, max_distance)` hashes the image with the
+/// digest type of `expected`, and panics if the hashes are more than
+/// `max_distance` apart, like [`check_similar`]. The panic message shows both
+/// hashes and the grid of blocks whose bits differ. As with [`assert!`], a
+/// custom message can be given after the arguments.
+///
+/// This is intended for visual regression tests of screenshots, where exact
+/// comparisons fail because of antialiasing and other rendering noise.
+///
+/// [`check_similar`]: crate::check_similar
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{assert_similar, Blockhash64};
+///
+/// let expected: Blockhash64 = "c7c48f8989c77e0c".parse().unwrap();
+/// let screenshot = image::open("images/example.png").unwrap();
+///
+/// assert_similar!(expected, screenshot.blur(0.5), 4);
+/// assert_similar!(expected, screenshot, 0, "screenshot of {}", "example.png");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_similar {
+    ($expected:expr, $image:expr, $max_distance:expr $(,)?) => {
+        if let ::core::result::Result::Err(err) =
+            $crate::check_similar(&$expected, &$image, $max_distance)
+        {
+            ::core::panic!("assertion failed: image is not similar to hash\n{}", err);
+        }
+    };
+    ($expected:expr, $image:expr, $max_distance:expr, $($arg:tt)+) => {
+        if let ::core::result::Result::Err(err) =
+            $crate::check_similar(&$expected, &$image, $max_distance)
+        {
+            ::core::panic!(
+                "assertion failed: image is not similar to hash: {}\n{}",
+                ::core::format_args!($($arg)+),
+                err,
+            );
+        }
+    };
+}
+
+/// Functions used by the code generated by [`define_blockhash!`].
+///
+/// These aren't part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use crate::BlockhashParseError;
+    use core::fmt::{self, Formatter};
+
+    #[inline]
+    #[must_use]
+    pub fn distance<const SIZE: usize>(left: &[u8; SIZE], right: &[u8; SIZE]) -> u32 {
+        crate::distance(left, right)
+    }
+
+    #[inline]
+    pub fn parse_hash<const SIZE: usize>(s: &[u8]) -> Result<[u8; SIZE], BlockhashParseError> {
+        crate::parse_hash(s)
+    }
+
+    #[inline]
+    pub fn fmt_hash<const SIZE: usize>(f: &mut Formatter, hash: [u8; SIZE]) -> fmt::Result {
+        crate::fmt_hash(f, hash)
+    }
+}
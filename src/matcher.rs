@@ -0,0 +1,157 @@
+use crate::{Digest, HashIndex};
+use alloc::vec::Vec;
+
+/// The result of offering a hash to a [`Matcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchOutcome<T> {
+    /// No kept hash was within the near-duplicate distance, so the hash was
+    /// kept.
+    New,
+    /// The hash is a duplicate of a kept hash.
+    Duplicate {
+        /// The ID of the kept hash.
+        of: T,
+        /// The distance to the kept hash.
+        distance: u32,
+    },
+    /// The hash is a near-duplicate of a kept hash.
+    NearDuplicate {
+        /// The ID of the kept hash.
+        of: T,
+        /// The distance to the kept hash.
+        distance: u32,
+    },
+}
+
+impl<T> MatchOutcome<T> {
+    /// Returns whether the hash was new.
+    #[inline]
+    #[must_use]
+    pub fn is_new(&self) -> bool {
+        matches!(self, MatchOutcome::New)
+    }
+}
+
+/// Decides whether each hash in a stream is new, a duplicate, or a
+/// near-duplicate of an earlier hash.
+///
+/// Each hash is compared against the hashes that have been kept so far. If
+/// the closest is within the duplicate distance, the hash is a duplicate, and
+/// if it is within the near-duplicate distance, it is a near-duplicate.
+/// Otherwise, the hash is new, and is kept so later hashes can be compared
+/// against it. Duplicates and near-duplicates aren't kept, so every kept hash
+/// represents a distinct image.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, MatchOutcome, Matcher};
+///
+/// let mut matcher = Matcher::new(8);
+///
+/// let a = Blockhash64::from(0xc7c4_8f89_89c7_7e0c);
+/// let b = Blockhash64::from(0xc7c4_8f89_89c7_7e0f);
+/// let c = Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f);
+///
+/// assert_eq!(matcher.offer(a, "a.png"), MatchOutcome::New);
+/// assert_eq!(
+///     matcher.offer(a, "a copy.png"),
+///     MatchOutcome::Duplicate { of: "a.png", distance: 0 },
+/// );
+/// assert_eq!(
+///     matcher.offer(b, "b.png"),
+///     MatchOutcome::NearDuplicate { of: "a.png", distance: 2 },
+/// );
+/// assert_eq!(matcher.offer(c, "c.png"), MatchOutcome::New);
+/// assert_eq!(matcher.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Matcher<D, T> {
+    index: HashIndex<D>,
+    ids: Vec<T>,
+    duplicate_distance: u32,
+    near_distance: u32,
+}
+
+impl<D: Digest, T: Clone> Matcher<D, T> {
+    /// Creates a matcher with the given near-duplicate distance.
+    ///
+    /// Only identical hashes are considered duplicates, unless the duplicate
+    /// distance is changed with
+    /// [`duplicate_distance`](Self::duplicate_distance).
+    #[inline]
+    #[must_use]
+    pub fn new(near_distance: u32) -> Self {
+        Matcher {
+            index: HashIndex::new(),
+            ids: Vec::new(),
+            duplicate_distance: 0,
+            near_distance,
+        }
+    }
+
+    /// Sets the maximum distance for a hash to be considered a duplicate.
+    ///
+    /// This should be no greater than the near-duplicate distance. If it is
+    /// greater, hashes within the duplicate distance are still duplicates.
+    #[inline]
+    #[must_use]
+    pub fn duplicate_distance(mut self, distance: u32) -> Self {
+        self.duplicate_distance = distance;
+        self
+    }
+
+    /// Returns the number of kept hashes.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns whether no hashes have been kept.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns the IDs of the kept hashes, in the order they were offered.
+    #[inline]
+    #[must_use]
+    pub fn kept(&self) -> &[T] {
+        &self.ids
+    }
+
+    /// Compares a hash against the kept hashes, and keeps it if it is new.
+    pub fn offer(&mut self, hash: D, id: T) -> MatchOutcome<T> {
+        let outcome = self.check(&hash);
+        if outcome.is_new() {
+            self.index.insert(hash);
+            self.ids.push(id);
+        }
+        outcome
+    }
+
+    /// Compares a hash against the kept hashes, without keeping it.
+    ///
+    /// If several kept hashes are equally close, the one that was kept first
+    /// is used.
+    #[must_use]
+    pub fn check(&self, hash: &D) -> MatchOutcome<T> {
+        let max_distance = self.near_distance.max(self.duplicate_distance);
+
+        match self.index.nearest(hash, max_distance) {
+            Some((pos, distance)) if distance <= self.duplicate_distance => {
+                MatchOutcome::Duplicate {
+                    of: self.ids[pos].clone(),
+                    distance,
+                }
+            }
+            Some((pos, distance)) => MatchOutcome::NearDuplicate {
+                of: self.ids[pos].clone(),
+                distance,
+            },
+            None => MatchOutcome::New,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{Digest, HashIndex};
+use alloc::vec::Vec;
+
+/// How hashes are paired by [`match_hashes`].
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Assignment {
+    /// Every pair of hashes within the threshold is returned.
+    All,
+    /// Each hash is paired with at most one hash from the other set.
+    ///
+    /// Pairs are chosen greedily, closest first, so each hash is paired with
+    /// its nearest remaining match. Ties are broken by position in `a` and then
+    /// in `b`.
+    OneToOne,
+}
+
+/// A pair of similar hashes found by [`match_hashes`].
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Match {
+    /// The position of the hash in the first set.
+    pub a: usize,
+    /// The position of the hash in the second set.
+    pub b: usize,
+    /// The distance between the hashes.
+    pub distance: u32,
+}
+
+/// Finds pairs of similar hashes between two sets.
+///
+/// This is useful for matching two collections of images against each other,
+/// such as a supplier's catalog against an existing one. The hashes in `b` are
+/// added to a [`HashIndex`], which is then searched for every hash in `a`, so
+/// this is much faster than comparing every pair when the threshold is small.
+///
+/// The matches are returned sorted by distance, then by position in `a`, and
+/// then by position in `b`.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{match_hashes, Assignment, Blockhash16, Match};
+///
+/// let ours = [0x0000, 0x00ff, 0xf0f0].map(Blockhash16::from);
+/// let theirs = [0x00fe, 0x0001, 0x0003].map(Blockhash16::from);
+///
+/// let matches = match_hashes(&ours, &theirs, 2, Assignment::OneToOne);
+/// assert_eq!(
+///     matches,
+///     [
+///         Match { a: 0, b: 1, distance: 1 },
+///         Match { a: 1, b: 0, distance: 1 },
+///     ],
+/// );
+///
+/// let matches = match_hashes(&ours, &theirs, 2, Assignment::All);
+/// assert_eq!(matches.len(), 3);
+/// ```
+#[must_use]
+pub fn match_hashes<D: Digest>(
+    a: &[D],
+    b: &[D],
+    max_distance: u32,
+    assignment: Assignment,
+) -> Vec<Match> {
+    let index: HashIndex<D> = b.iter().copied().collect();
+
+    let mut matches: Vec<Match> = a
+        .iter()
+        .enumerate()
+        .flat_map(|(i, hash)| {
+            index
+                .find(hash, max_distance)
+                .into_iter()
+                .map(move |(j, distance)| Match {
+                    a: i,
+                    b: j,
+                    distance,
+                })
+        })
+        .collect();
+
+    matches.sort_unstable_by_key(|m| (m.distance, m.a, m.b));
+
+    if assignment == Assignment::OneToOne {
+        let mut used_a = alloc::vec![false; a.len()];
+        let mut used_b = alloc::vec![false; b.len()];
+
+        matches.retain(|m| {
+            if used_a[m.a] || used_b[m.b] {
+                false
+            } else {
+                used_a[m.a] = true;
+                used_b[m.b] = true;
+                true
+            }
+        });
+    }
+
+    matches
+}
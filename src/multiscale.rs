@@ -0,0 +1,153 @@
+use crate::hash::{convert_to_bits, get_values, Accumulation, Threshold};
+use crate::{Blockhash, Blockhash16, Blockhash256, Blockhash64, Image};
+
+/// Generates the 16-bit, 64-bit, and 256-bit perceptual hashes of an image
+/// in a single pass.
+///
+/// The image is only read once, into the grid of 16 by 16 blocks, and the
+/// blocks of the coarser grids are the sums of the blocks they cover. The
+/// hashes are identical to those of [`blockhash16`], [`blockhash64`], and
+/// [`blockhash256`], but take about a third of the time to compute together,
+/// which suits coarse-to-fine matching.
+///
+/// [`blockhash16`]: crate::blockhash16
+/// [`blockhash64`]: crate::blockhash64
+/// [`blockhash256`]: crate::blockhash256
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash16, blockhash256, blockhash64, blockhash_multiscale};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let (hash16, hash64, hash256) = blockhash_multiscale(&img);
+///
+/// assert_eq!(hash16, blockhash16(&img));
+/// assert_eq!(hash64, blockhash64(&img));
+/// assert_eq!(hash256, blockhash256(&img));
+/// # }
+/// ```
+#[must_use]
+pub fn blockhash_multiscale<I: Image>(img: &I) -> (Blockhash16, Blockhash64, Blockhash256) {
+    let hash = ProgressiveHash::new(img);
+    (hash.blockhash16(), hash.blockhash64(), hash.blockhash256())
+}
+
+/// The block values of an image, from which its 16-bit, 64-bit, and 256-bit
+/// hashes can be generated as they are needed.
+///
+/// The image is read once when this is created, into the grid of 16 by 16
+/// blocks, and doesn't need to be kept. Each hash is then generated from the
+/// stored values without reading the image again. This suits pipelines that
+/// compare the 16-bit hash first, to cheaply reject images that can't match,
+/// and only refine to the larger hashes for the few candidates that remain.
+///
+/// The hashes are identical to those of [`blockhash16`], [`blockhash64`], and
+/// [`blockhash256`].
+///
+/// [`blockhash16`]: crate::blockhash16
+/// [`blockhash64`]: crate::blockhash64
+/// [`blockhash256`]: crate::blockhash256
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{Blockhash16, Blockhash64, ProgressiveHash};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = ProgressiveHash::new(&img);
+/// drop(img);
+///
+/// // Most candidates are rejected by the 16-bit hash
+/// let candidate: Blockhash16 = "5c69".parse().unwrap();
+/// assert!(hash.blockhash16().distance(&candidate) > 2);
+///
+/// let candidate: Blockhash16 = "a396".parse().unwrap();
+/// assert!(hash.blockhash16().distance(&candidate) <= 2);
+/// let candidate: Blockhash64 = "c7c48f8989c77e0c".parse().unwrap();
+/// assert_eq!(hash.blockhash64().distance(&candidate), 0);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProgressiveHash {
+    values: [u64; 256],
+    width: u32,
+    height: u32,
+    max_value: u32,
+}
+
+impl ProgressiveHash {
+    /// Reads an image into the grid of 16 by 16 blocks.
+    #[must_use]
+    pub fn new<I: Image>(img: &I) -> Self {
+        let (width, height) = img.dimensions();
+        let accumulation = Accumulation::select(width, height, 16);
+
+        ProgressiveHash {
+            values: get_values::<I, 16, 256>(img, accumulation),
+            width,
+            height,
+            max_value: img.max_brightness(),
+        }
+    }
+
+    /// Generates the 16-bit hash of the image.
+    #[inline]
+    #[must_use]
+    pub fn blockhash16(&self) -> Blockhash16 {
+        Blockhash(self.convert(&coarsen::<4, 16>(&self.values)))
+    }
+
+    /// Generates the 64-bit hash of the image.
+    #[inline]
+    #[must_use]
+    pub fn blockhash64(&self) -> Blockhash64 {
+        Blockhash(self.convert(&coarsen::<8, 64>(&self.values)))
+    }
+
+    /// Generates the 256-bit hash of the image.
+    #[inline]
+    #[must_use]
+    pub fn blockhash256(&self) -> Blockhash256 {
+        Blockhash(self.convert(&self.values))
+    }
+
+    fn convert<const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>(
+        &self,
+        values: &[u64; NUM_BLOCKS],
+    ) -> [u8; DIGEST_SIZE] {
+        convert_to_bits(
+            self.width,
+            self.height,
+            values,
+            self.max_value,
+            4,
+            Threshold::Median,
+        )
+    }
+}
+
+/// Combines the values of a grid of 16 by 16 blocks into a grid of `BITS` by
+/// `BITS` blocks.
+///
+/// The values of each grid are scaled by its number of blocks, so the sums
+/// are divided by the number of blocks combined, which is always exact.
+fn coarsen<const BITS: u32, const NUM_BLOCKS: usize>(values: &[u64; 256]) -> [u64; NUM_BLOCKS] {
+    debug_assert_eq!(NUM_BLOCKS, (BITS * BITS) as usize);
+
+    let factor = (16 / BITS) as usize;
+    let mut coarse = [0_u64; NUM_BLOCKS];
+
+    for (idx, &val) in values.iter().enumerate() {
+        let (x, y) = (idx % 16 / factor, idx / 16 / factor);
+        coarse[y * BITS as usize + x] += val;
+    }
+
+    for val in &mut coarse {
+        *val /= (factor * factor) as u64;
+    }
+
+    coarse
+}
@@ -0,0 +1,1471 @@
+use crate::hash::{
+    blockhash_overlapping, blockhash_quick, blockhash_with, canonicalize_mirroring, Accumulation,
+    Threshold,
+};
+use crate::srgb::srgb_to_linear;
+#[cfg(feature = "icc")]
+use crate::ColorSpace;
+use crate::{
+    Blockhash, Blockhash1024, Blockhash144, Blockhash16, Blockhash256, Blockhash64, Image, Region,
+};
+use core::fmt::{self, Display, Formatter};
+use core::time::Duration;
+
+/// Options for customizing how images are hashed.
+///
+/// The default options produce exactly the same hashes as [`blockhash16`],
+/// [`blockhash64`], [`blockhash144`], and [`blockhash256`].
+///
+/// [`blockhash16`]: crate::blockhash16
+/// [`blockhash64`]: crate::blockhash64
+/// [`blockhash144`]: crate::blockhash144
+/// [`blockhash256`]: crate::blockhash256
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::BlockhashOptions;
+///
+/// let img = image::open("images/example.png").unwrap().into_rgb16();
+/// let options = BlockhashOptions::new().quantize_to_8_bits(true);
+///
+/// assert_eq!(options.blockhash64(&img).to_string(), "c7c48f8989c77e0c");
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BlockhashOptions {
+    quantize: bool,
+    linearize: bool,
+    luma: LumaWeights,
+    channel_max: Option<u32>,
+    background: Option<Background>,
+    transparent: Background,
+    premultiplied: bool,
+    #[cfg(feature = "icc")]
+    color_space: ColorSpace,
+    gamma: Option<u32>,
+    accumulation: Option<Accumulation>,
+    mirroring: bool,
+    quick: bool,
+    overlap: bool,
+    bands: u32,
+    threshold: Threshold,
+    margins: Margins,
+}
+
+/// Margins to exclude from an image before hashing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Margins {
+    None,
+    /// The left, top, right, and bottom margins in pixels.
+    Pixels([u32; 4]),
+    /// The left, top, right, and bottom margins as percentages of the width
+    /// or height.
+    Percent([u8; 4]),
+}
+
+impl Default for BlockhashOptions {
+    #[inline]
+    fn default() -> Self {
+        BlockhashOptions {
+            quantize: false,
+            linearize: false,
+            luma: LumaWeights::Equal,
+            channel_max: None,
+            background: None,
+            transparent: Background::White,
+            premultiplied: false,
+            #[cfg(feature = "icc")]
+            color_space: ColorSpace::Srgb,
+            gamma: None,
+            accumulation: None,
+            mirroring: false,
+            quick: false,
+            overlap: false,
+            bands: 4,
+            threshold: Threshold::Median,
+            margins: Margins::None,
+        }
+    }
+}
+
+impl BlockhashOptions {
+    /// Creates the default options.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether channels with more than 8 bits are quantized to 8 bits
+    /// before the brightness is computed.
+    ///
+    /// Images with 16-bit channels are normally hashed at full precision, so
+    /// the same image saved with 8- and 16-bit channels can produce slightly
+    /// different hashes. With this option enabled, each channel (including
+    /// alpha) is first rounded to the nearest 8-bit value, exactly as when
+    /// converting the image to 8 bits, so both produce identical hashes.
+    ///
+    /// This has no effect on images with 8-bit channels.
+    #[inline]
+    #[must_use]
+    pub fn quantize_to_8_bits(mut self, quantize: bool) -> Self {
+        self.quantize = quantize;
+        self
+    }
+
+    /// Sets whether channels are converted from sRGB to linear light before
+    /// the brightness is computed.
+    ///
+    /// The brightness of a pixel is normally the sum of its channels as they
+    /// are stored, which for almost all images means gamma-encoded sRGB. With
+    /// this option enabled, each channel is first converted to the intensity
+    /// of light it represents, so the brightness of each block is the average
+    /// intensity of its pixels. This changes which blocks are brighter than
+    /// the median, mostly in images with a lot of detail in the midtones, so
+    /// hashes computed with and without this option shouldn't be compared.
+    ///
+    /// Channels are quantized first if [`quantize_to_8_bits`] is also
+    /// enabled. Alpha isn't converted.
+    ///
+    /// [`quantize_to_8_bits`]: Self::quantize_to_8_bits
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::BlockhashOptions;
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().linearize_srgb(true);
+    ///
+    /// assert_eq!(options.blockhash64(&img).to_string(), "c6cc8f8989c77e24");
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn linearize_srgb(mut self, linearize: bool) -> Self {
+        self.linearize = linearize;
+        self
+    }
+
+    /// Sets how the color channels are weighted when computing the
+    /// brightness of a pixel.
+    ///
+    /// The brightness is normally the unweighted sum of the red, green, and
+    /// blue channels, so a pure green pixel is as bright as a pure blue one,
+    /// even though green appears far brighter. A luma formula weights each
+    /// channel by its contribution to perceived brightness instead. The
+    /// weights are applied after any conversion to [linear
+    /// light](Self::linearize_srgb), and have no effect on grayscale images.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weights are [custom](LumaWeights::Custom) and every
+    /// weight is zero. See [`try_luma_weights`](Self::try_luma_weights) for
+    /// a version that returns an error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{BlockhashOptions, LumaWeights};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().luma_weights(LumaWeights::Bt709);
+    ///
+    /// assert_eq!(options.blockhash64(&img).to_string(), "c68e8f8981e7fc24");
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn luma_weights(self, luma: LumaWeights) -> Self {
+        self.try_luma_weights(luma)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets how the color channels are weighted when computing the
+    /// brightness of a pixel, or returns an error if the weights are invalid.
+    ///
+    /// See [`luma_weights`](Self::luma_weights).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OptionsError::ZeroChannelWeights`] if the weights are
+    /// [custom](LumaWeights::Custom) and every weight is zero.
+    #[inline]
+    pub fn try_luma_weights(mut self, luma: LumaWeights) -> Result<Self, OptionsError> {
+        if luma == LumaWeights::Custom([0; 3]) {
+            return Err(OptionsError::ZeroChannelWeights);
+        }
+        self.luma = luma;
+        Ok(self)
+    }
+
+    /// Sets custom weights for the red, green, and blue channels when
+    /// computing the brightness of a pixel.
+    ///
+    /// This is the same as [`luma_weights`](Self::luma_weights) with
+    /// [`LumaWeights::Custom`]. Only the ratios between the weights matter, so
+    /// `[1, 0, 0]` computes the brightness from the red channel alone, which
+    /// is useful for false-color imagery where only one channel is meaningful.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every weight is zero. Use
+    /// [`try_luma_weights`](Self::try_luma_weights) with
+    /// [`LumaWeights::Custom`] to get an error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{blockhash64, BlockhashOptions};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().channel_weights([1, 0, 0]);
+    ///
+    /// let rgb = img.to_rgb8();
+    /// let red = image::GrayImage::from_fn(320, 192, |x, y| image::Luma([rgb.get_pixel(x, y)[0]]));
+    /// assert_eq!(options.blockhash64(&img), blockhash64(&red));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn channel_weights(self, weights: [u16; 3]) -> Self {
+        self.luma_weights(LumaWeights::Custom(weights))
+    }
+
+    /// Sets the maximum value of a channel, or `None` to use the maximum
+    /// value of the type of the channels.
+    ///
+    /// This is useful for images whose channels don't use their full range,
+    /// such as 12-bit sensor data stored in 16-bit channels, where the
+    /// maximum would be 4095. The maximum is used to decide whether blocks
+    /// that tie with the median are bright, and to scale the channels for
+    /// [`quantize_to_8_bits`](Self::quantize_to_8_bits) and
+    /// [`linearize_srgb`](Self::linearize_srgb). Channels greater than the
+    /// maximum are treated as the maximum. It doesn't apply to alpha.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum is zero. See
+    /// [`try_channel_max`](Self::try_channel_max) for a version that returns
+    /// an error instead.
+    #[inline]
+    #[must_use]
+    pub fn channel_max(self, max: Option<u32>) -> Self {
+        self.try_channel_max(max)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the maximum value of a channel, or returns an error if the
+    /// maximum is zero.
+    ///
+    /// See [`channel_max`](Self::channel_max).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OptionsError::ZeroChannelMax`] if the maximum is zero.
+    #[inline]
+    pub fn try_channel_max(mut self, max: Option<u32>) -> Result<Self, OptionsError> {
+        if max == Some(0) {
+            return Err(OptionsError::ZeroChannelMax);
+        }
+        self.channel_max = max;
+        Ok(self)
+    }
+
+    /// Sets the background color that transparent pixels are composited
+    /// over, or `None` to only give fully transparent pixels the
+    /// [transparent color](Self::transparent_color).
+    ///
+    /// By default, the alpha channel is ignored except that fully transparent
+    /// pixels are white, so a logo with soft, partially
+    /// transparent edges doesn't hash the same as the same logo flattened
+    /// onto a background. With a background color, each pixel is blended
+    /// with the background according to its alpha, in the same way as
+    /// flattening the image, so both produce the same hash.
+    ///
+    /// Pixels are blended before any other conversion, in the color space
+    /// the image is stored in, as most image editors do when flattening.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{blockhash64, Background, BlockhashOptions};
+    /// use image::{Rgb, RgbImage, Rgba, RgbaImage};
+    ///
+    /// // A white circle with soft edges on a transparent background
+    /// let logo = RgbaImage::from_fn(64, 64, |x, y| {
+    ///     let dist = ((x as f32 - 24.0).powi(2) + (y as f32 - 32.0).powi(2)).sqrt();
+    ///     let alpha = (255.0 * (20.0 - dist).clamp(0.0, 1.0)) as u8;
+    ///     Rgba([255, 255, 255, alpha])
+    /// });
+    ///
+    /// // The same logo flattened onto black
+    /// let flattened = RgbImage::from_fn(64, 64, |x, y| {
+    ///     let alpha = logo.get_pixel(x, y)[3];
+    ///     Rgb([alpha; 3])
+    /// });
+    ///
+    /// let options = BlockhashOptions::new().alpha_background(Some(Background::Black));
+    /// assert_eq!(options.blockhash64(&logo), blockhash64(&flattened));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn alpha_background(mut self, background: Option<Background>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets the color of fully transparent pixels.
+    ///
+    /// Fully transparent pixels are normally white, as in the reference
+    /// implementation, whatever their color channels contain. This suits
+    /// images that are shown on light backgrounds, but sprites and icons
+    /// shown on dark backgrounds are better hashed with transparent pixels
+    /// as black. Partially transparent pixels are unaffected, and this has no
+    /// effect if an [alpha background](Self::alpha_background) is set, since
+    /// fully transparent pixels then take the color of the background.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{blockhash64, Background, BlockhashOptions};
+    /// use image::{Rgb, RgbImage, Rgba, RgbaImage};
+    ///
+    /// // A white square on a transparent background
+    /// let sprite = RgbaImage::from_fn(32, 32, |x, y| {
+    ///     let inside = (8..24).contains(&x) && (8..24).contains(&y);
+    ///     Rgba([255, 255, 255, if inside { 255 } else { 0 }])
+    /// });
+    /// let on_black = RgbImage::from_fn(32, 32, |x, y| Rgb([sprite.get_pixel(x, y)[3]; 3]));
+    ///
+    /// let options = BlockhashOptions::new().transparent_color(Background::Black);
+    /// assert_eq!(options.blockhash64(&sprite), blockhash64(&on_black));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn transparent_color(mut self, color: Background) -> Self {
+        self.transparent = color;
+        self
+    }
+
+    /// Sets whether the color channels of the image are premultiplied by
+    /// alpha.
+    ///
+    /// Images read back from a GPU, and from many compositing and video APIs,
+    /// store each color channel already multiplied by the alpha, so partially
+    /// transparent pixels would otherwise appear darker than they are. With
+    /// this option enabled, the color channels are divided by the alpha
+    /// before anything else, so the hash is the same as that of the image
+    /// with straight alpha.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{blockhash16, BlockhashOptions};
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// // Gray and white stripes at 20% opacity
+    /// let straight = RgbaImage::from_fn(8, 8, |x, _| {
+    ///     let val = if x % 4 < 2 { 100 } else { 255 };
+    ///     Rgba([val, val, val, 51])
+    /// });
+    /// let premultiplied = RgbaImage::from_fn(8, 8, |x, _| {
+    ///     let val = if x % 4 < 2 { 20 } else { 51 };
+    ///     Rgba([val, val, val, 51])
+    /// });
+    ///
+    /// let options = BlockhashOptions::new().premultiplied_alpha(true);
+    /// assert_eq!(options.blockhash16(&premultiplied), blockhash16(&straight));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn premultiplied_alpha(mut self, premultiplied: bool) -> Self {
+        self.premultiplied = premultiplied;
+        self
+    }
+
+    /// Sets the color space that the color channels of the image are encoded
+    /// in.
+    ///
+    /// The same photo exported with different color profiles has different
+    /// channel values, and so can produce a different hash. With a color
+    /// space other than sRGB, the channels are first converted to sRGB, so
+    /// that every export produces nearly the same hash. Colors outside of the
+    /// sRGB gamut are clipped. The conversion is done after [premultiplied
+    /// alpha](Self::premultiplied_alpha) is removed, and before anything else.
+    ///
+    /// The color space of an image can be identified from its ICC profile
+    /// with [`ColorSpace::from_icc_profile`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{BlockhashOptions, ColorSpace};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().color_space(ColorSpace::DisplayP3);
+    ///
+    /// assert_eq!(
+    ///     options.blockhash256(&img).to_string(),
+    ///     "e87ef07ef070e090e0ffc0ffc066c043c043c065f03dfc3f7c7e7fd42eba0080",
+    /// );
+    /// # }
+    /// ```
+    #[cfg(feature = "icc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "icc")))]
+    #[inline]
+    #[must_use]
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Sets the gamma used to adjust the brightness of each pixel.
+    ///
+    /// The brightness of each pixel, relative to the maximum, is raised to
+    /// the power of `1 / gamma`, so a gamma greater than 1 brightens the
+    /// shadows and compresses the highlights, while a gamma less than 1 does
+    /// the opposite. This is useful for images that are almost entirely dark,
+    /// such as photos of the night sky, where the differences between the
+    /// blocks are otherwise lost among the darkest values. The adjustment is
+    /// applied after every other conversion, and the default gamma of 1
+    /// leaves the brightness unchanged.
+    ///
+    /// Unlike the other conversions, this uses floating-point arithmetic, so
+    /// in rare cases the hash of an image can differ between platforms, and
+    /// [`self_test`](crate::self_test) doesn't cover it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gamma isn't a positive, finite number. See
+    /// [`try_gamma`](Self::try_gamma) for a version that returns an error
+    /// instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::BlockhashOptions;
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().gamma(2.2);
+    ///
+    /// assert_eq!(options.blockhash64(&img).to_string(), "c7c48f8989c77e24");
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[inline]
+    #[must_use]
+    pub fn gamma(self, gamma: f32) -> Self {
+        self.try_gamma(gamma)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the gamma used to adjust the brightness of each pixel, or returns
+    /// an error if the gamma is invalid.
+    ///
+    /// See [`gamma`](Self::gamma).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OptionsError::InvalidGamma`] if the gamma isn't a positive,
+    /// finite number.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[inline]
+    pub fn try_gamma(mut self, gamma: f32) -> Result<Self, OptionsError> {
+        if !(gamma.is_finite() && gamma > 0.0) {
+            return Err(OptionsError::InvalidGamma);
+        }
+        self.gamma = if gamma == 1.0 {
+            None
+        } else {
+            Some(gamma.to_bits())
+        };
+        Ok(self)
+    }
+
+    /// Sets the strategy used to sum the brightness of the pixels in each
+    /// block, or `None` to choose the fastest strategy automatically.
+    ///
+    /// Every strategy produces the same hashes, so this is mainly useful for
+    /// testing and for validating other implementations, where the same
+    /// arithmetic needs to be used for images of different sizes. If the
+    /// strategy doesn't support the dimensions of an image, the generic
+    /// strategy is used instead, since it supports every image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{Accumulation, BlockhashOptions};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().accumulation(Some(Accumulation::Generic));
+    /// let (hash, stats) = options.blockhash64_with_stats(&img);
+    ///
+    /// assert_eq!(hash.to_string(), "c7c48f8989c77e0c");
+    /// assert_eq!(stats.accumulation(), Accumulation::Generic);
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn accumulation(mut self, accumulation: Option<Accumulation>) -> Self {
+        self.accumulation = accumulation;
+        self
+    }
+
+    /// Sets whether an image and its mirror images produce the same hash.
+    ///
+    /// With this option enabled, the hash of the image is compared with the
+    /// hashes of its horizontal, vertical, and combined mirror images, and the
+    /// smallest is returned, so mirrored copies of an image can be matched.
+    /// This makes the hash less specific, since an image that happens to look
+    /// like the mirror image of another will also match it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::BlockhashOptions;
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let mirrored = img.fliph();
+    /// let options = BlockhashOptions::new().normalize_mirroring(true);
+    ///
+    /// assert_eq!(options.blockhash64(&img), options.blockhash64(&mirrored));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn normalize_mirroring(mut self, mirroring: bool) -> Self {
+        self.mirroring = mirroring;
+        self
+    }
+
+    /// Sets whether hashes are computed in the same way as the quick mode of
+    /// the reference implementation.
+    ///
+    /// In quick mode, each block covers a whole number of pixels, and any
+    /// pixels to the right of or below the last block are ignored, rather than
+    /// splitting pixels between blocks. This is faster, especially for large
+    /// images, and produces the same hashes as the reference implementation's
+    /// `--quick` option. If the width and height of the image are multiples of
+    /// the size of the grid, the hashes are the same as normal.
+    ///
+    /// Images that are smaller than the grid are always hashed normally, since
+    /// quick mode would leave every block empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{blockhash256, BlockhashOptions};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().quick(true);
+    ///
+    /// // 320x192 is a multiple of 16x16, so the hash is unchanged
+    /// assert_eq!(options.blockhash256(&img), blockhash256(&img));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn quick(mut self, quick: bool) -> Self {
+        self.quick = quick;
+        self
+    }
+
+    /// Sets whether each block overlaps its neighbors by half of its width and
+    /// height.
+    ///
+    /// Normally, the image is divided into a grid of blocks that don't
+    /// overlap, so a small translation, such as cropping a single row or
+    /// column of pixels from a small image, moves the boundaries of every
+    /// block, and can change many bits at once. With this option enabled, each
+    /// block is twice as wide and tall as the spacing between blocks, so every
+    /// pixel away from the edges of the image contributes to several blocks,
+    /// and the hash changes more gradually as the image moves. This makes the
+    /// hash less sensitive to fine detail.
+    ///
+    /// Hashes computed with overlapping blocks can't be compared with normal
+    /// hashes. Quick mode is ignored when blocks overlap, which
+    /// [`validate`](Self::validate) reports as a conflict.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::BlockhashOptions;
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let hash = BlockhashOptions::new().overlapping_blocks(true).blockhash64(&img);
+    ///
+    /// assert_eq!(hash.to_string(), "c7868f8585c7e62c");
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn overlapping_blocks(mut self, overlap: bool) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Sets the number of bands the blocks are divided into for thresholding.
+    ///
+    /// Each bit of the hash is set if its block is brighter than the median of
+    /// the blocks in the same band. The blocks are normally divided into 4
+    /// horizontal bands, in row-major order, so the hash reflects the
+    /// brightness of each block relative to others at a similar height. More
+    /// bands make the hash more sensitive to local detail.
+    ///
+    /// With a single band, every block is compared against one global median.
+    /// This is more stable for images with large areas of a single color, such
+    /// as black bars at the sides. With several bands, a band that is mostly
+    /// black has a black median, so its bits only record which blocks aren't
+    /// black, while the bits of the other bands record their detail. A small
+    /// change in the size of the black area can then change which bands
+    /// collapse in this way, and so change many bits at once.
+    ///
+    /// The number of bands must be 1, 2, 4, 8, or 16, so that the blocks of
+    /// every size of hash are divided evenly. With 8 or 16 bands, some bands
+    /// don't cover whole rows of blocks, so [mirroring
+    /// normalization](Self::normalize_mirroring) is no longer exact.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of bands isn't supported. See
+    /// [`try_bands`](Self::try_bands) for a version that returns an error
+    /// instead, which is useful when the number comes from user input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::BlockhashOptions;
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let hash = BlockhashOptions::new().bands(1).blockhash64(&img);
+    ///
+    /// assert_eq!(hash.to_string(), "efcf8f8180477e04");
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn bands(self, bands: u32) -> Self {
+        self.try_bands(bands)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the number of bands the blocks are divided into for thresholding,
+    /// or returns an error if the number isn't supported.
+    ///
+    /// See [`bands`](Self::bands).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OptionsError::UnsupportedBands`] if the number of bands isn't
+    /// 1, 2, 4, 8, or 16.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{BlockhashOptions, OptionsError};
+    ///
+    /// assert!(BlockhashOptions::new().try_bands(8).is_ok());
+    /// assert_eq!(
+    ///     BlockhashOptions::new().try_bands(3),
+    ///     Err(OptionsError::UnsupportedBands(3)),
+    /// );
+    /// ```
+    #[inline]
+    pub fn try_bands(mut self, bands: u32) -> Result<Self, OptionsError> {
+        if !matches!(bands, 1 | 2 | 4 | 8 | 16) {
+            return Err(OptionsError::UnsupportedBands(bands));
+        }
+        self.bands = bands;
+        Ok(self)
+    }
+
+    /// Sets the method used to choose the threshold for the bits of each band.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{BlockhashOptions, Threshold};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let hash = BlockhashOptions::new().threshold(Threshold::Otsu).blockhash64(&img);
+    ///
+    /// assert_eq!(hash.to_string(), "efcf8f8181c77e2c");
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn threshold(mut self, threshold: Threshold) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Excludes margins of the given number of pixels from each side of the
+    /// image.
+    ///
+    /// This is useful for images that carry fixed strips along their edges,
+    /// such as watermarks or captions, which shouldn't contribute to the
+    /// hash. The margins are skipped while reading the image, so no cropped
+    /// copy is made. Margins that would leave no pixels are reduced so that at
+    /// least one column and one row remain.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{blockhash64, BlockhashOptions};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let options = BlockhashOptions::new().crop_margins(0, 0, 0, 32);
+    ///
+    /// assert_eq!(options.blockhash64(&img), blockhash64(&img.crop_imm(0, 0, 320, 160)));
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn crop_margins(mut self, left: u32, top: u32, right: u32, bottom: u32) -> Self {
+        self.margins = Margins::Pixels([left, top, right, bottom]);
+        self
+    }
+
+    /// Excludes margins from each side of the image, given as percentages of
+    /// the width or height of the image.
+    ///
+    /// Each margin is rounded down to a whole number of pixels, and
+    /// percentages greater than 100 are treated as 100. See
+    /// [`crop_margins`](Self::crop_margins).
+    #[inline]
+    #[must_use]
+    pub fn crop_margins_percent(mut self, left: u8, top: u8, right: u8, bottom: u8) -> Self {
+        self.margins = Margins::Percent([left, top, right, bottom]);
+        self
+    }
+
+    /// Checks that the options don't contain settings that conflict with each
+    /// other.
+    ///
+    /// Each setter only checks its own value, so a combination in which one
+    /// setting would silently be ignored is only detected here. This is useful
+    /// for rejecting options that come from user input up front, rather than
+    /// producing hashes that don't reflect them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OptionsError::Conflicting`] with the names of the two setters
+    /// if:
+    ///
+    /// * [`quick`](Self::quick) mode is combined with
+    ///   [`overlapping_blocks`](Self::overlapping_blocks), which ignores it.
+    /// * [`quick`](Self::quick) mode is combined with an
+    ///   [`accumulation`](Self::accumulation) strategy, which quick mode
+    ///   doesn't use.
+    /// * An [`alpha_background`](Self::alpha_background) is combined with a
+    ///   [`transparent_color`](Self::transparent_color), which is only used
+    ///   when there is no background.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::{BlockhashOptions, OptionsError};
+    ///
+    /// let options = BlockhashOptions::new().overlapping_blocks(true);
+    /// assert_eq!(options.validate(), Ok(()));
+    /// assert_eq!(
+    ///     options.quick(true).validate(),
+    ///     Err(OptionsError::Conflicting("quick", "overlapping_blocks")),
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        if self.quick && self.overlap {
+            return Err(OptionsError::Conflicting("quick", "overlapping_blocks"));
+        }
+        if self.quick && self.accumulation.is_some() {
+            return Err(OptionsError::Conflicting("quick", "accumulation"));
+        }
+        if self.background.is_some() && self.transparent != Background::White {
+            return Err(OptionsError::Conflicting(
+                "alpha_background",
+                "transparent_color",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Generates a 16-bit perceptual hash of an image.
+    ///
+    /// See [`blockhash16`](crate::blockhash16).
+    #[inline]
+    #[must_use]
+    pub fn blockhash16<I: Image>(&self, img: &I) -> Blockhash16 {
+        Blockhash(self.hash::<I, 4, 16, 2>(img))
+    }
+
+    /// Generates a 64-bit perceptual hash of an image.
+    ///
+    /// See [`blockhash64`](crate::blockhash64).
+    #[inline]
+    #[must_use]
+    pub fn blockhash64<I: Image>(&self, img: &I) -> Blockhash64 {
+        Blockhash(self.hash::<I, 8, 64, 8>(img))
+    }
+
+    /// Generates a 144-bit perceptual hash of an image.
+    ///
+    /// See [`blockhash144`](crate::blockhash144).
+    #[inline]
+    #[must_use]
+    pub fn blockhash144<I: Image>(&self, img: &I) -> Blockhash144 {
+        Blockhash(self.hash::<I, 12, 144, 18>(img))
+    }
+
+    /// Generates a 256-bit perceptual hash of an image.
+    ///
+    /// See [`blockhash256`](crate::blockhash256).
+    #[inline]
+    #[must_use]
+    pub fn blockhash256<I: Image>(&self, img: &I) -> Blockhash256 {
+        Blockhash(self.hash::<I, 16, 256, 32>(img))
+    }
+
+    /// Generates a 1024-bit perceptual hash of an image.
+    ///
+    /// See [`blockhash1024`](crate::blockhash1024).
+    #[inline]
+    #[must_use]
+    pub fn blockhash1024<I: Image>(&self, img: &I) -> Blockhash1024 {
+        Blockhash(self.hash::<I, 32, 1024, 128>(img))
+    }
+
+    /// Generates a 16-bit perceptual hash of an image, along with statistics
+    /// about how it was computed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "image")] {
+    /// use blockhash::{Accumulation, BlockhashOptions};
+    ///
+    /// let img = image::open("images/example.png").unwrap();
+    /// let (hash, stats) = BlockhashOptions::new().blockhash16_with_stats(&img);
+    ///
+    /// assert_eq!(hash.to_string(), "a396");
+    /// assert_eq!(stats.pixels(), 256 * 240);
+    /// assert_eq!(stats.accumulation(), Accumulation::Aligned);
+    /// # }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn blockhash16_with_stats<I: Image>(&self, img: &I) -> (Blockhash16, HashStats) {
+        let (digest, stats) = self.hash_with_stats::<I, 4, 16, 2>(img);
+        (Blockhash(digest), stats)
+    }
+
+    /// Generates a 64-bit perceptual hash of an image, along with statistics
+    /// about how it was computed.
+    ///
+    /// See [`blockhash16_with_stats`](Self::blockhash16_with_stats).
+    #[inline]
+    #[must_use]
+    pub fn blockhash64_with_stats<I: Image>(&self, img: &I) -> (Blockhash64, HashStats) {
+        let (digest, stats) = self.hash_with_stats::<I, 8, 64, 8>(img);
+        (Blockhash(digest), stats)
+    }
+
+    /// Generates a 144-bit perceptual hash of an image, along with statistics
+    /// about how it was computed.
+    ///
+    /// See [`blockhash16_with_stats`](Self::blockhash16_with_stats).
+    #[inline]
+    #[must_use]
+    pub fn blockhash144_with_stats<I: Image>(&self, img: &I) -> (Blockhash144, HashStats) {
+        let (digest, stats) = self.hash_with_stats::<I, 12, 144, 18>(img);
+        (Blockhash(digest), stats)
+    }
+
+    /// Generates a 256-bit perceptual hash of an image, along with statistics
+    /// about how it was computed.
+    ///
+    /// See [`blockhash16_with_stats`](Self::blockhash16_with_stats).
+    #[inline]
+    #[must_use]
+    pub fn blockhash256_with_stats<I: Image>(&self, img: &I) -> (Blockhash256, HashStats) {
+        let (digest, stats) = self.hash_with_stats::<I, 16, 256, 32>(img);
+        (Blockhash(digest), stats)
+    }
+
+    /// Generates a 1024-bit perceptual hash of an image, along with
+    /// statistics about how it was computed.
+    ///
+    /// See [`blockhash16_with_stats`](Self::blockhash16_with_stats).
+    #[inline]
+    #[must_use]
+    pub fn blockhash1024_with_stats<I: Image>(&self, img: &I) -> (Blockhash1024, HashStats) {
+        let (digest, stats) = self.hash_with_stats::<I, 32, 1024, 128>(img);
+        (Blockhash(digest), stats)
+    }
+
+    fn hash_with_stats<
+        I: Image,
+        const BITS: u32,
+        const NUM_BLOCKS: usize,
+        const DIGEST_SIZE: usize,
+    >(
+        &self,
+        img: &I,
+    ) -> ([u8; DIGEST_SIZE], HashStats) {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+
+        let digest = self.hash::<I, BITS, NUM_BLOCKS, DIGEST_SIZE>(img);
+
+        #[cfg(feature = "std")]
+        let elapsed = Some(start.elapsed());
+        #[cfg(not(feature = "std"))]
+        let elapsed = None;
+
+        let (_, _, width, height) = self.crop_for(img.dimensions());
+        let quick = self.quick_for(width, height, BITS);
+        let pixels = if quick {
+            u64::from(width / BITS * BITS) * u64::from(height / BITS * BITS)
+        } else {
+            u64::from(width) * u64::from(height)
+        };
+
+        let stats = HashStats {
+            pixels,
+            accumulation: self.accumulation_for(width, height, BITS + u32::from(self.overlap)),
+            elapsed,
+            quantized: self.quantizes(img),
+            quick,
+        };
+
+        (digest, stats)
+    }
+
+    fn hash<I: Image, const BITS: u32, const NUM_BLOCKS: usize, const DIGEST_SIZE: usize>(
+        &self,
+        img: &I,
+    ) -> [u8; DIGEST_SIZE] {
+        if self.margins == Margins::None {
+            return self.hash_cropped::<I, BITS, NUM_BLOCKS, DIGEST_SIZE>(img);
+        }
+
+        let (x, y, width, height) = self.crop_for(img.dimensions());
+        let region = Region::new(img, x, y, width, height);
+        self.hash_cropped::<_, BITS, NUM_BLOCKS, DIGEST_SIZE>(&region)
+    }
+
+    /// Hashes an image after any margins have been removed.
+    fn hash_cropped<
+        I: Image,
+        const BITS: u32,
+        const NUM_BLOCKS: usize,
+        const DIGEST_SIZE: usize,
+    >(
+        &self,
+        img: &I,
+    ) -> [u8; DIGEST_SIZE] {
+        let digest = if self.converts(img) {
+            let img = Converted { img, options: self };
+            self.hash_converted::<_, BITS, NUM_BLOCKS, DIGEST_SIZE>(&img)
+        } else {
+            self.hash_converted::<_, BITS, NUM_BLOCKS, DIGEST_SIZE>(img)
+        };
+
+        if self.mirroring {
+            canonicalize_mirroring(digest, BITS)
+        } else {
+            digest
+        }
+    }
+
+    /// Hashes an image after any brightness conversions have been applied.
+    fn hash_converted<
+        I: Image,
+        const BITS: u32,
+        const NUM_BLOCKS: usize,
+        const DIGEST_SIZE: usize,
+    >(
+        &self,
+        img: &I,
+    ) -> [u8; DIGEST_SIZE] {
+        let (width, height) = img.dimensions();
+
+        if self.quick_for(width, height, BITS) {
+            blockhash_quick::<_, BITS, NUM_BLOCKS, DIGEST_SIZE>(img, self.bands, self.threshold)
+        } else if self.overlap {
+            let accumulation = self.accumulation_for(width, height, BITS + 1);
+            blockhash_overlapping::<_, BITS, NUM_BLOCKS, DIGEST_SIZE>(
+                img,
+                accumulation,
+                self.bands,
+                self.threshold,
+            )
+        } else {
+            let accumulation = self.accumulation_for(width, height, BITS);
+            blockhash_with::<_, BITS, NUM_BLOCKS, DIGEST_SIZE>(
+                img,
+                accumulation,
+                self.bands,
+                self.threshold,
+            )
+        }
+    }
+
+    /// Returns the position and dimensions of the part of an image with the
+    /// given dimensions that remains after removing the margins.
+    fn crop_for(&self, (width, height): (u32, u32)) -> (u32, u32, u32, u32) {
+        let [left, top, right, bottom] = match self.margins {
+            Margins::None => return (0, 0, width, height),
+            Margins::Pixels(margins) => margins,
+            Margins::Percent(percent) => {
+                let of =
+                    |len: u32, pct: u8| (u64::from(len) * u64::from(pct.min(100)) / 100) as u32;
+                [
+                    of(width, percent[0]),
+                    of(height, percent[1]),
+                    of(width, percent[2]),
+                    of(height, percent[3]),
+                ]
+            }
+        };
+
+        // At least one pixel remains along each axis, unless the image is empty
+        let left = left.min(width.saturating_sub(1));
+        let right = right.min(width - left - u32::from(width > 0));
+        let top = top.min(height.saturating_sub(1));
+        let bottom = bottom.min(height - top - u32::from(height > 0));
+
+        (left, top, width - left - right, height - top - bottom)
+    }
+
+    /// Returns whether an image with the given dimensions is hashed in quick
+    /// mode.
+    fn quick_for(&self, width: u32, height: u32, bits: u32) -> bool {
+        self.quick && !self.overlap && width >= bits && height >= bits
+    }
+
+    /// Returns the accumulation strategy to use for an image with the given
+    /// dimensions.
+    fn accumulation_for(&self, width: u32, height: u32, bits: u32) -> Accumulation {
+        match self.accumulation {
+            Some(accumulation) if accumulation.supports(width, height, bits) => accumulation,
+            Some(_) => Accumulation::Generic,
+            None => Accumulation::select(width, height, bits),
+        }
+    }
+
+    /// Returns whether the channels of the image need to be quantized.
+    fn quantizes<I: Image>(&self, img: &I) -> bool {
+        self.quantize && self.channel_max_for(img) > u8::MAX.into()
+    }
+
+    /// Returns the maximum value of a color channel of the image.
+    fn channel_max_for<I: Image>(&self, img: &I) -> u32 {
+        let max_channel = img.max_channel();
+        self.channel_max
+            .map_or(max_channel, |max| max.min(max_channel))
+    }
+
+    /// Returns whether the brightness of the image needs to be converted.
+    fn converts<I: Image>(&self, img: &I) -> bool {
+        self.linearize
+            || self.luma != LumaWeights::Equal
+            || self.channel_max.is_some()
+            || self.background.is_some()
+            || self.transparent != Background::White
+            || self.premultiplied
+            || self.converts_color_space()
+            || self.gamma.is_some()
+            || self.quantizes(img)
+    }
+
+    /// Returns whether the channels of the image need to be converted to
+    /// sRGB.
+    fn converts_color_space(&self) -> bool {
+        #[cfg(feature = "icc")]
+        return self.color_space != ColorSpace::Srgb;
+        #[cfg(not(feature = "icc"))]
+        return false;
+    }
+}
+
+/// An error returned when setting an invalid option on [`BlockhashOptions`],
+/// or when validating conflicting options.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OptionsError {
+    /// The number of bands isn't supported. Contains the number of bands.
+    ///
+    /// See [`BlockhashOptions::bands`].
+    UnsupportedBands(u32),
+    /// The maximum value of a channel is zero.
+    ///
+    /// See [`BlockhashOptions::channel_max`].
+    ZeroChannelMax,
+    /// Every custom channel weight is zero.
+    ///
+    /// See [`BlockhashOptions::channel_weights`].
+    ZeroChannelWeights,
+    /// The gamma isn't a positive, finite number.
+    ///
+    /// See [`BlockhashOptions::gamma`].
+    InvalidGamma,
+    /// Two settings can't be used together. Contains the names of the
+    /// setters.
+    ///
+    /// See [`BlockhashOptions::validate`].
+    Conflicting(&'static str, &'static str),
+}
+
+impl Display for OptionsError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            OptionsError::UnsupportedBands(bands) => {
+                write!(f, "unsupported number of bands {}", bands)
+            }
+            OptionsError::ZeroChannelMax => f.write_str("maximum channel value must not be zero"),
+            OptionsError::ZeroChannelWeights => f.write_str("at least one weight must not be zero"),
+            OptionsError::InvalidGamma => f.write_str("gamma must be positive and finite"),
+            OptionsError::Conflicting(a, b) => {
+                write!(f, "options `{}` and `{}` can't be combined", a, b)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OptionsError {}
+
+/// Weights of the color channels used to compute the brightness of a pixel.
+///
+/// See [`BlockhashOptions::luma_weights`].
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LumaWeights {
+    /// The channels are summed without weights, as in the reference
+    /// implementation.
+    #[default]
+    Equal,
+    /// The weights of [ITU-R BT.601], used for standard-definition video and
+    /// most JPEG images: 0.299 red, 0.587 green, and 0.114 blue.
+    ///
+    /// [ITU-R BT.601]: https://en.wikipedia.org/wiki/Rec._601
+    Bt601,
+    /// The weights of [ITU-R BT.709], which match the primaries of sRGB:
+    /// 0.2126 red, 0.7152 green, and 0.0722 blue.
+    ///
+    /// [ITU-R BT.709]: https://en.wikipedia.org/wiki/Rec._709
+    Bt709,
+    /// Custom weights of the red, green, and blue channels, relative to their
+    /// sum.
+    ///
+    /// See [`BlockhashOptions::channel_weights`].
+    Custom([u16; 3]),
+}
+
+impl LumaWeights {
+    /// Returns the weights of the red, green, and blue channels, relative to
+    /// their sum, or `None` if the channels are summed without weights.
+    fn weights(self) -> Option<[u64; 3]> {
+        match self {
+            LumaWeights::Equal => None,
+            LumaWeights::Bt601 => Some([2990, 5870, 1140]),
+            LumaWeights::Bt709 => Some([2126, 7152, 722]),
+            LumaWeights::Custom([r, g, b]) => Some([r.into(), g.into(), b.into()]),
+        }
+    }
+}
+
+/// A background color for transparent pixels.
+///
+/// See [`BlockhashOptions::alpha_background`] and
+/// [`BlockhashOptions::transparent_color`].
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Background {
+    /// Black.
+    Black,
+    /// White.
+    White,
+    /// The average of the white and light gray squares of the checkerboard
+    /// that image editors commonly show behind transparent pixels, which is
+    /// `[230, 230, 230]`.
+    Checkerboard,
+    /// A color with the given 8-bit red, green, and blue channels, which are
+    /// scaled to the range of the channels of the image.
+    Rgb([u8; 3]),
+}
+
+impl Background {
+    /// Returns the 8-bit red, green, and blue channels of the color.
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Background::Black => [0; 3],
+            Background::White => [u8::MAX; 3],
+            Background::Checkerboard => [230; 3],
+            Background::Rgb(rgb) => rgb,
+        }
+    }
+}
+
+/// Statistics about how a hash was computed.
+///
+/// These are returned by the `_with_stats` methods of [`BlockhashOptions`],
+/// such as [`BlockhashOptions::blockhash64_with_stats`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HashStats {
+    pixels: u64,
+    accumulation: Accumulation,
+    elapsed: Option<Duration>,
+    quantized: bool,
+    quick: bool,
+}
+
+impl HashStats {
+    /// Returns the number of pixels that were read.
+    #[inline]
+    #[must_use]
+    pub fn pixels(&self) -> u64 {
+        self.pixels
+    }
+
+    /// Returns the strategy that was used to sum the brightness of each block.
+    ///
+    /// The generic strategy is much slower than the others, so images that
+    /// are smaller than the grid can take far longer to hash than their size
+    /// would suggest.
+    #[inline]
+    #[must_use]
+    pub fn accumulation(&self) -> Accumulation {
+        self.accumulation
+    }
+
+    /// Returns the time taken to compute the hash.
+    ///
+    /// This is only measured if the `std` feature is enabled, and is `None`
+    /// otherwise.
+    #[inline]
+    #[must_use]
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+
+    /// Returns whether the channels were quantized to 8 bits before hashing.
+    ///
+    /// See [`BlockhashOptions::quantize_to_8_bits`].
+    #[inline]
+    #[must_use]
+    pub fn quantized(&self) -> bool {
+        self.quantized
+    }
+
+    /// Returns whether the hash was computed in quick mode.
+    ///
+    /// See [`BlockhashOptions::quick`]. If so, the accumulation strategy wasn't
+    /// used.
+    #[inline]
+    #[must_use]
+    pub fn quick(&self) -> bool {
+        self.quick
+    }
+}
+
+/// An image with the brightness conversions from [`BlockhashOptions`] applied.
+struct Converted<'a, I> {
+    img: &'a I,
+    options: &'a BlockhashOptions,
+}
+
+impl<I: Image> Converted<'_, I> {
+    /// Returns whether the brightness of the image is a single channel rather
+    /// than the sum of three.
+    fn gray(&self) -> bool {
+        self.img.max_brightness() == self.img.max_channel()
+    }
+
+    /// Returns the maximum value of a color channel before conversion.
+    fn max_color(&self) -> u32 {
+        self.options.channel_max_for(self.img)
+    }
+
+    /// Returns the maximum value of a channel after quantization.
+    fn max_quantized(&self) -> u32 {
+        if self.options.quantize {
+            u8::MAX.into()
+        } else {
+            self.max_color()
+        }
+    }
+
+    /// Returns the maximum value of a channel after conversion.
+    fn max_converted(&self) -> u32 {
+        if self.options.linearize {
+            u16::MAX.into()
+        } else {
+            self.max_quantized()
+        }
+    }
+
+    /// Returns the color channels of a background color, scaled to the range
+    /// of the color channels of the image.
+    fn background(&self, background: Background) -> [u32; 3] {
+        let max = self.max_color();
+        let [r, g, b] = background.rgb().map(u32::from);
+        if self.gray() {
+            [scale_from_8_bits((r + g + b + 1) / 3, max); 3]
+        } else {
+            [r, g, b].map(|val| scale_from_8_bits(val, max))
+        }
+    }
+
+    /// Quantizes a channel value with the given maximum.
+    fn quantize(&self, val: u32, max: u32) -> u32 {
+        if self.options.quantize {
+            let max = u64::from(max);
+            ((u64::from(val) * u64::from(u8::MAX) + max / 2) / max) as u32
+        } else {
+            val
+        }
+    }
+
+    /// Converts the value of a color channel.
+    fn channel(&self, val: u32) -> u32 {
+        let max = self.max_color();
+        let val = self.quantize(val.min(max), max);
+        if self.options.linearize {
+            srgb_to_linear(val, self.max_quantized())
+        } else {
+            val
+        }
+    }
+}
+
+impl<I: Image> Image for Converted<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let [mut r, mut g, mut b, a] = self.img.channels(x, y);
+
+        let max_alpha = self.img.max_channel();
+        if self.options.premultiplied {
+            r = unpremultiply(r, a, max_alpha);
+            g = unpremultiply(g, a, max_alpha);
+            b = unpremultiply(b, a, max_alpha);
+        }
+
+        #[cfg(feature = "icc")]
+        if self.options.color_space != ColorSpace::Srgb {
+            let max = self.max_color();
+            [r, g, b] = self
+                .options
+                .color_space
+                .to_srgb([r, g, b].map(|val| val.min(max)), max);
+        }
+
+        if let Some(background) = self.options.background {
+            let max = self.max_color();
+            let [bg_r, bg_g, bg_b] = self.background(background);
+            r = composite(r.min(max), a, bg_r, max_alpha);
+            g = composite(g.min(max), a, bg_g, max_alpha);
+            b = composite(b.min(max), a, bg_b, max_alpha);
+        } else if self.quantize(a, max_alpha) == 0 {
+            if self.options.transparent == Background::White {
+                return self.max_brightness();
+            }
+            [r, g, b] = self.background(self.options.transparent);
+        }
+
+        let val = if self.gray() {
+            self.channel(r)
+        } else if let Some([wr, wg, wb]) = self.options.luma.weights() {
+            // The weighted average, scaled like the sum of the channels
+            let total = wr + wg + wb;
+            let sum = wr * u64::from(self.channel(r))
+                + wg * u64::from(self.channel(g))
+                + wb * u64::from(self.channel(b));
+            ((sum * 3 + total / 2) / total) as u32
+        } else {
+            self.channel(r) + self.channel(g) + self.channel(b)
+        };
+
+        #[cfg(feature = "std")]
+        if let Some(gamma) = self.options.gamma {
+            return adjust_gamma(val, self.max_brightness(), f32::from_bits(gamma));
+        }
+
+        val
+    }
+
+    fn max_brightness(&self) -> u32 {
+        if self.gray() {
+            self.max_converted()
+        } else {
+            self.max_converted() * 3
+        }
+    }
+}
+
+/// Blends a color channel with a background color channel according to an
+/// alpha channel in the range `0..=max_alpha`.
+fn composite(val: u32, alpha: u32, background: u32, max_alpha: u32) -> u32 {
+    let alpha = u64::from(alpha.min(max_alpha));
+    let max_alpha = u64::from(max_alpha);
+    let blended = u64::from(val) * alpha + u64::from(background) * (max_alpha - alpha);
+    ((blended + max_alpha / 2) / max_alpha) as u32
+}
+
+/// Divides a color channel that is premultiplied by an alpha channel in the
+/// range `0..=max_alpha` by the alpha.
+fn unpremultiply(val: u32, alpha: u32, max_alpha: u32) -> u32 {
+    if alpha == 0 {
+        return 0;
+    }
+
+    let (val, alpha) = (u64::from(val), u64::from(alpha));
+    let straight = (val * u64::from(max_alpha) + alpha / 2) / alpha;
+    straight.min(max_alpha.into()) as u32
+}
+
+/// Raises a brightness in the range `0..=max`, relative to the maximum, to the
+/// power of `1 / gamma`.
+#[cfg(feature = "std")]
+fn adjust_gamma(val: u32, max: u32, gamma: f32) -> u32 {
+    if max == 0 {
+        return 0;
+    }
+
+    let max = f64::from(max);
+    let adjusted = (f64::from(val) / max).powf(1.0 / f64::from(gamma)) * max;
+    adjusted.round().min(max) as u32
+}
+
+/// Scales an 8-bit channel value to the range `0..=max`.
+fn scale_from_8_bits(val: u32, max: u32) -> u32 {
+    let max = u64::from(max);
+    ((u64::from(val) * max + 127) / 255) as u32
+}
@@ -0,0 +1,90 @@
+/// The order in which the bits for each block are packed into bytes.
+///
+/// Hashes are normally packed in row-major order, with the first block in the
+/// most significant bit of the first byte. Some other implementations of the
+/// algorithm use different orders, so hashes stored by them need to be
+/// converted before they can be compared with hashes from this crate.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Blocks are packed row by row, starting from the most significant bit of
+    /// each byte.
+    ///
+    /// This is the order used by this crate and the reference implementation.
+    #[default]
+    RowMajorMsbFirst,
+    /// Blocks are packed row by row, starting from the least significant bit
+    /// of each byte.
+    RowMajorLsbFirst,
+    /// Blocks are packed column by column, starting from the most significant
+    /// bit of each byte.
+    ColumnMajorMsbFirst,
+    /// Blocks are packed column by column, starting from the least significant
+    /// bit of each byte.
+    ColumnMajorLsbFirst,
+}
+
+impl BitOrder {
+    /// Returns the byte and bit mask for the block at the given position.
+    fn position(self, x: u32, y: u32, columns: u32, rows: u32) -> (usize, u8) {
+        let idx = match self {
+            BitOrder::RowMajorMsbFirst | BitOrder::RowMajorLsbFirst => y * columns + x,
+            BitOrder::ColumnMajorMsbFirst | BitOrder::ColumnMajorLsbFirst => x * rows + y,
+        } as usize;
+
+        let mask = match self {
+            BitOrder::RowMajorMsbFirst | BitOrder::ColumnMajorMsbFirst => 0x80 >> (idx % 8),
+            BitOrder::RowMajorLsbFirst | BitOrder::ColumnMajorLsbFirst => 1 << (idx % 8),
+        };
+
+        (idx / 8, mask)
+    }
+}
+
+/// Converts the bytes of a hash from one bit order to another.
+pub(crate) fn repack<const SIZE: usize>(
+    bytes: &[u8; SIZE],
+    columns: u32,
+    rows: u32,
+    from: BitOrder,
+    to: BitOrder,
+) -> [u8; SIZE] {
+    let mut res = [0_u8; SIZE];
+
+    for y in 0..rows {
+        for x in 0..columns {
+            let (src, src_mask) = from.position(x, y, columns, rows);
+            if bytes[src] & src_mask != 0 {
+                let (dst, dst_mask) = to.position(x, y, columns, rows);
+                res[dst] |= dst_mask;
+            }
+        }
+    }
+
+    res
+}
+
+/// Mirrors the blocks of a hash horizontally, vertically, or both.
+pub(crate) fn flip<const SIZE: usize>(
+    bytes: &[u8; SIZE],
+    columns: u32,
+    rows: u32,
+    horizontal: bool,
+    vertical: bool,
+) -> [u8; SIZE] {
+    let order = BitOrder::RowMajorMsbFirst;
+    let mut res = [0_u8; SIZE];
+
+    for y in 0..rows {
+        for x in 0..columns {
+            let (src, src_mask) = order.position(x, y, columns, rows);
+            if bytes[src] & src_mask != 0 {
+                let dx = if horizontal { columns - 1 - x } else { x };
+                let dy = if vertical { rows - 1 - y } else { y };
+                let (dst, dst_mask) = order.position(dx, dy, columns, rows);
+                res[dst] |= dst_mask;
+            }
+        }
+    }
+
+    res
+}
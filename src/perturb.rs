@@ -0,0 +1,85 @@
+//! Synthetic perturbations for testing matching behavior.
+//!
+//! These functions apply the kinds of changes that images typically undergo
+//! when they are shared, such as lossy recompression, resizing, and cropping.
+//! They are intended for integration tests that check whether hashes of
+//! modified images still match the originals at a given threshold.
+//!
+//! # Examples
+//!
+//! ```
+//! use blockhash::perturb;
+//! use blockhash::blockhash64;
+//!
+//! let img = image::open("images/example.png").unwrap();
+//! let modified = perturb::recompress(&perturb::resize(&img, 160, 96), 75);
+//!
+//! assert!(blockhash64(&img).distance(&blockhash64(&modified)) <= 4);
+//! ```
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+
+/// Encodes an image as a JPEG with the given quality, and decodes it again.
+///
+/// The quality is clamped to the range `1..=100`. Any transparency is
+/// discarded, since JPEG doesn't support it.
+///
+/// # Panics
+///
+/// Panics if the image has a width or height of zero, or is too large to be
+/// encoded as a JPEG.
+#[must_use]
+pub fn recompress(img: &DynamicImage, quality: u8) -> DynamicImage {
+    let mut buf = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut buf, quality.clamp(1, 100));
+    DynamicImage::ImageRgb8(img.to_rgb8())
+        .write_with_encoder(encoder)
+        .expect("failed to encode image");
+
+    image::load_from_memory_with_format(&buf, ImageFormat::Jpeg).expect("failed to decode image")
+}
+
+/// Resizes an image to exactly the given dimensions, ignoring its aspect
+/// ratio.
+#[must_use]
+pub fn resize(img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    img.resize_exact(width, height, FilterType::Triangle)
+}
+
+/// Adds borders of the given color around an image.
+#[must_use]
+pub fn pad(
+    img: &DynamicImage,
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    color: Rgba<u8>,
+) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let mut padded = RgbaImage::from_pixel(left + width + right, top + height + bottom, color);
+    imageops::replace(&mut padded, &img.to_rgba8(), left.into(), top.into());
+    DynamicImage::ImageRgba8(padded)
+}
+
+/// Crops an image to the given rectangle.
+///
+/// The rectangle is clipped to the bounds of the image.
+#[must_use]
+pub fn crop(img: &DynamicImage, x: u32, y: u32, width: u32, height: u32) -> DynamicImage {
+    img.crop_imm(x, y, width, height)
+}
+
+/// Draws another image, such as a logo or caption, on top of an image with its
+/// top-left corner at the given position.
+///
+/// The overlay is blended using its alpha channel, and any part of it outside
+/// the bounds of the image is clipped.
+#[must_use]
+pub fn overlay(img: &DynamicImage, top: &DynamicImage, x: i64, y: i64) -> DynamicImage {
+    let mut result = img.to_rgba8();
+    imageops::overlay(&mut result, &top.to_rgba8(), x, y);
+    DynamicImage::ImageRgba8(result)
+}
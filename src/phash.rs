@@ -0,0 +1,126 @@
+use crate::hash::{get_values, Accumulation};
+use crate::{Blockhash, Blockhash256, Blockhash64, Image};
+use std::f64::consts::PI;
+
+/// Generates a 64-bit DCT-based perceptual hash (pHash) of an image.
+///
+/// The image is divided into a grid of 32 by 32 blocks, in the same way as for
+/// [`blockhash`](crate::blockhash64), and the two-dimensional discrete cosine
+/// transform of the block brightnesses is computed. Each bit of the hash is
+/// then set if the corresponding coefficient in the lowest 8 by 8 frequencies
+/// is above the median of those coefficients. Bits are packed in row-major
+/// order, with the vertical frequency increasing from row to row.
+///
+/// pHash is generally more robust than blockhash to changes in brightness,
+/// contrast, and compression, but is slower to compute. The two kinds of hash
+/// use the same digest types, but aren't comparable with each other.
+///
+/// Unlike blockhash, this uses floating-point arithmetic, so in rare cases the
+/// hash of an image can differ between platforms, and allocates memory for
+/// the transform.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::phash64;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = phash64(&img);
+///
+/// assert_eq!(hash.to_string(), "bbb9c0e22f873618");
+/// # }
+/// ```
+#[must_use]
+pub fn phash64<I: Image>(img: &I) -> Blockhash64 {
+    Blockhash(phash::<I, 32, 1024, 8, 8>(img))
+}
+
+/// Generates a 256-bit DCT-based perceptual hash (pHash) of an image.
+///
+/// This is the same as [`phash64`], but uses a grid of 64 by 64 blocks, and
+/// the lowest 16 by 16 frequencies.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::phash256;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = phash256(&img);
+///
+/// assert_eq!(
+///     hash.to_string(),
+///     "bb17bb13c01be2662f668742368d1f072e8e2e5f5d2cf617d9a112e949760ae0",
+/// );
+/// # }
+/// ```
+#[must_use]
+pub fn phash256<I: Image>(img: &I) -> Blockhash256 {
+    Blockhash(phash::<I, 64, 4096, 16, 32>(img))
+}
+
+fn phash<
+    I: Image,
+    const SIZE: u32,
+    const NUM_BLOCKS: usize,
+    const FREQUENCIES: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+) -> [u8; DIGEST_SIZE] {
+    // These values are related, but need to be passed in separately due to
+    // limitations with const generics.
+    debug_assert_eq!(NUM_BLOCKS, (SIZE * SIZE) as usize);
+    debug_assert_eq!(FREQUENCIES * FREQUENCIES, DIGEST_SIZE * 8);
+
+    let (width, height) = img.dimensions();
+    let accumulation = Accumulation::select(width, height, SIZE);
+    let values = get_values::<I, SIZE, NUM_BLOCKS>(img, accumulation);
+
+    let n = SIZE as usize;
+
+    // cos[k * n + i] is the weight of sample i for frequency k
+    let cos: Vec<f64> = (0..(FREQUENCIES * n))
+        .map(|j| {
+            let (k, i) = (j / n, j % n);
+            (PI * (2 * i + 1) as f64 * k as f64 / (2 * n) as f64).cos()
+        })
+        .collect();
+
+    // Transform each row, keeping only the lowest frequencies
+    let mut rows = vec![0.0; n * FREQUENCIES];
+    for y in 0..n {
+        for u in 0..FREQUENCIES {
+            rows[y * FREQUENCIES + u] = (0..n)
+                .map(|x| values[y * n + x] as f64 * cos[u * n + x])
+                .sum();
+        }
+    }
+
+    // Then transform each column
+    let mut coeffs = vec![0.0; FREQUENCIES * FREQUENCIES];
+    for v in 0..FREQUENCIES {
+        for u in 0..FREQUENCIES {
+            coeffs[v * FREQUENCIES + u] = (0..n)
+                .map(|y| rows[y * FREQUENCIES + u] * cos[v * n + y])
+                .sum();
+        }
+    }
+
+    let mut sorted = coeffs.clone();
+    sorted.sort_unstable_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    let median = (sorted[mid - 1] + sorted[mid]) / 2.0;
+
+    let mut res = [0_u8; DIGEST_SIZE];
+
+    for (i, &coeff) in coeffs.iter().enumerate() {
+        if coeff > median {
+            res[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+
+    res
+}
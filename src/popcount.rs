@@ -0,0 +1,37 @@
+/// Returns the number of set bits in a byte.
+///
+/// On most targets `count_ones` compiles to a single instruction or a short
+/// sequence of bitwise operations, but on small embedded targets without a
+/// popcount instruction it can compile to a call to a comparatively slow
+/// library function. A lookup table is used on those targets instead.
+#[inline]
+pub(crate) fn count_ones(byte: u8) -> u32 {
+    if cfg!(any(
+        target_arch = "arm",
+        target_arch = "avr",
+        target_arch = "msp430",
+        target_arch = "riscv32",
+        target_arch = "xtensa",
+    )) {
+        count_ones_table(byte)
+    } else {
+        byte.count_ones()
+    }
+}
+
+/// Returns the number of set bits in a byte, using a lookup table.
+#[inline]
+pub(crate) fn count_ones_table(byte: u8) -> u32 {
+    u32::from(TABLE[usize::from(byte)])
+}
+
+/// The number of set bits in each byte.
+static TABLE: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 1;
+    while i < 256 {
+        table[i] = table[i / 2] + (i % 2) as u8;
+        i += 1;
+    }
+    table
+};
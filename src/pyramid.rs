@@ -0,0 +1,154 @@
+use crate::{Digest, Image};
+
+/// The largest number of pixels per block along the longer side of each
+/// level of a [`BlockhashPyramid`], starting with the full-size image.
+const LEVEL_SCALES: [u32; 4] = [u32::MAX, 8, 4, 2];
+
+/// The hashes of an image at several resolutions.
+///
+/// A small thumbnail usually hashes slightly differently to the image it was
+/// made from, since the boundaries between blocks fall between pixels of the
+/// thumbnail, and the filter that made it may have dropped or blurred
+/// details. The smaller the thumbnail, the larger the difference. This type
+/// hashes the full-size image, and the image scaled down with a box filter so
+/// that each block covers at most 8, 4, and 2 pixels along its longer side.
+/// Levels are never scaled up, so the levels of a small thumbnail can be the
+/// same.
+///
+/// Comparing the pyramids of an original and a thumbnail compares the hashes
+/// level by level, and the closest pair of levels is usually closer than
+/// the full-size hashes, since one of the smaller levels of the original
+/// suffers the same rounding as the thumbnail. Different images remain far
+/// apart at every level.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash256, Blockhash256, BlockhashPyramid};
+/// use image::imageops::FilterType;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let thumbnail = img.resize(24, 24, FilterType::Lanczos3);
+///
+/// let a = BlockhashPyramid::<Blockhash256>::from_image(&img);
+/// let b = BlockhashPyramid::<Blockhash256>::from_image(&thumbnail);
+///
+/// assert_eq!(a.levels()[0], blockhash256(&img));
+/// assert_eq!(a.level_distances(&b), [14, 16, 16, 12]);
+/// assert_eq!(a.distance(&b), 12);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct BlockhashPyramid<D> {
+    levels: [D; 4],
+}
+
+impl<D: Digest> BlockhashPyramid<D> {
+    /// Hashes an image at each level of the pyramid.
+    #[must_use]
+    pub fn from_image<I: Image>(img: &I) -> Self {
+        let (width, height) = img.dimensions();
+        let long_side = width.max(height);
+        let blocks = D::COLUMNS.max(D::ROWS);
+
+        let levels = LEVEL_SCALES.map(|scale| {
+            let size = scale.saturating_mul(blocks);
+            if long_side <= size {
+                D::from_image(img)
+            } else {
+                D::from_image(&Downscaled::new(img, size))
+            }
+        });
+
+        BlockhashPyramid { levels }
+    }
+
+    /// Returns the hashes of the levels, starting with the full-size image.
+    #[inline]
+    #[must_use]
+    pub fn levels(&self) -> &[D; 4] {
+        &self.levels
+    }
+
+    /// Returns the distance between the hashes of each pair of levels.
+    #[must_use]
+    pub fn level_distances(&self, other: &Self) -> [u32; 4] {
+        let mut distances = [0; 4];
+        for (dist, (a, b)) in distances
+            .iter_mut()
+            .zip(self.levels.iter().zip(&other.levels))
+        {
+            *dist = a.distance(b);
+        }
+        distances
+    }
+
+    /// Returns the distance between two pyramids, which is the smallest
+    /// distance between a pair of levels.
+    ///
+    /// This is never greater than the distance between the full-size hashes.
+    #[inline]
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> u32 {
+        let [a, b, c, d] = self.level_distances(other);
+        a.min(b).min(c).min(d)
+    }
+}
+
+/// An image scaled down with a box filter, so that its longer side has a
+/// given number of pixels.
+struct Downscaled<'a, I> {
+    img: &'a I,
+    width: u32,
+    height: u32,
+}
+
+impl<'a, I: Image> Downscaled<'a, I> {
+    fn new(img: &'a I, size: u32) -> Self {
+        let (width, height) = img.dimensions();
+        let long_side = u64::from(width.max(height));
+        let scale = |len: u32| {
+            ((u64::from(len) * u64::from(size) + long_side / 2) / long_side).max(1) as u32
+        };
+
+        Downscaled {
+            img,
+            width: scale(width),
+            height: scale(height),
+        }
+    }
+}
+
+impl<I: Image> Image for Downscaled<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let (width, height) = self.img.dimensions();
+        let span = |pos: u32, len: u32, scaled: u32| {
+            let start = u64::from(pos) * u64::from(len) / u64::from(scaled);
+            let end = (u64::from(pos) + 1) * u64::from(len) / u64::from(scaled);
+            (start as u32, end.max(start + 1) as u32)
+        };
+        let (left, right) = span(x, width, self.width);
+        let (top, bottom) = span(y, height, self.height);
+
+        let mut sum = 0_u64;
+        for y in top..bottom {
+            for x in left..right {
+                sum += u64::from(self.img.brightness(x, y));
+            }
+        }
+
+        let count = u64::from(right - left) * u64::from(bottom - top);
+        ((sum + count / 2) / count) as u32
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{Digest, Image};
+use core::cell::Cell;
+
+/// Estimates of the sharpness and exposure of an image, measured while it is
+/// hashed by [`blockhash_with_quality`].
+///
+/// These need no reference image, so they can be used to skip frames that
+/// aren't worth indexing, such as frames blurred by camera shake, or taken
+/// with the lens covered, before they are added to an index. They are rough
+/// estimates, and suitable thresholds depend on the source of the images.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ImageQuality {
+    pixels: u64,
+    max_brightness: u32,
+    brightness: u64,
+    dark: u64,
+    bright: u64,
+    // The sums of the first and second differences between horizontally
+    // adjacent pixels, and the number of pixels they were measured at
+    gradient: u64,
+    curvature: u64,
+    neighbors: u64,
+}
+
+impl ImageQuality {
+    /// Returns the number of pixels that were measured.
+    #[inline]
+    #[must_use]
+    pub fn pixels(&self) -> u64 {
+        self.pixels
+    }
+
+    /// Returns how sharp the edges of the image are, from 0 to 1.
+    ///
+    /// This is roughly the reciprocal of the width of the edges in pixels, so
+    /// an edge between two pixels has a sharpness of 1, and an edge blurred
+    /// over 4 pixels has a sharpness of about 0.25. It is measured along each
+    /// row, and doesn't depend on the contrast of the edges, but noise makes
+    /// images appear sharper. If the image has no edges at all, this is zero.
+    #[must_use]
+    pub fn sharpness(&self) -> f64 {
+        match self.gradient {
+            0 => 0.0,
+            gradient => self.curvature as f64 / (2 * gradient) as f64,
+        }
+    }
+
+    /// Returns the average difference in brightness between adjacent pixels,
+    /// relative to the maximum brightness.
+    ///
+    /// Images that are almost flat, such as frames taken with the lens
+    /// covered or in fog, have a very low contrast, and their hashes mostly
+    /// depend on noise.
+    #[must_use]
+    pub fn contrast(&self) -> f64 {
+        match self.neighbors {
+            0 => 0.0,
+            n => self.gradient as f64 / n as f64 / f64::from(self.max_brightness.max(1)),
+        }
+    }
+
+    /// Returns the average brightness of the image, from 0 to 1.
+    #[must_use]
+    pub fn mean_brightness(&self) -> f64 {
+        match self.pixels {
+            0 => 0.0,
+            n => self.brightness as f64 / n as f64 / f64::from(self.max_brightness.max(1)),
+        }
+    }
+
+    /// Returns the fraction of pixels that are nearly black, within 1/32 of
+    /// the maximum brightness.
+    #[must_use]
+    pub fn underexposed(&self) -> f64 {
+        self.fraction(self.dark)
+    }
+
+    /// Returns the fraction of pixels that are nearly white, within 1/32 of
+    /// the maximum brightness.
+    #[must_use]
+    pub fn overexposed(&self) -> f64 {
+        self.fraction(self.bright)
+    }
+
+    fn fraction(&self, count: u64) -> f64 {
+        match self.pixels {
+            0 => 0.0,
+            n => count as f64 / n as f64,
+        }
+    }
+}
+
+/// Generates a perceptual hash of an image, along with estimates of its
+/// sharpness and exposure.
+///
+/// The estimates are measured from the brightness of each pixel as it is
+/// read to compute the hash, so the image is only read once.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash_with_quality, Blockhash64, Digest, RawImage};
+///
+/// // Vertical stripes, with sharp edges and blurred edges
+/// let stripes = |blur: usize| {
+///     let mut data = vec![0_u8; 64 * 64];
+///     for (i, val) in data.iter_mut().enumerate() {
+///         let x = i % 64 % 16;
+///         let ramp = |x: usize| (x * 255 / blur).min(255);
+///         *val = ramp(x.saturating_sub(4)).min(ramp(16 - x)) as u8;
+///     }
+///     data
+/// };
+///
+/// let (sharp, blurred) = (stripes(1), stripes(6));
+/// let (hash, quality) =
+///     blockhash_with_quality::<Blockhash64, _>(&RawImage::<_, 1>::new(&sharp, 64, 64));
+/// assert_eq!(hash, Blockhash64::from_image(&RawImage::<_, 1>::new(&sharp, 64, 64)));
+/// assert!(quality.sharpness() > 0.9);
+///
+/// let (_, quality) =
+///     blockhash_with_quality::<Blockhash64, _>(&RawImage::<_, 1>::new(&blurred, 64, 64));
+/// assert!(quality.sharpness() < 0.25);
+/// ```
+#[must_use]
+pub fn blockhash_with_quality<D: Digest, I: Image>(img: &I) -> (D, ImageQuality) {
+    let measured = Measured {
+        img,
+        max_brightness: img.max_brightness(),
+        quality: Cell::new(ImageQuality {
+            max_brightness: img.max_brightness(),
+            ..ImageQuality::default()
+        }),
+        previous: Cell::new(None),
+    };
+
+    let hash = D::from_image(&measured);
+    (hash, measured.quality.get())
+}
+
+/// An image that measures the quality of the pixels that are read from it.
+///
+/// The hashing functions read each pixel once, one row at a time, so the
+/// differences between adjacent pixels are measured from the pixels read
+/// immediately before.
+struct Measured<'a, I> {
+    img: &'a I,
+    max_brightness: u32,
+    quality: Cell<ImageQuality>,
+    previous: Cell<Option<Pixel>>,
+}
+
+/// The last pixel read from a [`Measured`] image.
+#[derive(Debug, Copy, Clone)]
+struct Pixel {
+    x: u32,
+    y: u32,
+    brightness: u32,
+    /// The difference from the pixel read before it, if they are adjacent.
+    diff: Option<i64>,
+}
+
+impl<I: Image> Image for Measured<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let val = self.img.brightness(x, y);
+        let mut quality = self.quality.get();
+
+        quality.pixels += 1;
+        quality.brightness += u64::from(val);
+        if u64::from(val) * 32 <= u64::from(self.max_brightness) {
+            quality.dark += 1;
+        }
+        if u64::from(self.max_brightness.saturating_sub(val)) * 32 <= u64::from(self.max_brightness)
+        {
+            quality.bright += 1;
+        }
+
+        let mut diff = None;
+        if let Some(prev) = self.previous.get() {
+            if prev.y == y && prev.x.checked_add(1) == Some(x) {
+                let d = i64::from(val) - i64::from(prev.brightness);
+                quality.gradient += d.unsigned_abs();
+                quality.neighbors += 1;
+                if let Some(prev_diff) = prev.diff {
+                    quality.curvature += (d - prev_diff).unsigned_abs();
+                }
+                diff = Some(d);
+            }
+        }
+
+        self.previous.set(Some(Pixel {
+            x,
+            y,
+            brightness: val,
+            diff,
+        }));
+        self.quality.set(quality);
+        val
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.max_brightness
+    }
+}
@@ -0,0 +1,141 @@
+use crate::Image;
+
+/// A type that can be used for the channels of a [`RawImage`].
+///
+/// This trait is sealed, and is implemented for `u8` and `u16`.
+pub trait Channel: Copy + Into<u32> + private::Sealed {
+    /// The maximum possible value of the channel.
+    const MAX: u32;
+}
+
+impl Channel for u8 {
+    const MAX: u32 = u8::MAX as u32;
+}
+
+impl Channel for u16 {
+    const MAX: u32 = u16::MAX as u32;
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+}
+
+/// An image stored in a buffer of interleaved channels.
+///
+/// The number of channels determines how each pixel is interpreted:
+///
+/// * 1: grayscale
+/// * 2: grayscale with alpha
+/// * 3: RGB
+/// * 4: RGBA
+///
+/// Pixels are stored in row-major order, with no padding between rows.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash16, RawImage};
+///
+/// let data = [0_u8, 0, 255, 255, 0, 0, 255, 255];
+/// let img = RawImage::<_, 1>::new(&data, 4, 2);
+///
+/// assert_eq!(blockhash16(&img).to_string(), "3333");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct RawImage<'a, T, const CHANNELS: usize> {
+    data: &'a [T],
+    width: u32,
+    height: u32,
+}
+
+impl<'a, T: Channel, const CHANNELS: usize> RawImage<'a, T, CHANNELS> {
+    const VALID_CHANNELS: () = assert!(CHANNELS >= 1 && CHANNELS <= 4);
+
+    /// Creates an image from a buffer of interleaved channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the buffer doesn't match the dimensions of the
+    /// image.
+    #[must_use]
+    pub fn new(data: &'a [T], width: u32, height: u32) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_CHANNELS;
+
+        assert_eq!(
+            data.len() as u64,
+            u64::from(width) * u64::from(height) * CHANNELS as u64,
+        );
+
+        RawImage {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the underlying buffer.
+    #[inline]
+    #[must_use]
+    pub fn as_slice(&self) -> &'a [T] {
+        self.data
+    }
+
+    #[inline]
+    fn pixel(&self, x: u32, y: u32) -> &[T] {
+        let idx = (y as usize * self.width as usize + x as usize) * CHANNELS;
+        &self.data[idx..(idx + CHANNELS)]
+    }
+}
+
+impl<T: Channel, const CHANNELS: usize> Image for RawImage<'_, T, CHANNELS> {
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        if CHANNELS > 2 {
+            T::MAX * 3
+        } else {
+            T::MAX
+        }
+    }
+
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        match *self.pixel(x, y) {
+            [y] => y.into(),
+            [y, a] => match a.into() {
+                0 => self.max_brightness(),
+                _ => y.into(),
+            },
+            [r, g, b] => r.into() + g.into() + b.into(),
+            [r, g, b, a] => match a.into() {
+                0 => self.max_brightness(),
+                _ => r.into() + g.into() + b.into(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        T::MAX
+    }
+
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        match *self.pixel(x, y) {
+            [y] => [y.into(), y.into(), y.into(), T::MAX],
+            [y, a] => [y.into(), y.into(), y.into(), a.into()],
+            [r, g, b] => [r.into(), g.into(), b.into(), T::MAX],
+            [r, g, b, a] => [r.into(), g.into(), b.into(), a.into()],
+            _ => unreachable!(),
+        }
+    }
+}
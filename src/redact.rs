@@ -0,0 +1,160 @@
+use crate::popcount::count_ones;
+use crate::{AnyBlockhash, PartialDistance};
+
+/// A hash with some of its bits withheld, for sharing with parties that
+/// shouldn't receive the full hash.
+///
+/// A redacted hash consists of the known bits of the hash, with every other
+/// bit cleared, and a mask of the same size with a bit set for each known bit.
+/// Both can be shared as ordinary hashes, and the receiver can reconstruct the
+/// redacted hash with [`RedactedHash::new`] to compare it against full hashes.
+///
+/// Fewer known bits reveal less about the image, but also make matches less
+/// certain, since unrelated images are more likely to agree on a small number
+/// of bits.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{AnyBlockhash, Blockhash64, RedactedHash};
+///
+/// let hash = Blockhash64::from(0xc7c4_8f89_89c7_7e0c);
+/// let shared = RedactedHash::truncate(hash, 32);
+/// assert_eq!(shared.hash().to_string(), "c7c48f8900000000");
+/// assert_eq!(shared.mask().to_string(), "ffffffff00000000");
+///
+/// // The receiver compares the known bits against their full hashes
+/// let received = RedactedHash::new(*shared.hash(), *shared.mask()).unwrap();
+/// let similar = AnyBlockhash::from(Blockhash64::from(0xc7c4_8f88_0000_0000));
+///
+/// let dist = received.distance(&similar).unwrap();
+/// assert_eq!(dist.distance(), 1);
+/// assert_eq!(dist.compared(), 32);
+/// assert_eq!(dist.scaled(), 2);
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct RedactedHash {
+    hash: AnyBlockhash,
+    mask: AnyBlockhash,
+}
+
+impl RedactedHash {
+    /// Creates a redacted hash from its known bits and a mask of the bits that
+    /// are known.
+    ///
+    /// Any bits of the hash that aren't in the mask are cleared. Returns `None`
+    /// if the hash and mask have different sizes.
+    #[must_use]
+    pub fn new(hash: AnyBlockhash, mask: AnyBlockhash) -> Option<Self> {
+        if hash.bits() != mask.bits() {
+            return None;
+        }
+
+        let mut bytes = [0; 128];
+        let bytes = &mut bytes[..hash.as_bytes().len()];
+        for ((byte, &h), &m) in bytes.iter_mut().zip(hash.as_bytes()).zip(mask.as_bytes()) {
+            *byte = h & m;
+        }
+
+        Some(RedactedHash {
+            hash: AnyBlockhash::from_bytes(bytes)?,
+            mask,
+        })
+    }
+
+    /// Redacts all but the first `bits` bits of a hash.
+    ///
+    /// Bits are counted in row-major order from the top-left block, so this
+    /// keeps the top rows of the grid. If `bits` is greater than the size of
+    /// the hash, nothing is redacted.
+    #[must_use]
+    pub fn truncate<D: Into<AnyBlockhash>>(hash: D, bits: u32) -> Self {
+        let hash = hash.into();
+        Self::with_mask(hash, |idx| idx < bits)
+    }
+
+    /// Redacts all but a range of the horizontal bands of a hash.
+    ///
+    /// The grid of a hash is divided into four bands of rows, and the bits of
+    /// each band are computed independently of the others, so sharing only
+    /// some bands reveals nothing about the rest of the image. Bands are
+    /// numbered from 0 at the top, and `start` is inclusive while `end` is
+    /// exclusive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is greater than `end`, or `end` is greater than 4.
+    #[must_use]
+    pub fn keep_bands<D: Into<AnyBlockhash>>(hash: D, start: u32, end: u32) -> Self {
+        assert!(start <= end && end <= 4, "invalid band range");
+
+        let hash = hash.into();
+        let band_size = hash.bits() / 4;
+        Self::with_mask(hash, |idx| (start..end).contains(&(idx / band_size)))
+    }
+
+    fn with_mask<F: Fn(u32) -> bool>(hash: AnyBlockhash, known: F) -> Self {
+        let mut mask = [0; 128];
+        let mask = &mut mask[..hash.as_bytes().len()];
+        for idx in 0..hash.bits() {
+            if known(idx) {
+                mask[(idx / 8) as usize] |= 0x80 >> (idx % 8);
+            }
+        }
+
+        Self::new(hash, AnyBlockhash::from_bytes(mask).unwrap()).unwrap()
+    }
+
+    /// Returns the known bits of the hash, with every other bit cleared.
+    #[inline]
+    #[must_use]
+    pub fn hash(&self) -> &AnyBlockhash {
+        &self.hash
+    }
+
+    /// Returns the mask of known bits.
+    #[inline]
+    #[must_use]
+    pub fn mask(&self) -> &AnyBlockhash {
+        &self.mask
+    }
+
+    /// Returns the number of known bits.
+    #[must_use]
+    pub fn known_bits(&self) -> u32 {
+        self.mask
+            .as_bytes()
+            .iter()
+            .map(|&byte| count_ones(byte))
+            .sum()
+    }
+
+    /// Returns the distance between the known bits and the same bits of a full
+    /// hash, or `None` if the hashes have different sizes.
+    ///
+    /// [`PartialDistance::scaled`] estimates the distance between the full
+    /// hashes, so it can be compared against the usual thresholds. Since fewer
+    /// bits are compared, the estimate is less reliable, and a lower threshold
+    /// should be used to avoid false matches.
+    #[must_use]
+    pub fn distance(&self, hash: &AnyBlockhash) -> Option<PartialDistance> {
+        if hash.bits() != self.hash.bits() {
+            return None;
+        }
+
+        let distance = self
+            .hash
+            .as_bytes()
+            .iter()
+            .zip(hash.as_bytes())
+            .zip(self.mask.as_bytes())
+            .map(|((&a, &b), &m)| count_ones((a ^ b) & m))
+            .sum();
+
+        Some(PartialDistance::new(
+            distance,
+            self.known_bits(),
+            self.hash.bits(),
+        ))
+    }
+}
@@ -0,0 +1,101 @@
+use crate::Image;
+
+/// A rectangular region of an image.
+///
+/// This allows part of an image to be hashed without copying it. Hashing a
+/// region produces exactly the same digest as hashing a copy of the same
+/// pixels, since the brightness of each pixel is read directly from the
+/// underlying image.
+///
+/// If the `image` feature is enabled, a region can also be created from a
+/// [`SubImage`](image::SubImage) returned by
+/// [`GenericImageView::view`](image::GenericImageView::view).
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, Region};
+/// use image::GenericImageView;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let hash = blockhash64(&Region::new(&img, 10, 10, 50, 50));
+///
+/// let view = img.view(10, 10, 50, 50);
+/// assert_eq!(blockhash64(&Region::from(&view)), hash);
+/// assert_eq!(blockhash64(&view.to_image()), hash);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Region<'a, I> {
+    img: &'a I,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl<'a, I: Image> Region<'a, I> {
+    /// Creates a region of an image, with the top-left corner at the given
+    /// position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region extends outside the bounds of the image.
+    #[must_use]
+    pub fn new(img: &'a I, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let (img_width, img_height) = img.dimensions();
+        assert!(u64::from(x) + u64::from(width) <= u64::from(img_width));
+        assert!(u64::from(y) + u64::from(height) <= u64::from(img_height));
+
+        Region {
+            img,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the position of the top-left corner of the region within the
+    /// underlying image.
+    #[inline]
+    #[must_use]
+    pub fn offsets(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// Returns the underlying image.
+    #[inline]
+    #[must_use]
+    pub fn inner(&self) -> &'a I {
+        self.img
+    }
+}
+
+impl<I: Image> Image for Region<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        self.img.brightness(self.x + x, self.y + y)
+    }
+
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        self.img.channels(self.x + x, self.y + y)
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.img.max_channel()
+    }
+}
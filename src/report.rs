@@ -0,0 +1,185 @@
+use crate::compare::bit;
+use crate::Digest;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// The height of each panel of a comparison image, in pixels.
+const PANEL_HEIGHT: u32 = 256;
+
+/// The space around and between the panels, in pixels.
+const MARGIN: u32 = 8;
+
+/// The size of each pixel of the digit font.
+const FONT_SCALE: u32 = 3;
+
+/// The height of the strip below the panels containing the distance.
+const CAPTION_HEIGHT: u32 = 5 * FONT_SCALE + MARGIN;
+
+const BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+const TEXT: Rgb<u8> = Rgb([255, 255, 255]);
+const BAR: Rgb<u8> = Rgb([96, 96, 96]);
+const CHANGED: Rgb<u8> = Rgb([255, 0, 0]);
+
+/// A 3x5 pixel font for the digits 0 to 9 and `/`, with each row of a glyph
+/// stored in the low 3 bits of a byte.
+const GLYPHS: [[u8; 5]; 11] = [
+    [7, 5, 5, 5, 7],
+    [2, 6, 2, 2, 7],
+    [7, 1, 7, 4, 7],
+    [7, 1, 7, 1, 7],
+    [5, 5, 7, 1, 1],
+    [7, 4, 7, 1, 7],
+    [7, 4, 7, 5, 7],
+    [7, 1, 1, 1, 1],
+    [7, 5, 7, 5, 7],
+    [7, 5, 7, 1, 7],
+    [1, 1, 2, 4, 4],
+];
+
+/// Renders a side-by-side comparison of two images for manual review.
+///
+/// Both images are hashed as `D`, and the result contains three panels: the
+/// first image, the second image, and the first image dimmed, with the blocks
+/// whose bits differ between the hashes highlighted in red. Each panel is
+/// 256 pixels high, and keeps the aspect ratio of its image. Below the panels
+/// are the distance and the number of bits in the hash, such as `12/64`, and a
+/// bar whose length is proportional to the distance.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{comparison_image, Blockhash64};
+///
+/// let a = image::open("images/example.png").unwrap();
+/// let b = a.brighten(40);
+///
+/// let report = comparison_image::<Blockhash64>(&a, &b);
+/// assert_eq!(report.height(), 295);
+/// ```
+#[must_use]
+pub fn comparison_image<D: Digest>(a: &DynamicImage, b: &DynamicImage) -> RgbImage {
+    let (hash_a, hash_b) = (D::from_image(a), D::from_image(b));
+    let distance = hash_a.distance(&hash_b);
+
+    let panel_a = panel(a);
+    let panel_b = panel(b);
+    let overlay = overlay(&panel_a, &hash_a, &hash_b);
+
+    let width = panel_a.width() * 2 + panel_b.width() + MARGIN * 4;
+    let height = PANEL_HEIGHT + CAPTION_HEIGHT + MARGIN * 2;
+    let mut report = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    let mut x = MARGIN;
+    for panel in &[panel_a, panel_b, overlay] {
+        imageops::replace(&mut report, panel, x.into(), MARGIN.into());
+        x += panel.width() + MARGIN;
+    }
+
+    // The distance, followed by a bar proportional to it
+    let y = PANEL_HEIGHT + MARGIN * 2;
+    let mut x = MARGIN;
+    for digit in Digits::new(distance)
+        .chain(Some(10))
+        .chain(Digits::new(D::BITS))
+    {
+        draw_glyph(&mut report, digit, x, y);
+        x += 4 * FONT_SCALE;
+    }
+
+    let bar_left = x + MARGIN;
+    let bar_width = width.saturating_sub(bar_left + MARGIN);
+    let bar_right = bar_left + bar_width * distance / D::BITS;
+    for by in y..(y + 5 * FONT_SCALE) {
+        for bx in bar_left..(bar_left + bar_width) {
+            let color = if bx < bar_right { CHANGED } else { BAR };
+            report.put_pixel(bx, by, color);
+        }
+    }
+
+    report
+}
+
+/// Resizes an image to the height of a panel, keeping its aspect ratio.
+fn panel(img: &DynamicImage) -> RgbImage {
+    let (width, height) = (img.width().max(1), img.height().max(1));
+    let panel_width = (u64::from(width) * u64::from(PANEL_HEIGHT) / u64::from(height)).max(1);
+    let panel_width = panel_width.min(u64::from(PANEL_HEIGHT) * 16) as u32;
+    imageops::resize(
+        &img.to_rgb8(),
+        panel_width,
+        PANEL_HEIGHT,
+        FilterType::Triangle,
+    )
+}
+
+/// Dims a panel, and highlights the blocks that differ between two hashes.
+fn overlay<D: Digest>(panel: &RgbImage, a: &D, b: &D) -> RgbImage {
+    let (width, height) = panel.dimensions();
+    let mut overlay = panel.clone();
+
+    for (x, y, pixel) in overlay.enumerate_pixels_mut() {
+        let bx = x * D::COLUMNS / width;
+        let by = y * D::ROWS / height;
+        let changed = bit(a, bx, by) != bit(b, bx, by);
+
+        for (channel, &highlight) in pixel.0.iter_mut().zip(&CHANGED.0) {
+            *channel = if changed {
+                ((u16::from(*channel) + u16::from(highlight)) / 2) as u8
+            } else {
+                *channel / 3
+            };
+        }
+    }
+
+    overlay
+}
+
+/// Draws a glyph of the digit font with its top-left corner at the given
+/// position.
+fn draw_glyph(img: &mut RgbImage, glyph: usize, x: u32, y: u32) {
+    for (row, bits) in GLYPHS[glyph].iter().enumerate() {
+        for col in 0..3 {
+            if bits & (4 >> col) == 0 {
+                continue;
+            }
+            for dy in 0..FONT_SCALE {
+                for dx in 0..FONT_SCALE {
+                    let px = x + col * FONT_SCALE + dx;
+                    let py = y + row as u32 * FONT_SCALE + dy;
+                    if px < img.width() && py < img.height() {
+                        img.put_pixel(px, py, TEXT);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the decimal digits of a number, most significant first.
+struct Digits {
+    value: u32,
+    divisor: u32,
+}
+
+impl Digits {
+    fn new(value: u32) -> Self {
+        let mut divisor = 1;
+        while value / divisor >= 10 {
+            divisor *= 10;
+        }
+        Digits { value, divisor }
+    }
+}
+
+impl Iterator for Digits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.divisor == 0 {
+            return None;
+        }
+        let digit = self.value / self.divisor % 10;
+        self.divisor /= 10;
+        Some(digit as usize)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::Image;
+
+/// A source of image data that can be sampled at arbitrary positions.
+///
+/// This can be implemented for sources that don't have a fixed set of pixels,
+/// such as a tile server or a vector renderer, so that they can be hashed
+/// without rendering the whole image first. See [`Sampled`].
+pub trait Sampler {
+    /// The maximum possible brightness for a sample.
+    const MAX_BRIGHTNESS: u32;
+
+    /// Returns the brightness at the given position, in the range
+    /// `0..=MAX_BRIGHTNESS`.
+    ///
+    /// The position is given as a fraction of the width and height of the
+    /// source, so `(0.0, 0.0)` is the top-left corner and `(1.0, 1.0)` is the
+    /// bottom-right corner.
+    fn sample(&self, x: f64, y: f64) -> u32;
+}
+
+/// An image created by sampling a [`Sampler`] on a regular grid.
+///
+/// The source is sampled once at the center of each cell of a grid with the
+/// given number of columns and rows, and only when the sample is needed while
+/// hashing. Choosing a number of columns and rows that is a multiple of the
+/// number of blocks along each side of the hash (4, 8, 12, 16, or 32) ensures
+/// that every block receives the same number of samples; 96 is a multiple of
+/// all of them.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash64, Sampled, Sampler};
+///
+/// struct Gradient;
+///
+/// impl Sampler for Gradient {
+///     const MAX_BRIGHTNESS: u32 = 1000;
+///
+///     fn sample(&self, x: f64, _y: f64) -> u32 {
+///         (x * 1000.0) as u32
+///     }
+/// }
+///
+/// let hash = blockhash64(&Sampled::new(Gradient, 48, 48));
+///
+/// assert_eq!(hash.to_string(), "0f0f0f0f0f0f0f0f");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Sampled<S> {
+    sampler: S,
+    columns: u32,
+    rows: u32,
+}
+
+impl<S: Sampler> Sampled<S> {
+    /// Creates an image by sampling the source on a grid with the given number
+    /// of columns and rows.
+    #[inline]
+    #[must_use]
+    pub fn new(sampler: S, columns: u32, rows: u32) -> Self {
+        Sampled {
+            sampler,
+            columns,
+            rows,
+        }
+    }
+
+    /// Returns the underlying sampler.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.sampler
+    }
+}
+
+impl<S: Sampler> Image for Sampled<S> {
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        S::MAX_BRIGHTNESS
+    }
+
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.columns, self.rows)
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let x = (f64::from(x) + 0.5) / f64::from(self.columns);
+        let y = (f64::from(y) + 0.5) / f64::from(self.rows);
+        self.sampler.sample(x, y)
+    }
+}
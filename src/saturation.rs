@@ -0,0 +1,73 @@
+use crate::hash::blockhash;
+use crate::{Blockhash, Blockhash256, Blockhash64, Image};
+
+/// Generates a 64-bit perceptual hash of the saturation of an image.
+///
+/// This is the same as [`blockhash64`](crate::blockhash64), except that the
+/// value of each pixel is its saturation rather than its brightness, as in the
+/// HSV color model: the difference between its largest and smallest color
+/// channels, relative to the largest. Gray pixels have no saturation, and
+/// fully saturated colors, such as pure red, have the maximum.
+///
+/// A grayscale copy of an image usually has the same or a very similar
+/// brightness hash to the original, but its saturation is zero everywhere, so
+/// the saturation hash can be used alongside the brightness hash to tell them
+/// apart. The saturation hash of a grayscale image has no bits set. Fully
+/// transparent pixels are treated as white, so have no saturation.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::blockhash64_saturation;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let gray = img.grayscale();
+///
+/// assert_eq!(blockhash64_saturation(&img).to_string(), "213f2f61e1e2c1f2");
+/// assert_eq!(blockhash64_saturation(&gray).to_string(), "0000000000000000");
+/// # }
+/// ```
+#[inline]
+#[must_use]
+pub fn blockhash64_saturation<I: Image>(img: &I) -> Blockhash64 {
+    Blockhash(blockhash::<_, 8, 64, 8>(&Saturation(img)))
+}
+
+/// Generates a 256-bit perceptual hash of the saturation of an image.
+///
+/// This is the same as [`blockhash64_saturation`], but uses a grid of 16 by
+/// 16 blocks.
+#[inline]
+#[must_use]
+pub fn blockhash256_saturation<I: Image>(img: &I) -> Blockhash256 {
+    Blockhash(blockhash::<_, 16, 256, 32>(&Saturation(img)))
+}
+
+/// An image whose brightness is the saturation of another image.
+struct Saturation<'a, I>(&'a I);
+
+impl<I: Image> Image for Saturation<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let [r, g, b, a] = self.0.channels(x, y);
+        let max_channel = self.0.max_channel();
+        let max = r.max(g).max(b).min(max_channel);
+        let min = r.min(g).min(b).min(max);
+        if a == 0 || max == 0 {
+            return 0;
+        }
+
+        let chroma = u64::from(max - min) * u64::from(max_channel);
+        ((chroma + u64::from(max) / 2) / u64::from(max)) as u32
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.0.max_channel()
+    }
+}
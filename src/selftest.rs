@@ -0,0 +1,131 @@
+use crate::{Blockhash144, Blockhash16, Blockhash256, Blockhash64, Digest, Image};
+use core::fmt::{self, Display, Formatter};
+
+/// An error returned by [`self_test`] when a hash doesn't match the expected
+/// value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SelfTestError {
+    width: u32,
+    height: u32,
+    bits: u32,
+}
+
+impl Display for SelfTestError {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "incorrect {}-bit hash for {}x{} test image",
+            self.bits, self.width, self.height,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SelfTestError {}
+
+/// Checks that hashes computed on this platform match the expected values.
+///
+/// The hashing algorithm only uses integer arithmetic, so every platform
+/// should produce exactly the same hashes regardless of its architecture or
+/// endianness. This function hashes a set of generated images that exercise
+/// every accumulation strategy with every hash size, and compares the results
+/// against hashes that are known to be correct.
+///
+/// This can be called at startup by applications that share hashes between
+/// machines with different architectures, as a safeguard against
+/// miscompilation.
+///
+/// Only the integer arithmetic is checked. Options that use floating-point
+/// arithmetic, such as [`BlockhashOptions::gamma`](crate::BlockhashOptions::gamma),
+/// can still produce different hashes on different platforms.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(blockhash::self_test(), Ok(()));
+/// ```
+pub fn self_test() -> Result<(), SelfTestError> {
+    for &(width, height, expected) in EXPECTED {
+        let img = Pattern { width, height };
+
+        check::<Blockhash16>(&img, expected[0])?;
+        check::<Blockhash64>(&img, expected[1])?;
+        check::<Blockhash144>(&img, expected[2])?;
+        check::<Blockhash256>(&img, expected[3])?;
+    }
+
+    Ok(())
+}
+
+fn check<D: Digest>(img: &Pattern, expected: &str) -> Result<(), SelfTestError> {
+    if expected.parse() == Ok(D::from_image(img)) {
+        Ok(())
+    } else {
+        Err(SelfTestError {
+            width: img.width,
+            height: img.height,
+            bits: D::BITS,
+        })
+    }
+}
+
+/// The dimensions of each test image, and the expected 16-, 64-, 144-, and
+/// 256-bit hashes.
+///
+/// The dimensions are chosen so that every accumulation strategy is used.
+const EXPECTED: &[(u32, u32, [&str; 4])] = &[
+    (
+        96,
+        48,
+        [
+            "3666",
+            "0f1e1e1e3c3c3c78",
+            "07e07e07e0fc0fc0fc1f81f81f83f03f03f0",
+            "01fe01fe01fe03fc03fc03fc07f807f807f80ff00ff00ff01fe01fe01fe03fc0",
+        ],
+    ),
+    (
+        37,
+        23,
+        [
+            "3666",
+            "1e1e1e1e3c3c3c78",
+            "07e07e07e0fc0fc0fc0fc1f81f81f83f03f0",
+            "01fe01fc03fc03fe01fc03fc03fc07fc07f807f80ff00ff00ff01fe01fe01fe0",
+        ],
+    ),
+    (
+        5,
+        3,
+        [
+            "3336",
+            "0f0f0f0f0f1e3c3c",
+            "01f01f01f01f07f07f07f07f07c0fc0fc0fc",
+            "007f007f007f007f007f007f01ff01ff01ff01ff01ff03f803f803f803f803f8",
+        ],
+    ),
+];
+
+/// A generated test image.
+struct Pattern {
+    width: u32,
+    height: u32,
+}
+
+impl Image for Pattern {
+    fn max_brightness(&self) -> u32 {
+        765
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        // A smooth gradient with some noise, so that block values vary but
+        // occasionally tie
+        let noise = x.wrapping_mul(2_654_435_761) ^ y.wrapping_mul(40_503);
+        (x * 765 / self.width + y * 255 / self.height + noise % 64) % 766
+    }
+}
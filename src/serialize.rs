@@ -0,0 +1,63 @@
+use crate::Blockhash;
+use core::convert::TryFrom;
+use core::fmt::{self, Formatter};
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+// Human-readable formats, such as JSON, use the same hexadecimal strings as
+// `Display` and `FromStr`. Binary formats use the bytes of the digest.
+impl<const BYTES: usize> Serialize for Blockhash<BYTES> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de, const BYTES: usize> Deserialize<'de> for Blockhash<BYTES> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(DigestVisitor)
+        } else {
+            deserializer.deserialize_bytes(DigestVisitor)
+        }
+    }
+}
+
+/// Deserializes a digest from either a hexadecimal string or its bytes.
+struct DigestVisitor<const BYTES: usize>;
+
+impl<'de, const BYTES: usize> Visitor<'de> for DigestVisitor<BYTES> {
+    type Value = Blockhash<BYTES>;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a {}-bit blockhash", BYTES * 8)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Blockhash<BYTES>, E> {
+        s.parse()
+            .map_err(|_| E::invalid_value(Unexpected::Str(s), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Blockhash<BYTES>, E> {
+        <[u8; BYTES]>::try_from(bytes)
+            .map(Blockhash)
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+
+    // Some binary formats encode bytes as a sequence
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Blockhash<BYTES>, A::Error> {
+        let mut bytes = [0; BYTES];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(BYTES + 1, &self));
+        }
+        Ok(Blockhash(bytes))
+    }
+}
@@ -0,0 +1,93 @@
+use crate::compare::bit;
+use crate::{Digest, Image};
+use core::fmt::{self, Display, Formatter};
+
+/// The error returned by [`check_similar`] when an image doesn't look like
+/// the expected hash.
+///
+/// The message lists both hashes and their distance, followed by the grid of
+/// blocks, where each block whose bit differs is marked with `X` and each
+/// other block with `.`, to show which part of the image changed.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct NotSimilar<D> {
+    /// The expected hash.
+    pub expected: D,
+    /// The hash of the image.
+    pub actual: D,
+    /// The distance between the hashes.
+    pub distance: u32,
+    /// The largest distance that was allowed.
+    pub max_distance: u32,
+}
+
+impl<D: Digest> Display for NotSimilar<D> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "expected: {}", self.expected)?;
+        writeln!(f, "  actual: {}", self.actual)?;
+        write!(
+            f,
+            "distance {} is greater than {}",
+            self.distance, self.max_distance,
+        )?;
+
+        for y in 0..D::ROWS {
+            f.write_str("\n")?;
+            for x in 0..D::COLUMNS {
+                let differs = bit(&self.expected, x, y) != bit(&self.actual, x, y);
+                f.write_str(if differs { "X" } else { "." })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: Digest> std::error::Error for NotSimilar<D> {}
+
+/// Checks that an image is within `max_distance` of an expected hash.
+///
+/// This is intended for visual regression tests, such as comparing
+/// screenshots of a user interface against hashes recorded from a known good
+/// build. Unlike comparing pixels exactly, small differences in antialiasing,
+/// font hinting, or image compression between machines don't cause failures,
+/// while moved or missing elements still do. The [`assert_similar!`] macro
+/// panics with the message of the error instead.
+///
+/// [`assert_similar!`]: crate::assert_similar
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{check_similar, Blockhash64};
+///
+/// let expected: Blockhash64 = "c7c48f8989c77e0c".parse().unwrap();
+/// let screenshot = image::open("images/example.png").unwrap();
+///
+/// assert_eq!(check_similar(&expected, &screenshot.blur(0.5), 4), Ok(()));
+///
+/// let err = check_similar(&expected, &screenshot.fliph(), 4).unwrap_err();
+/// assert_eq!(err.distance, expected.distance(&err.actual));
+/// assert!(err.to_string().contains("X"));
+/// # }
+/// ```
+pub fn check_similar<D: Digest, I: Image>(
+    expected: &D,
+    img: &I,
+    max_distance: u32,
+) -> Result<(), NotSimilar<D>> {
+    let actual = D::from_image(img);
+    let distance = expected.distance(&actual);
+
+    if distance <= max_distance {
+        Ok(())
+    } else {
+        Err(NotSimilar {
+            expected: *expected,
+            actual,
+            distance,
+            max_distance,
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{summarize_bursts, Digest};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A distinct slide found by [`detect_slides`].
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Slide<D> {
+    /// The hash of the frame chosen to represent the slide.
+    pub hash: D,
+    /// The position of the frame chosen to represent the slide.
+    pub frame: usize,
+    /// The times at which the slide is shown, in order.
+    ///
+    /// Each range starts at the timestamp of the first frame showing the
+    /// slide, and ends at the timestamp of the next frame, or of the last
+    /// frame if the slide is shown until the end.
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl<D> Slide<D> {
+    /// Returns the total time the slide is shown for.
+    #[inline]
+    #[must_use]
+    pub fn duration(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+}
+
+/// Finds the distinct slides in a recording of a presentation, such as a
+/// lecture video, and the times at which each is shown.
+///
+/// Each frame is a timestamp and a hash, and the frames must be sorted by
+/// timestamp. The frames are first divided into runs, as with
+/// [`summarize_bursts`], where each frame is within `max_distance` of the
+/// previous one, so the small changes of a slide whose points appear one at a
+/// time don't split it. Runs shorter than `min_duration` are discarded as
+/// transitions, such as animations or cuts to the speaker. The remaining runs
+/// are slides, and runs whose representative frames are within
+/// `max_distance` of each other are the same slide, shown again when the
+/// speaker goes back to it.
+///
+/// The slides are returned in the order they are first shown. Timestamps can
+/// be in any unit, as long as `min_duration` uses the same unit.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{detect_slides, Blockhash16};
+///
+/// let frames = [
+///     (0, Blockhash16::from(0x0f0f)),
+///     (10, Blockhash16::from(0x0f0f)),
+///     (20, Blockhash16::from(0x0f0e)),
+///     (30, Blockhash16::from(0x6666)), // a transition
+///     (31, Blockhash16::from(0xf0f0)),
+///     (50, Blockhash16::from(0xf0f0)),
+///     (60, Blockhash16::from(0x0f0f)),
+///     (80, Blockhash16::from(0x0f0f)),
+/// ];
+///
+/// let slides = detect_slides(&frames, 2, 5);
+/// assert_eq!(slides.len(), 2);
+/// assert_eq!(slides[0].ranges, [0..30, 60..80]);
+/// assert_eq!(slides[1].ranges.len(), 1);
+/// assert_eq!(slides[1].ranges[0], 31..60);
+/// assert_eq!(slides[1].duration(), 29);
+/// ```
+#[must_use]
+pub fn detect_slides<D: Digest>(
+    frames: &[(u64, D)],
+    max_distance: u32,
+    min_duration: u64,
+) -> Vec<Slide<D>> {
+    let mut slides: Vec<Slide<D>> = Vec::new();
+
+    for run in summarize_bursts(frames, max_distance, u64::MAX) {
+        let start = frames[run.start].0;
+        let end = frames.get(run.end).unwrap_or(&frames[run.end - 1]).0;
+        if end - start < min_duration {
+            continue;
+        }
+
+        let hash = frames[run.representative].1;
+        match slides
+            .iter_mut()
+            .find(|slide| slide.hash.distance(&hash) <= max_distance)
+        {
+            Some(slide) => slide.ranges.push(start..end),
+            None => slides.push(Slide {
+                hash,
+                frame: run.representative,
+                ranges: alloc::vec![start..end],
+            }),
+        }
+    }
+
+    slides
+}
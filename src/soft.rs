@@ -0,0 +1,106 @@
+use crate::hash::{convert_to_bits, get_values, Accumulation, Threshold};
+use crate::{Blockhash, Blockhash256, Image};
+
+/// Generates a 256-bit perceptual hash of an image, along with a soft hash
+/// computed from the same block values.
+///
+/// This reads the image only once, so it is faster than generating the hashes
+/// separately. The binary hash is the same as the one returned by
+/// [`blockhash256`](crate::blockhash256).
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash256, soft_blockhash256};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let (hash, soft) = soft_blockhash256(&img);
+///
+/// assert_eq!(hash, blockhash256(&img));
+/// assert_eq!(soft.distance(&soft), 0);
+/// # }
+/// ```
+#[must_use]
+pub fn soft_blockhash256<I: Image>(img: &I) -> (Blockhash256, SoftBlockhash256) {
+    let (width, height) = img.dimensions();
+    let accumulation = Accumulation::select(width, height, 16);
+    let values = get_values::<I, 16, 256>(img, accumulation);
+
+    let max_value = img.max_brightness();
+    let hash = convert_to_bits(width, height, &values, max_value, 4, Threshold::Median);
+
+    // The values are scaled so that a white block has this value
+    let full = (u64::from(max_value) * u64::from(width) * u64::from(height)).max(1);
+
+    let mut soft = [0; 256];
+    for (level, &value) in soft.iter_mut().zip(&values) {
+        *level = (value * 255 / full).min(255) as u8;
+    }
+
+    (Blockhash(hash), SoftBlockhash256(soft))
+}
+
+/// A soft perceptual hash, storing the brightness of each of 256 blocks.
+///
+/// Where a binary hash records only whether each block is brighter than the
+/// median of its band, a soft hash records the average brightness of each
+/// block as a value from 0 (black) to 255 (white). This makes it useful for
+/// ranking close matches, which often have the same or similar binary hashes.
+/// The blocks are stored in row-major order, in the same grid as a
+/// [`Blockhash256`].
+///
+/// Since the values are absolute, a soft hash is more sensitive than a binary
+/// hash to changes in brightness or contrast.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct SoftBlockhash256([u8; 256]);
+
+impl SoftBlockhash256 {
+    /// Returns the L1 distance between two hashes, which is the sum of the
+    /// differences in brightness between each pair of blocks.
+    ///
+    /// The distance is in the range `0..=65280`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::SoftBlockhash256;
+    ///
+    /// let mut levels = [128; 256];
+    /// let a = SoftBlockhash256::from(levels);
+    /// levels[0] = 120;
+    /// levels[1] = 130;
+    /// let b = SoftBlockhash256::from(levels);
+    ///
+    /// assert_eq!(a.distance(&b), 10);
+    /// ```
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> u32 {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(&a, &b)| u32::from(a.abs_diff(b)))
+            .sum()
+    }
+
+    /// Returns the brightness of each block.
+    #[inline]
+    #[must_use]
+    pub fn levels(&self) -> &[u8; 256] {
+        &self.0
+    }
+}
+
+impl From<[u8; 256]> for SoftBlockhash256 {
+    #[inline]
+    fn from(levels: [u8; 256]) -> Self {
+        SoftBlockhash256(levels)
+    }
+}
+
+impl From<SoftBlockhash256> for [u8; 256] {
+    #[inline]
+    fn from(hash: SoftBlockhash256) -> Self {
+        hash.0
+    }
+}
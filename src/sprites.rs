@@ -0,0 +1,44 @@
+use crate::{BlockRect, Digest, Image, Region};
+use alloc::vec::Vec;
+
+/// Hashes each sprite of a sprite sheet.
+///
+/// Each frame is a rectangle of the sheet, and is hashed as a [`Region`], so
+/// the hashes are the same as those of copies of the frames, but no pixels
+/// are copied. The hashes are returned in the same order as the frames.
+///
+/// # Panics
+///
+/// Panics if a frame extends outside the bounds of the sheet.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, hash_sprites, BlockRect, Blockhash64};
+///
+/// let sheet = image::open("images/example.png").unwrap();
+/// let frames = [
+///     BlockRect { x: 0, y: 0, width: 32, height: 32 },
+///     BlockRect { x: 32, y: 0, width: 32, height: 32 },
+/// ];
+///
+/// let hashes: Vec<Blockhash64> = hash_sprites(&sheet, &frames);
+/// assert_eq!(hashes[1], blockhash64(&sheet.crop_imm(32, 0, 32, 32)));
+/// # }
+/// ```
+#[must_use]
+pub fn hash_sprites<D: Digest, I: Image>(sheet: &I, frames: &[BlockRect]) -> Vec<D> {
+    frames
+        .iter()
+        .map(|frame| {
+            D::from_image(&Region::new(
+                sheet,
+                frame.x,
+                frame.y,
+                frame.width,
+                frame.height,
+            ))
+        })
+        .collect()
+}
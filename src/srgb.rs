@@ -0,0 +1,80 @@
+/// The linear intensity of each 8-bit sRGB value, scaled to `0..=u16::MAX`.
+#[rustfmt::skip]
+static SRGB_TO_LINEAR: [u16; 256] = [
+    0, 20, 40, 60, 80, 99, 119, 139, 159, 179, 199, 219,
+    241, 264, 288, 313, 340, 367, 396, 427, 458, 491, 526, 562,
+    599, 637, 677, 718, 761, 805, 851, 898, 947, 997, 1048, 1101,
+    1156, 1212, 1270, 1330, 1391, 1453, 1517, 1583, 1651, 1720, 1790, 1863,
+    1937, 2013, 2090, 2170, 2250, 2333, 2418, 2504, 2592, 2681, 2773, 2866,
+    2961, 3058, 3157, 3258, 3360, 3464, 3570, 3678, 3788, 3900, 4014, 4129,
+    4247, 4366, 4488, 4611, 4736, 4864, 4993, 5124, 5257, 5392, 5530, 5669,
+    5810, 5953, 6099, 6246, 6395, 6547, 6700, 6856, 7014, 7174, 7335, 7500,
+    7666, 7834, 8004, 8177, 8352, 8528, 8708, 8889, 9072, 9258, 9445, 9635,
+    9828, 10022, 10219, 10417, 10619, 10822, 11028, 11235, 11446, 11658, 11873, 12090,
+    12309, 12530, 12754, 12980, 13209, 13440, 13673, 13909, 14146, 14387, 14629, 14874,
+    15122, 15371, 15623, 15878, 16135, 16394, 16656, 16920, 17187, 17456, 17727, 18001,
+    18277, 18556, 18837, 19121, 19407, 19696, 19987, 20281, 20577, 20876, 21177, 21481,
+    21787, 22096, 22407, 22721, 23038, 23357, 23678, 24002, 24329, 24658, 24990, 25325,
+    25662, 26001, 26344, 26688, 27036, 27386, 27739, 28094, 28452, 28813, 29176, 29542,
+    29911, 30282, 30656, 31033, 31412, 31794, 32179, 32567, 32957, 33350, 33745, 34143,
+    34544, 34948, 35355, 35764, 36176, 36591, 37008, 37429, 37852, 38278, 38706, 39138,
+    39572, 40009, 40449, 40891, 41337, 41785, 42236, 42690, 43147, 43606, 44069, 44534,
+    45002, 45473, 45947, 46423, 46903, 47385, 47871, 48359, 48850, 49344, 49841, 50341,
+    50844, 51349, 51858, 52369, 52884, 53401, 53921, 54445, 54971, 55500, 56032, 56567,
+    57105, 57646, 58190, 58737, 59287, 59840, 60396, 60955, 61517, 62082, 62650, 63221,
+    63795, 64372, 64952, 65535,
+];
+
+/// Converts a channel value in the range `0..=max` from sRGB to linear light,
+/// in the range `0..=u16::MAX`.
+///
+/// Values of 8-bit channels are looked up directly, and values of other
+/// channels are interpolated between the nearest 8-bit values, which is
+/// accurate to within about 0.01% of the full range.
+pub(crate) fn srgb_to_linear(val: u32, max: u32) -> u32 {
+    to_linear(&SRGB_TO_LINEAR, val, max)
+}
+
+/// Converts a channel value in the range `0..=max` to linear light, in the
+/// range `0..=u16::MAX`, using a table of the linear intensity of each 8-bit
+/// value.
+pub(crate) fn to_linear(table: &[u16; 256], val: u32, max: u32) -> u32 {
+    if max == u32::from(u8::MAX) {
+        return table[val as usize].into();
+    }
+    if max == 0 {
+        return 0;
+    }
+
+    // The position of the value between 8-bit values, in 1/256ths
+    let pos = u64::from(val.min(max)) * 255 * 256 / u64::from(max);
+    let (idx, frac) = ((pos / 256) as usize, pos % 256);
+    if idx == 255 {
+        return u16::MAX.into();
+    }
+
+    let low = u64::from(table[idx]);
+    let high = u64::from(table[idx + 1]);
+    ((low * (256 - frac) + high * frac + 128) / 256) as u32
+}
+
+/// Converts an intensity of linear light in the range `0..=u16::MAX` to an
+/// sRGB channel value in the range `0..=max`.
+///
+/// This is the inverse of [`srgb_to_linear`], interpolating between the
+/// nearest 8-bit values in the same way.
+#[cfg(feature = "icc")]
+pub(crate) fn linear_to_srgb(val: u32, max: u32) -> u32 {
+    let val = val.min(u16::MAX.into()) as u16;
+    let idx = SRGB_TO_LINEAR.partition_point(|&linear| linear <= val) - 1;
+    if idx == 255 {
+        return max;
+    }
+
+    // The position of the value between 8-bit values, in 1/256ths
+    let low = u64::from(SRGB_TO_LINEAR[idx]);
+    let high = u64::from(SRGB_TO_LINEAR[idx + 1]);
+    let frac = (u64::from(val) - low) * 256 / (high - low);
+    let pos = idx as u64 * 256 + frac;
+    ((pos * u64::from(max) + 255 * 128) / (255 * 256)) as u32
+}
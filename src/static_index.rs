@@ -0,0 +1,322 @@
+use crate::popcount::count_ones;
+use crate::{Digest, HashIndex};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt::{self, Display, Formatter};
+use core::marker::PhantomData;
+
+/// The bytes at the start of every compiled index.
+const MAGIC: [u8; 4] = *b"BHIX";
+
+/// The version of the compiled index format.
+const VERSION: u8 = 2;
+
+/// The length of the header, including the magic bytes, version, grid size,
+/// and the number of nodes and edges.
+const HEADER_LEN: usize = 15;
+
+/// The length of each edge, including the distance and the child position.
+const EDGE_LEN: usize = 6;
+
+/// An error returned by [`StaticIndex::from_bytes`] when a compiled index is
+/// invalid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StaticIndexError {
+    /// The data doesn't start with the expected header.
+    InvalidHeader,
+    /// The index was compiled by an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The index was compiled for a different size of hash. Contains the
+    /// number of columns and rows in the grid of the compiled hashes.
+    WrongSize(u8, u8),
+    /// The length of the data doesn't match its header.
+    InvalidLength,
+    /// The tree structure of the index is invalid.
+    Corrupt,
+}
+
+impl Display for StaticIndexError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            StaticIndexError::InvalidHeader => f.write_str("invalid index header"),
+            StaticIndexError::UnsupportedVersion(version) => {
+                write!(f, "unsupported index version {}", version)
+            }
+            StaticIndexError::WrongSize(columns, rows) => {
+                write!(
+                    f,
+                    "index contains hashes of a different size ({}x{})",
+                    columns, rows
+                )
+            }
+            StaticIndexError::InvalidLength => f.write_str("index has an invalid length"),
+            StaticIndexError::Corrupt => f.write_str("index is corrupt"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StaticIndexError {}
+
+/// A read-only index of hashes, stored in a compact binary format.
+///
+/// An index is compiled once with [`StaticIndex::compile`], and the resulting
+/// bytes can be embedded in a program with [`include_bytes!`], or shipped to
+/// clients alongside it, so that images can be checked against a known set of
+/// hashes locally. Loading the index with [`StaticIndex::from_bytes`] checks
+/// its structure, but doesn't copy or decode the hashes, so it is fast even
+/// for large indexes.
+///
+/// Like [`HashIndex`], the index is a BK-tree, and each hash is identified by
+/// its position in the order it was given to `compile`.
+///
+/// # Format
+///
+/// All integers are big-endian. The data starts with the bytes `BHIX`, a
+/// format version of 2, the number of columns and rows in the grid of the
+/// hashes, and the number of nodes and edges in the tree as `u32`s. Each node is then
+/// stored in breadth-first order as the bytes of its hash, its ID as a `u32`,
+/// and the position of its first edge as a `u32`. Finally, each edge is
+/// stored as the distance to the child as a `u16`, and the position of the
+/// child as a `u32`.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{Blockhash64, StaticIndex};
+///
+/// let bytes = StaticIndex::compile([
+///     Blockhash64::from(0xffff_0000_ffff_0000),
+///     Blockhash64::from(0x0f0f_0f0f_0f0f_0f0f),
+///     Blockhash64::from(0x0f0f_0f0f_0f0f_0f0e),
+/// ]);
+///
+/// // Usually loaded with `include_bytes!` or from a file
+/// let index = StaticIndex::<Blockhash64>::from_bytes(&bytes).unwrap();
+///
+/// let query = Blockhash64::from(0x0f0f_0f0f_0f0f_0f00);
+/// assert_eq!(index.find(&query, 4), [(2, 3), (1, 4)]);
+/// assert_eq!(index.nearest(&query, 10), Some((2, 3)));
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct StaticIndex<'a, D> {
+    nodes: &'a [u8],
+    edges: &'a [u8],
+    marker: PhantomData<D>,
+}
+
+impl<'a, D: Digest> StaticIndex<'a, D> {
+    /// Compiles a set of hashes into an index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` hashes.
+    #[must_use]
+    pub fn compile<T: IntoIterator<Item = D>>(hashes: T) -> Vec<u8> {
+        let index: HashIndex<D> = hashes.into_iter().collect();
+        let count: u32 = index.len().try_into().expect("too many hashes");
+        let edges = count.saturating_sub(1);
+
+        let mut bytes = Vec::with_capacity(
+            HEADER_LEN + count as usize * node_len::<D>() + edges as usize * EDGE_LEN,
+        );
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(D::COLUMNS as u8);
+        bytes.push(D::ROWS as u8);
+        bytes.extend_from_slice(&count.to_be_bytes());
+        bytes.extend_from_slice(&edges.to_be_bytes());
+
+        // Nodes are numbered in the order they are added to the queue, so the
+        // children of each node are numbered consecutively.
+        let mut queue = VecDeque::new();
+        if count > 0 {
+            queue.push_back(0);
+        }
+        let mut queued = 1_u32;
+        let mut edge_bytes = Vec::with_capacity(edges as usize * EDGE_LEN);
+
+        while let Some(old) = queue.pop_front() {
+            let (hash, children) = index.node(old);
+            let first_edge = (edge_bytes.len() / EDGE_LEN) as u32;
+
+            bytes.extend_from_slice(hash.as_bytes());
+            bytes.extend_from_slice(&(old as u32).to_be_bytes());
+            bytes.extend_from_slice(&first_edge.to_be_bytes());
+
+            for &(dist, child) in children {
+                edge_bytes.extend_from_slice(&(dist as u16).to_be_bytes());
+                edge_bytes.extend_from_slice(&queued.to_be_bytes());
+                queue.push_back(child);
+                queued += 1;
+            }
+        }
+
+        bytes.extend_from_slice(&edge_bytes);
+        bytes
+    }
+
+    /// Loads an index compiled by [`StaticIndex::compile`].
+    ///
+    /// The hashes aren't copied, so the index borrows the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data doesn't have a valid header, contains
+    /// hashes of a different size, or doesn't contain a valid tree.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, StaticIndexError> {
+        if bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+            return Err(StaticIndexError::InvalidHeader);
+        }
+        if bytes[4] != VERSION {
+            return Err(StaticIndexError::UnsupportedVersion(bytes[4]));
+        }
+        if (u32::from(bytes[5]), u32::from(bytes[6])) != (D::COLUMNS, D::ROWS) {
+            return Err(StaticIndexError::WrongSize(bytes[5], bytes[6]));
+        }
+
+        let count = read_u32(bytes, 7) as usize;
+        let edge_count = read_u32(bytes, 11) as usize;
+        let nodes_len = count.checked_mul(node_len::<D>());
+        let edges_len = edge_count.checked_mul(EDGE_LEN);
+        let len = nodes_len
+            .zip(edges_len)
+            .and_then(|(nodes, edges)| nodes.checked_add(edges)?.checked_add(HEADER_LEN));
+        if len != Some(bytes.len()) {
+            return Err(StaticIndexError::InvalidLength);
+        }
+
+        let (nodes, edges) = bytes[HEADER_LEN..].split_at(count * node_len::<D>());
+        let index = StaticIndex {
+            nodes,
+            edges,
+            marker: PhantomData,
+        };
+
+        // Every child must come after its parent, so that searches always
+        // terminate, and every range of edges must be in bounds.
+        for node in 0..count {
+            let (start, end) = index.edge_range(node);
+            if start > end || end > edge_count {
+                return Err(StaticIndexError::Corrupt);
+            }
+            for edge in start..end {
+                let child = index.edge(edge).1;
+                if child <= node || child >= count {
+                    return Err(StaticIndexError::Corrupt);
+                }
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Returns the number of hashes in the index.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len() / node_len::<D>()
+    }
+
+    /// Returns whether the index is empty.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Finds every hash within the given distance of the query.
+    ///
+    /// Returns the ID of each hash and its distance from the query, sorted by
+    /// distance and then by ID.
+    #[must_use]
+    pub fn find(&self, query: &D, max_distance: u32) -> Vec<(usize, u32)> {
+        let mut results = Vec::new();
+        self.search(query, max_distance, |id, dist| results.push((id, dist)));
+        results.sort_unstable_by_key(|&(id, dist)| (dist, id));
+        results
+    }
+
+    /// Finds the hash closest to the query, if it is within the given distance.
+    ///
+    /// If several hashes are equally close, the one with the lowest ID is
+    /// returned.
+    #[must_use]
+    pub fn nearest(&self, query: &D, max_distance: u32) -> Option<(usize, u32)> {
+        let mut best: Option<(usize, u32)> = None;
+        self.search(query, max_distance, |id, dist| {
+            if best.map_or(true, |best| (dist, id) < (best.1, best.0)) {
+                best = Some((id, dist));
+            }
+        });
+        best
+    }
+
+    /// Returns the range of edges for the children of a node.
+    fn edge_range(&self, node: usize) -> (usize, usize) {
+        let start = self.first_edge(node);
+        let end = if node + 1 < self.len() {
+            self.first_edge(node + 1)
+        } else {
+            self.edges.len() / EDGE_LEN
+        };
+        (start, end)
+    }
+
+    fn first_edge(&self, node: usize) -> usize {
+        read_u32(self.nodes, (node + 1) * node_len::<D>() - 4) as usize
+    }
+
+    /// Returns the distance to and position of the child of an edge.
+    fn edge(&self, edge: usize) -> (u32, usize) {
+        let offset = edge * EDGE_LEN;
+        let dist = u16::from_be_bytes([self.edges[offset], self.edges[offset + 1]]);
+        (u32::from(dist), read_u32(self.edges, offset + 2) as usize)
+    }
+
+    fn search<F: FnMut(usize, u32)>(&self, query: &D, max_distance: u32, mut f: F) {
+        if self.is_empty() {
+            return;
+        }
+
+        let query = query.as_bytes();
+        let hash_len = query.len();
+        let mut stack = alloc::vec![0];
+
+        while let Some(node) = stack.pop() {
+            let offset = node * node_len::<D>();
+            let hash = &self.nodes[offset..(offset + hash_len)];
+            let dist = hash
+                .iter()
+                .zip(query)
+                .map(|(a, b)| count_ones(a ^ b))
+                .sum::<u32>();
+            if dist <= max_distance {
+                f(read_u32(self.nodes, offset + hash_len) as usize, dist);
+            }
+
+            let range = dist.saturating_sub(max_distance)..=dist.saturating_add(max_distance);
+            let (start, end) = self.edge_range(node);
+            stack.extend(
+                (start..end)
+                    .map(|edge| self.edge(edge))
+                    .filter(|(d, _)| range.contains(d))
+                    .map(|(_, child)| child),
+            );
+        }
+    }
+}
+
+/// Returns the length of each node, including the hash, its ID, and the
+/// position of its first edge.
+fn node_len<D: Digest>() -> usize {
+    // The last byte of the hash is padded if the number of bits isn't a
+    // multiple of 8
+    (D::BITS as usize + 7) / 8 + 8
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..(offset + 4)].try_into().unwrap())
+}
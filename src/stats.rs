@@ -0,0 +1,114 @@
+use crate::random::SplitMix64;
+use crate::Digest;
+use alloc::vec::Vec;
+
+/// A histogram of the pairwise distances between hashes.
+///
+/// See [`distance_histogram`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistanceHistogram {
+    counts: Vec<u64>,
+}
+
+impl DistanceHistogram {
+    /// Returns the number of pairs for each distance, indexed by distance.
+    ///
+    /// The returned slice has one entry for every possible distance, from zero
+    /// up to and including the number of bits in the hash.
+    #[inline]
+    #[must_use]
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the total number of pairs in the histogram.
+    #[inline]
+    #[must_use]
+    pub fn pairs(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the smallest distance such that at least the given fraction of
+    /// pairs are at or below that distance.
+    ///
+    /// `q` is clamped to the range `0.0..=1.0`. Returns `None` if the histogram
+    /// is empty.
+    #[must_use]
+    pub fn quantile(&self, q: f64) -> Option<u32> {
+        let pairs = self.pairs();
+        if pairs == 0 {
+            return None;
+        }
+
+        // Rounded up without `f64::ceil`, which requires `std`
+        let exact = q.clamp(0.0, 1.0) * pairs as f64;
+        let mut target = exact as u64;
+        if (target as f64) < exact {
+            target += 1;
+        }
+        let mut seen = 0;
+
+        for (dist, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target.max(1) {
+                return Some(dist as u32);
+            }
+        }
+
+        None
+    }
+}
+
+/// Computes the distribution of pairwise distances for a random sample of
+/// hashes.
+///
+/// Up to `sample_size` hashes are chosen uniformly at random from `hashes`
+/// using reservoir sampling, so the corpus only needs to be iterated once and
+/// never needs to be held in memory. The distance between every pair of sampled
+/// hashes is then added to the histogram.
+///
+/// The sample is determined by `seed`, so the same corpus and seed will always
+/// produce the same histogram.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{distance_histogram, Blockhash16};
+///
+/// let hashes = [0x0000, 0x0001, 0x0003, 0xffff].map(Blockhash16::from);
+/// let hist = distance_histogram(hashes, 10, 0);
+///
+/// assert_eq!(hist.pairs(), 6);
+/// assert_eq!(hist.counts()[1], 2);
+/// assert_eq!(hist.quantile(0.5), Some(2));
+/// ```
+#[must_use]
+pub fn distance_histogram<D, I>(hashes: I, sample_size: usize, seed: u64) -> DistanceHistogram
+where
+    D: Digest,
+    I: IntoIterator<Item = D>,
+{
+    let mut rng = SplitMix64(seed);
+    let mut sample = Vec::with_capacity(sample_size.min(1024));
+
+    for (i, hash) in hashes.into_iter().enumerate() {
+        if i < sample_size {
+            sample.push(hash);
+        } else {
+            let j = rng.below(i as u64 + 1) as usize;
+            if j < sample_size {
+                sample[j] = hash;
+            }
+        }
+    }
+
+    let mut counts = alloc::vec![0_u64; D::BITS as usize + 1];
+
+    for (i, a) in sample.iter().enumerate() {
+        for b in &sample[(i + 1)..] {
+            counts[a.distance(b) as usize] += 1;
+        }
+    }
+
+    DistanceHistogram { counts }
+}
@@ -0,0 +1,169 @@
+use crate::Image;
+
+/// The maximum brightness of a [`Stretched`] image.
+const MAX_VALUE: u32 = u16::MAX as u32;
+
+/// A single-channel image of floating-point values, such as an astronomical
+/// exposure, stretched to brightness with a percentile clip and an asinh
+/// curve.
+///
+/// Most of an astronomical image is close to the sky background, with a few
+/// stars or galaxies that are many times brighter, so hashing the values
+/// linearly leaves almost every block black. Re-processing also often changes
+/// the scale and offset of the values. This adapter first clips the values to
+/// a range of percentiles, set by [`percentiles`](Self::percentiles), and
+/// then applies the asinh stretch used for survey images, set by
+/// [`softening`](Self::softening), which brings out faint structure while
+/// keeping bright objects distinct.
+///
+/// This crate doesn't parse FITS files. The data of an image HDU can be read
+/// with a FITS crate, with `BSCALE` and `BZERO` applied, and passed here.
+/// Values that aren't finite, such as blank pixels stored as NaN, are
+/// treated as black.
+///
+/// Pixels are stored in row-major order, with no padding between rows.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash16, Stretched};
+///
+/// let data = [0.0_f32, 0.1, 250.0, 4000.0, 0.2, f32::NAN, 180.0, 9000.0];
+/// let img = Stretched::new(&data, 4, 2);
+///
+/// assert_eq!(blockhash16(&img).to_string(), "3333");
+///
+/// // Rescaled data has the same hash
+/// let rescaled: Vec<f32> = data.iter().map(|val| val * 2.5 + 100.0).collect();
+/// assert_eq!(blockhash16(&Stretched::new(&rescaled, 4, 2)).to_string(), "3333");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stretched<'a> {
+    data: &'a [f32],
+    width: u32,
+    height: u32,
+    low: f32,
+    high: f32,
+    softening: f32,
+    lower: f32,
+    upper: f32,
+}
+
+impl<'a> Stretched<'a> {
+    /// Creates an image from a buffer of values.
+    ///
+    /// By default, the values are clipped to the range from the 0.25th to the
+    /// 99.75th percentile, and the softening is 0.1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the buffer doesn't match the dimensions of the
+    /// image.
+    #[must_use]
+    pub fn new(data: &'a [f32], width: u32, height: u32) -> Self {
+        assert_eq!(data.len() as u64, u64::from(width) * u64::from(height));
+
+        Stretched {
+            data,
+            width,
+            height,
+            low: 0.25,
+            high: 99.75,
+            softening: 0.1,
+            lower: 0.0,
+            upper: 0.0,
+        }
+        .update()
+    }
+
+    /// Sets the percentiles of the values that are mapped to black and
+    /// white.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either percentile is outside the range `0.0..=100.0`, or if
+    /// `low` is greater than `high`.
+    #[must_use]
+    pub fn percentiles(mut self, low: f32, high: f32) -> Self {
+        assert!(
+            (0.0..=100.0).contains(&low) && (0.0..=100.0).contains(&high) && low <= high,
+            "invalid percentiles",
+        );
+
+        self.low = low;
+        self.high = high;
+        self.update()
+    }
+
+    /// Sets the softening of the asinh stretch.
+    ///
+    /// The stretch is close to linear for values that are small compared to
+    /// the softening, and logarithmic for larger values, where values are
+    /// measured as a fraction of the clipped range. Smaller values bring out
+    /// fainter structure. A softening of 0 disables the stretch, so the
+    /// clipped values are mapped linearly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the softening is negative or isn't finite.
+    #[must_use]
+    pub fn softening(mut self, softening: f32) -> Self {
+        assert!(
+            softening >= 0.0 && softening.is_finite(),
+            "invalid softening",
+        );
+
+        self.softening = softening;
+        self
+    }
+
+    /// Computes the values at the percentiles.
+    fn update(mut self) -> Self {
+        let mut sorted: Vec<f32> = self
+            .data
+            .iter()
+            .copied()
+            .filter(|val| val.is_finite())
+            .collect();
+        sorted.sort_unstable_by(f32::total_cmp);
+
+        let percentile = |p: f32| match sorted.len() {
+            0 => 0.0,
+            len => sorted[(p / 100.0 * (len - 1) as f32).round() as usize],
+        };
+        self.lower = percentile(self.low);
+        self.upper = percentile(self.high);
+        self
+    }
+}
+
+impl Image for Stretched<'_> {
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        MAX_VALUE
+    }
+
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let val = self.data[y as usize * self.width as usize + x as usize];
+        if !val.is_finite() || val <= self.lower {
+            return 0;
+        } else if val >= self.upper {
+            return MAX_VALUE;
+        }
+
+        let fraction = (val - self.lower) / (self.upper - self.lower);
+        let stretched = match self.softening {
+            softening if softening > 0.0 => {
+                (fraction / softening).asinh() / softening.recip().asinh()
+            }
+            _ => fraction,
+        };
+
+        (stretched * MAX_VALUE as f32 + 0.5) as u32
+    }
+}
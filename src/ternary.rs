@@ -0,0 +1,157 @@
+use crate::hash::{get_values, Accumulation};
+use crate::{Blockhash64, Image};
+use core::cmp::Ordering;
+
+/// A 64-block perceptual hash that records whether each block is darker than,
+/// similar to, or brighter than the median of its band.
+///
+/// A binary hash sets a bit for each block brighter than the median, so a
+/// block whose brightness is close to the median can flip between
+/// re-encodings of the same image, such as JPEG files saved at different
+/// quality levels. A ternary hash treats blocks within a dead zone around the
+/// median as similar, and [`distance`](Self::distance) counts a change
+/// between similar and darker or brighter as half of a change between darker
+/// and brighter.
+///
+/// The hash is stored as two bit planes, each in the same layout as a
+/// [`Blockhash64`]: one with a bit set for each brighter block, and one with a
+/// bit set for each darker block.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::TernaryBlockhash;
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let a = TernaryBlockhash::from_image(&img, 16);
+/// let b = TernaryBlockhash::from_image(&img.blur(0.8), 16);
+///
+/// assert_eq!(a.distance(&b), 0);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct TernaryBlockhash {
+    brighter: Blockhash64,
+    darker: Blockhash64,
+}
+
+impl TernaryBlockhash {
+    /// Generates a ternary hash of an image.
+    ///
+    /// Blocks whose brightness differs from the median of their band by no
+    /// more than `dead_zone` are considered similar to the median, where the
+    /// dead zone is measured on a scale where 255 is the difference between
+    /// black and white. With a dead zone of 0, only blocks equal to the median
+    /// are similar.
+    #[must_use]
+    pub fn from_image<I: Image>(img: &I, dead_zone: u8) -> Self {
+        let (width, height) = img.dimensions();
+        let accumulation = Accumulation::select(width, height, 8);
+        let values = get_values::<I, 8, 64>(img, accumulation);
+
+        // The values are scaled so that a white block has this value
+        let full = u64::from(img.max_brightness()) * u64::from(width) * u64::from(height);
+        let zone = full * u64::from(dead_zone) / 255;
+
+        let mut brighter = 0_u64;
+        let mut darker = 0_u64;
+
+        for (band, band_values) in values.chunks(16).enumerate() {
+            let mut sorted = [0; 16];
+            sorted.copy_from_slice(band_values);
+            sorted.sort_unstable();
+            let median = (sorted[7] + sorted[8]) / 2;
+
+            for (n, &val) in band_values.iter().enumerate() {
+                let bit = 1 << (63 - (band * 16 + n));
+                if val > median.saturating_add(zone) {
+                    brighter |= bit;
+                } else if val < median.saturating_sub(zone) {
+                    darker |= bit;
+                }
+            }
+        }
+
+        TernaryBlockhash {
+            brighter: Blockhash64::from(brighter),
+            darker: Blockhash64::from(darker),
+        }
+    }
+
+    /// Creates a ternary hash from its bit planes.
+    ///
+    /// Returns `None` if any block is set in both planes.
+    #[must_use]
+    pub fn from_planes(brighter: Blockhash64, darker: Blockhash64) -> Option<Self> {
+        if u64::from(brighter) & u64::from(darker) != 0 {
+            return None;
+        }
+        Some(TernaryBlockhash { brighter, darker })
+    }
+
+    /// Returns the bit plane of blocks brighter than the median of their band.
+    #[inline]
+    #[must_use]
+    pub fn brighter(&self) -> Blockhash64 {
+        self.brighter
+    }
+
+    /// Returns the bit plane of blocks darker than the median of their band.
+    #[inline]
+    #[must_use]
+    pub fn darker(&self) -> Blockhash64 {
+        self.darker
+    }
+
+    /// Returns how a block compares to the median of its band.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is greater than 7.
+    #[must_use]
+    pub fn get(&self, x: u32, y: u32) -> Ordering {
+        assert!(x < 8 && y < 8, "block out of range");
+
+        let bit = 1 << (63 - (y * 8 + x));
+        if u64::from(self.brighter) & bit != 0 {
+            Ordering::Greater
+        } else if u64::from(self.darker) & bit != 0 {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    /// Returns the weighted distance between two hashes.
+    ///
+    /// Each block that is darker in one hash and brighter in the other adds 2
+    /// to the distance, and each block that is similar in one hash but not the
+    /// other adds 1, so the distance is in the range `0..=128`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use blockhash::TernaryBlockhash;
+    ///
+    /// let a = TernaryBlockhash::from_planes(0xf0.into(), 0x0f.into()).unwrap();
+    /// let b = TernaryBlockhash::from_planes(0x70.into(), 0x8f.into()).unwrap();
+    /// let c = TernaryBlockhash::from_planes(0x70.into(), 0x0f.into()).unwrap();
+    ///
+    /// assert_eq!(a.distance(&b), 2);
+    /// assert_eq!(a.distance(&c), 1);
+    /// ```
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> u32 {
+        self.brighter.distance(&other.brighter) + self.darker.distance(&other.darker)
+    }
+}
+
+impl From<TernaryBlockhash> for Blockhash64 {
+    /// Converts a ternary hash into a binary hash, with a bit set for each
+    /// brighter block.
+    #[inline]
+    fn from(hash: TernaryBlockhash) -> Self {
+        hash.brighter
+    }
+}
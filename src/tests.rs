@@ -2,6 +2,24 @@
 
 use super::*;
 
+/// A seeded xorshift generator, so that random test data is the same on
+/// every run.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number in the range `0..n`.
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next() % u64::from(n)) as u32
+    }
+}
+
 #[test]
 fn example_16x16_rgb() {
     let im = image::open("images/16x16_rgb.png").unwrap();
@@ -256,3 +274,2938 @@ fn example_5x5_rgb() {
             .unwrap(),
     );
 }
+
+#[test]
+fn distance_histogram_sample() {
+    let hashes = (0..1000_u32).map(|i| Blockhash64::from(u64::from(i) * 0x0101_0101));
+
+    let hist = distance_histogram(hashes.clone(), 20, 1);
+    assert_eq!(hist.counts().len(), 65);
+    assert_eq!(hist.pairs(), 190);
+    assert_eq!(hist, distance_histogram(hashes.clone(), 20, 1));
+
+    let hist = distance_histogram(hashes.take(5), 20, 1);
+    assert_eq!(hist.pairs(), 10);
+}
+
+#[test]
+fn region_matches_copy() {
+    use image::GenericImageView;
+
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    for &(x, y, w, h) in &[(0, 0, 450, 300), (13, 7, 200, 111), (100, 50, 3, 20)] {
+        let view = im.view(x, y, w, h);
+        let copy = view.to_image();
+        let region = Region::new(&im, x, y, w, h);
+
+        assert_eq!(blockhash16(&region), blockhash16(&copy));
+        assert_eq!(blockhash64(&region), blockhash64(&copy));
+        assert_eq!(blockhash144(&region), blockhash144(&copy));
+        assert_eq!(blockhash256(&region), blockhash256(&copy));
+        assert_eq!(blockhash256(&Region::from(&view)), blockhash256(&copy));
+        assert_eq!(blockhash256(&*view), blockhash256(&copy));
+    }
+}
+
+#[test]
+fn pixel_types_agree() {
+    let im = image::open("images/512x512_y.png").unwrap();
+    let expected = blockhash256(&im.to_luma8());
+    assert_eq!(blockhash256(&im.to_luma16()), expected);
+    assert_eq!(blockhash256(&im.to_luma_alpha8()), expected);
+    assert_eq!(blockhash256(&im.to_luma_alpha16()), expected);
+
+    let im = image::open("images/512x512_rgb.png").unwrap();
+    let expected = blockhash256(&im.to_rgb8());
+    assert_eq!(blockhash256(&im.to_rgba8()), expected);
+    assert_eq!(blockhash256(&im.to_rgb16()), expected);
+    assert_eq!(blockhash256(&im.to_rgba16()), expected);
+}
+
+#[test]
+fn custom_sizes_match_named_functions() {
+    for path in &[
+        "images/26x17_rgb.png",
+        "images/450x300_rgb.png",
+        "images/5x2_rgb.png",
+    ] {
+        let im = image::open(path).unwrap();
+        assert_eq!(
+            blockhash_custom::<_, 4, 16, 2>(&im),
+            <[u8; 2]>::from(blockhash16(&im)),
+        );
+        assert_eq!(
+            blockhash_custom::<_, 12, 144, 18>(&im),
+            <[u8; 18]>::from(blockhash144(&im)),
+        );
+        assert_eq!(
+            blockhash_custom::<_, 16, 256, 32>(&im),
+            <[u8; 32]>::from(blockhash256(&im)),
+        );
+    }
+
+    // Larger grids keep half of the bits of each band set
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let hash = blockhash_custom::<_, 20, 400, 50>(&im);
+    let ones: u32 = hash.iter().map(|byte| byte.count_ones()).sum();
+    assert!((190..=210).contains(&ones));
+}
+
+#[test]
+fn custom_sizes_not_multiple_of_4() {
+    let im = image::open("images/450x300_rgb.png").unwrap();
+
+    // The unused bits of the last byte are zero
+    let hash = blockhash_custom::<_, 6, 36, 5>(&im);
+    assert_eq!(hash[4] & 0x0f, 0);
+    let ones: u32 = hash.iter().map(|byte| byte.count_ones()).sum();
+    assert!((14..=22).contains(&ones));
+
+    let hash = blockhash_custom::<_, 10, 100, 13>(&im);
+    assert_eq!(hash[12] & 0x0f, 0);
+    let ones: u32 = hash.iter().map(|byte| byte.count_ones()).sum();
+    assert!((44..=56).contains(&ones));
+
+    define_blockhash!(Custom36, 6);
+    let hash = Custom36::from_image(&im);
+    assert_eq!(hash.as_bytes(), blockhash_custom::<_, 6, 36, 5>(&im));
+    assert_eq!(hash.to_string().len(), 10);
+    assert_eq!(hash.distance(&hash), 0);
+    assert_eq!(hash.to_string().parse(), Ok(hash));
+}
+
+#[test]
+fn defined_digest_matches_named_type() {
+    define_blockhash!(Custom256, 16);
+
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let hash = Custom256::from_image(&im);
+    let expected = blockhash256(&im);
+    assert_eq!(hash.as_bytes(), expected.as_bytes());
+    assert_eq!(hash.to_string(), expected.to_string());
+    assert_eq!(expected.to_string().parse(), Ok(hash));
+
+    let other = Custom256::from_image(&image::open("images/512x512_rgb.png").unwrap());
+    let other_expected = blockhash256(&image::open("images/512x512_rgb.png").unwrap());
+    assert_eq!(hash.distance(&other), expected.distance(&other_expected));
+    assert_eq!(<[u8; 32]>::from(hash), <[u8; 32]>::from(expected));
+}
+
+#[test]
+fn multiscale_matches_separate_hashes() {
+    for path in &[
+        "images/1x1_rgb.png",
+        "images/5x2_rgb.png",
+        "images/26x17_rgb.png",
+        "images/256x256_rgb.png",
+        "images/450x300_rgb.png",
+    ] {
+        let im = image::open(path).unwrap();
+        assert_eq!(
+            blockhash_multiscale(&im),
+            (blockhash16(&im), blockhash64(&im), blockhash256(&im)),
+            "{}",
+            path,
+        );
+    }
+}
+
+#[test]
+fn progressive_hash_refines() {
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let hash = ProgressiveHash::new(&im);
+    assert_eq!(hash.blockhash16(), blockhash16(&im));
+    assert_eq!(hash.blockhash64(), blockhash64(&im));
+    assert_eq!(hash.blockhash256(), blockhash256(&im));
+
+    let im = im.to_luma16();
+    let hash = ProgressiveHash::new(&im);
+    assert_eq!(hash.blockhash16(), blockhash16(&im));
+    assert_eq!(hash.blockhash256(), blockhash256(&im));
+}
+
+#[test]
+fn downsampled_hashes() {
+    for path in &[
+        "images/26x17_rgb.png",
+        "images/256x256_rgb.png",
+        "images/450x300_rgb.png",
+    ] {
+        let im = image::open(path).unwrap();
+        let hash = blockhash256(&im);
+
+        // Coarsening compares against the downsampled hash
+        let small = blockhash64(&im);
+        assert_eq!(
+            AnyBlockhash::from(hash).distance_with(&small.into(), SizePolicy::Coarsen),
+            Some(hash.downsample64().distance(&small)),
+        );
+
+        // The downsampled hashes are close to the hashes of the smaller sizes
+        let hash144 = blockhash144(&im);
+        let hash16 = blockhash16(&im);
+        assert!(hash.downsample16().distance(&hash16) <= 5, "{}", path);
+        assert!(hash.downsample64().distance(&small) <= 16, "{}", path);
+        assert!(hash.downsample144().distance(&hash144) <= 32, "{}", path);
+        assert!(hash144.downsample16().distance(&hash16) <= 5, "{}", path);
+        assert!(hash144.downsample64().distance(&small) <= 16, "{}", path);
+        assert!(small.downsample16().distance(&hash16) <= 5, "{}", path);
+    }
+}
+
+#[test]
+fn blockhash1024_matches_custom_grid() {
+    for path in &[
+        "images/1x1_rgb.png",
+        "images/26x17_rgb.png",
+        "images/450x300_rgb.png",
+        "images/512x512_rgb.png",
+    ] {
+        let im = image::open(path).unwrap();
+        let hash = blockhash1024(&im);
+        assert_eq!(
+            <[u8; 128]>::from(hash),
+            blockhash_custom::<_, 32, 1024, 128>(&im),
+        );
+        assert_eq!(hash.to_string().parse(), Ok(hash));
+        assert_eq!(
+            Blockhash1024::from_hex_bytes(hash.to_string().as_bytes()),
+            Ok(hash)
+        );
+        assert_eq!(hash.flip_horizontal().flip_horizontal(), hash);
+        assert!(
+            hash.downsample256().distance(&blockhash256(&im)) <= 48,
+            "{}",
+            path
+        );
+        assert_eq!(BlockhashOptions::new().blockhash1024(&im), hash);
+
+        let any = AnyBlockhash::from(hash);
+        let small = AnyBlockhash::from(hash.downsample16());
+        assert_eq!(any.bits(), 1024);
+        assert_eq!(any.distance_with(&small, SizePolicy::Coarsen), Some(0));
+        assert_eq!(split_digests(&concat_digests(&[any])), Ok(vec![any]));
+        assert_eq!(BlockhashDyn::from(any), BlockhashDyn::from(hash));
+    }
+}
+
+#[test]
+fn rectangular_grids() {
+    for path in &[
+        "images/26x17_rgb.png",
+        "images/450x300_rgb.png",
+        "images/5x2_rgb.png",
+    ] {
+        let im = image::open(path).unwrap();
+        assert_eq!(
+            blockhash_grid::<_, 8, 8, 64, 8>(&im),
+            <[u8; 8]>::from(blockhash64(&im)),
+        );
+
+        // Transposing the image transposes the values of the blocks
+        let transposed = im.rotate90().fliph();
+        let (width, height) = Image::dimensions(&im);
+        let accumulation = Accumulation::select_grid(width, height, 16, 4);
+        let values = hash::get_grid_values::<_, 16, 4, 64>(&im, accumulation);
+        let accumulation = Accumulation::select_grid(height, width, 4, 16);
+        let expected = hash::get_grid_values::<_, 4, 16, 64>(&transposed, accumulation);
+        for y in 0..4 {
+            for x in 0..16 {
+                assert_eq!(values[y * 16 + x], expected[x * 4 + y], "{}", path);
+            }
+        }
+    }
+
+    // Every strategy gives the same values
+    let im = image::open("images/450x300_rgb.png")
+        .unwrap()
+        .crop_imm(0, 0, 448, 300);
+    let expected = hash::get_grid_values::<_, 16, 4, 64>(&im, Accumulation::Generic);
+    for accumulation in [Accumulation::Aligned, Accumulation::Larger] {
+        let values = hash::get_grid_values::<_, 16, 4, 64>(&im, accumulation);
+        assert_eq!(values, expected);
+    }
+
+    define_blockhash!(Blockhash16x9, 16, 9);
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let hash = Blockhash16x9::from_image(&im);
+    assert_eq!((Blockhash16x9::COLUMNS, Blockhash16x9::ROWS), (16, 9));
+    assert_eq!(hash.as_bytes(), blockhash_grid::<_, 16, 9, 144, 18>(&im));
+    assert_eq!(hash.distance(&hash), 0);
+}
+
+#[test]
+fn thermal_frames() {
+    let im = image::open("images/450x300_rgb.png").unwrap().to_luma16();
+    let (width, height) = im.dimensions();
+    let data = im.as_raw();
+
+    // The full range leaves the counts unchanged
+    let img = ThermalFrame::new(data, width, height).normalization(Normalization::Fixed {
+        low: 0,
+        high: 65535,
+    });
+    assert_eq!(
+        blockhash256(&img),
+        blockhash256(&RawImage::<_, 1>::new(data, width, height)),
+    );
+
+    // Drift between 14-bit frames doesn't change the hash
+    let frame: Vec<u16> = data.iter().map(|&val| val >> 2).collect();
+    let drifted: Vec<u16> = frame.iter().map(|&val| val + 700).collect();
+    let expected = blockhash256(&ThermalFrame::new(&frame, width, height));
+    assert_eq!(
+        blockhash256(&ThermalFrame::new(&drifted, width, height)),
+        expected
+    );
+
+    let img = ThermalFrame::new(&frame, width, height)
+        .normalization(Normalization::Fixed { low: 0, high: 100 });
+    assert_ne!(blockhash256(&img), expected);
+    assert_eq!(
+        blockhash256(&img.normalization(Normalization::MinMax)),
+        expected
+    );
+}
+
+#[test]
+fn stretched_values() {
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let luma = im.to_luma8();
+    let (width, height) = luma.dimensions();
+    let expected = blockhash256(&RawImage::<_, 1>::new(luma.as_raw(), width, height));
+
+    // Without clipping or stretching, the values are mapped linearly
+    let data: Vec<f32> = luma.as_raw().iter().map(|&val| f32::from(val)).collect();
+    let img = Stretched::new(&data, width, height)
+        .percentiles(0.0, 100.0)
+        .softening(0.0);
+    assert!(blockhash256(&img).distance(&expected) <= 4);
+
+    // The hash doesn't depend on the scale or offset of the values
+    let hash = blockhash256(&Stretched::new(&data, width, height));
+    let rescaled: Vec<f32> = data.iter().map(|&val| val * 1000.0 - 20.0).collect();
+    let img = Stretched::new(&rescaled, width, height);
+    assert!(blockhash256(&img).distance(&hash) <= 2);
+
+    // Blank pixels are black
+    let mut blank = data.clone();
+    blank[..(width as usize * 10)].fill(f32::NAN);
+    let img = Stretched::new(&blank, width, height);
+    assert_eq!(img.brightness(0, 0), 0);
+}
+
+#[test]
+fn windowed_values() {
+    let im = image::open("images/450x300_rgb.png").unwrap().to_luma16();
+    let (width, height) = im.dimensions();
+    let data = im.as_raw();
+    let expected = blockhash256(&RawImage::<_, 1>::new(data, width, height));
+
+    // A window covering every stored value leaves them unchanged
+    let img = Windowed::new(data, width, height).window(32768.0, 65536.0);
+    assert_eq!(blockhash256(&img), expected);
+
+    // Without a window, the range of rescaled values is used
+    let img = Windowed::new(data, width, height).rescale(2.0, -1000.0);
+    assert_eq!(
+        blockhash256(&img),
+        blockhash256(&Windowed::new(data, width, height))
+    );
+
+    // A window below every value makes the whole image white
+    let img = Windowed::new(data, width, height).window(0.0, 1.0);
+    assert_eq!(
+        blockhash256(&img.inverted(true)).distance(&blockhash256(&img)),
+        256
+    );
+
+    let signed: Vec<i16> = data
+        .iter()
+        .map(|&val| (i32::from(val) - 32768) as i16)
+        .collect();
+    let img = Windowed::new(&signed, width, height).rescale(1.0, 32768.0);
+    assert_eq!(blockhash256(&img), expected);
+}
+
+#[test]
+fn dyn_hash_sizes() {
+    let im = image::open("images/450x300_rgb.png").unwrap();
+
+    let hash = BlockhashDyn::from_image(&im, 16).unwrap();
+    assert_eq!(hash, BlockhashDyn::from(blockhash256(&im)));
+    assert_eq!(
+        hash,
+        BlockhashDyn::from(AnyBlockhash::from(blockhash256(&im)))
+    );
+    assert_eq!(hash.as_bytes(), blockhash256(&im).as_bytes());
+
+    let hash = BlockhashDyn::from_image(&im, 6).unwrap();
+    assert_eq!(hash.as_bytes(), blockhash_custom::<_, 6, 36, 5>(&im));
+    assert_eq!((hash.columns(), hash.rows(), hash.bits()), (6, 6, 36));
+    assert_eq!(hash.to_string().parse(), Ok(hash.clone()));
+
+    for size in [0, 3, 34] {
+        assert_eq!(BlockhashDyn::from_image(&im, size), None);
+    }
+
+    // The unused bits must be zero, and the length must match a size
+    assert!("000000000f".parse::<BlockhashDyn>().is_err());
+    assert!("000000".parse::<BlockhashDyn>().is_err());
+
+    define_blockhash!(Blockhash16x4, 16, 4);
+    let rect = Blockhash16x4::from_image(&im);
+    assert_eq!(rect.distance(&rect), 0);
+    let rect = BlockhashDyn::from(rect);
+    assert_eq!((rect.columns(), rect.rows()), (16, 4));
+    assert_eq!(
+        rect.distance(&BlockhashDyn::from_image(&im, 8).unwrap()),
+        None
+    );
+    assert_eq!(rect.distance(&rect), Some(0));
+}
+
+#[test]
+fn excluded_text_regions() {
+    let im = image::open("images/512x512_rgb.png").unwrap().to_rgb8();
+    let field = BlockRect {
+        x: 300,
+        y: 20,
+        width: 180,
+        height: 120,
+    };
+    let stamp = |density: u32| {
+        let mut im = im.clone();
+        for y in field.y..field.y + field.height {
+            for x in field.x..field.x + field.width {
+                let ink = (x * 3 + y * 7) % 5 < density;
+                im.put_pixel(x, y, image::Rgb(if ink { [0; 3] } else { [255; 3] }));
+            }
+        }
+        im
+    };
+    let (a, b) = (stamp(1), stamp(4));
+    assert_ne!(blockhash256(&a), blockhash256(&b));
+    assert_eq!(
+        blockhash256(&Excluded::new(&a, &[field])),
+        blockhash256(&Excluded::new(&b, &[field])),
+    );
+
+    // The fill only depends on the pixels around the field
+    let inside = Excluded::new(&a, &[field]);
+    assert_eq!(
+        inside.brightness(field.x, field.y),
+        inside.brightness(field.x + field.width - 1, field.y + field.height - 1),
+    );
+    assert_eq!(inside.brightness(0, 0), a.brightness(0, 0));
+
+    // Rectangles outside the image are clipped or ignored
+    let outside = BlockRect {
+        x: 600,
+        y: 0,
+        width: 10,
+        height: 10,
+    };
+    assert_eq!(
+        blockhash256(&Excluded::new(&im, &[outside])),
+        blockhash256(&im),
+    );
+    let everything = BlockRect {
+        x: 0,
+        y: 0,
+        width: u32::MAX,
+        height: u32::MAX,
+    };
+    assert_eq!(
+        blockhash256(&Excluded::new(&im, &[everything])),
+        Blockhash256::from([0; 32]),
+    );
+}
+
+#[test]
+fn sprite_hashes_match_crops() {
+    let sheet = image::open("images/512x512_rgb.png").unwrap();
+    let mut frames = Vec::new();
+    for y in (0..512).step_by(64) {
+        for x in (0..512).step_by(64) {
+            frames.push(BlockRect {
+                x,
+                y,
+                width: 64,
+                height: 48,
+            });
+        }
+    }
+    frames.push(BlockRect {
+        x: 500,
+        y: 3,
+        width: 12,
+        height: 5,
+    });
+
+    let hashes: Vec<Blockhash256> = hash_sprites(&sheet, &frames);
+    assert_eq!(hashes.len(), frames.len());
+    for (hash, frame) in hashes.iter().zip(&frames) {
+        let crop = sheet.crop_imm(frame.x, frame.y, frame.width, frame.height);
+        assert_eq!(*hash, blockhash256(&crop));
+    }
+}
+
+#[test]
+fn similar_screenshots() {
+    let im = image::open("images/512x512_rgb.png").unwrap();
+    let expected = blockhash16(&im);
+    assert_eq!(check_similar(&expected, &im, 0), Ok(()));
+    assert_similar!(expected, im.blur(1.0), 1);
+
+    // Each differing block is marked in the grid
+    let flipped = im.flipv();
+    let err = check_similar(&expected, &flipped, 0).unwrap_err();
+    assert_eq!(err.distance, expected.distance(&err.actual));
+    let message = err.to_string();
+    let grid: Vec<&str> = message.lines().skip(3).collect();
+    assert!(grid.iter().all(|row| row.len() == 4));
+    assert_eq!(grid.concat().matches('X').count() as u32, err.distance);
+
+    let result = std::panic::catch_unwind(|| assert_similar!(expected, flipped, 0, "frame {}", 3));
+    let message = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.starts_with("assertion failed: image is not similar to hash: frame 3\n"));
+    assert!(message.ends_with(&err.to_string()));
+}
+
+#[test]
+fn diff_gate_verdicts() {
+    let im = image::open("images/512x512_rgb.png").unwrap();
+    let baseline = blockhash256(&im);
+    let gate = DiffGate::new(24).identical_distance(4);
+
+    assert_eq!(
+        gate.check_image(&baseline, &im),
+        Verdict::Identical { distance: 0 },
+    );
+    let verdict = gate.check_image(&baseline, &im.brighten(10));
+    assert!(verdict.passed());
+    assert_eq!(
+        verdict.distance(),
+        baseline.distance(&blockhash256(&im.brighten(10)))
+    );
+
+    let verdict = gate.check_image(&baseline, &im.rotate180());
+    assert!(matches!(verdict, Verdict::Fail { distance } if distance > 24));
+
+    // An identical distance above the acceptable distance still applies
+    let gate = DiffGate::new(2).identical_distance(8);
+    let mut output = [0x0f; 32];
+    output[0] = 0;
+    assert_eq!(
+        gate.check(&Blockhash256::from([0x0f; 32]), &Blockhash256::from(output)),
+        Verdict::Identical { distance: 4 },
+    );
+}
+
+#[test]
+fn pyramid_levels() {
+    use image::imageops::{self, FilterType};
+
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let pyramid = BlockhashPyramid::<Blockhash64>::from_image(&im);
+    assert_eq!(pyramid.levels()[0], blockhash64(&im));
+
+    // Scaling down by a whole factor averages exactly
+    let small = im.resize_exact(64, 40, FilterType::Triangle).to_rgb8();
+    let large = imageops::resize(&small, 128, 80, FilterType::Nearest);
+    let levels = BlockhashPyramid::<Blockhash64>::from_image(&large)
+        .levels()
+        .to_owned();
+    assert_eq!(levels[0], blockhash64(&large));
+    assert_eq!(levels[1], blockhash64(&small));
+    assert_eq!(
+        BlockhashPyramid::<Blockhash64>::from_image(&small).levels()[1],
+        levels[1]
+    );
+
+    // Levels are never scaled up
+    let thumbnail = im.resize(24, 24, FilterType::Nearest);
+    let thumbnail = BlockhashPyramid::<Blockhash64>::from_image(&thumbnail);
+    let levels = thumbnail.levels();
+    assert_eq!(levels[..3], [levels[0]; 3]);
+
+    let distances = pyramid.level_distances(&thumbnail);
+    assert_eq!(
+        pyramid.distance(&thumbnail),
+        *distances.iter().min().unwrap()
+    );
+    assert!(pyramid.distance(&thumbnail) < distances[0]);
+
+    let other = image::open("images/512x512_rgb.png").unwrap();
+    let other = BlockhashPyramid::<Blockhash64>::from_image(&other);
+    assert!(pyramid.distance(&other) > 24);
+}
+
+#[test]
+fn tiled_hashes() {
+    let photo = image::open("images/512x512_rgb.png").unwrap().to_rgb8();
+    let other = image::open("images/450x300_rgb.png").unwrap().to_rgb8();
+
+    // A collage with the photo in the bottom-left corner
+    let mut collage = image::RgbImage::new(900, 600);
+    image::imageops::overlay(&mut collage, &other, 0, 0);
+    image::imageops::overlay(&mut collage, &other, 450, 300);
+    let small = image::imageops::resize(&photo, 450, 300, image::imageops::FilterType::Triangle);
+    image::imageops::overlay(&mut collage, &small, 0, 300);
+
+    let tiles = blockhash256_tiles(&collage, 2, 2);
+    assert_eq!(tiles[2], blockhash256(&small));
+    let best = best_tile(&blockhash256(&photo), &tiles).unwrap();
+    assert_eq!(best.index, 2);
+    assert!(best.distance < 32);
+    assert!(blockhash256(&photo).distance(&blockhash256(&collage)) > 64);
+
+    let photo_tiles = blockhash256_tiles(&small, 1, 1);
+    assert_eq!(best_tile_distance(&tiles, &photo_tiles), Some(0));
+    assert_eq!(best_tile_distance(&tiles, &[]), None);
+
+    // Tiles are as even as possible, even when some are empty
+    let tiles = blockhash256_tiles(&photo, 3, 1000);
+    assert_eq!(tiles.len(), 3000);
+    assert_eq!(
+        tiles[3 * 999 + 2],
+        blockhash256(&image::imageops::crop_imm(&photo, 341, 511, 171, 1).to_image()),
+    );
+}
+
+#[test]
+fn saturation_hash() {
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let rgb = im.to_rgb8();
+
+    // Averaging the channels keeps the brightness but removes the saturation
+    let gray = image::RgbImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+        let [r, g, b] = rgb.get_pixel(x, y).0.map(u32::from);
+        image::Rgb([((r + g + b) / 3) as u8; 3])
+    });
+    assert!(blockhash256(&gray).distance(&blockhash256(&rgb)) <= 4);
+    assert_eq!(u64::from(blockhash64_saturation(&gray)), 0);
+    assert_ne!(u64::from(blockhash64_saturation(&rgb)), 0);
+
+    // Saturation doesn't depend on the brightness or bit depth
+    let darker = image::RgbImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+        image::Rgb(rgb.get_pixel(x, y).0.map(|c| c / 2))
+    });
+    assert!(blockhash256_saturation(&darker).distance(&blockhash256_saturation(&rgb)) <= 16);
+    assert!(blockhash256_saturation(&im.to_rgb16()).distance(&blockhash256_saturation(&rgb)) <= 4);
+}
+
+#[test]
+fn lut_identity() {
+    let table8: Vec<u16> = (0..=255).collect();
+    let table16: Vec<u16> = (0..=65535).collect();
+
+    let im = image::open("images/241x159_ya.png").unwrap();
+    assert_eq!(
+        blockhash256(&Lut::new(&im, &table8)),
+        blockhash256(&im.to_rgba8()),
+    );
+
+    let im = im.into_rgba16();
+    assert_eq!(blockhash256(&Lut::new(&im, &table16)), blockhash256(&im));
+}
+
+#[test]
+fn lut_channels() {
+    let table: Vec<u16> = (0..=255).map(|val| val * 4).collect();
+    let sprite = image::RgbaImage::from_fn(32, 32, |x, y| {
+        let inside = (8..24).contains(&x) && (8..24).contains(&y);
+        image::Rgba([200, 100, 50, if inside { 255 } else { 0 }])
+    });
+    let lut = Lut::new(&sprite, &table);
+    assert_eq!(lut.max_channel(), 1020);
+    assert_eq!(lut.channels(0, 0), [800, 400, 200, 0]);
+    assert_eq!(lut.channels(8, 8), [800, 400, 200, 1020]);
+
+    let options = BlockhashOptions::new().transparent_color(Background::Black);
+    assert_eq!(options.blockhash64(&lut), options.blockhash64(&sprite));
+    assert_ne!(options.blockhash64(&lut), blockhash64(&lut));
+
+    // The table of a nested Lut covers the runtime range of the inner one
+    assert_eq!(lut.max_brightness(), 1020 * 3);
+    let table: Vec<u16> = (0..=1020).map(|val| 1020 - val).collect();
+    let inverted = Lut::new(&lut, &table);
+    assert_eq!(inverted.channels(8, 8), [220, 620, 820, 1020]);
+    assert_eq!(inverted.max_brightness(), 1020 * 3);
+}
+
+#[test]
+fn quantize_to_8_bits() {
+    let options = BlockhashOptions::new().quantize_to_8_bits(true);
+
+    for path in &["images/450x300_rgb.png", "images/512x512_y.png"] {
+        let im = image::open(path).unwrap();
+        let expected = blockhash256(&im);
+
+        // Perturb the low bits, as a different 16-bit export of the same image
+        // might
+        let mut im16 = im.to_rgba16();
+        for (x, y, px) in im16.enumerate_pixels_mut() {
+            let noise = (x * 7 + y * 13) % 201;
+            for c in &mut px.0[..3] {
+                *c = (i64::from(*c) + i64::from(noise) - 100).clamp(0, 65535) as u16;
+            }
+        }
+
+        assert_eq!(options.blockhash256(&im16), expected);
+        assert_eq!(
+            options.blockhash256(&im.to_luma16()),
+            blockhash256(&im.to_luma8())
+        );
+        assert_eq!(
+            options.blockhash256(&im.to_rgb8()),
+            blockhash256(&im.to_rgb8())
+        );
+    }
+}
+
+#[test]
+fn options_with_lut() {
+    // The options scale against the range of the table, not of its entries
+    let table: Vec<u16> = (0..=255).collect();
+    let im = image::open("images/example.png").unwrap();
+    let lut = Lut::new(&im, &table);
+    assert_eq!(blockhash64(&lut).to_string(), "c7c48f8989c77e0c");
+    assert_eq!(
+        BlockhashOptions::new()
+            .quantize_to_8_bits(true)
+            .blockhash64(&lut),
+        blockhash64(&im),
+    );
+
+    let im = image::open("images/241x159_ya.png").unwrap().into_rgba8();
+    let lut = Lut::new(&im, &table);
+    for options in [
+        BlockhashOptions::new().transparent_color(Background::Black),
+        BlockhashOptions::new().alpha_background(Some(Background::Rgb([40, 120, 200]))),
+        BlockhashOptions::new().premultiplied_alpha(true),
+        BlockhashOptions::new().linearize_srgb(true),
+    ] {
+        assert_eq!(options.blockhash256(&lut), options.blockhash256(&im));
+    }
+}
+
+#[test]
+fn linearize_srgb() {
+    let options = BlockhashOptions::new().linearize_srgb(true);
+    let table: Vec<u16> = (0..=255)
+        .map(|val| {
+            let val = f64::from(val) / 255.0;
+            let linear = if val <= 0.04045 {
+                val / 12.92
+            } else {
+                ((val + 0.055) / 1.055).powf(2.4)
+            };
+            (linear * 65535.0).round() as u16
+        })
+        .collect();
+
+    for path in &["images/450x300_rgb.png", "images/512x512_y.png"] {
+        let im = image::open(path).unwrap();
+        let expected = blockhash256(&Lut::new(&im.to_rgb8(), &table));
+
+        assert_eq!(options.blockhash256(&im.to_rgb8()), expected);
+        assert_eq!(options.blockhash256(&im.to_rgb16()), expected);
+        assert_ne!(options.blockhash256(&im.to_rgb8()), blockhash256(&im));
+    }
+
+    // Channels that aren't 8 bits are interpolated
+    let mut prev = 0;
+    for val in 0..=u16::MAX {
+        let linear = srgb::srgb_to_linear(val.into(), u16::MAX.into());
+        assert!(linear >= prev);
+        prev = linear;
+    }
+    assert_eq!(prev, u32::from(u16::MAX));
+}
+
+#[test]
+fn luma_weights() {
+    // Pure green on the left, pure blue on the right
+    let im = image::RgbImage::from_fn(64, 64, |x, _| {
+        if x < 32 {
+            image::Rgb([0, 255, 0])
+        } else {
+            image::Rgb([0, 0, 255])
+        }
+    });
+
+    let hash = blockhash16(&im);
+    assert_eq!(hash, Blockhash16::from(0));
+    for luma in [LumaWeights::Bt601, LumaWeights::Bt709] {
+        let options = BlockhashOptions::new().luma_weights(luma);
+        assert_eq!(options.blockhash16(&im), Blockhash16::from(0xcccc));
+    }
+
+    // Custom weights only depend on their ratios
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    assert_eq!(
+        BlockhashOptions::new()
+            .channel_weights([2126, 7152, 722])
+            .blockhash256(&im),
+        BlockhashOptions::new()
+            .luma_weights(LumaWeights::Bt709)
+            .blockhash256(&im),
+    );
+
+    // Equal weights are the same as the default
+    let options = BlockhashOptions::new().luma_weights(LumaWeights::Equal);
+    assert_eq!(options.blockhash256(&im), blockhash256(&im));
+
+    // Grayscale images are unaffected
+    let im = image::open("images/512x512_y.png").unwrap();
+    let options = BlockhashOptions::new().luma_weights(LumaWeights::Bt709);
+    assert_eq!(options.blockhash256(&im), blockhash256(&im));
+}
+
+#[test]
+fn delta_hasher() {
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d_u64);
+
+    // Sizes that exercise every accumulation strategy
+    for &(width, height) in &[(64, 48), (37, 29), (10, 7)] {
+        let mut frame =
+            image::GrayImage::from_fn(width, height, |x, y| image::Luma([(x * 7 + y * 13) as u8]));
+        let mut hasher = DeltaHasher::new();
+        assert_eq!(hasher.blockhash256(&frame, None), blockhash256(&frame));
+
+        for _ in 0..20 {
+            // Change a few rows, and report them in overlapping ranges
+            let top = rng.below(height);
+            let bottom = top + 1 + rng.below(height - top);
+            for y in top..bottom {
+                let x = rng.below(width);
+                frame.put_pixel(x, y, image::Luma([rng.below(256) as u8]));
+            }
+            let dirty = [top..bottom, top..top + 1, bottom..height + 5];
+            assert_eq!(hasher.blockhash256(&frame, dirty), blockhash256(&frame));
+        }
+
+        // Changing the size of the hash reads the whole frame
+        assert_eq!(hasher.blockhash16(&frame, None), blockhash16(&frame));
+        assert_eq!(hasher.blockhash144(&frame, None), blockhash144(&frame));
+    }
+
+    // Unreported changes are missed until the hasher is reset
+    let mut frame = image::GrayImage::new(16, 16);
+    let mut hasher = DeltaHasher::new();
+    let before = hasher.blockhash64(&frame, None);
+    frame.put_pixel(3, 3, image::Luma([255]));
+    assert_eq!(hasher.blockhash64(&frame, None), before);
+    hasher.reset();
+    assert_eq!(hasher.blockhash64(&frame, None), blockhash64(&frame));
+    assert!(hasher.changed_regions(0.0).is_empty());
+
+    // Rows reported as dirty that didn't change aren't in any region
+    hasher.blockhash64(&frame, Some(0..16));
+    assert!(hasher.changed_regions(0.0).is_empty());
+
+    // A change to one pixel is a small fraction of the brightness of a block
+    frame.put_pixel(15, 15, image::Luma([255]));
+    hasher.blockhash64(&frame, Some(15..16));
+    let corner = BlockRect {
+        x: 14,
+        y: 14,
+        width: 2,
+        height: 2,
+    };
+    assert_eq!(hasher.changed_regions(0.2), [corner]);
+    assert!(hasher.changed_regions(0.3).is_empty());
+}
+
+#[test]
+fn channel_max() {
+    // 12-bit data stored in 16-bit channels, where every block ties with the
+    // median, so the bits depend on whether it is brighter than half the
+    // maximum
+    let im = image::ImageBuffer::from_pixel(32, 32, image::Luma([3000_u16]));
+    assert_eq!(blockhash64(&im), Blockhash64::from(0));
+
+    let options = BlockhashOptions::new().channel_max(Some(4095));
+    assert_eq!(options.blockhash64(&im), Blockhash64::from(u64::MAX));
+
+    // Quantization is scaled by the maximum, and larger values are clamped
+    let im = image::ImageBuffer::from_fn(32, 32, |x, y| {
+        image::Rgb([(x * 128) as u16, (y * 128) as u16, 5000])
+    });
+    let expected = image::RgbImage::from_fn(32, 32, |x, y| {
+        let scale = |val: u32| ((val * 128 * 255 + 2047) / 4095) as u8;
+        image::Rgb([scale(x), scale(y), 255])
+    });
+    let options = options.quantize_to_8_bits(true);
+    assert_eq!(options.blockhash256(&im), blockhash256(&expected));
+}
+
+#[test]
+fn alpha_background() {
+    let im = image::open("images/241x159_ya.png").unwrap();
+    let blend = |val: u8, alpha: u8, bg: u32| {
+        let (val, alpha) = (u32::from(val), u32::from(alpha));
+        ((val * alpha + bg * (255 - alpha) + 127) / 255) as u8
+    };
+
+    // Grayscale images are blended with the average of the background
+    let gray = im.to_luma_alpha8();
+    let flattened = image::GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        let [l, a] = gray.get_pixel(x, y).0;
+        image::Luma([blend(l, a, 80)])
+    });
+    let options = BlockhashOptions::new().alpha_background(Some(Background::Rgb([10, 200, 30])));
+    assert_eq!(options.blockhash256(&gray), blockhash256(&flattened));
+
+    // Fade from transparent on the left to opaque on the right
+    let mut rgba = im.to_rgba8();
+    let width = rgba.width();
+    for (x, _, px) in rgba.enumerate_pixels_mut() {
+        px[3] = (x * 255 / (width - 1)) as u8;
+    }
+    let flattened = image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        image::Rgb([blend(r, a, 230), blend(g, a, 230), blend(b, a, 230)])
+    });
+    let options = BlockhashOptions::new().alpha_background(Some(Background::Checkerboard));
+    assert_eq!(options.blockhash256(&rgba), blockhash256(&flattened));
+
+    // Fully transparent pixels are no longer white
+    let options = BlockhashOptions::new().alpha_background(Some(Background::Black));
+    assert_ne!(options.blockhash256(&rgba), blockhash256(&rgba));
+}
+
+#[test]
+fn gamma() {
+    let gray = image::open("images/450x300_rgb.png").unwrap().into_luma8();
+    let adjusted = image::GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        let val = f64::from(gray.get_pixel(x, y)[0]) / 255.0;
+        image::Luma([(val.powf(1.0 / 2.2) * 255.0).round() as u8])
+    });
+
+    let options = BlockhashOptions::new().gamma(2.2);
+    assert_eq!(options.blockhash256(&gray), blockhash256(&adjusted));
+    assert_ne!(options.blockhash256(&gray), blockhash256(&gray));
+
+    let options = BlockhashOptions::new().gamma(1.0);
+    assert_eq!(options, BlockhashOptions::new());
+
+    for gamma in [0.0, -1.0, f32::INFINITY, f32::NAN] {
+        assert_eq!(
+            BlockhashOptions::new().try_gamma(gamma),
+            Err(OptionsError::InvalidGamma),
+        );
+    }
+}
+
+#[test]
+fn transparent_color() {
+    // Make the left quarter fully transparent
+    let mut im = image::open("images/241x159_ya.png")
+        .unwrap()
+        .into_luma_alpha8();
+    for (x, _, px) in im.enumerate_pixels_mut() {
+        if x < 60 {
+            px[1] = 0;
+        }
+    }
+    let im = image::DynamicImage::ImageLumaA8(im);
+    let rgba = im.to_rgba8();
+    let gray = im.to_luma_alpha8();
+
+    for (background, color) in [
+        (Background::White, [255, 255, 255]),
+        (Background::Black, [0, 0, 0]),
+        (Background::Rgb([20, 40, 90]), [20, 40, 90]),
+    ] {
+        let options = BlockhashOptions::new().transparent_color(background);
+
+        let replaced = image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            image::Rgb(if a == 0 { color } else { [r, g, b] })
+        });
+        assert_eq!(options.blockhash256(&rgba), blockhash256(&replaced));
+        assert_eq!(
+            options.blockhash256(&im.to_rgba16()),
+            blockhash256(&replaced)
+        );
+
+        // Grayscale images use the average of the color
+        let average = ((color.iter().map(|&c| u32::from(c)).sum::<u32>() + 1) / 3) as u8;
+        let replaced = image::GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+            let [l, a] = gray.get_pixel(x, y).0;
+            image::Luma([if a == 0 { average } else { l }])
+        });
+        assert_eq!(options.blockhash256(&gray), blockhash256(&replaced));
+    }
+
+    let options = BlockhashOptions::new().transparent_color(Background::Black);
+    assert_ne!(options.blockhash256(&rgba), blockhash256(&rgba));
+}
+
+#[test]
+fn canonical_hash_orientation() {
+    use image::ImageEncoder;
+
+    let img = image::open("images/example.png").unwrap();
+
+    // Stored sideways, with an Exif orientation that rotates it upright
+    let sideways = img.rotate270().into_rgb16();
+    let exif = [
+        b'M', b'M', 0, 42, 0, 0, 0, 8, // header
+        0, 1, // one entry
+        0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, // orientation: rotate 90
+        0, 0, 0, 0, // no more entries
+    ];
+    let mut bytes = Vec::new();
+    let mut encoder = image::codecs::png::PngEncoder::new(&mut bytes);
+    encoder.set_exif_metadata(exif.to_vec()).unwrap();
+    encoder
+        .write_image(
+            image::DynamicImage::ImageRgb16(sideways.clone()).as_bytes(),
+            sideways.width(),
+            sideways.height(),
+            image::ExtendedColorType::Rgb16,
+        )
+        .unwrap();
+
+    // The format is detected from the contents, not the extension
+    let path = std::env::temp_dir().join(format!("blockhash-canonical-{}.dat", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    let hash: Result<Blockhash256, _> = canonical_hash(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(hash.unwrap(), blockhash256(&img));
+    assert!(canonical_hash::<Blockhash64, _>("images/missing.png").is_err());
+}
+
+#[test]
+fn capabilities_match_features() {
+    let capabilities = capabilities();
+    assert!(capabilities.std() && capabilities.alloc() && capabilities.image());
+    assert_eq!(capabilities.serde(), cfg!(feature = "serde"));
+
+    let formats: Vec<_> = capabilities.decodable_formats().collect();
+    assert!(formats.contains(&image::ImageFormat::Png));
+    assert!(formats.contains(&image::ImageFormat::Jpeg));
+    assert!(formats
+        .iter()
+        .all(|&format| capabilities.can_decode(format)));
+}
+
+/// Builds a minimal ICC profile with the given red and green colorants.
+#[cfg(feature = "icc")]
+fn icc_profile(red: [f64; 3], green: [f64; 3]) -> Vec<u8> {
+    let mut profile = vec![0; 132];
+    profile[16..20].copy_from_slice(b"RGB ");
+    profile[36..40].copy_from_slice(b"acsp");
+    profile[128..132].copy_from_slice(&2_u32.to_be_bytes());
+
+    let tags = [(b"rXYZ", red), (b"gXYZ", green)];
+    for (i, (signature, _)) in tags.iter().enumerate() {
+        let offset = 156 + i as u32 * 20;
+        profile.extend_from_slice(&signature[..]);
+        profile.extend_from_slice(&offset.to_be_bytes());
+        profile.extend_from_slice(&20_u32.to_be_bytes());
+    }
+    for (_, xyz) in &tags {
+        profile.extend_from_slice(b"XYZ \0\0\0\0");
+        for val in xyz {
+            profile.extend_from_slice(&((val * 65536.0).round() as i32).to_be_bytes());
+        }
+    }
+    profile
+}
+
+#[cfg(feature = "icc")]
+#[test]
+fn color_space_from_icc_profile() {
+    let srgb = icc_profile([0.4361, 0.2225, 0.0139], [0.3851, 0.7169, 0.0971]);
+    let p3 = icc_profile([0.5151, 0.2412, -0.0011], [0.2920, 0.6923, 0.0419]);
+    let adobe = icc_profile([0.6097, 0.3111, 0.0195], [0.2053, 0.6257, 0.0609]);
+    assert_eq!(ColorSpace::from_icc_profile(&srgb), Some(ColorSpace::Srgb));
+    assert_eq!(
+        ColorSpace::from_icc_profile(&p3),
+        Some(ColorSpace::DisplayP3)
+    );
+    assert_eq!(
+        ColorSpace::from_icc_profile(&adobe),
+        Some(ColorSpace::AdobeRgb)
+    );
+
+    // ProPhoto RGB isn't supported
+    let prophoto = icc_profile([0.7977, 0.2880, 0.0], [0.1352, 0.7119, 0.0]);
+    assert_eq!(ColorSpace::from_icc_profile(&prophoto), None);
+
+    for len in 0..p3.len() {
+        assert_eq!(ColorSpace::from_icc_profile(&p3[..len]), None);
+    }
+    let mut invalid = p3;
+    invalid[36] = b'b';
+    assert_eq!(ColorSpace::from_icc_profile(&invalid), None);
+}
+
+#[cfg(feature = "icc")]
+#[test]
+fn color_space_conversion() {
+    let srgb = image::open("images/450x300_rgb.png").unwrap().into_rgb8();
+
+    // Neutral colors are the same in Display P3 and sRGB
+    let gray = image::DynamicImage::ImageRgb8(srgb.clone()).into_luma8();
+    let gray = image::DynamicImage::ImageLuma8(gray).into_rgb8();
+    let options = BlockhashOptions::new().color_space(ColorSpace::DisplayP3);
+    assert_eq!(options.blockhash256(&gray), blockhash256(&gray));
+
+    // Convert the image to Display P3 with floating-point arithmetic
+    let to_linear = |c: f64| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let from_linear = |c: f64| {
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    let matrix = [
+        [0.8225, 0.1774, 0.0],
+        [0.0332, 0.9669, 0.0],
+        [0.0171, 0.0724, 0.9108],
+    ];
+    let p3 = image::RgbImage::from_fn(srgb.width(), srgb.height(), |x, y| {
+        let linear = srgb
+            .get_pixel(x, y)
+            .0
+            .map(|c| to_linear(f64::from(c) / 255.0));
+        image::Rgb(matrix.map(|row| {
+            let val: f64 = row.iter().zip(&linear).map(|(w, c)| w * c).sum();
+            (from_linear(val) * 255.0).round() as u8
+        }))
+    });
+
+    assert_eq!(options.blockhash256(&p3), blockhash256(&srgb));
+
+    // A dull red that is brighter than a gray in Display P3, but darker once
+    // converted to sRGB
+    let blocks = image::RgbImage::from_fn(16, 16, |x, y| {
+        image::Rgb(if (x / 4 + y / 4) % 2 == 0 {
+            [200, 50, 50]
+        } else {
+            [95, 95, 95]
+        })
+    });
+    let hash = u16::from(blockhash16(&blocks));
+    assert_eq!(u16::from(options.blockhash16(&blocks)), !hash);
+}
+
+#[test]
+fn premultiplied_alpha() {
+    // Channels that are multiples of 5, with alpha that divides them exactly
+    let im = image::open("images/450x300_rgb.png").unwrap().to_rgb8();
+    let straight = image::RgbaImage::from_fn(im.width(), im.height(), |x, y| {
+        let [r, g, b] = im.get_pixel(x, y).0.map(|c| c / 5 * 5);
+        let a = [0, 51, 255][((x / 30 + y / 20) % 3) as usize];
+        image::Rgba([r, g, b, a])
+    });
+    let premultiplied = image::RgbaImage::from_fn(im.width(), im.height(), |x, y| {
+        let [r, g, b, a] = straight.get_pixel(x, y).0;
+        let premultiply = |c: u8| (u32::from(c) * u32::from(a) / 255) as u8;
+        image::Rgba([premultiply(r), premultiply(g), premultiply(b), a])
+    });
+
+    let options = BlockhashOptions::new().premultiplied_alpha(true);
+    assert_eq!(
+        options.blockhash256(&premultiplied),
+        blockhash256(&straight)
+    );
+    assert_ne!(blockhash256(&premultiplied), blockhash256(&straight));
+
+    // Compositing works on the straight channels
+    let options = options.alpha_background(Some(Background::Black));
+    assert_eq!(
+        options.blockhash256(&premultiplied),
+        BlockhashOptions::new()
+            .alpha_background(Some(Background::Black))
+            .blockhash256(&straight),
+    );
+}
+
+#[test]
+fn dynamic_dispatch() {
+    use image::DynamicImage;
+
+    // A dark 16-bit gradient, which is black when converted to 8 bits
+    let dark = DynamicImage::ImageLuma16(image::ImageBuffer::from_fn(64, 64, |x, y| {
+        image::Luma([(x + y) as u16])
+    }));
+
+    for im in &[image::open("images/241x159_ya.png").unwrap(), dark.clone()] {
+        let variants = [
+            DynamicImage::ImageLuma8(im.to_luma8()),
+            DynamicImage::ImageLumaA8(im.to_luma_alpha8()),
+            DynamicImage::ImageRgb8(im.to_rgb8()),
+            DynamicImage::ImageRgba8(im.to_rgba8()),
+            DynamicImage::ImageLuma16(im.to_luma16()),
+            DynamicImage::ImageLumaA16(im.to_luma_alpha16()),
+            DynamicImage::ImageRgb16(im.to_rgb16()),
+            DynamicImage::ImageRgba16(im.to_rgba16()),
+            DynamicImage::ImageRgb32F(im.to_rgb32f()),
+            DynamicImage::ImageRgba32F(im.to_rgba32f()),
+        ];
+
+        for im in &variants {
+            assert_eq!(blockhash16_dynamic(im), blockhash16(im));
+            assert_eq!(blockhash64_dynamic(im), blockhash64(im));
+            assert_eq!(blockhash144_dynamic(im), blockhash144(im));
+            assert_eq!(blockhash256_dynamic(im), blockhash256(im));
+            assert_eq!(blockhash1024_dynamic(im), blockhash1024(im));
+        }
+    }
+
+    // The full precision of a 16-bit image is used by hashing its buffer
+    let buffer = dark.as_luma16().unwrap();
+    assert_ne!(blockhash16(buffer), blockhash16_dynamic(&dark));
+}
+
+#[test]
+fn sampled_matches_raster() {
+    struct Nearest(image::RgbImage);
+
+    impl Sampler for Nearest {
+        const MAX_BRIGHTNESS: u32 = 765;
+
+        fn sample(&self, x: f64, y: f64) -> u32 {
+            let px = (x * f64::from(self.0.width())) as u32;
+            let py = (y * f64::from(self.0.height())) as u32;
+            let image::Rgb([r, g, b]) = *self.0.get_pixel(px, py);
+            u32::from(r) + u32::from(g) + u32::from(b)
+        }
+    }
+
+    let im = image::open("images/26x17_rgb.png").unwrap().into_rgb8();
+    let expected = blockhash144(&im);
+    let (width, height) = im.dimensions();
+    assert_eq!(
+        blockhash144(&Sampled::new(Nearest(im), width, height)),
+        expected,
+    );
+}
+
+#[test]
+fn frames_match_individual_hashes() {
+    let ims: Vec<_> = ["images/16x16_rgb.png", "images/4x4_rgb.png"]
+        .iter()
+        .map(|path| {
+            image::open(path)
+                .unwrap()
+                .resize_exact(40, 30, image::imageops::FilterType::Triangle)
+                .into_rgba8()
+        })
+        .collect();
+
+    let mut data = Vec::new();
+    for _ in 0..10 {
+        for im in &ims {
+            data.extend_from_slice(im.as_raw());
+        }
+    }
+
+    let hashes: Vec<Blockhash256> = hash_frames::<_, _, 4>(&data, 40, 30);
+    assert_eq!(hashes.len(), 20);
+    for (i, hash) in hashes.iter().enumerate() {
+        assert_eq!(*hash, blockhash256(&ims[i % 2]));
+    }
+}
+
+#[test]
+fn blocklist_capacity() {
+    let mut list = Blocklist::<Blockhash16, 2>::default();
+    assert!(list.is_empty());
+    assert_eq!(list.insert(Blockhash16::from(0xff00)), Ok(()));
+    assert_eq!(list.insert(Blockhash16::from(0x00ff)), Ok(()));
+    assert_eq!(
+        list.insert(Blockhash16::from(0xffff)),
+        Err(Blockhash16::from(0xffff)),
+    );
+    assert_eq!(list.len(), 2);
+    assert_eq!(list.get(1), Some(&Blockhash16::from(0x00ff)));
+    assert_eq!(list.get(2), None);
+
+    // Equally distant from both, so the first is returned
+    assert_eq!(list.nearest(&Blockhash16::from(0xf00f), 8), Some((0, 8)));
+    assert!(list.contains_near(&Blockhash16::from(0x01ff), 1));
+    assert!(!list.contains_near(&Blockhash16::from(0x0fff), 3));
+}
+
+#[test]
+fn aspect_ratio_independent() {
+    use image::imageops::FilterType;
+
+    for path in &["images/26x17_rgb.png", "images/450x300_rgb.png"] {
+        let im = image::open(path).unwrap();
+        let (width, height) = (im.width(), im.height());
+        let wide = im.resize_exact(width * 3, height, FilterType::Nearest);
+        let tall = im.resize_exact(width, height * 2, FilterType::Nearest);
+
+        assert_eq!(blockhash256(&wide), blockhash256(&im));
+        assert_eq!(blockhash256(&tall), blockhash256(&im));
+        assert_eq!(blockhash144(&wide), blockhash144(&im));
+    }
+}
+
+#[test]
+fn letterbox_detection() {
+    let hash: Blockhash64 = "00000000003c3c00".parse().unwrap();
+    assert_eq!(
+        ActiveRegion::detect(&hash),
+        ActiveRegion {
+            left: 2,
+            top: 5,
+            right: 6,
+            bottom: 7,
+        },
+    );
+
+    let hash: Blockhash64 = "ffffffffffffffff".parse().unwrap();
+    assert_eq!(
+        ActiveRegion::detect(&hash),
+        ActiveRegion::full::<Blockhash64>()
+    );
+
+    // The same content, with and without bars at the top and bottom
+    let a: Blockhash64 = "0f0f0f0ff0f0f0f0".parse().unwrap();
+    let b: Blockhash64 = "ffff0f0ff0f0ffff".parse().unwrap();
+    let dist = letterbox_distance(&a, &b);
+    assert_eq!(
+        (dist.distance(), dist.compared(), dist.scaled()),
+        (0, 32, 0)
+    );
+    assert_eq!(a.distance(&b), 16);
+
+    let a: Blockhash16 = "0f00".parse().unwrap();
+    assert_eq!(
+        ActiveRegion::detect(&a),
+        ActiveRegion {
+            left: 0,
+            top: 1,
+            right: 4,
+            bottom: 2,
+        },
+    );
+
+    // Regions built by hand may be inverted, and are then empty
+    let inverted = ActiveRegion {
+        left: 3,
+        top: 0,
+        right: 1,
+        bottom: 4,
+    };
+    assert_eq!(inverted.area(), 0);
+    assert_eq!(
+        inverted
+            .intersection(&ActiveRegion::full::<Blockhash16>())
+            .area(),
+        0
+    );
+}
+
+#[test]
+fn localize_changes_regions() {
+    let a = Blockhash64::from(0);
+    // Two separate regions: an L shape in the top left, and the bottom right
+    // block
+    let b = Blockhash64::from(0xc080_0000_0000_0001);
+
+    assert_eq!(
+        localize_changes(&a, &b, 100, 50),
+        [
+            BlockRect {
+                x: 0,
+                y: 0,
+                width: 25,
+                height: 13,
+            },
+            BlockRect {
+                x: 87,
+                y: 43,
+                width: 13,
+                height: 7,
+            },
+        ],
+    );
+    assert!(localize_changes(&a, &a, 100, 50).is_empty());
+}
+
+#[test]
+fn hash_index_matches_linear_search() {
+    let mut rng = XorShift(0x1234_5678_u64);
+    let mut next = || Blockhash64::from(rng.next());
+
+    let hashes: Vec<_> = (0..500).map(|_| next()).collect();
+    let index: HashIndex<_> = hashes.iter().copied().collect();
+
+    for _ in 0..20 {
+        let query = next();
+        for &max_distance in &[0, 16, 28, 64] {
+            let mut expected: Vec<_> = hashes
+                .iter()
+                .enumerate()
+                .map(|(i, hash)| (i, hash.distance(&query)))
+                .filter(|&(_, dist)| dist <= max_distance)
+                .collect();
+            expected.sort_by_key(|&(i, dist)| (dist, i));
+
+            assert_eq!(index.find(&query, max_distance), expected);
+            assert_eq!(
+                index.nearest(&query, max_distance),
+                expected.first().copied(),
+            );
+        }
+    }
+}
+
+#[test]
+fn leakage_in_files() {
+    let train = ["images/example.png", "images/512x512_rgb.png"];
+    let test = ["images/450x300_rgb.png", "images/example-gray.png"];
+
+    let report = find_leakage_in_files::<Blockhash64, _>(&train, &test, 4).unwrap();
+    assert_eq!(report.leaked(), [1]);
+    assert_eq!(report.matches()[0].a, 0);
+    assert_eq!(report.leaked_fraction(), 0.5);
+
+    assert!(find_leakage_in_files::<Blockhash64, _>(&train, &["missing.png"], 4).is_err());
+}
+
+#[test]
+fn burst_representative() {
+    let frames: Vec<_> = [0x0000, 0x0001, 0x0003, 0xff00, 0xff01]
+        .iter()
+        .enumerate()
+        .map(|(i, &hash)| (i as u64 * 100, Blockhash16::from(hash)))
+        .collect();
+
+    let bursts = summarize_bursts(&frames, 1, 100);
+    assert_eq!(bursts.len(), 2);
+    assert_eq!((bursts[0].start, bursts[0].end), (0, 3));
+    assert_eq!(bursts[0].representative, 1);
+    assert_eq!(bursts[1].len(), 2);
+    assert_eq!(bursts[1].representative, 3);
+
+    // The gap between frames is too long
+    assert_eq!(summarize_bursts(&frames, 1, 99).len(), frames.len());
+    assert!(summarize_bursts::<Blockhash16>(&[], 1, 100).is_empty());
+
+    // A long run picks the same frame as comparing every pair of frames
+    let frames: Vec<_> = (0..500_u32)
+        .map(|i| {
+            let noise = (i + 1).wrapping_mul(0x9e37_79b9).rotate_left(i % 32);
+            (
+                u64::from(i),
+                Blockhash64::from(0xf0f0_0000_0000_0f0f ^ u64::from(noise & noise >> 7)),
+            )
+        })
+        .collect();
+    let bursts = summarize_bursts(&frames, 64, 1);
+    assert_eq!(bursts.len(), 1);
+    let total = |a: &Blockhash64| -> u32 { frames.iter().map(|(_, b)| a.distance(b)).sum() };
+    let expected = (0..frames.len())
+        .min_by_key(|&i| total(&frames[i].1))
+        .unwrap();
+    assert_eq!(bursts[0].representative, expected);
+}
+
+#[test]
+fn lecture_slides() {
+    let first = image::open("images/example.png").unwrap().to_rgb8();
+    let second = image::imageops::resize(
+        &image::open("images/450x300_rgb.png").unwrap().to_rgb8(),
+        320,
+        192,
+        image::imageops::FilterType::Triangle,
+    );
+
+    // A point of the first slide appears partway through
+    let mut built = first.clone();
+    for (x, y, pixel) in built.enumerate_pixels_mut() {
+        if (200..260).contains(&x) && (150..160).contains(&y) {
+            *pixel = image::Rgb([255, 255, 255]);
+        }
+    }
+    let mut fade = first.clone();
+    image::imageops::overlay(&mut fade, &second, 0, 0);
+    for (pixel, &other) in fade.pixels_mut().zip(first.pixels()) {
+        for (a, b) in pixel.0.iter_mut().zip(other.0) {
+            *a = ((u16::from(*a) + u16::from(b)) / 2) as u8;
+        }
+    }
+
+    let sequence = [
+        &first, &first, &first, &built, &built, &fade, &second, &second, &second, &built, &built,
+        &built,
+    ];
+    let frames: Vec<_> = sequence
+        .iter()
+        .enumerate()
+        .map(|(i, img)| (i as u64 * 1000, blockhash64(*img)))
+        .collect();
+
+    let slides = detect_slides(&frames, 6, 1500);
+    assert_eq!(slides.len(), 2);
+    assert_eq!(slides[0].ranges, [0..5000, 9000..11000]);
+    assert_eq!(slides[0].frame, 0);
+    assert_eq!(slides[1].ranges.len(), 1);
+    assert_eq!(slides[1].ranges[0], 6000..9000);
+    assert_eq!(slides[1].hash, blockhash64(&second));
+    assert_eq!(slides[0].duration() + slides[1].duration(), 10000);
+
+    assert_eq!(detect_slides(&frames, 6, 5000).len(), 1);
+    assert!(detect_slides::<Blockhash64>(&[], 6, 0).is_empty());
+}
+
+#[test]
+fn gradient_lighting() {
+    let im = image::open("images/example.png").unwrap().to_rgb8();
+    let other = image::open("images/450x300_rgb.png").unwrap().to_rgb8();
+
+    // The sun comes out over the right of the scene
+    let mut lit = im.clone();
+    for (x, _, pixel) in lit.enumerate_pixels_mut() {
+        let gain = 0.35 + 0.65 * x as f32 / im.width() as f32;
+        for channel in &mut pixel.0 {
+            *channel = (f32::from(*channel) * gain) as u8;
+        }
+    }
+
+    let hash = |img: &image::RgbImage| blockhash64(&Gradient::new(img));
+    assert!(hash(&lit).distance(&hash(&im)) <= 6);
+    assert!(hash(&other).distance(&hash(&im)) > 6);
+
+    // Without the adapter, the change in lighting is a large difference
+    assert!(blockhash64(&lit).distance(&blockhash64(&im)) > 6);
+}
+
+#[test]
+fn watermark_corners() {
+    let a = Blockhash16::from(0x0000);
+    let b = Blockhash16::from(0x8001);
+
+    assert_eq!(
+        watermark_distance(&a, &b, Corner::TopLeft, 1, 1).distance(),
+        1
+    );
+    assert_eq!(
+        watermark_distance(&a, &b, Corner::BottomRight, 1, 1).distance(),
+        1
+    );
+    assert_eq!(
+        watermark_distance(&a, &b, Corner::TopRight, 3, 4).distance(),
+        1
+    );
+    assert_eq!(
+        watermark_distance(&a, &b, Corner::BottomLeft, 3, 4).distance(),
+        1
+    );
+
+    let all = watermark_distance(&a, &b, Corner::TopLeft, 10, 10);
+    assert_eq!((all.distance(), all.compared(), all.scaled()), (0, 0, 0));
+}
+
+#[test]
+fn caption_strips() {
+    let img = image::open("images/example.png").unwrap().to_rgb8();
+    let (width, height) = img.dimensions();
+    assert_eq!(CaptionStrips::detect(&img), CaptionStrips::default());
+
+    // Add a black caption bar with white text to the bottom of the image
+    let mut captioned = image::RgbImage::new(width, height + 40);
+    image::imageops::replace(&mut captioned, &img, 0, 0);
+    for y in (height + 10)..(height + 30) {
+        for x in (20..(width - 20)).step_by(4) {
+            captioned.put_pixel(x, y, image::Rgb([255, 255, 255]));
+        }
+    }
+
+    let strips = CaptionStrips::detect(&captioned);
+    assert_eq!(strips, CaptionStrips { top: 0, bottom: 40 });
+    assert_eq!(
+        blockhash256(&strips.exclude(&captioned)),
+        blockhash256(&img),
+    );
+
+    // A bar without any text isn't detected
+    let padded = image::RgbImage::from_fn(width, height + 40, |x, y| {
+        if y < height {
+            *img.get_pixel(x, y)
+        } else {
+            image::Rgb([0, 0, 0])
+        }
+    });
+    assert_eq!(CaptionStrips::detect(&padded), CaptionStrips::default());
+}
+
+#[test]
+fn hash_stats() {
+    let options = BlockhashOptions::new();
+
+    for &(path, accumulation) in &[
+        ("images/256x256_rgb.png", Accumulation::Aligned),
+        ("images/450x300_rgb.png", Accumulation::Larger),
+        ("images/3x20_rgb.png", Accumulation::Generic),
+    ] {
+        let img = image::open(path).unwrap();
+        let (hash, stats) = options.blockhash256_with_stats(&img);
+
+        assert_eq!(hash, blockhash256(&img));
+        assert_eq!(stats.accumulation(), accumulation);
+        assert_eq!(
+            stats.pixels(),
+            u64::from(img.width()) * u64::from(img.height())
+        );
+        assert!(stats.elapsed().is_some());
+        assert!(!stats.quantized());
+    }
+
+    let img = image::open("images/256x256_rgb16.png")
+        .unwrap()
+        .into_rgb16();
+    let (_, stats) = options
+        .quantize_to_8_bits(true)
+        .blockhash64_with_stats(&img);
+    assert!(stats.quantized());
+}
+
+#[test]
+fn forced_accumulation() {
+    for path in &[
+        "images/256x256_rgb.png",
+        "images/450x300_rgb.png",
+        "images/26x17_rgb.png",
+        "images/3x20_rgb.png",
+    ] {
+        let img = image::open(path).unwrap();
+        let expected = blockhash256(&img);
+
+        for &accumulation in &[
+            Accumulation::Aligned,
+            Accumulation::Larger,
+            Accumulation::Generic,
+        ] {
+            let options = BlockhashOptions::new().accumulation(Some(accumulation));
+            let (hash, stats) = options.blockhash256_with_stats(&img);
+            assert_eq!(hash, expected);
+
+            if accumulation == Accumulation::Generic || stats.accumulation() != accumulation {
+                assert_eq!(stats.accumulation(), Accumulation::Generic);
+            }
+        }
+    }
+}
+
+#[test]
+fn bit_order_round_trip() {
+    let img = image::open("images/example.png").unwrap();
+    let hash = blockhash144(&img);
+
+    for &order in &[
+        BitOrder::RowMajorMsbFirst,
+        BitOrder::RowMajorLsbFirst,
+        BitOrder::ColumnMajorMsbFirst,
+        BitOrder::ColumnMajorLsbFirst,
+    ] {
+        let bytes = hash.to_bytes_ordered(order);
+        assert_eq!(Blockhash144::from_bytes_ordered(bytes, order), hash);
+    }
+
+    assert_eq!(
+        hash.to_bytes_ordered(BitOrder::default()),
+        <[u8; 18]>::from(hash),
+    );
+
+    // Reversing the bits of each byte converts between MSB- and LSB-first
+    let msb = hash.to_bytes_ordered(BitOrder::ColumnMajorMsbFirst);
+    let lsb = hash.to_bytes_ordered(BitOrder::ColumnMajorLsbFirst);
+    assert_eq!(msb.map(u8::reverse_bits), lsb);
+}
+
+#[test]
+fn parse_ascii_padding() {
+    let hash = Blockhash64::from(0xc7c4_8f89_89c7_7e0c);
+
+    assert_eq!(Blockhash64::from_hex_bytes(b"c7c48f8989c77e0c"), Ok(hash));
+    assert_eq!(Blockhash64::from_hex_bytes(b"C7C48F8989C77E0C"), Ok(hash));
+    assert_eq!(Blockhash64::from_ascii(b"c7c48f8989c77e0c"), Ok(hash));
+    assert_eq!(Blockhash64::from_ascii(b"c7c48f8989c77e0c \0 \0"), Ok(hash));
+
+    assert!(Blockhash64::from_hex_bytes(b"c7c48f8989c77e0c\0").is_err());
+    assert!(Blockhash64::from_ascii(b" c7c48f8989c77e0c").is_err());
+    assert!(Blockhash64::from_ascii(b"c7c48f89 89c77e0c").is_err());
+    assert!(Blockhash64::from_ascii(b"c7c48f8989c77e\0\0").is_err());
+    assert!(Blockhash64::from_ascii(b"\0\0\0\0").is_err());
+    assert!(Blockhash64::from_ascii(b"").is_err());
+}
+
+#[test]
+fn bundle_errors() {
+    let img = image::open("images/example.png").unwrap();
+    let hashes = [
+        AnyBlockhash::from(blockhash256(&img)),
+        AnyBlockhash::from(blockhash144(&img)),
+        AnyBlockhash::from(blockhash16(&img)),
+    ];
+    let bundle = concat_digests(&hashes);
+    assert_eq!(split_digests(&bundle), Ok(hashes.to_vec()));
+    assert_eq!(split_digests(&concat_digests(&[])), Ok(Vec::new()));
+
+    assert_eq!(split_digests(b"BH"), Err(BundleError::InvalidHeader));
+    assert_eq!(
+        split_digests(b"XY\x01\x00\x00"),
+        Err(BundleError::InvalidHeader)
+    );
+    assert_eq!(
+        split_digests(b"BH\x02\x00\x00"),
+        Err(BundleError::UnsupportedVersion(2)),
+    );
+    assert_eq!(
+        split_digests(b"BH\x01\x00\x01\x05\x00"),
+        Err(BundleError::UnknownSize(5)),
+    );
+    assert_eq!(
+        split_digests(&bundle[..(bundle.len() - 1)]),
+        Err(BundleError::Truncated),
+    );
+
+    let mut extra = bundle.clone();
+    extra.push(0);
+    assert_eq!(split_digests(&extra), Err(BundleError::TrailingBytes));
+}
+
+#[test]
+fn bucket_code_sizes() {
+    // Every size of hash is reduced to the same grid of cells, so a pattern
+    // that is aligned to the cells gives the same code for every size
+    let stripes = |bits: u32| -> String {
+        let columns = (bits as f64).sqrt() as u32;
+        let mut bytes = vec![0_u8; bits as usize / 8];
+        for y in 0..columns {
+            for x in 0..columns {
+                if (x * 2 / columns) == (y * 2 / columns) {
+                    let idx = (y * columns + x) as usize;
+                    bytes[idx / 8] |= 0x80 >> (idx % 8);
+                }
+            }
+        }
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    };
+
+    let expected = 0xf0f0_0f0f;
+    let hash16: Blockhash16 = stripes(16).parse().unwrap();
+    let hash64: Blockhash64 = stripes(64).parse().unwrap();
+    let hash144: Blockhash144 = stripes(144).parse().unwrap();
+    let hash256: Blockhash256 = stripes(256).parse().unwrap();
+
+    assert_eq!(bucket_code(&hash16), expected);
+    assert_eq!(bucket_code(&hash64), expected);
+    assert_eq!(bucket_code(&hash144), expected);
+    assert_eq!(bucket_code(&hash256), expected);
+}
+
+// Checks the collision rates given in the documentation of `bucket_code`.
+// Run with `cargo test --release -- --ignored bucket_code_collisions`
+#[test]
+#[ignore]
+fn bucket_code_collisions() {
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d_u64);
+
+    // Synthetic images, each a sum of a few random soft spots
+    let hashes: Vec<Blockhash256> = (0..2000)
+        .map(|_| {
+            let mut data = vec![0_u32; 64 * 64];
+            for _ in 0..3 + rng.below(4) {
+                let (cx, cy) = (rng.below(64) as i32, rng.below(64) as i32);
+                let radius = 4 + rng.below(20) as i32;
+                let amplitude = 64 + rng.below(192);
+                for (i, val) in data.iter_mut().enumerate() {
+                    let (dx, dy) = (i as i32 % 64 - cx, i as i32 / 64 - cy);
+                    let falloff = radius * radius - dx * dx - dy * dy;
+                    if falloff > 0 {
+                        *val += amplitude * falloff as u32 / (radius * radius) as u32;
+                    }
+                }
+            }
+            let data: Vec<u8> = data.iter().map(|&val| val.min(255) as u8).collect();
+            blockhash256(&RawImage::<_, 1>::new(&data, 64, 64))
+        })
+        .collect();
+
+    let mut unchanged = Vec::new();
+    for &flips in &[1, 4, 8, 16, 32] {
+        let mut same = 0;
+        for _ in 0..100_000 {
+            let hash = hashes[rng.below(2000) as usize];
+            let mut bytes = <[u8; 32]>::from(hash);
+            let mut chosen = [false; 256];
+            let mut count = 0;
+            while count < flips {
+                let idx = rng.below(256) as usize;
+                if !chosen[idx] {
+                    chosen[idx] = true;
+                    bytes[idx / 8] ^= 0x80 >> (idx % 8);
+                    count += 1;
+                }
+            }
+            same += u32::from(bucket_code(&Blockhash256::from(bytes)) == bucket_code(&hash));
+        }
+        // Per mille, rounded to the nearest
+        unchanged.push((same + 50) / 100);
+    }
+
+    let mut codes: Vec<_> = hashes.iter().map(bucket_code).collect();
+    codes.sort_unstable();
+    codes.dedup();
+
+    assert_eq!(unchanged, [929, 752, 577, 349, 127]);
+    assert_eq!(codes.len(), 1926);
+}
+
+#[test]
+fn phash_robustness() {
+    use image::imageops::FilterType;
+
+    let img = image::open("images/example.png").unwrap();
+    let hash = phash64(&img);
+
+    let resized = img.resize_exact(173, 97, FilterType::Triangle);
+    assert!(phash64(&resized).distance(&hash) <= 4);
+
+    let brighter = img.brighten(40);
+    assert!(phash64(&brighter).distance(&hash) <= 4);
+
+    let other = image::open("images/512x512_rgb.png").unwrap();
+    assert!(phash64(&other).distance(&hash) > 16);
+
+    // Tiny images use the generic accumulation path
+    let tiny = image::open("images/5x2_rgb.png").unwrap();
+    assert_eq!(
+        phash256(&tiny),
+        phash256(&tiny.resize_exact(10, 4, FilterType::Nearest))
+    );
+}
+
+#[test]
+fn popcount_table() {
+    for byte in 0..=u8::MAX {
+        assert_eq!(popcount::count_ones_table(byte), byte.count_ones());
+    }
+}
+
+#[test]
+fn frozen_index_matches_hash_index() {
+    let mut rng = XorShift(0x8765_4321_u64);
+    let mut next = || Blockhash64::from(rng.next());
+
+    let hashes: Vec<_> = (0..500).map(|_| next()).collect();
+    let index: HashIndex<_> = hashes.iter().copied().collect();
+
+    let builder = IndexBuilder::new(3);
+    for (i, &hash) in hashes.iter().enumerate() {
+        builder.insert(i, hash);
+    }
+    let frozen = builder.freeze();
+    assert_eq!(frozen.len(), hashes.len());
+
+    for _ in 0..20 {
+        let query = next();
+        for &max_distance in &[0, 20, 28, 64] {
+            assert_eq!(
+                frozen.find(&query, max_distance),
+                index.find(&query, max_distance),
+            );
+            assert_eq!(
+                frozen.nearest(&query, max_distance),
+                index.nearest(&query, max_distance),
+            );
+        }
+    }
+
+    let empty = IndexBuilder::<Blockhash64>::new(2).freeze();
+    assert!(empty.is_empty());
+    assert_eq!(empty.nearest(&hashes[0], 64), None);
+}
+
+#[test]
+fn ahash_threshold() {
+    // A uniform image has no blocks above the mean
+    let flat = image::GrayImage::from_pixel(32, 32, image::Luma([128]));
+    assert_eq!(ahash64(&flat), Blockhash64::from(0));
+
+    // The left quarter is brighter than the mean, and the rest is darker
+    let img =
+        image::GrayImage::from_fn(64, 64, |x, _| image::Luma([if x < 16 { 200 } else { 100 }]));
+    assert_eq!(ahash64(&img), Blockhash64::from(0xc0c0_c0c0_c0c0_c0c0));
+    assert_eq!(ahash64(&img), ahash64(&Region::new(&img, 0, 0, 64, 63)));
+
+    let photo = image::open("images/example.png").unwrap();
+    assert!(ahash256(&photo.brighten(20)).distance(&ahash256(&photo)) <= 8);
+}
+
+#[test]
+fn index_memory_usage() {
+    use std::mem::size_of;
+
+    let hashes: Vec<_> = (0..1000_u64)
+        .map(|i| Blockhash256::from([(i % 256) as u8; 32]))
+        .collect();
+
+    let index: HashIndex<_> = hashes.iter().copied().collect();
+    assert!(index.memory_usage() >= 1000 * 32);
+    assert!(HashIndex::<Blockhash256>::new().memory_usage() < index.memory_usage());
+
+    let builder = IndexBuilder::new(2);
+    for (i, &hash) in hashes.iter().enumerate() {
+        builder.insert(i, hash);
+    }
+    let frozen = builder.freeze();
+
+    // The frozen index stores each hash and ID, and one edge per hash other
+    // than the root of each tree
+    let min = 1000 * (32 + size_of::<usize>() + 8) + 998 * 8;
+    assert!(frozen.memory_usage() >= min);
+    assert!(frozen.memory_usage() < index.memory_usage());
+}
+
+#[test]
+fn crop_resistant_matching() {
+    let img = image::open("images/example.png").unwrap();
+    let other = image::open("images/512x512_rgb.png").unwrap();
+    let hashes = crop_resistant_hash(&img);
+    assert!(!hashes.is_empty());
+
+    // Cropped and padded copies still match, even though their blockhashes
+    // are very different
+    let cropped = img.crop_imm(42, 0, 200, 192);
+    let mut padded = image::RgbImage::new(300, 260);
+    image::imageops::replace(&mut padded, &img.to_rgb8(), 30, 10);
+
+    for copy in &[cropped, image::DynamicImage::ImageRgb8(padded)] {
+        assert!(blockhash64(copy).distance(&blockhash64(&img)) > 10);
+        assert!(count_matching_segments(&hashes, &crop_resistant_hash(copy), 10) >= 1);
+    }
+
+    assert_eq!(
+        count_matching_segments(&hashes, &crop_resistant_hash(&other), 10),
+        0,
+    );
+}
+
+#[test]
+fn static_index_matches_hash_index() {
+    let hashes: Vec<_> = (0..500_u64)
+        .map(|i| Blockhash64::from(i.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+        .collect();
+    let index: HashIndex<_> = hashes.iter().copied().collect();
+    let bytes = StaticIndex::compile(hashes.iter().copied());
+    let loaded = StaticIndex::<Blockhash64>::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.len(), 500);
+
+    for query in hashes.iter().step_by(25) {
+        assert_eq!(loaded.find(query, 24), index.find(query, 24));
+        assert_eq!(loaded.nearest(query, 24), index.nearest(query, 24));
+    }
+
+    assert_eq!(
+        StaticIndex::<Blockhash256>::from_bytes(&bytes).unwrap_err(),
+        StaticIndexError::WrongSize(8, 8),
+    );
+    assert_eq!(
+        StaticIndex::<Blockhash64>::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+        StaticIndexError::InvalidLength,
+    );
+
+    // An edge pointing back at the root would make searches loop forever
+    let mut corrupt = bytes.clone();
+    let last = corrupt.len() - 4;
+    corrupt[last..].copy_from_slice(&0_u32.to_be_bytes());
+    assert_eq!(
+        StaticIndex::<Blockhash64>::from_bytes(&corrupt).unwrap_err(),
+        StaticIndexError::Corrupt,
+    );
+
+    let empty = StaticIndex::<Blockhash16>::compile(None);
+    let empty = StaticIndex::<Blockhash16>::from_bytes(&empty).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty.nearest(&Blockhash16::from(0), 16), None);
+}
+
+#[test]
+fn static_index_custom_digests() {
+    // The last byte of each 36-bit hash is padded
+    define_blockhash!(B36, 6);
+    let hashes: Vec<_> = (0..40_u8)
+        .map(|i| B36::from([i, i.wrapping_mul(37), 0x5a ^ i, i.wrapping_mul(11), i << 4]))
+        .collect();
+    let bytes = StaticIndex::compile(hashes.iter().copied());
+    let loaded = StaticIndex::<B36>::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.len(), 40);
+    let index: HashIndex<_> = hashes.iter().copied().collect();
+    for query in &hashes {
+        assert_eq!(loaded.find(query, 8), index.find(query, 8));
+        assert_eq!(
+            loaded.nearest(query, 0).map(|(_, dist)| dist),
+            Some(query.distance(query)),
+        );
+    }
+
+    // Grids with the same number of columns are told apart by their rows
+    define_blockhash!(B16x9, 16, 9);
+    let hash = B16x9::from([0; 18]);
+    let bytes = StaticIndex::compile([hash]);
+    let loaded = StaticIndex::<B16x9>::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.find(&hash, 0), [(0, hash.distance(&hash))]);
+    assert_eq!(
+        StaticIndex::<Blockhash256>::from_bytes(&bytes).unwrap_err(),
+        StaticIndexError::WrongSize(16, 9),
+    );
+}
+
+#[test]
+fn index_file_round_trip() {
+    let entries: Vec<_> = (0..300_u64)
+        .map(|i| (i % 7, Blockhash144::from([(i * 37 % 256) as u8; 18])))
+        .collect();
+    let file = IndexFile::new(HashAlgorithm::PerceptualHash, entries.iter().copied());
+
+    let mut bytes = Vec::new();
+    file.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 1036 + 300 * 26 + 4);
+    assert_eq!(IndexFile::read_from(&bytes[..]).unwrap(), file);
+
+    for &(id, hash) in &entries {
+        let found = file.lookup(&hash.into());
+        assert!(found.iter().any(|&(i, _)| i == id));
+        assert!(found.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+    assert!(file.lookup(&Blockhash16::from(0).into()).is_empty());
+
+    let mut unsupported = bytes.clone();
+    unsupported[4] = 2;
+    assert!(matches!(
+        IndexFile::read_from(&unsupported[..]),
+        Err(IndexFileError::UnsupportedVersion(2)),
+    ));
+
+    let mut unknown = bytes.clone();
+    unknown[5] = 9;
+    assert!(matches!(
+        IndexFile::read_from(&unknown[..]),
+        Err(IndexFileError::UnknownAlgorithm(9)),
+    ));
+
+    // Swapping the IDs of the first two entries breaks the sort order
+    let mut unsorted = bytes.clone();
+    unsorted[1036 + 18..1036 + 26].copy_from_slice(&u64::MAX.to_be_bytes());
+    assert!(matches!(
+        IndexFile::read_from(&unsorted[..]),
+        Err(IndexFileError::Corrupt),
+    ));
+
+    assert!(matches!(
+        IndexFile::read_from(&bytes[..bytes.len() - 1]),
+        Err(IndexFileError::Io(_)),
+    ));
+}
+
+#[test]
+fn index_file_verify() {
+    let file = IndexFile::new(
+        HashAlgorithm::Blockhash,
+        [
+            (1, Blockhash16::from(0x0100)),
+            (2, Blockhash16::from(0x0200)),
+            (3, Blockhash16::from(0x0200)),
+        ],
+    );
+    let mut bytes = Vec::new();
+    file.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 1070);
+
+    let verification = IndexFile::verify(&bytes[..]).unwrap();
+    assert!(verification.is_valid());
+    assert_eq!(verification.version(), 1);
+    assert_eq!(verification.entries(), 3);
+
+    let problems = |bytes: &[u8]| {
+        let verification = IndexFile::verify(bytes).unwrap();
+        verification.problems().to_vec()
+    };
+    let is_checksum_mismatch = |problem: &Corruption| {
+        problem.offset == 1066
+            && matches!(problem.kind, CorruptionKind::ChecksumMismatch { stored, computed } if stored != computed)
+    };
+
+    // The third entry has the same hash as the second but a smaller ID
+    let mut unsorted = bytes.clone();
+    unsorted[1055] = 9;
+    let found = problems(&unsorted);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].offset, 1056);
+    assert_eq!(found[0].kind, CorruptionKind::Unsorted);
+    assert!(is_checksum_mismatch(&found[1]));
+
+    // Every hash is in a bucket before 7, so it starts after the last entry
+    let mut bucket = bytes.clone();
+    bucket[12 + 7 * 4 + 3] = 1;
+    let found = problems(&bucket);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].offset, 40);
+    assert_eq!(
+        found[0].kind,
+        CorruptionKind::BucketMismatch {
+            bucket: 7,
+            stored: 1,
+            expected: 3,
+        },
+    );
+    assert!(is_checksum_mismatch(&found[1]));
+
+    let truncated = |len: usize, offset: u64| {
+        let found = problems(&bytes[..len]);
+        found.len() == 1 && found[0].offset == offset && found[0].kind == CorruptionKind::Truncated
+    };
+    assert!(truncated(1060, 1056));
+    assert!(truncated(1068, 1066));
+    assert!(truncated(100, 12));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    let found = problems(&trailing);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].offset, 1070);
+    assert_eq!(found[0].kind, CorruptionKind::TrailingBytes);
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = 0;
+    assert!(IndexFile::verify(&bad_magic[..]).is_err());
+}
+
+#[test]
+fn mirrored_hashes() {
+    // 320x192 is a multiple of the 8x8 and 16x16 grids, so flipping the hash
+    // is exact
+    let img = image::open("images/example.png").unwrap();
+    let (h, v) = (img.fliph(), img.flipv());
+
+    assert_eq!(blockhash64(&h), blockhash64(&img).flip_horizontal());
+    assert_eq!(blockhash64(&v), blockhash64(&img).flip_vertical());
+    assert_eq!(blockhash256(&h), blockhash256(&img).flip_horizontal());
+    assert_eq!(blockhash256(&v), blockhash256(&img).flip_vertical());
+
+    let hash = blockhash144(&img);
+    assert_eq!(hash.flip_horizontal().flip_horizontal(), hash);
+    assert_eq!(hash.flip_vertical().flip_vertical(), hash);
+
+    let options = BlockhashOptions::new().normalize_mirroring(true);
+    let canonical = options.blockhash256(&img);
+    assert_eq!(options.blockhash256(&h), canonical);
+    assert_eq!(options.blockhash256(&v), canonical);
+    assert_eq!(options.blockhash256(&h.flipv()), canonical);
+    assert!(canonical <= blockhash256(&img));
+}
+
+#[test]
+fn cross_size_distance() {
+    let img = image::open("images/example.png").unwrap();
+    let other = image::open("images/512x512_rgb.png").unwrap();
+    let hashes = |img: &image::DynamicImage| -> [AnyBlockhash; 4] {
+        [
+            blockhash16(img).into(),
+            blockhash64(img).into(),
+            blockhash144(img).into(),
+            blockhash256(img).into(),
+        ]
+    };
+    let (a, b) = (hashes(&img), hashes(&other));
+
+    for x in &a {
+        for (y, z) in a.iter().zip(&b) {
+            assert_eq!(x.distance_with(y, SizePolicy::Reject), x.distance(y));
+
+            // The same image hashed at another size is always closer than a
+            // different image hashed at that size
+            for &policy in &[SizePolicy::Coarsen, SizePolicy::Scale] {
+                let same = x.distance_with(y, policy).unwrap();
+                assert_eq!(y.distance_with(x, policy), Some(same));
+                assert!(same < x.distance_with(z, policy).unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn quick_mode() {
+    let options = BlockhashOptions::new().quick(true);
+
+    // Blocks of 15x9 pixels, leaving a column and 15 rows unused
+    let im = image::open("images/241x159_ya.png").unwrap();
+    let (hash, stats) = options.blockhash256_with_stats(&im);
+    assert!(stats.quick());
+    assert_eq!(stats.pixels(), 240 * 144);
+    assert_ne!(hash, blockhash256(&im));
+    assert!(hash.distance(&blockhash256(&im)) < 64);
+
+    // Cropping the unused pixels doesn't change the hash
+    let cropped = im.crop_imm(0, 0, 240, 144);
+    assert_eq!(options.blockhash256(&cropped), hash);
+    assert_eq!(blockhash256(&cropped), hash);
+
+    // Images smaller than the grid are hashed normally
+    let im = image::open("images/1x1_rgb.png").unwrap();
+    let (hash, stats) = options.blockhash16_with_stats(&im);
+    assert!(!stats.quick());
+    assert_eq!(hash, blockhash16(&im));
+}
+
+#[test]
+fn overlapping_blocks() {
+    let options = BlockhashOptions::new().overlapping_blocks(true);
+
+    // Every strategy gives the same hash, including on a 17x17 grid of cells
+    let im = image::open("images/450x300_rgb.png").unwrap();
+    let im = im.crop_imm(0, 0, 17 * 20, 17 * 10);
+    let expected = options.blockhash256(&im);
+    for &accumulation in &[
+        Accumulation::Aligned,
+        Accumulation::Larger,
+        Accumulation::Generic,
+    ] {
+        let (hash, stats) = options
+            .accumulation(Some(accumulation))
+            .blockhash256_with_stats(&im);
+        assert_eq!(hash, expected);
+        assert_eq!(stats.accumulation(), accumulation);
+    }
+
+    // Quick mode is ignored
+    assert_eq!(options.quick(true).blockhash256(&im), expected);
+
+    // Every grid size is supported
+    let (hash, stats) = options.blockhash1024_with_stats(&im);
+    assert_eq!(options.blockhash1024(&im), hash);
+    assert_eq!(stats.pixels(), 17 * 20 * 17 * 10);
+
+    // Shifting a small image by a pixel changes fewer bits
+    let mut normal = 0;
+    let mut overlapping = 0;
+    for path in &[
+        "images/26x17_rgb.png",
+        "images/16x16_rgb.png",
+        "images/241x159_ya.png",
+    ] {
+        let im = image::open(path).unwrap();
+        let (width, height) = (im.width(), im.height());
+        let shifted = im.crop_imm(1, 0, width - 1, height);
+        normal += blockhash64(&im).distance(&blockhash64(&shifted));
+        overlapping += options
+            .blockhash64(&im)
+            .distance(&options.blockhash64(&shifted));
+    }
+    assert!(overlapping < normal, "{} >= {}", overlapping, normal);
+}
+
+#[test]
+fn redacted_hashes() {
+    let img = image::open("images/example.png").unwrap();
+    let other = image::open("images/512x512_rgb.png").unwrap();
+    let full = AnyBlockhash::from(blockhash256(&img));
+    let copy = AnyBlockhash::from(blockhash256(&img.blur(1.0)));
+    let different = AnyBlockhash::from(blockhash256(&other));
+
+    for band in 0..4 {
+        let shared = RedactedHash::keep_bands(full, band, band + 1);
+        assert_eq!(shared.known_bits(), 64);
+        assert_eq!(shared.distance(&full).unwrap().distance(), 0);
+
+        let same = shared.distance(&copy).unwrap();
+        let diff = shared.distance(&different).unwrap();
+        assert_eq!(same.compared(), 64);
+        assert!(same.scaled() < diff.scaled());
+    }
+
+    // The mask is applied on reconstruction
+    let shared = RedactedHash::new(full, *RedactedHash::truncate(full, 100).mask());
+    assert_eq!(shared, Some(RedactedHash::truncate(full, 100)));
+    assert_eq!(RedactedHash::truncate(full, 1000).known_bits(), 256);
+    assert_eq!(RedactedHash::keep_bands(full, 2, 2).known_bits(), 0);
+
+    let small = AnyBlockhash::from(blockhash16(&img));
+    assert_eq!(RedactedHash::new(full, small), None);
+    assert_eq!(RedactedHash::truncate(full, 64).distance(&small), None);
+}
+
+#[test]
+fn band_counts() {
+    let img = image::open("images/512x512_rgb.png").unwrap();
+
+    let options = BlockhashOptions::new().bands(4);
+    assert_eq!(options.blockhash16(&img), blockhash16(&img));
+    assert_eq!(options.blockhash144(&img), blockhash144(&img));
+
+    // Each band is split evenly at its median, unless some blocks are equal
+    // to the median
+    for &bands in &[1, 2, 4, 8, 16] {
+        let options = BlockhashOptions::new().bands(bands);
+        let ones = |bytes: &[u8]| bytes.iter().map(|b| b.count_ones()).sum::<u32>();
+        assert_eq!(ones(options.blockhash64(&img).as_bytes()), 32);
+        assert_eq!(ones(options.blockhash256(&img).as_bytes()), 128);
+
+        // Bands of a single block, and bands that don't cover whole rows
+        let _ = options.blockhash16(&img);
+        let _ = options.blockhash144(&img);
+    }
+}
+
+#[test]
+#[should_panic(expected = "unsupported number of bands")]
+fn unsupported_band_count() {
+    let _ = BlockhashOptions::new().bands(3);
+}
+
+#[test]
+fn invalid_options_return_errors() {
+    let options = BlockhashOptions::new();
+    assert_eq!(options.try_bands(0), Err(OptionsError::UnsupportedBands(0)),);
+    assert_eq!(options.try_bands(16), Ok(options.bands(16)));
+    assert_eq!(
+        options.try_channel_max(Some(0)),
+        Err(OptionsError::ZeroChannelMax),
+    );
+    assert_eq!(options.try_channel_max(None), Ok(options));
+    assert_eq!(
+        options.try_luma_weights(LumaWeights::Custom([0; 3])),
+        Err(OptionsError::ZeroChannelWeights),
+    );
+    assert_eq!(
+        options.try_luma_weights(LumaWeights::Custom([0, 1, 0])),
+        Ok(options.channel_weights([0, 1, 0])),
+    );
+    assert_eq!(
+        OptionsError::UnsupportedBands(3).to_string(),
+        "unsupported number of bands 3",
+    );
+}
+
+#[test]
+fn conflicting_options() {
+    let options = BlockhashOptions::new();
+    assert_eq!(options.validate(), Ok(()));
+    assert_eq!(options.quick(true).validate(), Ok(()));
+    assert_eq!(options.overlapping_blocks(true).validate(), Ok(()));
+
+    assert_eq!(
+        options.quick(true).overlapping_blocks(true).validate(),
+        Err(OptionsError::Conflicting("quick", "overlapping_blocks")),
+    );
+    assert_eq!(
+        options
+            .accumulation(Some(Accumulation::Generic))
+            .quick(true)
+            .validate(),
+        Err(OptionsError::Conflicting("quick", "accumulation")),
+    );
+
+    let background = options.alpha_background(Some(Background::Black));
+    assert_eq!(background.validate(), Ok(()));
+    assert_eq!(
+        background.transparent_color(Background::Black).validate(),
+        Err(OptionsError::Conflicting(
+            "alpha_background",
+            "transparent_color"
+        )),
+    );
+    assert_eq!(
+        OptionsError::Conflicting("quick", "overlapping_blocks").to_string(),
+        "options `quick` and `overlapping_blocks` can't be combined",
+    );
+}
+
+#[test]
+fn matcher_outcomes() {
+    let img = image::open("images/example.png").unwrap();
+    let other = image::open("images/512x512_rgb.png").unwrap();
+    let stream = [
+        (blockhash256(&img), 0),
+        (blockhash256(&other), 1),
+        (blockhash256(&img.blur(1.0)), 2),
+        (blockhash256(&img), 3),
+        (blockhash256(&other.brighten(10)), 4),
+    ];
+
+    let mut matcher = Matcher::new(24).duplicate_distance(1);
+    let outcomes: Vec<_> = stream
+        .iter()
+        .map(|&(hash, id)| matcher.offer(hash, id))
+        .collect();
+
+    assert!(outcomes[0].is_new());
+    assert!(outcomes[1].is_new());
+    assert_eq!(
+        outcomes[2],
+        MatchOutcome::NearDuplicate { of: 0, distance: 2 },
+    );
+    assert_eq!(outcomes[3], MatchOutcome::Duplicate { of: 0, distance: 0 });
+    assert_eq!(outcomes[4], MatchOutcome::Duplicate { of: 1, distance: 0 });
+    assert_eq!(matcher.kept(), [0, 1]);
+
+    // Checking doesn't keep the hash
+    assert!(matcher.check(&Blockhash256::from([0x55; 32])).is_new());
+    assert_eq!(matcher.len(), 2);
+}
+
+#[test]
+fn global_median() {
+    // Brightness increases down the image, and slightly across it
+    let img = image::GrayImage::from_fn(64, 64, |x, y| image::Luma([(y * 3 + x / 8) as u8]));
+
+    // Each band of two rows is split at its own median, so the lower row of
+    // each band is set
+    let hash = BlockhashOptions::new().bands(4).blockhash64(&img);
+    assert_eq!(hash, Blockhash64::from(0x00ff_00ff_00ff_00ff));
+
+    // With a single band, the lower half of the image is set
+    let hash = BlockhashOptions::new().bands(1).blockhash64(&img);
+    assert_eq!(hash, Blockhash64::from(0x0000_0000_ffff_ffff));
+}
+
+#[test]
+fn comparison_report() {
+    let a = image::open("images/example.png").unwrap();
+    let b = a.crop_imm(0, 0, 300, 192);
+
+    // Panels of 426, 400, and 426 pixels wide, with margins
+    let report = comparison_image::<Blockhash256>(&a, &b);
+    assert_eq!(report.dimensions(), (426 * 2 + 400 + 8 * 4, 295));
+
+    // The bar is drawn in pure red up to the distance
+    let red = |report: &image::RgbImage| {
+        report
+            .pixels()
+            .filter(|&&p| p == image::Rgb([255, 0, 0]))
+            .count()
+    };
+    assert!(red(&report) > 0);
+
+    // Identical images have an empty bar
+    let report = comparison_image::<Blockhash256>(&a, &a);
+    assert_eq!(red(&report), 0);
+}
+
+#[test]
+fn otsu_threshold() {
+    // Five rows of dark blocks of varying brightness, like text on a page,
+    // above three rows of bright blocks
+    let img = image::GrayImage::from_fn(64, 64, |x, y| {
+        let block = y / 8 * 8 + x / 8;
+        if y < 40 {
+            image::Luma([block as u8])
+        } else {
+            image::Luma([220])
+        }
+    });
+
+    // Otsu's method separates the bright blocks from the dark ones
+    let otsu = BlockhashOptions::new()
+        .bands(1)
+        .threshold(Threshold::Otsu)
+        .blockhash64(&img);
+    assert_eq!(otsu, Blockhash64::from(0x0000_0000_00ff_ffff));
+
+    // The median falls among the dark blocks, so some of them are set too
+    let median = BlockhashOptions::new()
+        .bands(1)
+        .threshold(Threshold::Median)
+        .blockhash64(&img);
+    assert_eq!(median, BlockhashOptions::new().bands(1).blockhash64(&img));
+    assert!(median.distance(&otsu) > 0);
+}
+
+#[test]
+fn match_explanation() {
+    let a = image::open("images/example.png").unwrap();
+
+    let same = explain_match::<Blockhash16, _>(&a, &a);
+    assert_eq!(same.distance, 0);
+    assert_eq!((same.columns, same.rows), (4, 4));
+    assert!(same
+        .blocks
+        .iter()
+        .all(|block| block.agrees && block.delta == 0));
+
+    // Inverting the image flips every bit, and darkens bright blocks
+    let mut b = a.clone();
+    b.invert();
+    let inverted = explain_match::<Blockhash16, _>(&a, &b);
+    assert_eq!(inverted.distance, 16);
+    assert_eq!(inverted.disagreements().count(), 16);
+    for (idx, block) in inverted.blocks.iter().enumerate() {
+        assert_eq!((block.x, block.y), (idx as u32 % 4, idx as u32 / 4));
+        assert!(block.brightness_a <= 128 || block.delta < 0);
+    }
+}
+
+#[test]
+fn ternary_hashes() {
+    let img = image::open("images/example.png").unwrap();
+
+    // With no dead zone, every block differs from the median, as in the
+    // binary hash
+    let ternary = TernaryBlockhash::from_image(&img, 0);
+    assert_eq!(Blockhash64::from(ternary), blockhash64(&img));
+    assert_eq!(
+        u64::from(ternary.brighter()) | u64::from(ternary.darker()),
+        u64::MAX
+    );
+
+    // A wider dead zone marks more blocks as similar
+    let wide = TernaryBlockhash::from_image(&img, 64);
+    let similar = |hash: &TernaryBlockhash| {
+        (0..64)
+            .filter(|&idx| hash.get(idx % 8, idx / 8) == core::cmp::Ordering::Equal)
+            .count()
+    };
+    assert!(similar(&wide) > similar(&ternary));
+
+    assert_eq!(ternary.distance(&ternary), 0);
+    assert!(TernaryBlockhash::from_planes(0x01.into(), 0x01.into()).is_none());
+}
+
+#[test]
+fn soft_hashes() {
+    let black = image::GrayImage::new(32, 32);
+    let white = image::GrayImage::from_pixel(32, 32, image::Luma([255]));
+
+    let (_, soft_black) = soft_blockhash256(&black);
+    let (_, soft_white) = soft_blockhash256(&white);
+    assert_eq!(soft_black.levels(), &[0; 256]);
+    assert_eq!(soft_white.levels(), &[255; 256]);
+    assert_eq!(soft_black.distance(&soft_white), 255 * 256);
+
+    // Small changes that don't affect the binary hash still affect the soft
+    // hash
+    let img = image::open("images/example.png").unwrap();
+    let (hash, soft) = soft_blockhash256(&img);
+    let (brighter_hash, brighter_soft) = soft_blockhash256(&img.brighten(4));
+    assert_eq!(hash, brighter_hash);
+    assert!(soft.distance(&brighter_soft) > 0);
+
+    // Images whose size isn't a multiple of the grid are scaled the same way
+    for (width, height) in [(37, 23), (5, 7)] {
+        let odd = image::GrayImage::from_pixel(width, height, image::Luma([255]));
+        assert_eq!(soft_blockhash256(&odd).1.levels(), &[255; 256]);
+    }
+}
+
+#[test]
+fn block_values() {
+    let img = image::open("images/example.png").unwrap();
+
+    let (hash, values) = blockhash16_with_values(&img);
+    assert_eq!(hash, blockhash16(&img));
+    let (hash, values64) = blockhash64_with_values(&img);
+    assert_eq!(hash, blockhash64(&img));
+    assert_eq!(blockhash144_with_values(&img).0, blockhash144(&img));
+
+    // Each block of the 16-bit grid covers four blocks of the 64-bit grid
+    for (idx, &value) in values.iter().enumerate() {
+        let (x, y) = (idx % 4 * 2, idx / 4 * 2);
+        let sum: f32 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .map(|&(dx, dy)| values64[(y + dy) * 8 + x + dx])
+            .sum();
+        assert!((value - sum / 4.0).abs() < 1e-4);
+    }
+
+    let white = image::GrayImage::from_pixel(20, 20, image::Luma([255]));
+    assert_eq!(blockhash256_with_values(&white).1, [1.0; 256]);
+}
+
+#[test]
+fn perturbations() {
+    let img = image::open("images/example.png").unwrap();
+    let hash = blockhash64(&img);
+
+    let recompressed = perturb::recompress(&img, 50);
+    assert_eq!(recompressed.dimensions(), (320, 192));
+    assert!(blockhash64(&recompressed).distance(&hash) <= 4);
+
+    let resized = perturb::resize(&img, 100, 60);
+    assert_eq!(resized.dimensions(), (100, 60));
+    assert!(blockhash64(&resized).distance(&hash) <= 4);
+
+    let padded = perturb::pad(&img, 10, 20, 30, 40, image::Rgba([0, 0, 0, 255]));
+    assert_eq!(padded.dimensions(), (360, 252));
+    assert_eq!(
+        perturb::crop(&padded, 10, 20, 320, 192).to_rgba8(),
+        img.to_rgba8()
+    );
+
+    // A fully transparent overlay leaves the image unchanged
+    let logo = image::DynamicImage::new_rgba8(50, 50);
+    let overlaid = perturb::overlay(&img, &logo, 300, -10);
+    assert_eq!(overlaid.to_rgba8(), img.to_rgba8());
+}
+
+#[test]
+fn jittered_boundaries() {
+    let img = image::open("images/example.png").unwrap();
+    let key = 0x0123_4567_89ab_cdef;
+
+    // Without any strength, the image is unchanged
+    let unwarped = Jittered::new(&img, key).strength(0);
+    assert_eq!(blockhash256(&unwarped), blockhash256(&img));
+
+    // The warp is proportional, so resized copies still match
+    let jittered = blockhash64(&Jittered::new(&img, key));
+    let resized = img.resize_exact(640, 384, image::imageops::FilterType::Triangle);
+    let resized_hash = blockhash64(&Jittered::new(&resized, key));
+    assert!(resized_hash.distance(&jittered) <= 2);
+
+    // Different keys move the boundaries differently
+    let other = Jittered::new(&img, key + 1).strength(127);
+    let warped = Jittered::new(&img, key).strength(127);
+    assert_ne!(blockhash256(&other), blockhash256(&warped));
+
+    // Tiny images are left alone
+    let tiny = image::open("images/5x2_rgb.png").unwrap();
+    assert_eq!(blockhash16(&Jittered::new(&tiny, key)), blockhash16(&tiny));
+}
+
+#[test]
+fn trimmed_borders() {
+    let img = image::open("images/example.png").unwrap().to_rgb8();
+    assert!(!Trimmed::new(&img).is_trimmed());
+
+    // Slightly noisy white borders of different sizes on each side
+    let mut padded =
+        image::RgbImage::from_fn(400, 300, |x, y| image::Rgb([250 + ((x + y) % 3) as u8; 3]));
+    image::imageops::replace(&mut padded, &img, 30, 50);
+
+    let trimmed = Trimmed::new(&padded);
+    assert!(trimmed.is_trimmed());
+    assert_eq!(trimmed.offsets(), (30, 50));
+    assert_eq!(trimmed.dimensions(), (320, 192));
+    assert_eq!(blockhash256(&trimmed), blockhash256(&img));
+
+    // Without a tolerance, the noise stops the border from being detected
+    assert!(!Trimmed::with_tolerance(&padded, 0).is_trimmed());
+
+    // A solid image is left alone
+    let solid = image::GrayImage::new(10, 10);
+    assert_eq!(Trimmed::new(&solid).dimensions(), (10, 10));
+}
+
+#[test]
+fn letterbox_bars() {
+    let img = image::open("images/example.png").unwrap().to_rgb8();
+    let hash = blockhash256(&img);
+    assert!(!Letterboxed::new(&img).has_bars());
+
+    // Pillarboxed into a 16:9 frame, with a logo on one of the bars
+    let mut frame = image::RgbImage::from_pixel(480, 192, image::Rgb([4, 4, 4]));
+    image::imageops::replace(&mut frame, &img, 80, 0);
+    for y in 10..20 {
+        for x in 10..20 {
+            frame.put_pixel(x, y, image::Rgb([255, 255, 255]));
+        }
+    }
+    let pillarboxed = Letterboxed::new(&frame);
+    assert_eq!(pillarboxed.offsets(), (80, 0));
+    assert_eq!(blockhash256(&pillarboxed), hash);
+
+    // Letterboxed into a 5:4 frame. The bars also change the medians that
+    // the bits are compared against, so the distance is only approximate
+    let mut padded = image::RgbImage::new(320, 256);
+    image::imageops::replace(&mut padded, &img, 0, 32);
+    let padded_hash = blockhash256(&padded);
+    assert_eq!(
+        ActiveRegion::detect(&padded_hash),
+        ActiveRegion {
+            left: 0,
+            top: 2,
+            right: 16,
+            bottom: 14,
+        },
+    );
+    let dist = letterbox_distance(&padded_hash, &hash);
+    assert_eq!(dist.compared(), 192);
+    assert!(dist.scaled() < padded_hash.distance(&hash));
+    assert_eq!(blockhash256(&Letterboxed::new(&padded)), hash);
+
+    // A uniform area along only one edge isn't a bar
+    let mut sky = image::RgbImage::from_pixel(320, 240, image::Rgb([0, 0, 0]));
+    image::imageops::replace(&mut sky, &img, 0, 48);
+    assert!(!Letterboxed::new(&sky).has_bars());
+    assert!(Trimmed::new(&sky).is_trimmed());
+}
+
+#[test]
+fn hash_index_namespaces() {
+    let mut rng = XorShift(0x8765_4321_u64);
+    let mut next = || Blockhash64::from(rng.next());
+
+    let mut index = HashIndex::new();
+    let hashes: Vec<_> = (0..300).map(|_| next()).collect();
+    for (i, &hash) in hashes.iter().enumerate() {
+        assert_eq!(index.insert_in(hash, i as u32 % 3), i);
+    }
+    assert_eq!(index.namespace(4), Some(1));
+    assert_eq!(index.namespace(300), None);
+
+    for _ in 0..10 {
+        let query = next();
+        for namespaces in [&[0][..], &[1, 2], &[]] {
+            let mut expected: Vec<_> = hashes
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| namespaces.contains(&(i as u32 % 3)))
+                .map(|(i, hash)| (i, hash.distance(&query)))
+                .filter(|&(_, dist)| dist <= 28)
+                .collect();
+            expected.sort_by_key(|&(i, dist)| (dist, i));
+
+            assert_eq!(index.find_in(&query, 28, namespaces), expected);
+            assert_eq!(
+                index.nearest_in(&query, 28, namespaces),
+                expected.first().copied(),
+            );
+        }
+    }
+}
+
+#[test]
+fn crop_margins() {
+    let img = image::open("images/example.png").unwrap();
+
+    let options = BlockhashOptions::new().crop_margins(10, 20, 30, 40);
+    let cropped = img.crop_imm(10, 20, 280, 132);
+    assert_eq!(options.blockhash256(&img), blockhash256(&cropped));
+    assert_eq!(options.blockhash64_with_stats(&img).1.pixels(), 280 * 132);
+
+    // 10% of 320 by 192 is 32 by 19 pixels
+    let options = BlockhashOptions::new().crop_margins_percent(10, 10, 0, 0);
+    let cropped = img.crop_imm(32, 19, 288, 173);
+    assert_eq!(options.blockhash64(&img), blockhash64(&cropped));
+
+    // Oversized margins leave a single pixel
+    let options = BlockhashOptions::new().crop_margins(1000, 0, 1000, 0);
+    assert_eq!(
+        options.blockhash16(&img),
+        blockhash16(&img.crop_imm(319, 0, 1, 192))
+    );
+    let options = BlockhashOptions::new().crop_margins_percent(100, 100, 100, 100);
+    assert_eq!(options.blockhash16_with_stats(&img).1.pixels(), 1);
+}
+
+#[test]
+fn expiring_index() {
+    let mut rng = XorShift(0x2468_ace0_u64);
+    let mut next = || Blockhash64::from(rng.next());
+
+    // Each hash is inserted one tick apart, and lives for 50 ticks
+    let mut index = ExpiringIndex::new();
+    let mut hashes = Vec::new();
+    for now in 0..1000 {
+        let hash = next();
+        assert_eq!(index.insert(hash, now, 50), now);
+        hashes.push(hash);
+    }
+
+    // Automatic eviction keeps the index from growing without bound
+    assert!(index.len() < 200);
+
+    let now = 1000;
+    let live: Vec<_> = index.live(now).map(|(id, _)| id).collect();
+    assert_eq!(live, (951..1000).collect::<Vec<_>>());
+    assert_eq!(index.get(999, now), Some(&hashes[999]));
+    assert_eq!(index.get(950, now), None);
+    assert_eq!(index.get(10, now), None);
+
+    for id in [0, 900, 950, 951, 999] {
+        let found = index.nearest(&hashes[id as usize], 0, now);
+        assert_eq!(found, (id > 950).then_some((id, 0)));
+    }
+
+    index.evict(now);
+    assert_eq!(index.len(), 49);
+    assert_eq!(index.evict(2000), 49);
+    assert!(index.is_empty());
+}
+
+#[test]
+fn hash_index_pages() {
+    let mut rng = XorShift(0x1357_9bdf_u64);
+    let mut next = || Blockhash64::from(rng.next());
+
+    let index: HashIndex<_> = (0..500).map(|_| next()).collect();
+    let query = next();
+    let expected = index.find(&query, 30);
+    assert!(expected.len() > 20);
+
+    for limit in [1, 7, expected.len(), expected.len() + 1] {
+        let mut results = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = index.find_page(&query, 30, cursor, limit);
+            assert!(page.results.len() <= limit);
+            results.extend(page.results);
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(results, expected);
+    }
+
+    let page = index.find_page(&query, 30, None, 0);
+    assert!(page.results.is_empty());
+    assert_eq!(page.next, None);
+
+    let mut index = HashIndex::new();
+    for i in 0..500 {
+        index.insert_in(next(), i % 3);
+    }
+    let expected = index.find_in(&query, 30, &[0, 2]);
+    assert!(expected.len() > 7);
+
+    let mut results = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = index.find_page_in(&query, 30, &[0, 2], cursor, 7);
+        results.extend(page.results);
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(results, expected);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_digests() {
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Configure, Token};
+
+    let hash = Blockhash64::from(0x0123_4567_89ab_cdef);
+    assert_tokens(&hash.readable(), &[Token::Str("0123456789abcdef")]);
+    assert_tokens(
+        &hash.compact(),
+        &[Token::Bytes(&[
+            0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+        ])],
+    );
+
+    let hash = Blockhash16::from(0xa396);
+    assert_de_tokens(
+        &hash.compact(),
+        &[
+            Token::Seq { len: Some(2) },
+            Token::U8(0xa3),
+            Token::U8(0x96),
+            Token::SeqEnd,
+        ],
+    );
+    assert_tokens(
+        &Blockhash256::from([0xff; 32]).compact(),
+        &[Token::Bytes(&[0xff; 32])],
+    );
+
+    assert_de_tokens_error::<serde_test::Readable<Blockhash16>>(
+        &[Token::Str("a39")],
+        "invalid value: string \"a39\", expected a 16-bit blockhash",
+    );
+    assert_de_tokens_error::<serde_test::Compact<Blockhash144>>(
+        &[Token::Bytes(&[0; 16])],
+        "invalid length 16, expected a 144-bit blockhash",
+    );
+}
+
+#[test]
+fn quality_estimates() {
+    let img = image::open("images/example.png").unwrap();
+    let (hash, quality) = blockhash_with_quality::<Blockhash256, _>(&img);
+    assert_eq!(hash, blockhash256(&img));
+    assert_eq!(quality.pixels(), 256 * 240);
+
+    // Every pixel is read once, whichever accumulation strategy is used
+    let thumbnail = img.thumbnail_exact(37, 23);
+    let (hash, quality) = blockhash_with_quality::<Blockhash144, _>(&thumbnail);
+    assert_eq!(hash, blockhash144(&thumbnail));
+    assert_eq!(quality.pixels(), 37 * 23);
+    let tiny = img.thumbnail_exact(5, 3);
+    let (_, quality) = blockhash_with_quality::<Blockhash64, _>(&tiny);
+    assert_eq!(quality.pixels(), 5 * 3);
+
+    let (_, sharp) = blockhash_with_quality::<Blockhash64, _>(&img);
+    let (_, blurred) = blockhash_with_quality::<Blockhash64, _>(&img.blur(3.0));
+    assert!(blurred.sharpness() < sharp.sharpness() / 2.0);
+    assert!(blurred.contrast() < sharp.contrast());
+
+    let dark = img.brighten(-200);
+    let (_, quality) = blockhash_with_quality::<Blockhash64, _>(&dark);
+    assert!(quality.underexposed() > 0.5);
+    assert!(quality.mean_brightness() < sharp.mean_brightness());
+    let bright = img.brighten(200);
+    let (_, quality) = blockhash_with_quality::<Blockhash64, _>(&bright);
+    assert!(quality.overexposed() > 0.5);
+
+    let flat = RawImage::<_, 1>::new(&[128_u8; 64], 8, 8);
+    let (_, quality) = blockhash_with_quality::<Blockhash16, _>(&flat);
+    assert_eq!(quality.sharpness(), 0.0);
+    assert_eq!(quality.contrast(), 0.0);
+}
+
+#[test]
+fn generic_digests() {
+    fn check<const BYTES: usize>(img: &image::DynamicImage)
+    where
+        Blockhash<BYTES>: Digest,
+    {
+        let hash = Blockhash::<BYTES>::from_image(img);
+        assert_eq!(hash.to_string().parse::<Blockhash<BYTES>>(), Ok(hash));
+        assert_eq!(Blockhash::from(<[u8; BYTES]>::from(hash)), hash);
+        assert_eq!(hash.as_bytes().len(), BYTES);
+        assert_eq!(Blockhash::<BYTES>::BITS as usize, BYTES * 8);
+
+        // The methods shared by every size use the grid of each size
+        assert_eq!(hash.distance(&hash.flip_horizontal().flip_horizontal()), 0);
+        assert_eq!(hash.flip_vertical().flip_vertical(), hash);
+        assert_eq!(
+            Blockhash::from_hex_bytes(hash.to_string().as_bytes()),
+            Ok(hash)
+        );
+        for &order in &[BitOrder::ColumnMajorMsbFirst, BitOrder::RowMajorLsbFirst] {
+            let bytes = hash.to_bytes_ordered(order);
+            assert_eq!(Blockhash::from_bytes_ordered(bytes, order), hash);
+        }
+    }
+
+    let img = image::open("images/example.png").unwrap();
+    check::<2>(&img);
+    check::<8>(&img);
+    check::<18>(&img);
+    check::<32>(&img);
+    check::<128>(&img);
+    assert_eq!(Blockhash::<8>::from_image(&img), blockhash64(&img));
+
+    // Each alias is named after its number of bits
+    assert_eq!(
+        format!("{:?}", Blockhash16::from([0xa3, 0x96])),
+        "Blockhash16([163, 150])",
+    );
+    assert!(format!("{:?}", blockhash144(&img)).starts_with("Blockhash144(["));
+}
@@ -0,0 +1,147 @@
+use crate::Image;
+
+/// The maximum brightness of a [`ThermalFrame`].
+const MAX_VALUE: u32 = u16::MAX as u32;
+
+/// The method used to map the raw counts of a [`ThermalFrame`] to brightness.
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum Normalization {
+    /// The lowest count in the frame is black, and the highest is white.
+    ///
+    /// This cancels out changes in the gain and offset of the sensor between
+    /// frames, so it suits comparing the content of frames regardless of
+    /// their absolute temperature.
+    #[default]
+    MinMax,
+    /// Counts at or below `low` are black, and counts at or above `high` are
+    /// white.
+    ///
+    /// This keeps the same mapping for every frame, so a change in
+    /// temperature changes the hash. The counts that correspond to a range of
+    /// temperatures depend on the calibration of the camera.
+    Fixed {
+        /// The count that is mapped to black.
+        low: u16,
+        /// The count that is mapped to white.
+        high: u16,
+    },
+}
+
+/// A single-channel frame of raw counts from a thermal camera.
+///
+/// Thermal sensors usually produce 14-bit or 16-bit counts, and a scene
+/// typically only covers a small part of that range, which drifts from frame
+/// to frame as the sensor warms up or recalibrates. Hashing the counts
+/// directly compares blocks that differ by only a few counts, so the hash of
+/// a static scene is unstable. This adapter stretches the counts to the full
+/// range of brightness first, as set by the [`Normalization`].
+///
+/// Pixels are stored in row-major order, with no padding between rows.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash16, Normalization, ThermalFrame};
+///
+/// let frame = [8000_u16, 8000, 8010, 8010, 8000, 8000, 8010, 8010];
+/// let img = ThermalFrame::new(&frame, 4, 2);
+/// assert_eq!(blockhash16(&img).to_string(), "3333");
+///
+/// // The same frame after the sensor has drifted
+/// let drifted = [8100_u16, 8100, 8110, 8110, 8100, 8100, 8110, 8110];
+/// let img = ThermalFrame::new(&drifted, 4, 2);
+/// assert_eq!(blockhash16(&img).to_string(), "3333");
+///
+/// let img = ThermalFrame::new(&frame, 4, 2)
+///     .normalization(Normalization::Fixed { low: 7000, high: 9000 });
+/// assert_eq!(blockhash16(&img).to_string(), "3333");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct ThermalFrame<'a> {
+    data: &'a [u16],
+    width: u32,
+    height: u32,
+    min: u16,
+    max: u16,
+    low: u16,
+    high: u16,
+}
+
+impl<'a> ThermalFrame<'a> {
+    /// Creates a frame from a buffer of raw counts, normalized to the range
+    /// of counts in the frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the buffer doesn't match the dimensions of the
+    /// frame.
+    #[must_use]
+    pub fn new(data: &'a [u16], width: u32, height: u32) -> Self {
+        assert_eq!(data.len() as u64, u64::from(width) * u64::from(height));
+
+        let min = data.iter().copied().min().unwrap_or(0);
+        let max = data.iter().copied().max().unwrap_or(0);
+
+        ThermalFrame {
+            data,
+            width,
+            height,
+            min,
+            max,
+            low: min,
+            high: max,
+        }
+    }
+
+    /// Sets the method used to map counts to brightness.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a fixed range has `low` greater than `high`.
+    #[must_use]
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        (self.low, self.high) = match normalization {
+            Normalization::MinMax => (self.min, self.max),
+            Normalization::Fixed { low, high } => {
+                assert!(low <= high, "the range of counts must not be reversed");
+                (low, high)
+            }
+        };
+        self
+    }
+
+    /// Returns the underlying buffer.
+    #[inline]
+    #[must_use]
+    pub fn as_slice(&self) -> &'a [u16] {
+        self.data
+    }
+}
+
+impl Image for ThermalFrame<'_> {
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        MAX_VALUE
+    }
+
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let count = self.data[y as usize * self.width as usize + x as usize];
+
+        if count <= self.low {
+            0
+        } else if count >= self.high {
+            MAX_VALUE
+        } else {
+            let range = u32::from(self.high - self.low);
+            let offset = u32::from(count - self.low);
+            ((u64::from(offset) * u64::from(MAX_VALUE) + u64::from(range) / 2) / u64::from(range))
+                as u32
+        }
+    }
+}
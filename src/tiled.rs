@@ -0,0 +1,103 @@
+use crate::{blockhash256, Blockhash256, Digest, Image, Region};
+use alloc::vec::Vec;
+
+/// The closest tile to a hash, found by [`best_tile`].
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct TileMatch {
+    /// The position of the tile in the list of tiles.
+    pub index: usize,
+    /// The distance between the hash and the hash of the tile.
+    pub distance: u32,
+}
+
+/// Divides an image into a grid of tiles, and generates a 256-bit hash of
+/// each tile independently.
+///
+/// The hash of a whole image says little about its parts, so an image that
+/// contains another, such as a collage or a screenshot with an embedded
+/// photo, usually has a hash that is far from the hash of the image it
+/// contains. Hashing each tile separately allows the contained image to be
+/// found with [`best_tile`], if it covers roughly one tile.
+///
+/// The image is divided into `columns` by `rows` tiles of nearly equal size,
+/// and the hashes are returned in row-major order. The hash of each tile is
+/// the same as the hash of a copy of the tile.
+///
+/// # Panics
+///
+/// Panics if `columns` or `rows` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{best_tile, blockhash256, blockhash256_tiles, TileMatch};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let tiles = blockhash256_tiles(&img, 2, 2);
+/// assert_eq!(tiles.len(), 4);
+///
+/// // The top-right quarter of the image
+/// let part = blockhash256(&img.crop_imm(160, 0, 160, 96));
+/// assert_eq!(best_tile(&part, &tiles), Some(TileMatch { index: 1, distance: 0 }));
+/// # }
+/// ```
+#[must_use]
+pub fn blockhash256_tiles<I: Image>(img: &I, columns: u32, rows: u32) -> Vec<Blockhash256> {
+    assert!(
+        columns > 0 && rows > 0,
+        "the grid of tiles must not be empty"
+    );
+
+    let (width, height) = img.dimensions();
+    let edge = |idx: u32, count: u32, len: u32| {
+        (u64::from(idx) * u64::from(len) / u64::from(count)) as u32
+    };
+
+    let mut tiles = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        let (top, bottom) = (edge(row, rows, height), edge(row + 1, rows, height));
+        for col in 0..columns {
+            let (left, right) = (edge(col, columns, width), edge(col + 1, columns, width));
+            tiles.push(blockhash256(&Region::new(
+                img,
+                left,
+                top,
+                right - left,
+                bottom - top,
+            )));
+        }
+    }
+
+    tiles
+}
+
+/// Finds the tile whose hash is closest to a hash.
+///
+/// Returns `None` if there are no tiles. If several tiles are equally close,
+/// the first is returned.
+#[must_use]
+pub fn best_tile<D: Digest>(hash: &D, tiles: &[D]) -> Option<TileMatch> {
+    tiles
+        .iter()
+        .enumerate()
+        .map(|(index, tile)| TileMatch {
+            index,
+            distance: hash.distance(tile),
+        })
+        .min_by_key(|m| m.distance)
+}
+
+/// Returns the smallest distance between a tile of one image and a tile of
+/// another.
+///
+/// This is small if the images share a region that covers about one tile in
+/// each, such as two collages containing the same photo. Returns `None` if
+/// either list of tiles is empty.
+#[must_use]
+pub fn best_tile_distance<D: Digest>(a: &[D], b: &[D]) -> Option<u32> {
+    a.iter()
+        .filter_map(|hash| best_tile(hash, b))
+        .map(|m| m.distance)
+        .min()
+}
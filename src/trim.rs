@@ -0,0 +1,161 @@
+use crate::Image;
+
+/// An image with any solid-color borders removed.
+///
+/// Large uniform borders, such as the black bars around a video frame or the
+/// white margins of a scanned page, cover many blocks with the same value.
+/// Since half the blocks of each band are set, this can push the content of
+/// the image into a few blocks, and images with similar borders collapse to
+/// similar repeating patterns. Trimming the borders before hashing means only
+/// the content contributes to the hash.
+///
+/// Borders are detected by scanning inwards from each edge, first the rows at
+/// the top and bottom and then the columns at the left and right, for as long
+/// as every pixel in the row or column is within the tolerance of the corner
+/// pixel on that side. The border can be any color, and each side can have a
+/// different color. If the whole image is a single color, nothing is trimmed.
+///
+/// Note that this can't distinguish borders from content that is uniform
+/// along the edges of the image, such as a clear sky.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash64, Trimmed};
+/// use image::{imageops, Rgb, RgbImage};
+///
+/// let img = image::open("images/example.png").unwrap().to_rgb8();
+///
+/// let mut padded = RgbImage::from_pixel(320, 400, Rgb([0, 0, 0]));
+/// imageops::replace(&mut padded, &img, 0, 100);
+///
+/// let trimmed = Trimmed::new(&padded);
+/// assert_eq!(trimmed.offsets(), (0, 100));
+/// assert_eq!(blockhash64(&trimmed), blockhash64(&img));
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Trimmed<'a, I> {
+    img: &'a I,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl<'a, I: Image> Trimmed<'a, I> {
+    /// Detects and removes the borders of an image.
+    ///
+    /// Pixels whose brightness differs from the corner pixel by no more than
+    /// 1/32 of the maximum brightness are treated as part of the border, which
+    /// allows for noise from lossy compression.
+    #[must_use]
+    pub fn new(img: &'a I) -> Self {
+        Self::with_tolerance(img, img.max_brightness() / 32)
+    }
+
+    /// Detects and removes the borders of an image, treating pixels within the
+    /// given difference in brightness of the corner pixel as part of the
+    /// border.
+    #[must_use]
+    pub fn with_tolerance(img: &'a I, tolerance: u32) -> Self {
+        let (width, height) = img.dimensions();
+        let full = Trimmed {
+            img,
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        if width == 0 || height == 0 {
+            return full;
+        }
+
+        let near = |x: u32, y: u32, val: u32| img.brightness(x, y).abs_diff(val) <= tolerance;
+        let row_is_border = |y: u32, val: u32| (0..width).all(|x| near(x, y, val));
+
+        let mut top = 0;
+        let mut bottom = height;
+        let (top_val, bottom_val) = (img.brightness(0, 0), img.brightness(0, height - 1));
+        while top < bottom && row_is_border(top, top_val) {
+            top += 1;
+        }
+        while bottom > top && row_is_border(bottom - 1, bottom_val) {
+            bottom -= 1;
+        }
+
+        if top == bottom {
+            return full;
+        }
+
+        let col_is_border = |x: u32, val: u32| (top..bottom).all(|y| near(x, y, val));
+
+        let mut left = 0;
+        let mut right = width;
+        let (left_val, right_val) = (img.brightness(0, top), img.brightness(width - 1, top));
+        while left < right && col_is_border(left, left_val) {
+            left += 1;
+        }
+        while right > left && col_is_border(right - 1, right_val) {
+            right -= 1;
+        }
+
+        Trimmed {
+            img,
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    /// Returns the position of the top-left corner of the remaining image
+    /// within the underlying image.
+    #[inline]
+    #[must_use]
+    pub fn offsets(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// Returns whether any borders were removed.
+    #[inline]
+    #[must_use]
+    pub fn is_trimmed(&self) -> bool {
+        (self.width, self.height) != self.img.dimensions()
+    }
+
+    /// Returns the underlying image.
+    #[inline]
+    #[must_use]
+    pub fn inner(&self) -> &'a I {
+        self.img
+    }
+}
+
+impl<I: Image> Image for Trimmed<'_, I> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        self.img.brightness(self.x + x, self.y + y)
+    }
+
+    #[inline]
+    fn channels(&self, x: u32, y: u32) -> [u32; 4] {
+        self.img.channels(self.x + x, self.y + y)
+    }
+
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        self.img.max_brightness()
+    }
+
+    #[inline]
+    fn max_channel(&self) -> u32 {
+        self.img.max_channel()
+    }
+}
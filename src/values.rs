@@ -0,0 +1,86 @@
+use crate::hash::{convert_to_bits, get_values, Accumulation, Threshold};
+use crate::{Blockhash, Blockhash144, Blockhash16, Blockhash256, Blockhash64, Image};
+
+/// Generates a 16-bit perceptual hash of an image, along with the brightness
+/// of each block.
+///
+/// See [`blockhash256_with_values`] for details.
+#[must_use]
+pub fn blockhash16_with_values<I: Image>(img: &I) -> (Blockhash16, [f32; 16]) {
+    let (hash, values) = hash_with_values::<I, 4, 16, 2>(img);
+    (Blockhash(hash), values)
+}
+
+/// Generates a 64-bit perceptual hash of an image, along with the brightness
+/// of each block.
+///
+/// See [`blockhash256_with_values`] for details.
+#[must_use]
+pub fn blockhash64_with_values<I: Image>(img: &I) -> (Blockhash64, [f32; 64]) {
+    let (hash, values) = hash_with_values::<I, 8, 64, 8>(img);
+    (Blockhash(hash), values)
+}
+
+/// Generates a 144-bit perceptual hash of an image, along with the brightness
+/// of each block.
+///
+/// See [`blockhash256_with_values`] for details.
+#[must_use]
+pub fn blockhash144_with_values<I: Image>(img: &I) -> (Blockhash144, [f32; 144]) {
+    let (hash, values) = hash_with_values::<I, 12, 144, 18>(img);
+    (Blockhash(hash), values)
+}
+
+/// Generates a 256-bit perceptual hash of an image, along with the brightness
+/// of each block.
+///
+/// The brightness of each block is the value the hash was computed from,
+/// normalized to the range `0.0..=1.0`, where 0 is black and 1 is white. The
+/// blocks are in row-major order, in the same order as the bits of the hash.
+/// This is useful for understanding why an image produced a particular hash,
+/// or for applying custom thresholds.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use blockhash::{blockhash256, blockhash256_with_values};
+///
+/// let img = image::open("images/example.png").unwrap();
+/// let (hash, values) = blockhash256_with_values(&img);
+///
+/// assert_eq!(hash, blockhash256(&img));
+/// assert!(values.iter().all(|&value| (0.0..=1.0).contains(&value)));
+/// # }
+/// ```
+#[must_use]
+pub fn blockhash256_with_values<I: Image>(img: &I) -> (Blockhash256, [f32; 256]) {
+    let (hash, values) = hash_with_values::<I, 16, 256, 32>(img);
+    (Blockhash(hash), values)
+}
+
+fn hash_with_values<
+    I: Image,
+    const BITS: u32,
+    const NUM_BLOCKS: usize,
+    const DIGEST_SIZE: usize,
+>(
+    img: &I,
+) -> ([u8; DIGEST_SIZE], [f32; NUM_BLOCKS]) {
+    let (width, height) = img.dimensions();
+    let accumulation = Accumulation::select(width, height, BITS);
+    let values = get_values::<I, BITS, NUM_BLOCKS>(img, accumulation);
+
+    let max_value = img.max_brightness();
+    let hash = convert_to_bits(width, height, &values, max_value, 4, Threshold::Median);
+
+    // The values are scaled so that a white block has this value
+    let full = (u64::from(max_value) * u64::from(width) * u64::from(height)).max(1);
+
+    let mut normalized = [0.0; NUM_BLOCKS];
+    for (norm, &value) in normalized.iter_mut().zip(&values) {
+        *norm = (value as f64 / full as f64) as f32;
+    }
+
+    (hash, normalized)
+}
@@ -0,0 +1,171 @@
+use crate::Image;
+
+/// The maximum brightness of a [`Windowed`] image.
+const MAX_VALUE: u32 = u16::MAX as u32;
+
+/// A grayscale image whose stored values are mapped to brightness through a
+/// rescale and a window, as in DICOM.
+///
+/// Medical images store raw values, often with more than 8 bits and
+/// sometimes signed, which only become meaningful after the modality LUT and
+/// the VOI window are applied. Each stored value is first rescaled with
+/// [`rescale`](Self::rescale), for example to convert CT values to Hounsfield
+/// units, and then mapped to brightness with the linear window function of
+/// the DICOM standard, set by [`window`](Self::window). Values below the
+/// window are black, and values above it are white. Without a window, the
+/// whole range of values in the image is used.
+///
+/// This crate doesn't parse DICOM files. The pixel data of each frame, along
+/// with the Rescale Slope, Rescale Intercept, Window Center, and Window Width
+/// attributes, can be read with a DICOM crate and passed here. Frames of a
+/// multi-frame image are stored one after another, so each one can be hashed
+/// separately by splitting the pixel data into chunks of `width * height`
+/// values.
+///
+/// # Examples
+///
+/// ```
+/// use blockhash::{blockhash16, Windowed};
+///
+/// // Air and soft tissue in a CT image, stored with an offset of 1024
+/// let data = [0_u16, 0, 1064, 1064, 0, 0, 1064, 1064];
+/// let img = Windowed::new(&data, 4, 2)
+///     .rescale(1.0, -1024.0)
+///     .window(40.0, 400.0);
+///
+/// assert_eq!(blockhash16(&img).to_string(), "3333");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Windowed<'a, T> {
+    data: &'a [T],
+    width: u32,
+    height: u32,
+    min: i32,
+    max: i32,
+    slope: f64,
+    intercept: f64,
+    window: Option<(f64, f64)>,
+    inverted: bool,
+    lower: f64,
+    upper: f64,
+}
+
+impl<'a, T: Copy + Into<i32>> Windowed<'a, T> {
+    /// Creates an image from a buffer of stored values.
+    ///
+    /// Pixels are stored in row-major order, with no padding between rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the buffer doesn't match the dimensions of the
+    /// image.
+    #[must_use]
+    pub fn new(data: &'a [T], width: u32, height: u32) -> Self {
+        assert_eq!(data.len() as u64, u64::from(width) * u64::from(height));
+
+        let min = data.iter().map(|&val| val.into()).min().unwrap_or(0);
+        let max = data.iter().map(|&val| val.into()).max().unwrap_or(0);
+
+        Windowed {
+            data,
+            width,
+            height,
+            min,
+            max,
+            slope: 1.0,
+            intercept: 0.0,
+            window: None,
+            inverted: false,
+            lower: 0.0,
+            upper: 0.0,
+        }
+        .update()
+    }
+
+    /// Sets the rescale slope and intercept, which convert stored values to
+    /// output units.
+    ///
+    /// The default is a slope of 1 and an intercept of 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slope is zero, or if either value isn't finite.
+    #[must_use]
+    pub fn rescale(mut self, slope: f64, intercept: f64) -> Self {
+        assert!(slope != 0.0 && slope.is_finite(), "invalid rescale slope");
+        assert!(intercept.is_finite(), "invalid rescale intercept");
+
+        self.slope = slope;
+        self.intercept = intercept;
+        self.update()
+    }
+
+    /// Sets the center and width of the window, in rescaled units.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the width is less than 1, or if either value isn't finite.
+    #[must_use]
+    pub fn window(mut self, center: f64, width: f64) -> Self {
+        assert!(center.is_finite(), "invalid window center");
+        assert!(width >= 1.0 && width.is_finite(), "invalid window width");
+
+        self.window = Some((center, width));
+        self.update()
+    }
+
+    /// Sets whether low values are white rather than black, as for the
+    /// `MONOCHROME1` photometric interpretation.
+    #[must_use]
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Computes the range of rescaled values that is mapped to brightness.
+    fn update(mut self) -> Self {
+        (self.lower, self.upper) = match self.window {
+            Some((center, width)) => (
+                center - 0.5 - (width - 1.0) / 2.0,
+                center - 0.5 + (width - 1.0) / 2.0,
+            ),
+            None => {
+                let a = f64::from(self.min) * self.slope + self.intercept;
+                let b = f64::from(self.max) * self.slope + self.intercept;
+                (a.min(b), a.max(b))
+            }
+        };
+        self
+    }
+}
+
+impl<T: Copy + Into<i32>> Image for Windowed<'_, T> {
+    #[inline]
+    fn max_brightness(&self) -> u32 {
+        MAX_VALUE
+    }
+
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> u32 {
+        let stored = self.data[y as usize * self.width as usize + x as usize].into();
+        let val = f64::from(stored) * self.slope + self.intercept;
+
+        let brightness = if val <= self.lower {
+            0
+        } else if val > self.upper {
+            MAX_VALUE
+        } else {
+            let fraction = (val - self.lower) / (self.upper - self.lower);
+            (fraction * f64::from(MAX_VALUE) + 0.5) as u32
+        };
+
+        match self.inverted {
+            true => MAX_VALUE - brightness,
+            false => brightness,
+        }
+    }
+}